@@ -6,150 +6,608 @@
 //!
 //!
 //!
-use std::collections::HashMap;
 use std::env;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
+use avail_subxt::api;
 use avail_subxt::config::Header as HeaderTrait;
-use avail_subxt::subxt_rpc::RpcParams;
-use avail_subxt::{api, build_client};
+use avail_subxt::subxt_rpc::{RpcParams, Subscription};
+use clap::{Parser, Subcommand};
 use codec::Encode;
-use log::debug;
-use plonky2x::frontend::ecc::curve25519::ed25519::eddsa::DUMMY_SIGNATURE;
-use sp_core::ed25519::{self};
-use sp_core::{blake2_256, Pair, H256};
-use vectorx::input::types::{GrandpaJustification, SignerMessage, StoredJustificationData};
-use vectorx::input::RpcDataFetcher;
-
-async fn listen_for_justifications(mut fetcher: RpcDataFetcher) {
-    let sub: Result<avail_subxt::subxt_rpc::Subscription<GrandpaJustification>, _> = fetcher
+use log::{debug, error, info, warn};
+use sp_core::{blake2_256, H256};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::time::sleep;
+use vectorx::consts::DEFAULT_AVAIL_URL;
+use vectorx::input::retry::RetryPolicy;
+use vectorx::input::types::GrandpaJustification;
+use vectorx::input::{
+    drop_unjustified_precommits, has_scheduled_change, is_round_regression, process_justification,
+    verify_votes_ancestries, RpcDataFetcher,
+};
+
+/// Number of justifications indexed between each prune of consumed justifications.
+const PRUNE_INTERVAL: u64 = 100;
+
+/// Backoff between attempts to re-establish a dropped justification subscription. The indexer retries
+/// until it reconnects or is shut down, so max_attempts is not used.
+const RECONNECT_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: usize::MAX,
+    base_delay: Duration::from_secs(1),
+    max_delay: Duration::from_secs(60),
+    jitter: true,
+};
+
+/// Default number of blocks between saved justifications.
+const DEFAULT_BLOCK_SAVE_INTERVAL: u32 = 90;
+
+/// Interval between checks of the lag of the last indexed block behind the finalized head.
+const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Parser, Debug, Clone)]
+#[command(about = "Index the justifications of an Avail chain into Redis.")]
+pub struct IndexerArgs {
+    /// Runs a one-off job instead of subscribing to new justifications.
+    #[command(subcommand)]
+    pub command: Option<IndexerCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum IndexerCommand {
+    /// Stores the justifications of the save interval blocks and epoch end blocks in [start, end],
+    /// and exits.
+    Backfill {
+        #[arg(long)]
+        start: u32,
+        #[arg(long)]
+        end: u32,
+    },
+}
+
+/// Reads the websocket url of the Avail node from AVAIL_WS_URL, falling back to AVAIL_URL and then
+/// DEFAULT_AVAIL_URL. Either can be a comma-separated list of fallback urls.
+fn avail_ws_url() -> String {
+    ["AVAIL_WS_URL", "AVAIL_URL"]
+        .iter()
+        .find_map(|key| env::var(key).ok().filter(|url| !url.is_empty()))
+        .unwrap_or_else(|| {
+            warn!(
+                "AVAIL_WS_URL is not set, defaulting to {}",
+                DEFAULT_AVAIL_URL
+            );
+            DEFAULT_AVAIL_URL.to_string()
+        })
+}
+
+/// Reads the number of blocks between saved justifications from BLOCK_SAVE_INTERVAL. Panics if the
+/// interval is not a positive integer.
+fn block_save_interval() -> u32 {
+    let interval = env::var("BLOCK_SAVE_INTERVAL")
+        .ok()
+        .filter(|interval| !interval.is_empty())
+        .map(|interval| {
+            interval
+                .parse::<u32>()
+                .expect("BLOCK_SAVE_INTERVAL must be a positive integer")
+        })
+        .unwrap_or(DEFAULT_BLOCK_SAVE_INTERVAL);
+    assert!(
+        interval > 0,
+        "BLOCK_SAVE_INTERVAL must be a positive integer"
+    );
+    interval
+}
+
+/// Reads the maximum number of blocks the last indexed block can lag behind the finalized head from
+/// MAX_INDEXER_LAG. Defaults to twice block_save_interval, as the indexer only saves a justification
+/// every block_save_interval blocks. Panics if the lag is not a positive integer.
+fn max_indexer_lag(block_save_interval: u32) -> u32 {
+    let max_lag = env::var("MAX_INDEXER_LAG")
+        .ok()
+        .filter(|max_lag| !max_lag.is_empty())
+        .map(|max_lag| {
+            max_lag
+                .parse::<u32>()
+                .expect("MAX_INDEXER_LAG must be a positive integer")
+        })
+        .unwrap_or(2 * block_save_interval);
+    assert!(max_lag > 0, "MAX_INDEXER_LAG must be a positive integer");
+    if max_lag <= block_save_interval {
+        warn!(
+            "MAX_INDEXER_LAG of {} blocks is at most BLOCK_SAVE_INTERVAL, so the lag check will warn between saved justifications",
+            max_lag
+        );
+    }
+    max_lag
+}
+
+/// Records the indexer cursor as the last indexed block, if it is past the last indexed block.
+async fn record_indexer_cursor(fetcher: &mut RpcDataFetcher, last_indexed: &AtomicU32) {
+    if let Some(cursor) = fetcher
+        .redis_client
+        .get_indexer_cursor(&fetcher.avail_chain_id)
+        .await
+    {
+        last_indexed.fetch_max(cursor, Ordering::Relaxed);
+    }
+}
+
+/// Returns the number of the finalized head, reconnecting the client if the request fails.
+async fn finalized_head_number(fetcher: &mut RpcDataFetcher) -> Result<u32, String> {
+    let head = async {
+        let head_hash = fetcher.client.rpc().finalized_head().await?;
+        fetcher.client.rpc().header(Some(head_hash)).await
+    }
+    .await;
+    match head {
+        Ok(Some(header)) => Ok(header.number),
+        Ok(None) => Err("Finalized head header not found".to_string()),
+        Err(e) => {
+            fetcher.reconnect().await?;
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Checks every LAG_CHECK_INTERVAL that the last indexed block is at most max_lag blocks behind the
+/// finalized head, and warns if it is not, as the indexer may be stuck while the chain advances.
+async fn monitor_indexer_lag(
+    mut fetcher: RpcDataFetcher,
+    last_indexed: Arc<AtomicU32>,
+    max_lag: u32,
+) {
+    loop {
+        sleep(LAG_CHECK_INTERVAL).await;
+
+        let last_indexed_block = last_indexed.load(Ordering::Relaxed);
+        if last_indexed_block == 0 {
+            debug!("No block indexed yet, skipping the lag check");
+            continue;
+        }
+        let head = match finalized_head_number(&mut fetcher).await {
+            Ok(head) => head,
+            Err(e) => {
+                warn!("Lag check failed to get the finalized head: {}", e);
+                continue;
+            }
+        };
+        let lag = head.saturating_sub(last_indexed_block);
+        if lag > max_lag {
+            warn!(
+                "Indexer is lagging: last indexed block {} is {} blocks behind finalized head {}, max lag is {}",
+                last_indexed_block, lag, head, max_lag
+            );
+        } else {
+            debug!(
+                "Indexer lag: last indexed block {} is {} blocks behind finalized head {}",
+                last_indexed_block, lag, head
+            );
+        }
+    }
+}
+
+/// Deletes the stored justifications below the prune watermark. Justifications are only pruned
+/// once a prover has set the watermark past them.
+async fn prune_consumed_justifications(fetcher: &mut RpcDataFetcher) {
+    let Some(watermark) = fetcher
+        .redis_client
+        .get_prune_watermark(&fetcher.avail_chain_id)
+        .await
+    else {
+        debug!("No prune watermark set, skipping pruning");
+        return;
+    };
+    let pruned = fetcher
+        .redis_client
+        .prune_older_than(&fetcher.avail_chain_id, watermark)
+        .await;
+    info!("Pruned {} justifications below block {}", pruned, watermark);
+}
+
+/// Verifies the justification and saves it to Redis if its block is a save interval block or an epoch
+/// end block. Advances the indexer cursor to the block of a saved justification, if the block is past
+/// the cursor, so backfilling an old range does not move the cursor back. Returns true if the
+/// justification was saved. Backfilled justifications are older than the rounds already seen on the
+/// live subscription, so their rounds are only checked for regressions if check_round is true.
+/// Errors if the header or authority set id of the block can't be fetched, which means the
+/// connection to the Avail node needs to be re-established.
+async fn index_justification(
+    fetcher: &mut RpcDataFetcher,
+    justification: GrandpaJustification,
+    block_save_interval: u32,
+    check_round: bool,
+) -> Result<bool, String> {
+    debug!(
+        "New justification from block {}",
+        justification.commit.target_number
+    );
+
+    // Get the header corresponding to the new justification.
+    let header = fetcher
         .client
         .rpc()
-        .deref()
-        .subscribe(
-            "grandpa_subscribeJustifications",
-            RpcParams::new(),
-            "grandpa_unsubscribeJustifications",
-        )
-        .await;
-    let mut sub = sub.unwrap();
+        .header(Some(justification.commit.target_hash))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| {
+            format!(
+                "Header not found for block {}",
+                justification.commit.target_number
+            )
+        })?;
+
+    // A bit redundant, but just to make sure the hash is correct. This confirms that the
+    // header encoding + block encoding match.
+    let block_hash = justification.commit.target_hash;
+    let header_hash = header.hash();
+    let calculated_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+    if header_hash != calculated_hash || block_hash != calculated_hash {
+        panic!("Header hash does not match block hash, avail-subxt crate is out of sync.");
+    }
 
-    // Wait for new justification.
-    while let Some(Ok(justification)) = sub.next().await {
-        debug!(
-            "New justification from block {}",
-            justification.commit.target_number
+    // The precommits must be for the justified block or its descendants, linked by the votes
+    // ancestries. Precommits for other blocks are not votes for the justified block, so they are
+    // dropped from the signer tally.
+    let mut justification = justification;
+    let num_dropped = drop_unjustified_precommits(&mut justification);
+    if num_dropped > 0 {
+        warn!(
+            "Dropped {} precommits not justified by the votes ancestries of block {}",
+            num_dropped, header.number
+        );
+    }
+    if justification.commit.precommits.is_empty() {
+        warn!(
+            "Skipping justification for block {}: no justified precommits",
+            header.number
         );
+        return Ok(false);
+    }
 
-        // Get the header corresponding to the new justification.
-        let header = fetcher
-            .client
-            .rpc()
-            .header(Some(justification.commit.target_hash))
-            .await
-            .unwrap()
-            .unwrap();
-
-        // A bit redundant, but just to make sure the hash is correct. This confirms that the
-        // header encoding + block encoding match.
-        let block_hash = justification.commit.target_hash;
-        let header_hash = header.hash();
-        let calculated_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
-        if header_hash != calculated_hash || block_hash != calculated_hash {
-            panic!("Header hash does not match block hash, avail-subxt crate is out of sync.");
+    // Every votes ancestry must be on the path of a justified precommit. Unused, broken or cyclic
+    // ancestries mean the ancestry set is malformed, so the whole justification is skipped.
+    if let Err(e) = verify_votes_ancestries(&justification) {
+        warn!("Skipping justification for block {}: {}", header.number, e);
+        return Ok(false);
+    }
+
+    // Get current authority set ID.
+    let set_id_key = api::storage().grandpa().current_set_id();
+    let authority_set_id = fetcher
+        .client
+        .storage()
+        .at(block_hash)
+        .fetch(&set_id_key)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Authority set id not found for block {}", header.number))?;
+
+    // GRANDPA rounds increase monotonically within an authority set. A round lower than or equal
+    // to the last one seen for this set indicates a replayed justification or a fork.
+    if check_round {
+        let last_round = fetcher
+            .redis_client
+            .get_last_justification_round(&fetcher.avail_chain_id, authority_set_id)
+            .await;
+        if is_round_regression(
+            last_round,
+            justification.round,
+            authority_set_id,
+            header.number,
+        ) {
+            let regressions = fetcher
+                .redis_client
+                .increment_round_regressions(&fetcher.avail_chain_id)
+                .await;
+            warn!("Total justification round regressions: {}", regressions);
+        } else {
+            fetcher
+                .redis_client
+                .set_last_justification_round(
+                    &fetcher.avail_chain_id,
+                    authority_set_id,
+                    justification.round,
+                )
+                .await;
         }
+    }
 
-        // Get current authority set ID.
-        let set_id_key = api::storage().grandpa().current_set_id();
-        let authority_set_id = fetcher
-            .client
-            .storage()
-            .at(block_hash)
-            .fetch(&set_id_key)
+    // Only save a justification every block_save_interval blocks, and at every epoch end block,
+    // which the rotate circuit needs.
+    if header.number % block_save_interval != 0 && !has_scheduled_change(&header) {
+        return Ok(false);
+    }
+
+    // GRANDPA can emit more than one justification for a block. Skip verifying the justification if
+    // the stored one already has at least as many signatures.
+    if fetcher
+        .redis_client
+        .justification_exists(&fetcher.avail_chain_id, header.number)
+        .await
+    {
+        if let Ok(stored) = fetcher
+            .redis_client
+            .get_justification(&fetcher.avail_chain_id, header.number)
             .await
-            .unwrap()
-            .unwrap();
-
-        // Form a message which is signed in the justification.
-        let signed_message = Encode::encode(&(
-            &SignerMessage::PrecommitMessage(justification.commit.precommits[0].clone().precommit),
-            &justification.round,
-            &authority_set_id,
-        ));
-
-        // Verify all the signatures of the justification and extract the public keys. The ordering
-        // of the authority set will already be canonical and sorted in the justification on ID.
-
-        let validators = justification
-            .commit
-            .precommits
-            .iter()
-            .filter_map(|precommit| {
-                let is_ok = <ed25519::Pair as Pair>::verify(
-                    &precommit.clone().signature,
-                    signed_message.as_slice(),
-                    &precommit.clone().id,
+        {
+            if justification.commit.precommits.len() <= stored.num_signatures() {
+                debug!(
+                    "Justification for block {} is already stored, skipping",
+                    header.number
                 );
-                if is_ok {
-                    Some((
-                        precommit.clone().id.0.to_vec(),
-                        precommit.clone().signature.0.to_vec(),
-                    ))
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-
-        let pubkeys = validators.iter().map(|v| v.0.clone()).collect::<Vec<_>>();
-        let signatures = validators.iter().map(|v| v.1.clone()).collect::<Vec<_>>();
+                return Ok(false);
+            }
+        }
+    }
 
-        // Create map from pubkey to signature.
-        let mut pubkey_to_signature = HashMap::new();
-        for (pubkey, signature) in pubkeys.iter().zip(signatures.iter()) {
-            pubkey_to_signature.insert(pubkey.to_vec(), signature.to_vec());
+    // Verify the signatures of the justification against the authority set that signed it, and
+    // create the justification data.
+    let (signed_authority_set_id, authorities, weights) = match fetcher
+        .get_signing_authorities(&justification, header.number)
+        .await
+    {
+        Ok(signing_authorities) => signing_authorities,
+        Err(e) => {
+            error!("Skipping justification for block {}: {}", header.number, e);
+            return Ok(false);
+        }
+    };
+    let store_justification_data = match process_justification(
+        header.number,
+        &justification,
+        signed_authority_set_id,
+        &authorities,
+        &weights,
+    ) {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Skipping justification for block {}: {}", header.number, e);
+            return Ok(false);
         }
+    };
+    if !fetcher
+        .redis_client
+        .add_justification(&fetcher.avail_chain_id, store_justification_data)
+        .await
+    {
+        return Ok(false);
+    }
+    let cursor = fetcher
+        .redis_client
+        .get_indexer_cursor(&fetcher.avail_chain_id)
+        .await;
+    if cursor.map_or(true, |cursor| header.number > cursor) {
+        fetcher
+            .redis_client
+            .set_indexer_cursor(&fetcher.avail_chain_id, header.number)
+            .await;
+    }
+    Ok(true)
+}
 
-        // Check that more than 2/3 of the validators signed the justification.
-        // Note: Assumes the validator set have equal voting power.
-        let authorities = fetcher.get_authorities(header.number - 1).await;
-        let num_authorities = authorities.len();
-        let signed_count = pubkeys.len();
-        let required_signatures = (num_authorities * 2) / 3;
-        if signed_count <= required_signatures {
+/// Returns the save interval blocks and the epoch end blocks in (after, end], in ascending order.
+async fn blocks_to_index(
+    fetcher: &mut RpcDataFetcher,
+    after: u32,
+    end: u32,
+    block_save_interval: u32,
+) -> Vec<u32> {
+    let mut blocks = (after + 1..=end)
+        .filter(|block_number| block_number % block_save_interval == 0)
+        .collect::<Vec<_>>();
+    let after_authority_set_id = fetcher.get_authority_set_id(after).await;
+    let end_authority_set_id = fetcher.get_authority_set_id(end).await;
+    for authority_set_id in after_authority_set_id..end_authority_set_id {
+        blocks.push(fetcher.last_justified_block(authority_set_id).await);
+    }
+    blocks.sort();
+    blocks.dedup();
+    blocks
+}
+
+/// Indexes the justifications of the save interval blocks and the epoch end blocks in (after, end].
+/// Avail nodes only keep the justifications of some blocks (such as epoch end blocks), so blocks
+/// without a stored justification are skipped. Returns the number of justifications written and
+/// the number of blocks skipped.
+async fn index_range(
+    fetcher: &mut RpcDataFetcher,
+    after: u32,
+    end: u32,
+    block_save_interval: u32,
+) -> (usize, usize) {
+    let blocks = blocks_to_index(fetcher, after, end, block_save_interval).await;
+    info!(
+        "Backfilling justifications for {} blocks in ({}, {}]",
+        blocks.len(),
+        after,
+        end
+    );
+
+    let mut num_written = 0;
+    let mut num_skipped = 0;
+    for block_number in blocks {
+        let Some(justification) = fetcher.get_stored_justification(block_number).await else {
+            debug!("No stored justification for block {}", block_number);
+            num_skipped += 1;
             continue;
+        };
+        match index_justification(fetcher, justification, block_save_interval, false).await {
+            Ok(true) => num_written += 1,
+            Ok(false) => num_skipped += 1,
+            Err(e) => {
+                warn!(
+                    "Failed to index justification for block {}: {}",
+                    block_number, e
+                );
+                num_skipped += 1;
+            }
+        }
+    }
+    (num_written, num_skipped)
+}
+
+/// Indexes the justifications of the blocks produced since the indexer cursor, before subscribing to
+/// new justifications, so that restarting the indexer does not leave gaps.
+async fn backfill_justifications(fetcher: &mut RpcDataFetcher, block_save_interval: u32) {
+    let Some(cursor) = fetcher
+        .redis_client
+        .get_indexer_cursor(&fetcher.avail_chain_id)
+        .await
+    else {
+        debug!("No indexer cursor set, skipping backfill");
+        return;
+    };
+    let head = fetcher.get_head().await.number;
+
+    let (num_written, _) = index_range(fetcher, cursor, head, block_save_interval).await;
+    info!("Backfilled {} justifications", num_written);
+}
+
+/// Subscribes to the justifications of new blocks.
+async fn subscribe_justifications(
+    fetcher: &RpcDataFetcher,
+) -> Result<Subscription<GrandpaJustification>, String> {
+    fetcher
+        .client
+        .rpc()
+        .deref()
+        .subscribe(
+            "grandpa_subscribeJustifications",
+            RpcParams::new(),
+            "grandpa_unsubscribeJustifications",
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Indexes the justifications of the subscription until it drops, the Avail node fails to return
+/// the data of a justification, or a SIGTERM is received, and records the block of each saved
+/// justification in last_indexed. Returns true if a SIGTERM was received.
+async fn index_subscription(
+    fetcher: &mut RpcDataFetcher,
+    mut sub: Subscription<GrandpaJustification>,
+    block_save_interval: u32,
+    sigterm: &mut Signal,
+    num_indexed: &mut u64,
+    last_indexed: &AtomicU32,
+) -> bool {
+    loop {
+        // Wait for a new justification. A SIGTERM is only handled between justifications, so the
+        // in-flight justification and the cursor are written before exiting.
+        let justification = tokio::select! {
+            justification = sub.next() => match justification {
+                Some(Ok(justification)) => justification,
+                Some(Err(e)) => {
+                    warn!("Justification subscription failed: {}", e);
+                    return false;
+                }
+                None => {
+                    warn!("Justification subscription closed");
+                    return false;
+                }
+            },
+            _ = sigterm.recv() => return true,
+        };
+
+        let block_number = justification.commit.target_number;
+        match index_justification(fetcher, justification, block_save_interval, true).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                warn!(
+                    "Failed to index justification for block {}: {}",
+                    block_number, e
+                );
+                return false;
+            }
         }
+        last_indexed.fetch_max(block_number, Ordering::Relaxed);
 
-        // Create justification data.
-        let mut justification_pubkeys = Vec::new();
-        let mut justification_signatures = Vec::new();
-        let mut validator_signed = Vec::new();
-        for authority_pubkey in authorities.iter() {
-            if let Some(signature) = pubkey_to_signature.get(&authority_pubkey.0.to_vec()) {
-                justification_pubkeys.push(authority_pubkey.0.to_vec());
-                justification_signatures.push(signature.to_vec());
-                validator_signed.push(true);
-            } else {
-                justification_pubkeys.push(authority_pubkey.0.to_vec());
-                justification_signatures.push(DUMMY_SIGNATURE.to_vec());
-                validator_signed.push(false);
+        *num_indexed += 1;
+        if *num_indexed % PRUNE_INTERVAL == 0 {
+            prune_consumed_justifications(fetcher).await;
+        }
+    }
+}
+
+/// Indexes new justifications until a SIGTERM is received. If the subscription drops, the client is
+/// rebuilt and the subscription is re-established with exponential backoff, and the justifications
+/// produced since the indexer cursor are backfilled so the gap is not missed. The last indexed block
+/// is recorded in last_indexed.
+async fn listen_for_justifications(
+    mut fetcher: RpcDataFetcher,
+    block_save_interval: u32,
+    mut sigterm: Signal,
+    last_indexed: Arc<AtomicU32>,
+) {
+    record_indexer_cursor(&mut fetcher, &last_indexed).await;
+    let mut num_indexed: u64 = 0;
+    // Number of failed attempts to re-establish the subscription, 0 if the subscription is healthy.
+    let mut reconnect_attempt: usize = 0;
+
+    loop {
+        if reconnect_attempt > 0 {
+            let delay = RECONNECT_POLICY.delay(reconnect_attempt);
+            info!(
+                "Reconnecting the justification subscription in {:?} (attempt {})",
+                delay, reconnect_attempt
+            );
+            // A SIGTERM during the backoff exits instead of reconnecting.
+            tokio::select! {
+                _ = sleep(delay) => {},
+                _ = sigterm.recv() => break,
+            }
+            if let Err(e) = fetcher.reconnect().await {
+                warn!("Failed to reconnect: {}", e);
+                reconnect_attempt += 1;
+                continue;
             }
         }
 
-        // Add justification to Redis.
-        let store_justification_data = StoredJustificationData {
-            block_number: header.number,
-            signed_message: signed_message.clone(),
-            pubkeys: justification_pubkeys,
-            signatures: justification_signatures,
-            num_authorities: authorities.len(),
-            validator_signed,
+        let sub = match subscribe_justifications(&fetcher).await {
+            Ok(sub) => sub,
+            Err(e) => {
+                warn!("Failed to subscribe to justifications: {}", e);
+                reconnect_attempt += 1;
+                continue;
+            }
         };
-        fetcher
-            .redis_client
-            .add_justification(&fetcher.avail_chain_id, store_justification_data)
-            .await;
+
+        // Backfill after re-subscribing, so the justifications produced while backfilling are
+        // buffered by the subscription.
+        if reconnect_attempt > 0 {
+            info!("Re-established the justification subscription");
+            backfill_justifications(&mut fetcher, block_save_interval).await;
+            record_indexer_cursor(&mut fetcher, &last_indexed).await;
+            reconnect_attempt = 0;
+        }
+
+        if index_subscription(
+            &mut fetcher,
+            sub,
+            block_save_interval,
+            &mut sigterm,
+            &mut num_indexed,
+            &last_indexed,
+        )
+        .await
+        {
+            break;
+        }
+        reconnect_attempt = 1;
     }
+
+    let cursor = fetcher
+        .redis_client
+        .get_indexer_cursor(&fetcher.avail_chain_id)
+        .await;
+    info!(
+        "Received SIGTERM, shutting down with indexer cursor {:?}",
+        cursor
+    );
 }
 
 #[tokio::main]
@@ -158,17 +616,65 @@ pub async fn main() {
     dotenv::dotenv().ok();
     env_logger::init();
 
-    // Get the chain from the environment.
-    let avail_url = env::var("AVAIL_URL").unwrap();
-    let avail_chain_id = env::var("AVAIL_CHAIN_ID").unwrap();
+    let args = IndexerArgs::parse();
+    let block_save_interval = block_save_interval();
+    info!(
+        "Saving justifications every {} blocks and at epoch end blocks",
+        block_save_interval
+    );
 
-    let fetcher = RpcDataFetcher {
-        client: build_client(avail_url.clone(), false).await.unwrap().0,
-        redis_client: vectorx::input::RedisClient::new().await,
-        avail_chain_id,
-        avail_url,
-        save: None,
-    };
+    let sigterm = signal(SignalKind::terminate()).expect("Failed to register the SIGTERM handler");
+
+    let avail_ws_url = avail_ws_url();
+    info!("Indexing justifications from {}", avail_ws_url);
+    let mut fetcher = RpcDataFetcher::new_with_url(&avail_ws_url).await;
+
+    // Justifications fetched from a node that has not imported the chain are incomplete.
+    match fetcher.health_check().await {
+        Ok(status) => info!(
+            "Avail node is synced: finalized_block={} best_block={} peers={}",
+            status.finalized_block_number, status.best_block_number, status.peers
+        ),
+        Err(e) => panic!(
+            "Refusing to start, the Avail node at {} is not healthy: {}",
+            fetcher.avail_url, e
+        ),
+    }
+
+    if let Some(IndexerCommand::Backfill { start, end }) = args.command {
+        assert!(start <= end, "start must be at most end");
+        // index_range covers (after, end]. The genesis block has no justification, so it is never
+        // part of the range.
+        let (num_written, num_skipped) = index_range(
+            &mut fetcher,
+            start.saturating_sub(1),
+            end,
+            block_save_interval,
+        )
+        .await;
+        info!(
+            "Backfilled [{}, {}]: wrote {} justifications, skipped {} blocks",
+            start, end, num_written, num_skipped
+        );
+        return;
+    }
+
+    backfill_justifications(&mut fetcher, block_save_interval).await;
+
+    // Check the lag of the indexer behind the chain alongside the subscription, with a separate
+    // client so a slow subscription does not delay the check.
+    let last_indexed = Arc::new(AtomicU32::new(0));
+    let max_lag = max_indexer_lag(block_save_interval);
+    info!(
+        "Warning if the last indexed block lags more than {} blocks behind the finalized head",
+        max_lag
+    );
+    let lag_fetcher = RpcDataFetcher::new_with_url(&avail_ws_url).await;
+    tokio::spawn(monitor_indexer_lag(
+        lag_fetcher,
+        last_indexed.clone(),
+        max_lag,
+    ));
 
-    listen_for_justifications(fetcher).await;
+    listen_for_justifications(fetcher, block_save_interval, sigterm, last_indexed).await;
 }
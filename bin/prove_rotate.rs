@@ -0,0 +1,188 @@
+//! To build the binary:
+//!
+//!     `cargo build --release --bin prove_rotate`
+//!
+//! Proves the rotate of an authority set locally, and writes the proof and its public inputs and
+//! outputs as JSON, for submission on-chain.
+//!
+//!
+
+use std::path::PathBuf;
+use std::time::Instant;
+use std::{env, fs};
+
+use clap::Parser;
+use ethers::types::H256;
+use log::info;
+use plonky2x::frontend::uint::uint64::U64Variable;
+use plonky2x::prelude::{
+    BoolVariable, Bytes32Variable, DefaultBuilder, GoldilocksField, U32Variable,
+};
+use serde_json::json;
+use vectorx::consts::{
+    DELAY_LENGTH, MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE, VALIDATOR_LENGTH,
+};
+use vectorx::input::types::DataSource;
+use vectorx::input::RpcDataFetcher;
+use vectorx::prover::prove_with_witness_dump;
+use vectorx::rotate::RotateCircuit;
+
+#[derive(Parser, Debug, Clone)]
+#[command(about = "Prove the rotate of an authority set and write the proof to a file.")]
+pub struct ProveRotateArgs {
+    /// Id of the authority set that signed its epoch end block.
+    #[arg(long)]
+    pub set_id: u64,
+    /// Hash of the authority set, as stored in the contract. Defaults to the hash computed from the
+    /// authority set on the Avail node.
+    #[arg(long)]
+    pub authority_set_hash: Option<H256>,
+    /// Expected epoch end block of the authority set. If set, the proof is not generated unless the
+    /// rotate hint finds this block.
+    #[arg(long)]
+    pub block: Option<u32>,
+    /// Maximum number of authorities supported by the circuit.
+    #[arg(long, default_value_t = MAX_AUTHORITY_SET_SIZE)]
+    pub max_authority_set_size: usize,
+    /// Path of the JSON file the proof is written to.
+    #[arg(long, default_value = "rotate_proof.json")]
+    pub output: PathBuf,
+    /// Websocket url of the Avail node to fetch the rotate data from. Defaults to AVAIL_URL.
+    #[arg(long)]
+    pub avail_url: Option<String>,
+}
+
+/// Builds and proves the rotate circuit with NUM_AUTHORITIES authorities, and writes the proof and
+/// its public inputs and outputs to args.output.
+fn prove_rotate<const NUM_AUTHORITIES: usize, const SUBARRAY_SIZE: usize>(args: &ProveRotateArgs) {
+    let source = DataSource::avail(args.avail_url.clone());
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let authority_set_hash = match args.authority_set_hash {
+        Some(authority_set_hash) => authority_set_hash,
+        None => rt.block_on(async {
+            let mut data_fetcher =
+                RpcDataFetcher::new_with_optional_url(source.avail_url.as_deref()).await;
+            data_fetcher.get_authority_set_hash(args.set_id).await
+        }),
+    };
+
+    // Run the rotate hint before building the circuit, so a wrong epoch end block fails fast. If
+    // the authority set has not ended, the proof shows that it has not ended, and the contract
+    // stores no new authority set.
+    let rotate = rt.block_on(RotateCircuit::<
+        NUM_AUTHORITIES,
+        MAX_HEADER_SIZE,
+        SUBARRAY_SIZE,
+    >::debug_inputs::<GoldilocksField>(args.set_id, &source));
+    if !rotate.is_epoch_end {
+        info!(
+            "Authority set {} has not ended, proving it has not ended at block {}",
+            args.set_id, rotate.epoch_end_block_number
+        );
+    }
+    if let Some(block) = args.block {
+        assert_eq!(
+            rotate.epoch_end_block_number, block,
+            "Epoch end block of authority set {} is {}, expected {}",
+            args.set_id, rotate.epoch_end_block_number, block
+        );
+    }
+
+    info!(
+        "Building rotate circuit with a max authority set size of {}",
+        NUM_AUTHORITIES
+    );
+    let mut builder = DefaultBuilder::new();
+    RotateCircuit::<NUM_AUTHORITIES, MAX_HEADER_SIZE, SUBARRAY_SIZE>::define_with_source(
+        &mut builder,
+        source,
+    );
+    let circuit = builder.build();
+
+    let mut input = circuit.input();
+    input.evm_write::<U64Variable>(args.set_id);
+    input.evm_write::<Bytes32Variable>(authority_set_hash);
+
+    info!(
+        "Generating rotate proof for authority set {} with hash {:?}",
+        args.set_id, authority_set_hash
+    );
+    let start = Instant::now();
+    let label = format!("rotate-{}", args.set_id);
+    let (proof, mut output) = prove_with_witness_dump(&circuit, &input, &label);
+    circuit.verify(&proof, &input, &output);
+    info!("Generated and verified proof in {:?}", start.elapsed());
+
+    let new_authority_set_hash = output.evm_read::<Bytes32Variable>();
+    let next_authority_set_id = output.evm_read::<U64Variable>();
+    let num_new_authorities = output.evm_read::<U32Variable>();
+    let activation_block_number = output.evm_read::<U32Variable>();
+    let num_signed = output.evm_read::<U32Variable>();
+    let is_epoch_end = output.evm_read::<BoolVariable>();
+
+    // The input and output bytes are packed as in the contract, see RotateCircuit::io_schema.
+    let input_bytes =
+        RotateCircuit::<NUM_AUTHORITIES, MAX_HEADER_SIZE, SUBARRAY_SIZE>::encode_inputs(
+            args.set_id,
+            authority_set_hash,
+        );
+    let output_bytes =
+        RotateCircuit::<NUM_AUTHORITIES, MAX_HEADER_SIZE, SUBARRAY_SIZE>::encode_outputs(
+            new_authority_set_hash,
+            next_authority_set_id,
+            num_new_authorities,
+            activation_block_number,
+            num_signed,
+            is_epoch_end,
+        );
+
+    let proof_json = json!({
+        "schema": RotateCircuit::<NUM_AUTHORITIES, MAX_HEADER_SIZE, SUBARRAY_SIZE>::io_schema(),
+        "epoch_end_block": rotate.epoch_end_block_number,
+        "inputs": {
+            "authority_set_id": args.set_id,
+            "authority_set_hash": authority_set_hash,
+        },
+        "outputs": {
+            "new_authority_set_hash": new_authority_set_hash,
+            "next_authority_set_id": next_authority_set_id,
+            "num_new_authorities": num_new_authorities,
+            "activation_block_number": activation_block_number,
+            "num_signed": num_signed,
+            "is_epoch_end": is_epoch_end,
+        },
+        "input_bytes": format!("0x{}", hex::encode(input_bytes)),
+        "output_bytes": format!("0x{}", hex::encode(output_bytes)),
+        "proof": format!("0x{}", hex::encode(proof.to_bytes())),
+    });
+    fs::write(
+        &args.output,
+        serde_json::to_string_pretty(&proof_json).unwrap(),
+    )
+    .unwrap_or_else(|e| panic!("Failed to write proof to {:?}: {}", args.output, e));
+    info!("Wrote rotate proof to {:?}", args.output);
+}
+
+fn main() {
+    env::set_var("RUST_LOG", "info");
+    dotenv::dotenv().ok();
+    env_logger::init();
+
+    let args = ProveRotateArgs::parse();
+
+    // The circuit size is a const generic, so only these sizes can be proven. Smaller circuits are
+    // faster to prove for test networks with few authorities.
+    const MAX_SUBARRAY_SIZE_4: usize = 4 * VALIDATOR_LENGTH + DELAY_LENGTH;
+    const MAX_SUBARRAY_SIZE_16: usize = 16 * VALIDATOR_LENGTH + DELAY_LENGTH;
+    const MAX_SUBARRAY_SIZE_100: usize = 100 * VALIDATOR_LENGTH + DELAY_LENGTH;
+    match args.max_authority_set_size {
+        4 => prove_rotate::<4, MAX_SUBARRAY_SIZE_4>(&args),
+        16 => prove_rotate::<16, MAX_SUBARRAY_SIZE_16>(&args),
+        100 => prove_rotate::<100, MAX_SUBARRAY_SIZE_100>(&args),
+        MAX_AUTHORITY_SET_SIZE => prove_rotate::<MAX_AUTHORITY_SET_SIZE, MAX_SUBARRAY_SIZE>(&args),
+        size => panic!(
+            "Unsupported max authority set size {}, expected one of 4, 16, 100 or {}",
+            size, MAX_AUTHORITY_SET_SIZE
+        ),
+    }
+}
@@ -0,0 +1,48 @@
+//! To build the binary:
+//!
+//!     `cargo build --release --bin resource_report`
+//!
+//! Reports the estimated peak memory and proving time of each circuit, to size proving machines.
+//! Components of the circuits are reported in a second table, to track their share of the gates.
+//!
+//!
+//!
+
+use std::env;
+
+use log::info;
+use vectorx::resource_report::{
+    component_configs, estimate_resources, format_report, registered_configs,
+    DEFAULT_PROVING_NS_PER_GATE,
+};
+
+fn main() {
+    env::set_var("RUST_LOG", "info");
+    dotenv::dotenv().ok();
+    env_logger::init();
+
+    let proving_ns_per_gate = env::var("PROVING_NS_PER_GATE")
+        .ok()
+        .filter(|ns| !ns.is_empty())
+        .map(|ns| {
+            ns.parse::<u64>()
+                .expect("PROVING_NS_PER_GATE must be a number of nanoseconds")
+        })
+        .unwrap_or(DEFAULT_PROVING_NS_PER_GATE);
+
+    let mut estimates = Vec::new();
+    for (name, stats) in registered_configs() {
+        info!("Building {} circuit", name);
+        estimates.push(estimate_resources(&stats(), proving_ns_per_gate));
+    }
+
+    println!("{}", format_report(&estimates));
+
+    let mut component_estimates = Vec::new();
+    for (name, stats) in component_configs() {
+        info!("Building {} component", name);
+        component_estimates.push(estimate_resources(&stats(), proving_ns_per_gate));
+    }
+
+    println!("{}", format_report(&component_estimates));
+}
@@ -390,6 +390,14 @@ impl VectorXOperator {
             // Get latest block of contract.
             let contract_latest_block_nb = self.contract.latest_block().await.unwrap();
 
+            // Justifications for blocks up to the latest block of the contract have been consumed,
+            // so the indexer can prune them.
+            let avail_chain_id = self.data_fetcher.avail_chain_id.clone();
+            self.data_fetcher
+                .redis_client
+                .set_prune_watermark(&avail_chain_id, contract_latest_block_nb)
+                .await;
+
             // block_to_request is the closest interval of block_interval less than min(avail_chain_latest_block_nb, data_commitment_max + current_block)
             let max_block = std::cmp::min(
                 avail_chain_latest_block_nb,
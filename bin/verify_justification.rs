@@ -0,0 +1,174 @@
+//! To build the binary:
+//!
+//!     `cargo build --release --bin verify_justification --features mock`
+//!
+//! Replays the justification stored in Redis for a block through `verify_simple_justification`, to
+//! debug a failed proof.
+//!
+//!
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::{env, fs};
+
+use clap::Parser;
+use ethers::types::H256;
+use log::{error, info};
+use plonky2x::frontend::uint::uint64::U64Variable;
+use plonky2x::prelude::{BoolVariable, Bytes32Variable, DefaultBuilder, U32Variable, Variable};
+use vectorx::builder::justification::{
+    check_circuit_justification, GrandpaJustificationVerifier, VerificationOptions,
+};
+use vectorx::consts::{ENCODED_PRECOMMIT_LENGTH, MAX_AUTHORITY_SET_SIZE};
+use vectorx::input::mock::{Fixtures, JustificationFixture};
+use vectorx::input::types::{DataSource, SimpleJustificationData};
+use vectorx::input::{
+    check_simple_justification, circuit_justification, compute_weighted_authority_set_hash,
+    decode_precommit, is_voting_threshold_met, verify_justification_signatures, RedisClient,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(about = "Replay a justification stored in Redis through the justification circuit.")]
+pub struct VerifyJustificationArgs {
+    #[arg(long)]
+    pub block: u32,
+}
+
+/// Returns the message of a panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+fn main() {
+    env::set_var("RUST_LOG", "info");
+    dotenv::dotenv().ok();
+    env_logger::init();
+
+    let args = VerifyJustificationArgs::parse();
+    let block_number = args.block;
+    let avail_chain_id = env::var("AVAIL_CHAIN_ID").expect("AVAIL_CHAIN_ID must be set");
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let stored = rt
+        .block_on(async {
+            let mut redis_client = RedisClient::new().await;
+            redis_client
+                .get_justification(&avail_chain_id, block_number)
+                .await
+        })
+        .unwrap_or_else(|e| panic!("No justification stored for block {}: {}", block_number, e));
+
+    if stored.signed_message.len() != ENCODED_PRECOMMIT_LENGTH {
+        error!(
+            "Precommit is {} bytes, expected {}",
+            stored.signed_message.len(),
+            ENCODED_PRECOMMIT_LENGTH
+        );
+        return;
+    }
+    let (block_hash, precommit_block_number, round, authority_set_id) =
+        decode_precommit(stored.signed_message.clone());
+    info!(
+        "Precommit: block {} with hash {:?}, round {}, authority set {}",
+        precommit_block_number, block_hash, round, authority_set_id
+    );
+
+    info!(
+        "Signed count: {} of {} authorities",
+        stored.num_signatures(),
+        stored.num_authorities
+    );
+    match verify_justification_signatures(&stored) {
+        Ok(num_valid) => info!("Signatures: {} valid", num_valid),
+        Err(e) => error!("Signatures: {}", e),
+    }
+
+    // The authority set id is taken from the precommit, as the stored justification does not record
+    // the authority set that signed it.
+    let data = SimpleJustificationData::from_stored(stored.clone(), authority_set_id);
+    let total_weight: u64 = data.weights.iter().sum();
+    info!(
+        "Threshold: voting weight {} of {}, met: {}",
+        data.voting_weight,
+        total_weight,
+        is_voting_threshold_met(data.voting_weight, total_weight)
+    );
+    let authority_set_hash = H256::from_slice(&compute_weighted_authority_set_hash(
+        &data.pubkeys,
+        &data.weights,
+    ));
+    info!("Authority set commitment: {:?}", authority_set_hash);
+
+    match check_simple_justification::<MAX_AUTHORITY_SET_SIZE>(block_number, data, authority_set_id)
+    {
+        Ok(data) => {
+            let justification = circuit_justification::<MAX_AUTHORITY_SET_SIZE>(data);
+            if let Err(e) = check_circuit_justification(&justification, authority_set_id) {
+                error!("Justification check failed: {:?}", e);
+            }
+        }
+        Err(e) => error!("Justification check failed: {}", e),
+    }
+
+    // Serve the stored justification to the justification hint as a fixture.
+    let fixtures = Fixtures {
+        justifications: HashMap::from([(
+            block_number,
+            JustificationFixture {
+                authority_set_id,
+                justification: stored,
+            },
+        )]),
+        ..Default::default()
+    };
+    let fixtures_path = env::temp_dir().join(format!("verify_justification_{}.json", block_number));
+    fs::write(&fixtures_path, serde_json::to_string(&fixtures).unwrap())
+        .expect("Failed to write fixtures");
+
+    info!("Building circuit");
+    let mut builder = DefaultBuilder::new();
+    let block_number_var = builder.read::<U32Variable>();
+    let block_hash_var = builder.read::<Bytes32Variable>();
+    let authority_set_id_var = builder.read::<U64Variable>();
+    let authority_set_hash_var = builder.read::<Bytes32Variable>();
+    let verified_justification = builder
+        .verify_simple_justification_from::<MAX_AUTHORITY_SET_SIZE>(
+            block_number_var,
+            block_hash_var,
+            authority_set_id_var,
+            authority_set_hash_var,
+            VerificationOptions::default(),
+            DataSource::fixtures(fixtures_path.to_str().unwrap()),
+        );
+    builder.write(verified_justification.success);
+    builder.write(verified_justification.num_signed);
+    builder.write(verified_justification.round);
+    let circuit = builder.build();
+
+    let mut input = circuit.input();
+    input.write::<U32Variable>(block_number);
+    input.write::<Bytes32Variable>(block_hash);
+    input.write::<U64Variable>(authority_set_id);
+    input.write::<Bytes32Variable>(authority_set_hash);
+
+    info!("Generating proof");
+    let result = panic::catch_unwind(AssertUnwindSafe(|| circuit.prove(&input)));
+    fs::remove_file(&fixtures_path).ok();
+    match result {
+        Ok((proof, mut output)) => {
+            circuit.verify(&proof, &input, &output);
+            let success = output.read::<BoolVariable>();
+            let num_signed = output.read::<Variable>();
+            let round = output.read::<U64Variable>();
+            info!(
+                "Circuit: success {}, signed count {}, round {}",
+                success, num_signed, round
+            );
+        }
+        Err(e) => error!("Circuit assertion failed: {}", panic_message(e.as_ref())),
+    }
+}
@@ -0,0 +1,298 @@
+use ethers::types::H256;
+use plonky2x::backend::circuit::Circuit;
+use plonky2x::frontend::uint::uint64::U64Variable;
+use plonky2x::prelude::{
+    Bytes32Variable, CircuitBuilder, CircuitVariable, PlonkParameters, RichField, U32Variable,
+    VariableStream,
+};
+use serde_json::Value;
+
+use crate::builder::justification::HintSimpleJustification;
+use crate::builder::rotate::RotateMethods;
+use crate::input::types::DataSource;
+use crate::io_schema::{encode_packed, io_schema, IoField, IoValue};
+use crate::rotate::{fetch_rotate_struct, RotateHint};
+use crate::vars::{RotateStruct, RotateVariable};
+
+/// Proves NUM_ROTATIONS consecutive rotations in one circuit, starting from the authority set at
+/// authority_set_id. Each rotation is proven as in `RotateCircuit`, and the authority set hash
+/// committed to by each rotation is the trusted authority set hash of the next, so the output is the
+/// authority set after the last rotation.
+#[derive(Clone, Debug)]
+pub struct BatchRotateCircuit<
+    const NUM_ROTATIONS: usize,
+    const MAX_AUTHORITY_SET_SIZE: usize,
+    const MAX_HEADER_SIZE: usize,
+    const MAX_SUBARRAY_SIZE: usize,
+> {}
+
+impl<
+        const NUM_ROTATIONS: usize,
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_HEADER_SIZE: usize,
+        const MAX_SUBARRAY_SIZE: usize,
+    >
+    BatchRotateCircuit<NUM_ROTATIONS, MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>
+{
+    /// Public inputs, in the order they are read in `define`.
+    pub const INPUTS: [IoField; 2] = [
+        IoField::new("authority_set_id", "uint64", 8),
+        IoField::new("authority_set_hash", "bytes32", 32),
+    ];
+
+    /// Public outputs, in the order they are written in `define`.
+    pub const OUTPUTS: [IoField; 4] = [
+        IoField::new("new_authority_set_hash", "bytes32", 32),
+        IoField::new("next_authority_set_id", "uint64", 8),
+        IoField::new("num_new_authorities", "uint32", 4),
+        IoField::new("activation_block_number", "uint32", 4),
+    ];
+
+    /// Schema of the public inputs and outputs. Must be kept in sync with `define`.
+    pub fn io_schema() -> Value {
+        io_schema("batch_rotate", &Self::INPUTS, &Self::OUTPUTS)
+    }
+
+    /// ABI encode packed public inputs, as passed by the contract.
+    pub fn encode_inputs(authority_set_id: u64, authority_set_hash: H256) -> Vec<u8> {
+        encode_packed(
+            &Self::INPUTS,
+            &[
+                IoValue::Uint64(authority_set_id),
+                IoValue::Bytes32(authority_set_hash),
+            ],
+        )
+    }
+
+    /// ABI encode packed public outputs, as decoded by the contract.
+    pub fn encode_outputs(
+        new_authority_set_hash: H256,
+        next_authority_set_id: u64,
+        num_new_authorities: u32,
+        activation_block_number: u32,
+    ) -> Vec<u8> {
+        encode_packed(
+            &Self::OUTPUTS,
+            &[
+                IoValue::Bytes32(new_authority_set_hash),
+                IoValue::Uint64(next_authority_set_id),
+                IoValue::Uint32(num_new_authorities),
+                IoValue::Uint32(activation_block_number),
+            ],
+        )
+    }
+
+    /// Defines the circuit as in `define`, with the hints fetching the rotate data and the
+    /// justifications from source instead of the Avail node at AVAIL_URL.
+    pub fn define_with_source<L: PlonkParameters<D>, const D: usize>(
+        builder: &mut CircuitBuilder<L, D>,
+        source: DataSource,
+    ) where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+    {
+        assert!(NUM_ROTATIONS > 0, "NUM_ROTATIONS must be positive");
+
+        // Read the on-chain inputs. The validators that signed the first epoch end block are defined
+        // by authority_set_id and authority_set_hash.
+        let mut authority_set_id = builder.evm_read::<U64Variable>();
+        let mut authority_set_hash = builder.evm_read::<Bytes32Variable>();
+
+        let true_v = builder._true();
+        let one = builder.constant::<U64Variable>(1u64);
+        let mut num_new_authorities = builder.zero::<U32Variable>();
+        let mut activation_block_number = builder.zero::<U32Variable>();
+        for _ in 0..NUM_ROTATIONS {
+            // Fetch the data for the rotate of authority_set_id. The hint finds the epoch end block
+            // of authority_set_id, as in `RotateCircuit`.
+            let rotate_fetcher = RotateHint::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE> {
+                source: source.clone(),
+            };
+            let mut input_stream = VariableStream::new();
+            input_stream.write(&authority_set_id);
+            let output_stream = builder.async_hint(input_stream, rotate_fetcher);
+
+            // rotate_var is untrusted and needs to be linked to authority_set_id and
+            // authority_set_hash.
+            let rotate_var = output_stream
+                .read::<RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>>(builder);
+
+            num_new_authorities =
+                U32Variable::from_variables_unsafe(&[rotate_var.target_header_num_authorities]);
+            activation_block_number = builder.activation_block_number(&rotate_var);
+
+            // The new authority set hash is the trusted authority set hash of the next rotation, and
+            // is the authority set at authority_set_id + 1.
+            let (new_authority_set_hash, _, is_epoch_end) = builder
+                .rotate_from::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, MAX_SUBARRAY_SIZE>(
+                    authority_set_id,
+                    authority_set_hash,
+                    rotate_var,
+                    source.clone(),
+                );
+            // Every authority set in the batch must have ended, as the next rotation is signed by
+            // the new authority set.
+            builder.assert_is_equal(is_epoch_end, true_v);
+            authority_set_hash = new_authority_set_hash;
+            authority_set_id = builder.add(authority_set_id, one);
+        }
+
+        // Write the outputs. The order of the outputs is (new_authority_set_hash, next_authority_set_id,
+        // num_new_authorities, activation_block_number), which must match the decoding of the output
+        // in the contract and OUTPUTS. num_new_authorities and activation_block_number are of the
+        // authority set after the last rotation.
+        builder.evm_write::<Bytes32Variable>(authority_set_hash);
+        builder.evm_write::<U64Variable>(authority_set_id);
+        builder.evm_write::<U32Variable>(num_new_authorities);
+        builder.evm_write::<U32Variable>(activation_block_number);
+    }
+
+    /// Runs the rotate hint of each rotation from authority_set_id without building or proving the
+    /// circuit, and returns the rotate data of each rotation in order. The epoch end block of each
+    /// rotation is the epoch_end_block_number of its rotate data.
+    pub async fn debug_inputs<F: RichField>(
+        authority_set_id: u64,
+        source: &DataSource,
+    ) -> Vec<RotateStruct<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, F>> {
+        let mut rotates = Vec::with_capacity(NUM_ROTATIONS);
+        for i in 0..NUM_ROTATIONS as u64 {
+            rotates.push(
+                fetch_rotate_struct::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, F>(
+                    source,
+                    authority_set_id + i,
+                )
+                .await,
+            );
+        }
+        rotates
+    }
+}
+
+impl<
+        const NUM_ROTATIONS: usize,
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_HEADER_SIZE: usize,
+        const MAX_SUBARRAY_SIZE: usize,
+    > Circuit
+    for BatchRotateCircuit<
+        NUM_ROTATIONS,
+        MAX_AUTHORITY_SET_SIZE,
+        MAX_HEADER_SIZE,
+        MAX_SUBARRAY_SIZE,
+    >
+{
+    fn define<L: PlonkParameters<D>, const D: usize>(builder: &mut CircuitBuilder<L, D>)
+    where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+    {
+        Self::define_with_source(builder, DataSource::default());
+    }
+
+    fn register_generators<L: PlonkParameters<D>, const D: usize>(
+        generator_registry: &mut plonky2x::prelude::HintRegistry<L, D>,
+    ) where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+    {
+        generator_registry
+            .register_async_hint::<RotateHint<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>>();
+        generator_registry.register_async_hint::<HintSimpleJustification<MAX_AUTHORITY_SET_SIZE>>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use plonky2x::prelude::{DefaultBuilder, EvmVariable, GoldilocksField};
+
+    use super::*;
+    use crate::consts::{DELAY_LENGTH, MAX_HEADER_SIZE, VALIDATOR_LENGTH};
+    use crate::input::RpcDataFetcher;
+
+    #[test]
+    fn test_batch_rotate_io_schema() {
+        const NUM_AUTHORITIES: usize = 4;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+        type BatchRotate =
+            BatchRotateCircuit<2, NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>;
+
+        let schema = BatchRotate::io_schema();
+        assert_eq!(schema["circuit"], "batch_rotate");
+        assert_eq!(schema["inputs_byte_length"], 40);
+        assert_eq!(schema["outputs_byte_length"], 48);
+        assert_eq!(schema["outputs"][1]["name"], "next_authority_set_id");
+        assert_eq!(schema["outputs"][2]["offset"], 40);
+
+        // The output bytes must match the encoding of the variables in the order they are written in
+        // define.
+        let new_authority_set_hash = H256::repeat_byte(0xab);
+        let mut evm_output_bytes =
+            Bytes32Variable::encode_value::<GoldilocksField>(new_authority_set_hash);
+        evm_output_bytes.extend(U64Variable::encode_value::<GoldilocksField>(2));
+        evm_output_bytes.extend(U32Variable::encode_value::<GoldilocksField>(4));
+        evm_output_bytes.extend(U32Variable::encode_value::<GoldilocksField>(4410));
+        assert_eq!(
+            BatchRotate::encode_outputs(new_authority_set_hash, 2, 4, 4410),
+            evm_output_bytes
+        );
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_batch_rotate_two_epochs() {
+        env::set_var("RUST_LOG", "debug");
+        dotenv::dotenv().ok();
+        env_logger::try_init().unwrap_or_default();
+
+        const NUM_ROTATIONS: usize = 2;
+        const NUM_AUTHORITIES: usize = 8;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+        type BatchRotate =
+            BatchRotateCircuit<NUM_ROTATIONS, NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>;
+
+        // Rotate from authority set 0 through the epoch end blocks of authority sets 0 and 1.
+        let authority_set_id = 0u64;
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (authority_set_hash, expected_authority_set_hash, rotates) = rt.block_on(async {
+            let mut data_fetcher = RpcDataFetcher::new().await;
+            (
+                data_fetcher.get_authority_set_hash(authority_set_id).await,
+                data_fetcher
+                    .get_authority_set_hash(authority_set_id + NUM_ROTATIONS as u64)
+                    .await,
+                BatchRotate::debug_inputs::<GoldilocksField>(
+                    authority_set_id,
+                    &DataSource::default(),
+                )
+                .await,
+            )
+        });
+        assert!(rotates
+            .windows(2)
+            .all(|rotates| rotates[0].epoch_end_block_number < rotates[1].epoch_end_block_number));
+
+        let mut builder = DefaultBuilder::new();
+        BatchRotate::define(&mut builder);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.evm_write::<U64Variable>(authority_set_id);
+        input.evm_write::<Bytes32Variable>(authority_set_hash);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        let new_authority_set_hash = output.evm_read::<Bytes32Variable>();
+        let next_authority_set_id = output.evm_read::<U64Variable>();
+        let num_new_authorities = output.evm_read::<U32Variable>();
+        let activation_block_number = output.evm_read::<U32Variable>();
+        assert_eq!(new_authority_set_hash, expected_authority_set_hash);
+        assert!(activation_block_number >= rotates[NUM_ROTATIONS - 1].epoch_end_block_number);
+        assert_eq!(
+            next_authority_set_id,
+            authority_set_id + NUM_ROTATIONS as u64
+        );
+        assert!(num_new_authorities > 0 && num_new_authorities as usize <= NUM_AUTHORITIES);
+    }
+}
@@ -1,20 +1,31 @@
 use plonky2x::frontend::vars::{EvmVariable, U32Variable};
 use plonky2x::prelude::{
-    ArrayVariable, ByteVariable, Bytes32Variable, BytesVariable, CircuitBuilder, CircuitVariable,
-    Field, PlonkParameters, U64Variable, Variable,
+    ByteVariable, Bytes32Variable, BytesVariable, CircuitBuilder, CircuitVariable, Field,
+    PlonkParameters, U64Variable, Variable,
 };
 
+use super::header::{decode_block_number, select_state_root, HeaderMethods};
 use crate::consts::{
-    DATA_ROOT_OFFSET_FROM_END, ENCODED_PRECOMMIT_LENGTH, HASH_SIZE, MAX_COMPACT_UINT_BYTES,
+    ENCODED_PRECOMMIT_LENGTH, HASH_SIZE, MAX_COMPACT_U64_BYTES, MAX_COMPACT_UINT_BYTES,
 };
 use crate::vars::*;
 
 pub trait DecodingMethods {
-    /// Decode the byte representation of a compact u32 into it's integer representation and SCALE
-    /// compress mode. Spec: https://docs.substrate.io/reference/scale-codec/#fn-1
-    fn decode_compact_int(
+    /// Decode the compact u64 starting at offset in bytes into its integer representation and the
+    /// number of bytes it is encoded in. Handles all four SCALE compress modes, with a "BigInt" of
+    /// at most 8 bytes. Spec: https://docs.substrate.io/reference/scale-codec/#fn-1
+    fn decode_compact_int<const N: usize>(
         &mut self,
-        compact_bytes: ArrayVariable<ByteVariable, 5>,
+        bytes: &BytesVariable<N>,
+        offset: Variable,
+    ) -> (U64Variable, Variable);
+
+    /// Decode the compact u32 starting at offset in bytes, as in `decode_compact_int`, and the
+    /// number of bytes it is encoded in, which is at most MAX_COMPACT_UINT_BYTES.
+    fn decode_compact_u32<const N: usize>(
+        &mut self,
+        bytes: &BytesVariable<N>,
+        offset: Variable,
     ) -> (U32Variable, Variable);
 
     /// Decode a header into its components: {block_nb, parent_hash, state_root and data_root}.
@@ -33,13 +44,30 @@ pub trait DecodingMethods {
 }
 
 impl<L: PlonkParameters<D>, const D: usize> DecodingMethods for CircuitBuilder<L, D> {
-    fn decode_compact_int(
+    fn decode_compact_int<const N: usize>(
         &mut self,
-        compact_bytes: ArrayVariable<ByteVariable, MAX_COMPACT_UINT_BYTES>,
-    ) -> (U32Variable, Variable) {
+        bytes: &BytesVariable<N>,
+        offset: Variable,
+    ) -> (U64Variable, Variable) {
+        let true_v = self._true();
+        let max_offset = self.constant::<Variable>(L::Field::from_canonical_usize(N));
+        let is_valid_offset = self.lt(offset, max_offset);
+        self.assert_is_equal(is_valid_offset, true_v);
+
+        // Read the MAX_COMPACT_U64_BYTES bytes at offset. The bytes are padded with zeros, so a
+        // compact int at the end of bytes can be read.
+        let zero_byte = self.constant::<ByteVariable>(0u8);
+        let mut padded_bytes = bytes.0.to_vec();
+        padded_bytes.resize(N + MAX_COMPACT_U64_BYTES, zero_byte);
+        let mut compact_bytes = Vec::with_capacity(MAX_COMPACT_U64_BYTES);
+        for i in 0..MAX_COMPACT_U64_BYTES {
+            let i = self.constant::<Variable>(L::Field::from_canonical_usize(i));
+            let idx = self.add(offset, i);
+            compact_bytes.push(self.select_array_random_gate(&padded_bytes, idx));
+        }
+
         // Flip the bit order within each byte to LE bits and flatten the array.
         let bool_targets = compact_bytes
-            .data
             .iter()
             .flat_map(|x| {
                 let mut bool_targets = x.as_bool_targets();
@@ -47,45 +75,94 @@ impl<L: PlonkParameters<D>, const D: usize> DecodingMethods for CircuitBuilder<L
                 bool_targets
             })
             .collect::<Vec<_>>();
+        let bits = compact_bytes
+            .iter()
+            .flat_map(|x| {
+                let mut bits = x.0;
+                bits.reverse();
+                bits
+            })
+            .collect::<Vec<_>>();
 
         // Get the compress mode which is the first two bits. {00, 01, 10, 11} -> Mode {0, 1, 2, 3}.
         let compress_mode = Variable(self.api.le_sum(bool_targets[0..2].iter()));
 
-        // Get all of the possible bytes that could be used to represent the compact int.
-        // Spec for compact scale encoding: https://docs.substrate.io/reference/scale-codec/#fn-1.
-        // Specifically, extract the LE bits of each potential value as follows:
+        // In mode 3, the upper 6 bits are the number of bytes of the "BigInt" - 4. A u64 has at most
+        // 8 bytes, so this is at most 4.
+        let three = self.constant(L::Field::from_canonical_u8(3));
+        let is_mode_three = self.is_equal(compress_mode, three);
+        let not_mode_three = self.not(is_mode_three);
+        let big_int_length = Variable(self.api.le_sum(bool_targets[2..8].iter()));
+        let four = self.constant(L::Field::from_canonical_u8(4));
+        let is_valid_big_int_length = self.lte(big_int_length, four);
+        let big_int_length_check = self.or(is_valid_big_int_length, not_mode_three);
+        self.assert_is_equal(big_int_length_check, true_v);
+
+        // The LE bits of each potential value, and the number of bytes it is encoded in:
         //  Mode 0: Upper 6 bits are the value.
         //  Mode 1: Upper 6 bits + next byte are the value.
         //  Mode 2: Upper 6 bits + next 3 bytes are the value.
-        //  Mode 3: Upper 6 bits are the length and next 4 bytes are the value.
-        let zero_mode_value = Variable(self.api.le_sum(bool_targets[2..8].iter()));
-        let one_mode_value = Variable(self.api.le_sum(bool_targets[2..16].iter()));
-        let two_mode_value = Variable(self.api.le_sum(bool_targets[2..32].iter()));
-        let three_mode_value = Variable(self.api.le_sum(bool_targets[8..40].iter()));
-
-        // Select the correct int value based on compress mode.
-        let value = self.select_array_random_gate(
-            &[
-                zero_mode_value,
-                one_mode_value,
-                two_mode_value,
-                three_mode_value,
-            ],
-            compress_mode,
-        );
-        let value = U32Variable::from_variables_unsafe(&[value]);
+        //  Mode 3: Upper 6 bits are the length and the next 4 to 8 bytes are the value.
+        let encodings = [
+            (2, 8, 1),
+            (2, 16, 2),
+            (2, 32, 4),
+            (8, 40, 5),
+            (8, 48, 6),
+            (8, 56, 7),
+            (8, 64, 8),
+            (8, 72, 9),
+        ];
+        let false_v = self._false();
+        let mut values = Vec::with_capacity(encodings.len());
+        let mut byte_lengths = Vec::with_capacity(encodings.len());
+        for (start, end, byte_length) in encodings {
+            let mut value_bits = bits[start..end].to_vec();
+            value_bits.resize(64, false_v);
+            // Group the LE bits into BE bytes, so CircuitVariable decoding (which expects BE) works
+            // correctly.
+            let mut value_bytes = value_bits
+                .chunks(8)
+                .map(|le_bits| {
+                    let mut be_bits = le_bits.to_vec();
+                    be_bits.reverse();
+                    ByteVariable(be_bits.try_into().unwrap())
+                })
+                .collect::<Vec<_>>();
+            value_bytes.reverse();
+            values.push(U64Variable::decode(self, &value_bytes));
+            byte_lengths
+                .push(self.constant::<Variable>(L::Field::from_canonical_usize(byte_length)));
+        }
 
-        // If mode is 3, check the upper 6 bits are 0. These bits represent the number of bytes for
-        // the "BigInt" - 4. Since block number is a u32 (4 bytes), this will always be 0.
-        let three = self.constant(L::Field::from_canonical_u8(3));
-        let is_mode_three = self.is_equal(compress_mode, three);
-        let zero = self.constant(L::Field::from_canonical_u8(0));
-        let encoded_byte_length = Variable(self.api.le_sum(bool_targets[2..8].iter()));
-        let is_encoded_length_zero = self.is_equal(encoded_byte_length, zero);
-        let is_valid_encoded_length_check = self.and(is_mode_three, is_encoded_length_zero);
-        self.assert_is_equal(is_mode_three, is_valid_encoded_length_check);
+        // Select the encoding: the compress mode for modes 0 to 2, and 3 + the "BigInt" length
+        // for mode 3.
+        let zero = self.zero::<Variable>();
+        let big_int_offset = self.select(is_mode_three, big_int_length, zero);
+        let encoding_idx = self.add(compress_mode, big_int_offset);
+        let value = self.select_array_random_gate(&values, encoding_idx);
+        let byte_length = self.select_array_random_gate(&byte_lengths, encoding_idx);
+
+        (value, byte_length)
+    }
 
-        (value, compress_mode)
+    fn decode_compact_u32<const N: usize>(
+        &mut self,
+        bytes: &BytesVariable<N>,
+        offset: Variable,
+    ) -> (U32Variable, Variable) {
+        let (value, byte_length) = self.decode_compact_int(bytes, offset);
+
+        // A compact u32 is at most MAX_COMPACT_UINT_BYTES bytes, a "BigInt" of exactly 4 bytes in
+        // mode 3, so the upper 4 bytes of the value are 0.
+        let true_v = self._true();
+        let max_byte_length =
+            self.constant::<Variable>(L::Field::from_canonical_usize(MAX_COMPACT_UINT_BYTES));
+        let is_valid_byte_length = self.lte(byte_length, max_byte_length);
+        self.assert_is_equal(is_valid_byte_length, true_v);
+
+        let value_bytes = value.encode(self);
+        (U32Variable::decode(self, &value_bytes[4..8]), byte_length)
     }
 
     fn decode_header<const S: usize>(
@@ -99,41 +176,14 @@ impl<L: PlonkParameters<D>, const D: usize> DecodingMethods for CircuitBuilder<L
         let parent_hash: Bytes32Variable = header.header_bytes[0..HASH_SIZE].into();
 
         // Next field is the block number in compact u32 SCALE encoding.
-        let block_number_bytes = ArrayVariable::<ByteVariable, MAX_COMPACT_UINT_BYTES>::from(
-            header.header_bytes[HASH_SIZE..HASH_SIZE + MAX_COMPACT_UINT_BYTES].to_vec(),
-        );
-        let (block_number, compress_mode) = self.decode_compact_int(block_number_bytes);
-
-        // The of block_number is 1, 2, 4, or 5 bytes depending on the encoding of the compact int.
-        let all_possible_state_roots = vec![
-            Bytes32Variable::from(&header.header_bytes[33..33 + HASH_SIZE]),
-            Bytes32Variable::from(&header.header_bytes[34..34 + HASH_SIZE]),
-            Bytes32Variable::from(&header.header_bytes[36..36 + HASH_SIZE]),
-            Bytes32Variable::from(&header.header_bytes[37..37 + HASH_SIZE]),
-        ];
+        let (block_number, block_number_length) = decode_block_number(self, header);
+
+        // The block_number is 1, 2, 4, or 5 bytes depending on the encoding of the compact int, and
+        // is followed by the state root.
+        let state_root = select_state_root(self, header, block_number_length);
 
-        let state_root = self.select_array_random_gate(&all_possible_state_roots, compress_mode);
-
-        // The next field is the data root. The data root is the last 32 bytes of the header.
-        // Spec: https://github.com/availproject/avail-core/blob/main/core/src/header/extension/v3.rs#L9-L15
-        let data_root_offset = self.constant::<U32Variable>(DATA_ROOT_OFFSET_FROM_END as u32);
-        let mut data_root_start = self.sub(header.header_size, data_root_offset);
-
-        // If header_size == 0, then set data_root_start to 0.
-        let header_is_zero_size = self.is_zero(header.header_size.variable);
-        let zero = self.zero();
-        data_root_start = self.select(header_is_zero_size, zero, data_root_start);
-
-        // Extract the data root from the header.
-        let data_root_bytes: Vec<ByteVariable> = self
-            .get_fixed_subarray::<S, HASH_SIZE>(
-                &header.header_bytes,
-                data_root_start.variable,
-                // Seed the RLC challenge with a commitment of the header (header_hash).
-                &header_hash.as_bytes(),
-            )
-            .as_vec();
-        let data_root = Bytes32Variable::from(data_root_bytes.as_slice());
+        // The next field is the data root.
+        let data_root = self.decode_data_root(header, header_hash);
 
         HeaderVariable {
             block_number,
@@ -193,58 +243,136 @@ pub mod tests {
 
     use avail_subxt::config::Header;
     use codec::{Compact, Encode};
+    use ethers::types::H256;
     use plonky2x::frontend::vars::U32Variable;
+    use plonky2x::prelude::plonky2::field::types::PrimeField64;
     use plonky2x::prelude::{
-        ArrayVariable, ByteVariable, Bytes32Variable, BytesVariable, DefaultBuilder, Field,
-        GoldilocksField, U64Variable, Variable,
+        ArrayVariable, Bytes32Variable, BytesVariable, DefaultBuilder, Field, GoldilocksField,
+        U64Variable, Variable,
     };
 
     use super::DecodingMethods;
-    use crate::consts::{ENCODED_PRECOMMIT_LENGTH, MAX_COMPACT_UINT_BYTES, MAX_HEADER_SIZE};
+    use crate::consts::{ENCODED_PRECOMMIT_LENGTH, MAX_COMPACT_U64_BYTES, MAX_HEADER_SIZE};
     use crate::input::RpcDataFetcher;
     use crate::vars::{EncodedHeader, EncodedHeaderVariable};
 
+    /// Proves decode_compact_int on bytes at offset, and returns the decoded value and the number of
+    /// bytes it is encoded in.
+    fn prove_decode_compact_int(bytes: [u8; 16], offset: usize) -> (u64, usize) {
+        let mut builder = DefaultBuilder::new();
+        let bytes_var = builder.read::<BytesVariable<16>>();
+        let offset_var = builder.read::<Variable>();
+        let (value, byte_length) = builder.decode_compact_int(&bytes_var, offset_var);
+        builder.write(value);
+        builder.write(byte_length);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<BytesVariable<16>>(bytes);
+        input.write::<Variable>(GoldilocksField::from_canonical_usize(offset));
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        let value = output.read::<U64Variable>();
+        let byte_length = output.read::<Variable>();
+        (value, byte_length.to_canonical_u64() as usize)
+    }
+
     #[test]
     fn test_decode_compact_int() {
         env::set_var("RUST_LOG", "debug");
         env_logger::try_init().unwrap_or_default();
 
-        type F = GoldilocksField;
+        // The boundaries of each compress mode, and "BigInt"s of 4 to 8 bytes.
+        let test_cases = [
+            0u64,
+            1,
+            63,
+            64,
+            16383,
+            16384,
+            (1 << 30) - 1,
+            1 << 30,
+            u32::MAX as u64,
+            1 << 32,
+            1 << 40,
+            1 << 48,
+            1 << 56,
+            u64::MAX,
+        ];
 
-        let mut builder = DefaultBuilder::new();
+        for value in test_cases {
+            let encoded = Compact(value).encode();
 
-        let compact_bytes = builder.read::<ArrayVariable<ByteVariable, MAX_COMPACT_UINT_BYTES>>();
+            // Preceded by 3 bytes that are not part of the compact int, and followed by 0xff bytes.
+            let mut bytes = [0xffu8; 16];
+            bytes[3..3 + encoded.len()].copy_from_slice(&encoded);
+            assert_eq!(prove_decode_compact_int(bytes, 3), (value, encoded.len()));
+        }
 
-        let (value, compress_mode) = builder.decode_compact_int(compact_bytes);
-        builder.write(value);
-        builder.write(compress_mode);
+        // A compact int in the last byte.
+        let mut bytes = [0xffu8; 16];
+        bytes[15] = Compact(42u64).encode()[0];
+        assert_eq!(prove_decode_compact_int(bytes, 15), (42, 1));
+    }
 
-        let circuit = builder.build();
+    #[test]
+    #[should_panic]
+    fn test_decode_compact_int_big_int_too_long() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
 
-        // Test cases are (compact int, compress mode).
-        let test_cases = [(1u32, 0), (64u32, 1), (16384u32, 2), (4294967295u32, 3)];
+        // Mode 3 with a "BigInt" of 9 bytes, which does not fit in a u64.
+        let mut bytes = [0u8; 16];
+        bytes[0] = 0b0001_0111;
+        prove_decode_compact_int(bytes, 0);
+    }
 
-        for i in 0..test_cases.len() {
-            let mut input = circuit.input();
+    /// Proves decode_compact_u32 on the compact u32 at the start of bytes, and returns the decoded
+    /// value and the number of bytes it is encoded in.
+    fn prove_decode_compact_u32(bytes: [u8; MAX_COMPACT_U64_BYTES]) -> (u32, usize) {
+        let mut builder = DefaultBuilder::new();
+        let bytes_var = builder.read::<BytesVariable<MAX_COMPACT_U64_BYTES>>();
+        let zero = builder.zero::<Variable>();
+        let (value, byte_length) = builder.decode_compact_u32(&bytes_var, zero);
+        builder.write(value);
+        builder.write(byte_length);
+        let circuit = builder.build();
 
-            // Use compact encoding to encode the block number.
-            let encoded_block_num = Compact(test_cases[i].0).encode();
+        let mut input = circuit.input();
+        input.write::<BytesVariable<MAX_COMPACT_U64_BYTES>>(bytes);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
 
-            // Extend encoding to MAX_COMPACT_UINT_BYTES.
-            let mut encoded_block_num = encoded_block_num.to_vec();
-            encoded_block_num.resize(MAX_COMPACT_UINT_BYTES, 0);
+        let value = output.read::<U32Variable>();
+        let byte_length = output.read::<Variable>();
+        (value, byte_length.to_canonical_u64() as usize)
+    }
 
-            input.write::<ArrayVariable<ByteVariable, MAX_COMPACT_UINT_BYTES>>(encoded_block_num);
+    #[test]
+    fn test_decode_compact_u32() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
 
-            let (proof, mut output) = circuit.prove(&input);
-            circuit.verify(&proof, &input, &output);
+        // The boundaries of each compress mode.
+        for value in [0u32, 63, 64, 16383, 16384, (1 << 30) - 1, 1 << 30, u32::MAX] {
+            let encoded = Compact(value).encode();
+            let mut bytes = [0u8; MAX_COMPACT_U64_BYTES];
+            bytes[..encoded.len()].copy_from_slice(&encoded);
+            assert_eq!(prove_decode_compact_u32(bytes), (value, encoded.len()));
+        }
+    }
 
-            let value = output.read::<U32Variable>();
-            let compress_mode = output.read::<Variable>();
+    #[test]
+    #[should_panic]
+    fn test_decode_compact_u32_big_int_too_long() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
 
-            assert_eq!(value, test_cases[i].0);
-            assert_eq!(compress_mode, F::from_canonical_usize(test_cases[i].1));
-        }
+        // Mode 3 with a "BigInt" of 5 bytes, which does not fit in a u32.
+        let mut bytes = [0u8; MAX_COMPACT_U64_BYTES];
+        bytes[..6].copy_from_slice(&Compact(1u64 << 32).encode());
+        prove_decode_compact_u32(bytes);
     }
 
     #[test]
@@ -382,4 +510,63 @@ pub mod tests {
         assert_eq!(block_number, expected_block_number);
         assert_eq!(authority_set_id, expected_authority_set_id);
     }
+
+    /// Prove that the fixture precommit for block 317857 (authority set 298) is bound to the given
+    /// block hash, block number and authority set id.
+    fn prove_precommit_binding(block_hash: H256, block_number: u32, authority_set_id: u64) {
+        let mut builder = DefaultBuilder::new();
+
+        let precommit = builder.read::<BytesVariable<ENCODED_PRECOMMIT_LENGTH>>();
+        let expected_block_hash = builder.read::<Bytes32Variable>();
+        let expected_block_number = builder.read::<U32Variable>();
+        let expected_authority_set_id = builder.read::<U64Variable>();
+
+        let decoded_precommit = builder.decode_precommit(precommit);
+        builder.assert_is_equal(decoded_precommit.block_hash, expected_block_hash);
+        builder.assert_is_equal(decoded_precommit.block_number, expected_block_number);
+        builder.assert_is_equal(
+            decoded_precommit.authority_set_id,
+            expected_authority_set_id,
+        );
+
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        let encoded_precommit = [
+            1u8, 38, 27, 45, 113, 196, 242, 16, 36, 228, 137, 117, 93, 79, 157, 136, 222, 239, 71,
+            241, 37, 152, 13, 194, 159, 190, 169, 38, 234, 124, 89, 223, 233, 161, 217, 4, 0, 75,
+            58, 0, 0, 0, 0, 0, 0, 42, 1, 0, 0, 0, 0, 0, 0,
+        ];
+        input.write::<BytesVariable<ENCODED_PRECOMMIT_LENGTH>>(encoded_precommit);
+        input.write::<Bytes32Variable>(block_hash);
+        input.write::<U32Variable>(block_number);
+        input.write::<U64Variable>(authority_set_id);
+
+        let (proof, output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+    }
+
+    #[test]
+    fn test_precommit_binding() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
+
+        let block_hash = "261b2d71c4f21024e489755d4f9d88deef47f125980dc29fbea926ea7c59dfe9"
+            .parse()
+            .unwrap();
+        prove_precommit_binding(block_hash, 317857, 298);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_precommit_binding_wrong_block_hash() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
+
+        // Same as the fixture block hash, except for the last byte.
+        let block_hash = "261b2d71c4f21024e489755d4f9d88deef47f125980dc29fbea926ea7c59dfea"
+            .parse()
+            .unwrap();
+        prove_precommit_binding(block_hash, 317857, 298);
+    }
 }
@@ -1,22 +1,249 @@
-use plonky2x::prelude::{Bytes32Variable, CircuitBuilder, PlonkParameters};
-
+use plonky2x::frontend::vars::{ByteVariable, U32Variable};
+use plonky2x::prelude::{
+    ArrayVariable, BoolVariable, Bytes32Variable, BytesVariable, CircuitBuilder, PlonkParameters,
+    Variable,
+};
+
+use super::decoder::DecodingMethods;
+use crate::consts::{
+    header_chunk_size, DATA_ROOT_OFFSET_FROM_END, HASH_SIZE, MAX_COMPACT_U64_BYTES,
+    MAX_HEADER_CHUNK_SIZE,
+};
 use crate::vars::*;
 
 pub trait HeaderMethods {
-    /// Get the Blake2b hash of an encoded header.
+    /// Verify that all bytes of an encoded header at index >= header_size are zero, so the padding
+    /// of the header is bound and the hashed region of the header is unambiguous. This is a few
+    /// gates per byte of MAX_HEADER_SIZE, so it is only called on headers whose bytes are read at
+    /// positions from a hint, which could otherwise point past the end of the hashed header.
+    fn verify_header_padding<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+    );
+
+    /// Get the Blake2b hash of an encoded header. This is blake2_256 of the SCALE-encoded header,
+    /// which is the Avail block hash that GRANDPA precommits sign, so it can be compared directly to
+    /// the block hash of a precommit. The padding of the header is not verified, see
+    /// `verify_header_padding`.
     fn hash_encoded_header<const MAX_HEADER_SIZE: usize>(
         &mut self,
         header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
     ) -> Bytes32Variable;
+
+    /// Verify that the first num_headers headers form a chain, i.e. the parent hash of each header
+    /// is the hash of the previous header. Headers at index >= num_headers are padding and are not
+    /// linked. Returns the hashes of the headers.
+    fn verify_header_chain<const MAX_HEADER_SIZE: usize, const MAX_NUM_HEADERS: usize>(
+        &mut self,
+        headers: &ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>,
+        num_headers: U32Variable,
+    ) -> Vec<Bytes32Variable>;
+
+    /// Decode the state root of an encoded header. The state root follows the parent hash and the
+    /// compact-encoded block number, which is 1, 2, 4, or 5 bytes, so its offset depends on the
+    /// encoding of the block number.
+    /// Decode the data root of an encoded header, which is the last 32 bytes of the header.
+    /// header_hash is used for the RLC challenge in get_fixed_subarray.
+    fn decode_data_root<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+        header_hash: &Bytes32Variable,
+    ) -> Bytes32Variable;
+
+    fn decode_state_root<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+    ) -> Bytes32Variable;
+
+    /// Compute the merkle commitment (SHA256) over the data roots of the first num_headers headers.
+    /// The data roots are the leaves of the tree, and the leaves of the headers at index >=
+    /// num_headers are zero. This is the data root commitment of the subchain verification, and has
+    /// the layout of `RpcDataFetcher::get_merkle_root`, so a data root can be proven against it
+    /// with a merkle proof. MAX_NUM_HEADERS must be a power of 2, and num_headers must be at most
+    /// MAX_NUM_HEADERS.
+    fn compute_data_root_commitment<const MAX_HEADER_SIZE: usize, const MAX_NUM_HEADERS: usize>(
+        &mut self,
+        headers: &ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>,
+        num_headers: U32Variable,
+    ) -> Bytes32Variable;
 }
 
 impl<L: PlonkParameters<D>, const D: usize> HeaderMethods for CircuitBuilder<L, D> {
+    fn verify_header_padding<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+    ) {
+        let true_v = self._true();
+        let zero_byte = self.constant::<ByteVariable>(0);
+
+        // in_padding is true for all bytes at index >= header_size.
+        let mut in_padding = self._false();
+        let mut padding_is_zero = self._true();
+        for i in 0..MAX_HEADER_SIZE {
+            let idx = self.constant::<U32Variable>(i as u32);
+            let is_header_end = self.is_equal(idx, header.header_size);
+            in_padding = self.or(in_padding, is_header_end);
+
+            let is_zero = self.is_equal(header.header_bytes[i], zero_byte);
+            let not_in_padding = self.not(in_padding);
+            let byte_is_valid: BoolVariable = self.or(not_in_padding, is_zero);
+            padding_is_zero = self.and(padding_is_zero, byte_is_valid);
+        }
+        self.assert_is_equal(padding_is_zero, true_v);
+    }
+
     fn hash_encoded_header<const MAX_HEADER_SIZE: usize>(
         &mut self,
         header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
     ) -> Bytes32Variable {
+        // A header of MAX_HEADER_SIZE bytes must fit in the MAX_HEADER_CHUNK_SIZE blake2b chunks
+        // the circuit can hash, so a misconfigured circuit fails to build.
+        assert!(
+            header_chunk_size(MAX_HEADER_SIZE) <= MAX_HEADER_CHUNK_SIZE,
+            "MAX_HEADER_SIZE of {} bytes is {} blake2b chunks, which exceeds MAX_HEADER_CHUNK_SIZE of {}",
+            MAX_HEADER_SIZE,
+            header_chunk_size(MAX_HEADER_SIZE),
+            MAX_HEADER_CHUNK_SIZE
+        );
+        // Note: The header is not hashed in chunks here. The Curta blake2b accelerator already
+        // absorbs the header bytes one BLAKE2B_CHUNK_SIZE_BYTES chunk at a time with its own
+        // compression state, and references the header's byte variables without copying them, so
+        // an accumulator over MAX_HEADER_CHUNK_SIZE chunks would not reduce the witness. The
+        // compression function is not exposed by the builder, so a chunked hash would have to
+        // reimplement blake2b outside the accelerator, which is far more expensive.
         self.curta_blake2b_variable(header.header_bytes.as_slice(), header.header_size)
     }
+
+    fn verify_header_chain<const MAX_HEADER_SIZE: usize, const MAX_NUM_HEADERS: usize>(
+        &mut self,
+        headers: &ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>,
+        num_headers: U32Variable,
+    ) -> Vec<Bytes32Variable> {
+        let true_v = self._true();
+        let false_v = self._false();
+
+        // Assert num_headers fits in headers, otherwise the chain would silently be truncated.
+        let max_num_headers = self.constant::<U32Variable>(MAX_NUM_HEADERS as u32);
+        let too_many_headers = self.lt(max_num_headers, num_headers);
+        self.assert_is_equal(too_many_headers, false_v);
+
+        // in_padding is true for all headers at index >= num_headers.
+        let mut in_padding = self._false();
+        let mut header_hashes: Vec<Bytes32Variable> = Vec::new();
+        for i in 0..MAX_NUM_HEADERS {
+            let idx = self.constant::<U32Variable>(i as u32);
+            let is_chain_end = self.is_equal(idx, num_headers);
+            in_padding = self.or(in_padding, is_chain_end);
+
+            let header_hash = self.hash_encoded_header(&headers[i]);
+            if i > 0 {
+                // The first 32 bytes of an encoded header are the parent hash.
+                let parent_hash: Bytes32Variable = headers[i].header_bytes[0..HASH_SIZE].into();
+                let is_linked = self.is_equal(parent_hash, header_hashes[i - 1]);
+                let link_check = self.or(is_linked, in_padding);
+                self.assert_is_equal(link_check, true_v);
+            }
+            header_hashes.push(header_hash);
+        }
+        header_hashes
+    }
+
+    fn decode_state_root<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+    ) -> Bytes32Variable {
+        let (_, block_number_length) = decode_block_number(self, header);
+        select_state_root(self, header, block_number_length)
+    }
+
+    fn decode_data_root<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+        header_hash: &Bytes32Variable,
+    ) -> Bytes32Variable {
+        // The data root is the last 32 bytes of the header. The header extension is the last field
+        // of the header, and ends with the Kate commitment, whose last field is the data root, so
+        // the offset does not depend on the length of the app lookup or the commitment. The hints
+        // check this holds for each header with `is_data_root_at_end`.
+        // Spec: https://github.com/availproject/avail-core/blob/main/core/src/header/extension/v3.rs#L9-L15
+        let data_root_offset = self.constant::<U32Variable>(DATA_ROOT_OFFSET_FROM_END as u32);
+        let mut data_root_start = self.sub(header.header_size, data_root_offset);
+
+        // If header_size == 0, then set data_root_start to 0.
+        let header_is_zero_size = self.is_zero(header.header_size.variable);
+        let zero = self.zero();
+        data_root_start = self.select(header_is_zero_size, zero, data_root_start);
+
+        // Extract the data root from the header.
+        let data_root_bytes: Vec<ByteVariable> = self
+            .get_fixed_subarray::<MAX_HEADER_SIZE, HASH_SIZE>(
+                &header.header_bytes,
+                data_root_start.variable,
+                // Seed the RLC challenge with a commitment of the header (header_hash).
+                &header_hash.as_bytes(),
+            )
+            .as_vec();
+        Bytes32Variable::from(data_root_bytes.as_slice())
+    }
+
+    fn compute_data_root_commitment<const MAX_HEADER_SIZE: usize, const MAX_NUM_HEADERS: usize>(
+        &mut self,
+        headers: &ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>,
+        num_headers: U32Variable,
+    ) -> Bytes32Variable {
+        // Assert num_headers fits in headers, otherwise the commitment would silently be computed
+        // over a truncated range.
+        let false_v = self._false();
+        let max_num_headers = self.constant::<U32Variable>(MAX_NUM_HEADERS as u32);
+        let too_many_headers = self.lt(max_num_headers, num_headers);
+        self.assert_is_equal(too_many_headers, false_v);
+
+        let mut data_roots = Vec::new();
+        for i in 0..MAX_NUM_HEADERS {
+            let header_hash = self.hash_encoded_header(&headers[i]);
+            data_roots.push(self.decode_data_root(&headers[i], &header_hash));
+        }
+        self.get_root_from_hashed_leaves::<MAX_NUM_HEADERS>(
+            ArrayVariable::<Bytes32Variable, MAX_NUM_HEADERS>::new(data_roots),
+            num_headers.variable,
+        )
+    }
+}
+
+/// Decode the block number of an encoded header, which follows the parent hash in compact u32 SCALE
+/// encoding, and the number of bytes it is encoded in.
+pub(crate) fn decode_block_number<L: PlonkParameters<D>, const D: usize, const S: usize>(
+    builder: &mut CircuitBuilder<L, D>,
+    header: &EncodedHeaderVariable<S>,
+) -> (U32Variable, Variable) {
+    let block_number_bytes = BytesVariable::<MAX_COMPACT_U64_BYTES>(
+        header.header_bytes[HASH_SIZE..HASH_SIZE + MAX_COMPACT_U64_BYTES]
+            .to_vec()
+            .try_into()
+            .unwrap(),
+    );
+    let zero = builder.zero::<Variable>();
+    builder.decode_compact_u32(&block_number_bytes, zero)
+}
+
+/// Select the state root of an encoded header, given the number of bytes its block number is
+/// encoded in. The state root starts right after the block number.
+pub(crate) fn select_state_root<L: PlonkParameters<D>, const D: usize, const S: usize>(
+    builder: &mut CircuitBuilder<L, D>,
+    header: &EncodedHeaderVariable<S>,
+    block_number_length: Variable,
+) -> Bytes32Variable {
+    // Index the state roots by the length of the block number - 1. A compact u32 is never 3 bytes,
+    // so the state root at that offset is never selected.
+    let all_possible_state_roots = (1..=5)
+        .map(|block_number_length| {
+            let start = HASH_SIZE + block_number_length;
+            Bytes32Variable::from(&header.header_bytes[start..start + HASH_SIZE])
+        })
+        .collect::<Vec<_>>();
+    let one = builder.one::<Variable>();
+    let state_root_idx = builder.sub(block_number_length, one);
+    builder.select_array_random_gate(&all_possible_state_roots, state_root_idx)
 }
 
 #[cfg(test)]
@@ -24,17 +251,337 @@ mod tests {
     use std::env;
 
     use avail_subxt::config::Header;
-    use codec::Encode;
+    use codec::{Compact, Encode};
     use ethers::types::H256;
     use plonky2x::frontend::vars::{ByteVariable, U32Variable};
     use plonky2x::prelude::{ArrayVariable, Bytes32Variable, DefaultBuilder, GoldilocksField};
-    use sp_core::{Blake2Hasher, Hasher};
+    use sp_core::{Blake2Hasher, Bytes, Hasher};
 
     use crate::builder::header::HeaderMethods;
     use crate::consts::MAX_HEADER_SIZE;
-    use crate::input::RpcDataFetcher;
+    use crate::input::mock::Fixtures;
+    use crate::input::{encode_header, RpcDataFetcher};
     use crate::vars::{EncodedHeader, EncodedHeaderVariable};
 
+    #[test]
+    #[should_panic(expected = "exceeds MAX_HEADER_CHUNK_SIZE")]
+    fn test_hash_encoded_header_too_many_chunks() {
+        const HEADER_LENGTH: usize = MAX_HEADER_SIZE + 1;
+
+        let mut builder = DefaultBuilder::new();
+        let header = builder.read::<EncodedHeaderVariable<HEADER_LENGTH>>();
+        builder.hash_encoded_header(&header);
+    }
+
+    fn prove_header_padding(header_bytes: Vec<u8>, header_size: u32) {
+        const HEADER_LENGTH: usize = 8;
+        type F = GoldilocksField;
+
+        let mut builder = DefaultBuilder::new();
+        let header = builder.read::<EncodedHeaderVariable<HEADER_LENGTH>>();
+        builder.verify_header_padding(&header);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<EncodedHeaderVariable<HEADER_LENGTH>>(EncodedHeader::<HEADER_LENGTH, F> {
+            header_bytes,
+            header_size,
+        });
+        let (proof, output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+    }
+
+    #[test]
+    fn test_verify_header_padding() {
+        prove_header_padding(vec![1, 2, 3, 0, 0, 0, 0, 0], 3);
+        // The header is allowed to end with zero bytes.
+        prove_header_padding(vec![1, 2, 0, 0, 0, 0, 0, 0], 3);
+        // A header of the maximum length has no padding.
+        prove_header_padding(vec![1, 2, 3, 4, 5, 6, 7, 8], 8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_verify_header_padding_nonzero() {
+        // Byte 5 is past the end of the header, but not zero.
+        prove_header_padding(vec![1, 2, 3, 0, 0, 9, 0, 0], 3);
+    }
+
+    // Length the fixture headers of a chain are padded to, which is 1 blake2b chunk.
+    const CHAIN_HEADER_LENGTH: usize = 128;
+
+    /// Fixtures of the SCALE-encoded headers of the blocks in [start_block, start_block + num_headers),
+    /// each linked to the previous header by its parent hash.
+    fn chain_fixtures(start_block: u32, num_headers: usize) -> Fixtures {
+        let mut fixtures = Fixtures::default();
+        let mut parent_hash = [0u8; 32];
+        for block_number in start_block..start_block + num_headers as u32 {
+            // Parent hash, number, state root and extrinsics root, followed by an empty digest.
+            let mut header = parent_hash.to_vec();
+            header.extend(Compact(block_number).encode());
+            header.extend_from_slice(&[block_number as u8; 64]);
+            header.push(0);
+            parent_hash = Blake2Hasher::hash(&header).0;
+            fixtures.headers.insert(block_number, Bytes(header));
+        }
+        fixtures
+    }
+
+    /// Proves verify_header_chain over the fixture headers of the blocks in [start_block,
+    /// start_block + 7], arranged by reorder, with num_headers linked headers.
+    fn prove_header_chain(
+        fixtures: &Fixtures,
+        start_block: u32,
+        num_headers: u32,
+        reorder: fn(&mut Vec<EncodedHeader<CHAIN_HEADER_LENGTH, GoldilocksField>>),
+    ) {
+        const NUM_HEADERS: usize = 8;
+
+        let mut builder = DefaultBuilder::new();
+        let headers = builder
+            .read::<ArrayVariable<EncodedHeaderVariable<CHAIN_HEADER_LENGTH>, NUM_HEADERS>>();
+        let num_headers_var = builder.read::<U32Variable>();
+        builder.verify_header_chain(&headers, num_headers_var);
+        let circuit = builder.build();
+
+        let mut encoded_headers = (start_block..start_block + NUM_HEADERS as u32)
+            .map(|block_number| {
+                let mut header_bytes = fixtures.headers[&block_number].to_vec();
+                let header_size = header_bytes.len() as u32;
+                header_bytes.resize(CHAIN_HEADER_LENGTH, 0);
+                EncodedHeader {
+                    header_bytes,
+                    header_size,
+                }
+            })
+            .collect::<Vec<_>>();
+        reorder(&mut encoded_headers);
+
+        let mut input = circuit.input();
+        input.write::<ArrayVariable<EncodedHeaderVariable<CHAIN_HEADER_LENGTH>, NUM_HEADERS>>(
+            encoded_headers,
+        );
+        input.write::<U32Variable>(num_headers);
+        let (proof, output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+    }
+
+    #[test]
+    fn test_verify_header_chain() {
+        let fixtures = chain_fixtures(272355, 8);
+        prove_header_chain(&fixtures, 272355, 8, |_| {});
+        // The headers after num_headers are padding, and are not linked.
+        prove_header_chain(&fixtures, 272355, 6, |headers| headers.swap(6, 7));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_verify_header_chain_unlinked() {
+        let fixtures = chain_fixtures(272355, 8);
+        prove_header_chain(&fixtures, 272355, 8, |headers| headers.swap(3, 4));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_verify_header_chain_wrong_parent() {
+        // Header 2 points at header 0 instead of header 1, as a header from a fork would.
+        let fixtures = chain_fixtures(272355, 8);
+        prove_header_chain(&fixtures, 272355, 8, |headers| {
+            let grandparent_hash = headers[1].header_bytes[0..32].to_vec();
+            headers[2].header_bytes[0..32].copy_from_slice(&grandparent_hash);
+        });
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_compute_data_root_commitment() {
+        const START_BLOCK_NUM: u32 = 272355;
+        const NUM_HEADERS: usize = 8;
+        // The last 2 headers are not committed to.
+        const NUM_COMMITTED_HEADERS: usize = 6;
+
+        let mut builder = DefaultBuilder::new();
+        let headers =
+            builder.read::<ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, NUM_HEADERS>>();
+        let num_headers = builder.read::<U32Variable>();
+        let data_root_commitment = builder.compute_data_root_commitment(&headers, num_headers);
+        builder.write(data_root_commitment);
+        let circuit = builder.build();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let headers = rt.block_on(async {
+            let mut data_fetcher = RpcDataFetcher::new().await;
+            data_fetcher
+                .get_headers_range(START_BLOCK_NUM, START_BLOCK_NUM + NUM_HEADERS as u32 - 1)
+                .await
+                .unwrap()
+        });
+        let encoded_headers = headers
+            .iter()
+            .map(|header| {
+                let mut header_bytes = header.encode();
+                let header_size = header_bytes.len() as u32;
+                header_bytes.resize(MAX_HEADER_SIZE, 0);
+                EncodedHeader {
+                    header_bytes,
+                    header_size,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut input = circuit.input();
+        input.write::<ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, NUM_HEADERS>>(
+            encoded_headers,
+        );
+        input.write::<U32Variable>(NUM_COMMITTED_HEADERS as u32);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        // The off-circuit commitment pads the data roots with zero leaves.
+        let mut data_root_leaves = headers[..NUM_COMMITTED_HEADERS]
+            .iter()
+            .map(|header| header.data_root().0.to_vec())
+            .collect::<Vec<_>>();
+        data_root_leaves.resize(NUM_HEADERS, [0u8; 32].to_vec());
+        let expected_commitment = RpcDataFetcher::get_merkle_root(data_root_leaves);
+        assert_eq!(
+            output.read::<Bytes32Variable>(),
+            H256::from_slice(&expected_commitment)
+        );
+    }
+
+    /// Proves compute_data_root_commitment over headers whose last 32 bytes are the data roots, and
+    /// checks it against the off-circuit merkle root of the data roots.
+    fn prove_data_root_commitment(data_roots: &[[u8; 32]], num_headers: u32) {
+        const HEADER_LENGTH: usize = 128;
+        const NUM_HEADERS: usize = 4;
+        type F = GoldilocksField;
+
+        let mut builder = DefaultBuilder::new();
+        let headers =
+            builder.read::<ArrayVariable<EncodedHeaderVariable<HEADER_LENGTH>, NUM_HEADERS>>();
+        let num_headers_var = builder.read::<U32Variable>();
+        let data_root_commitment = builder.compute_data_root_commitment(&headers, num_headers_var);
+        builder.write(data_root_commitment);
+        let circuit = builder.build();
+
+        // Headers of different sizes, each ending with its data root.
+        let encoded_headers = data_roots
+            .iter()
+            .enumerate()
+            .map(|(i, data_root)| {
+                let mut header_bytes = vec![i as u8 + 1; 40 + 16 * i];
+                header_bytes.extend_from_slice(data_root);
+                let header_size = header_bytes.len() as u32;
+                header_bytes.resize(HEADER_LENGTH, 0);
+                EncodedHeader::<HEADER_LENGTH, F> {
+                    header_bytes,
+                    header_size,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut input = circuit.input();
+        input.write::<ArrayVariable<EncodedHeaderVariable<HEADER_LENGTH>, NUM_HEADERS>>(
+            encoded_headers,
+        );
+        input.write::<U32Variable>(num_headers);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        let mut data_root_leaves = data_roots
+            .iter()
+            .take(num_headers as usize)
+            .map(|data_root| data_root.to_vec())
+            .collect::<Vec<_>>();
+        data_root_leaves.resize(NUM_HEADERS, [0u8; 32].to_vec());
+        assert_eq!(
+            output.read::<Bytes32Variable>(),
+            H256::from_slice(&RpcDataFetcher::get_merkle_root(data_root_leaves))
+        );
+    }
+
+    #[test]
+    fn test_compute_data_root_commitment_layout() {
+        let data_roots = [[0xaau8; 32], [0xbbu8; 32], [0xccu8; 32], [0xddu8; 32]];
+        for num_headers in [1, 3, 4] {
+            prove_data_root_commitment(&data_roots, num_headers);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compute_data_root_commitment_too_many_headers() {
+        let data_roots = [[0xaau8; 32], [0xbbu8; 32], [0xccu8; 32], [0xddu8; 32]];
+        prove_data_root_commitment(&data_roots, 5);
+    }
+
+    #[test]
+    fn test_hash_encoded_header_blake2_256() {
+        const HEADER_LENGTH: usize = 256;
+        type F = GoldilocksField;
+
+        let mut builder = DefaultBuilder::new();
+        let header = builder.read::<EncodedHeaderVariable<HEADER_LENGTH>>();
+        let header_hash = builder.hash_encoded_header(&header);
+        builder.write(header_hash);
+        let circuit = builder.build();
+
+        for header_size in [1usize, 100, HEADER_LENGTH] {
+            let header = (0..header_size)
+                .map(|i| (i * 7 + 3) as u8)
+                .collect::<Vec<_>>();
+            let mut header_bytes = header.clone();
+            header_bytes.resize(HEADER_LENGTH, 0);
+
+            let mut input = circuit.input();
+            input.write::<EncodedHeaderVariable<HEADER_LENGTH>>(
+                EncodedHeader::<HEADER_LENGTH, F> {
+                    header_bytes,
+                    header_size: header_size as u32,
+                },
+            );
+            let (proof, mut output) = circuit.prove(&input);
+            circuit.verify(&proof, &input, &output);
+
+            assert_eq!(
+                output.read::<Bytes32Variable>(),
+                H256::from(sp_core::blake2_256(&header))
+            );
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_decode_state_root() {
+        type F = GoldilocksField;
+
+        let mut builder = DefaultBuilder::new();
+        let header = builder.read::<EncodedHeaderVariable<MAX_HEADER_SIZE>>();
+        let state_root = builder.decode_state_root(&header);
+        builder.write(state_root);
+        let circuit = builder.build();
+
+        // The block numbers are compact-encoded in 1, 2, and 4 bytes.
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        for block_number in [42u32, 4321, 272355] {
+            let header = rt.block_on(async {
+                let data_fetcher = RpcDataFetcher::new().await;
+                data_fetcher.get_header(block_number).await
+            });
+
+            let mut input = circuit.input();
+            input.write::<EncodedHeaderVariable<MAX_HEADER_SIZE>>(
+                encode_header::<MAX_HEADER_SIZE, F>(&header).unwrap(),
+            );
+            let (proof, mut output) = circuit.prove(&input);
+            circuit.verify(&proof, &input, &output);
+
+            let state_root = output.read::<Bytes32Variable>();
+            assert_eq!(state_root, H256::from_slice(&header.state_root.0));
+        }
+    }
+
     #[test]
     #[cfg_attr(feature = "ci", ignore)]
     fn test_hash_headers() {
@@ -1,26 +1,274 @@
+use std::fmt;
+use std::time::Instant;
+
 use async_trait::async_trait;
 use ethers::types::U256;
-use log::debug;
+use log::{debug, error, info, warn};
 use plonky2x::frontend::curta::ec::point::{CompressedEdwardsY, CompressedEdwardsYVariable};
-use plonky2x::frontend::ecc::curve25519::ed25519::eddsa::EDDSASignatureVariableValue;
+use plonky2x::frontend::ecc::curve25519::ed25519::eddsa::{
+    EDDSASignatureVariable, EDDSASignatureVariableValue, DUMMY_PUBLIC_KEY, DUMMY_SIGNATURE,
+};
 use plonky2x::frontend::hint::asynchronous::hint::AsyncHint;
 use plonky2x::frontend::uint::uint64::U64Variable;
 use plonky2x::frontend::vars::{U32Variable, ValueStream, VariableStream};
+use plonky2x::prelude::plonky2::hash::poseidon::PoseidonHash;
 use plonky2x::prelude::{
-    ArrayVariable, BoolVariable, Bytes32Variable, CircuitBuilder, CircuitVariable, Field,
-    PlonkParameters, Variable,
+    ArrayVariable, BoolVariable, ByteVariable, Bytes32Variable, CircuitBuilder, CircuitVariable,
+    EvmVariable, Field, PlonkParameters, RichField, Variable,
 };
 use serde::{Deserialize, Serialize};
 
 use super::decoder::DecodingMethods;
-use crate::consts::ENCODED_PRECOMMIT_LENGTH;
-use crate::input::types::CircuitJustification;
-use crate::input::{verify_signature, RpcDataFetcher};
+use crate::consts::{
+    ENCODED_PRECOMMIT_LENGTH, MAX_AUTHORITY_WEIGHT, WEIGHTED_COMMITMENT_SHA256_CHUNKS,
+    WEIGHT_LENGTH,
+};
+#[cfg(any(test, feature = "mock"))]
+use crate::input::mock::MockDataFetcher;
+use crate::input::types::{CircuitJustification, CommitmentHash, DataSource, FetchError};
+use crate::input::{is_signature_valid, DataFetcher, RpcDataFetcher};
 use crate::vars::{JustificationStruct, JustificationVariable};
 
-/// Fetch the simple justification for a block.
+/// Errors returned by `HintSimpleJustification`. The error code is written to the output stream so
+/// the circuit can read whether the justification was fetched successfully.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JustificationError {
+    /// The justification could not be fetched for the block.
+    FetchFailed = 1,
+    /// The justification is not signed by the requested authority set.
+    AuthoritySetIdMismatch = 2,
+    /// The encoded precommit is not ENCODED_PRECOMMIT_LENGTH bytes.
+    InvalidPrecommitLength = 3,
+    /// A validator marked as signed has an invalid signature on the precommit.
+    InvalidSignature = 4,
+    /// The authority set has more authorities than NUM_AUTHORITIES, so it does not fit in the
+    /// circuit.
+    TooManyAuthorities = 5,
+}
+
+impl JustificationError {
+    /// Error code written to the output stream. 0 is reserved for success.
+    pub fn code(&self) -> u32 {
+        *self as u32
+    }
+}
+
+/// Checks the fetched justification is from the requested authority set and is correctly signed.
+pub fn check_circuit_justification(
+    justification_data: &CircuitJustification,
+    authority_set_id: u64,
+) -> Result<(), JustificationError> {
+    if justification_data.authority_set_id != authority_set_id {
+        warn!(
+            "Justification is signed by authority set {}, expected {}",
+            justification_data.authority_set_id, authority_set_id
+        );
+        return Err(JustificationError::AuthoritySetIdMismatch);
+    }
+
+    let encoded_precommit = &justification_data.signed_message;
+    if encoded_precommit.len() != ENCODED_PRECOMMIT_LENGTH {
+        return Err(JustificationError::InvalidPrecommitLength);
+    }
+
+    for i in 0..justification_data.num_authorities {
+        // Skip if the validator didn't sign.
+        if !justification_data.validator_signed[i] {
+            continue;
+        }
+        if !is_signature_valid(
+            justification_data.pubkeys[i].as_bytes(),
+            encoded_precommit,
+            &justification_data.signatures[i],
+        ) {
+            warn!(
+                "Invalid signature of validator {} in authority set {}",
+                i, authority_set_id
+            );
+            return Err(JustificationError::InvalidSignature);
+        }
+    }
+    Ok(())
+}
+
+/// Errors returned when converting a signature to the value type of `EDDSASignatureVariable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The signature is not 64 bytes (R || s).
+    InvalidLength { length: usize },
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::InvalidLength { length } => {
+                write!(f, "Signature is {} bytes, expected 64", length)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Converts an Ed25519 signature (R || s, with s a 32 byte little-endian scalar) to the value type of
+/// `EDDSASignatureVariable`. s is read as a fixed-width 256-bit integer, so a scalar with zero high
+/// bytes (or s = 0) is represented as is, rather than truncated. Errors if the signature is not 64
+/// bytes.
+pub fn signature_to_value_type<F: RichField>(
+    signature: &[u8],
+) -> Result<EDDSASignatureVariableValue<F>, SignatureError> {
+    if signature.len() != 64 {
+        return Err(SignatureError::InvalidLength {
+            length: signature.len(),
+        });
+    }
+    Ok(EDDSASignatureVariableValue {
+        r: CompressedEdwardsY::from_slice(&signature[0..32]).unwrap(),
+        s: U256::from_little_endian(&signature[32..64]),
+    })
+}
+
+/// Placeholder justification written by `HintSimpleJustification` on failure. It satisfies the
+/// unconditional constraints in `verify_simple_justification` (a non-zero authority set size and a
+/// precommit with a valid equivocation type), while all other checks are gated on success.
+pub fn dummy_justification<const NUM_AUTHORITIES: usize, F: RichField>(
+) -> JustificationStruct<NUM_AUTHORITIES, F> {
+    let mut encoded_precommit = [0u8; ENCODED_PRECOMMIT_LENGTH];
+    encoded_precommit[0] = 1;
+    JustificationStruct {
+        encoded_precommit,
+        validator_signed: vec![false; NUM_AUTHORITIES],
+        signatures: vec![signature_to_value_type(&DUMMY_SIGNATURE).unwrap(); NUM_AUTHORITIES],
+        pubkeys: vec![CompressedEdwardsY::from_slice(&DUMMY_PUBLIC_KEY).unwrap(); NUM_AUTHORITIES],
+        weights: vec![1; NUM_AUTHORITIES],
+        num_authorities: 1,
+        round: 0,
+    }
+}
+
+/// Fetch the simple justification for a block. Writes an error code (0 on success) followed by the
+/// justification. On failure, the justification is `dummy_justification`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HintSimpleJustification<const NUM_AUTHORITIES: usize> {}
+pub struct HintSimpleJustification<const NUM_AUTHORITIES: usize> {
+    /// Where the justification is fetched from.
+    pub source: DataSource,
+}
+
+/// Fetches the simple justification for a block from source, padded to NUM_AUTHORITIES.
+async fn get_justification_from_source<const NUM_AUTHORITIES: usize>(
+    source: &DataSource,
+    block_number: u32,
+) -> Result<CircuitJustification, FetchError> {
+    #[cfg(any(test, feature = "mock"))]
+    if let Some(fixtures_path) = &source.fixtures_path {
+        return MockDataFetcher::from_file(fixtures_path)
+            .get_justification_from_block::<NUM_AUTHORITIES>(block_number)
+            .await;
+    }
+    RpcDataFetcher::new_with_optional_url(source.avail_url.as_deref())
+        .await
+        .get_justification_from_block::<NUM_AUTHORITIES>(block_number)
+        .await
+}
+
+/// Fetches the simple justification for a block, checked to be from the authority set. Returns an
+/// error code (0 on success) and the justification. On failure, the justification is
+/// `dummy_justification`.
+pub async fn fetch_justification_struct<const NUM_AUTHORITIES: usize, F: RichField>(
+    source: &DataSource,
+    block_number: u32,
+    authority_set_id: u64,
+) -> (u32, JustificationStruct<NUM_AUTHORITIES, F>) {
+    debug!(
+        "HintSimpleJustification: downloading justification for block_number={} authority_set_id={}",
+        block_number, authority_set_id
+    );
+
+    let start = Instant::now();
+    // The genesis block has no justification.
+    let justification_data = if block_number == 0 {
+        Err(JustificationError::FetchFailed)
+    } else {
+        get_justification_from_source::<NUM_AUTHORITIES>(source, block_number)
+            .await
+            .map_err(|e| {
+                error!(
+                    "HintSimpleJustification: failed to get justification for block_number={} authority_set_id={} elapsed_ms={}: {}",
+                    block_number,
+                    authority_set_id,
+                    start.elapsed().as_millis(),
+                    e
+                );
+                match e {
+                    FetchError::AuthoritySetMismatch { .. } => {
+                        JustificationError::AuthoritySetIdMismatch
+                    }
+                    FetchError::PrecommitLengthMismatch { .. } => {
+                        JustificationError::InvalidPrecommitLength
+                    }
+                    FetchError::TooManyAuthorities { .. } => JustificationError::TooManyAuthorities,
+                    FetchError::InvalidSignatureLength { .. } => {
+                        JustificationError::InvalidSignature
+                    }
+                    _ => JustificationError::FetchFailed,
+                }
+            })
+            .and_then(|justification_data| {
+                check_circuit_justification(&justification_data, authority_set_id)
+                    .map(|_| justification_data)
+            })
+    };
+
+    // Note: An authority set larger than NUM_AUTHORITIES is a TooManyAuthorities error, rather than
+    // a justification truncated to NUM_AUTHORITIES.
+    let justification_data: CircuitJustification = match justification_data {
+        Ok(justification_data) => {
+            info!(
+                "HintSimpleJustification: fetched justification for block_number={} authority_set_id={} num_authorities={} elapsed_ms={}",
+                block_number,
+                authority_set_id,
+                justification_data.num_authorities,
+                start.elapsed().as_millis()
+            );
+            justification_data
+        }
+        Err(e) => {
+            error!(
+                "HintSimpleJustification: invalid justification for block_number={} authority_set_id={}: {:?}",
+                block_number, authority_set_id, e
+            );
+            return (e.code(), dummy_justification::<NUM_AUTHORITIES, F>());
+        }
+    };
+
+    let mut signatures = Vec::with_capacity(NUM_AUTHORITIES);
+    for (i, signature) in justification_data.signatures.iter().enumerate() {
+        match signature_to_value_type(signature) {
+            Ok(signature) => signatures.push(signature),
+            Err(e) => {
+                error!(
+                    "HintSimpleJustification: invalid signature of validator {} for block_number={}: {}",
+                    i, block_number, e
+                );
+                return (
+                    JustificationError::InvalidSignature.code(),
+                    dummy_justification::<NUM_AUTHORITIES, F>(),
+                );
+            }
+        }
+    }
+
+    let justification = JustificationStruct {
+        encoded_precommit: justification_data.signed_message.try_into().unwrap(),
+        validator_signed: justification_data.validator_signed,
+        signatures,
+        pubkeys: justification_data.pubkeys,
+        weights: justification_data.weights,
+        num_authorities: justification_data.num_authorities as u32,
+        round: justification_data.round,
+    };
+    (0, justification)
+}
 
 #[async_trait]
 impl<const NUM_AUTHORITIES: usize, L: PlonkParameters<D>, const D: usize> AsyncHint<L, D>
@@ -34,55 +282,179 @@ impl<const NUM_AUTHORITIES: usize, L: PlonkParameters<D>, const D: usize> AsyncH
         let block_number = input_stream.read_value::<U32Variable>();
         let authority_set_id = input_stream.read_value::<U64Variable>();
 
-        debug!(
-            "HintSimpleJustification: downloading justification for block_number={} authority_set_id={}",
-            block_number, authority_set_id
-        );
+        let (error_code, justification) = fetch_justification_struct::<NUM_AUTHORITIES, L::Field>(
+            &self.source,
+            block_number,
+            authority_set_id,
+        )
+        .await;
+        output_stream.write_value::<U32Variable>(error_code);
+        output_stream.write_value::<JustificationVariable<NUM_AUTHORITIES>>(justification);
+    }
+}
 
-        let mut data_fetcher = RpcDataFetcher::new().await;
-        let justification_data: CircuitJustification = data_fetcher
-            .get_justification_from_block::<NUM_AUTHORITIES>(block_number)
-            .await
-            .expect("Failed to get justification");
+/// Asserts check is true if success is true. If success is false, the check is skipped.
+pub(crate) fn assert_if_success<L: PlonkParameters<D>, const D: usize>(
+    builder: &mut CircuitBuilder<L, D>,
+    check: BoolVariable,
+    success: BoolVariable,
+) {
+    let true_v = builder._true();
+    let failed = builder.not(success);
+    let check_or_failed = builder.or(check, failed);
+    builder.assert_is_equal(check_or_failed, true_v);
+}
 
-        if justification_data.authority_set_id != authority_set_id {
-            panic!("Authority set id does not match");
-        }
+/// Returns true if the voting weight of the validators that signed is > the threshold of the total
+/// voting weight. Only the first num_active_authorities validators are counted, as the weights of
+/// the padding validators are not bound to the authority set commitment. If all weights are 1, this
+/// is the number of validators that signed compared to num_active_authorities. Returns false if an
+/// active weight is more than MAX_AUTHORITY_WEIGHT, as the scaled weights could then overflow.
+fn is_voting_threshold_met<
+    L: PlonkParameters<D>,
+    const D: usize,
+    const MAX_NUM_AUTHORITIES: usize,
+>(
+    builder: &mut CircuitBuilder<L, D>,
+    num_active_authorities: U32Variable,
+    validator_signed: &ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>,
+    weights: &ArrayVariable<U64Variable, MAX_NUM_AUTHORITIES>,
+    threshold_numerator: U64Variable,
+    threshold_denominator: U64Variable,
+) -> BoolVariable {
+    let zero = builder.zero::<U64Variable>();
+    let max_weight = builder.constant::<U64Variable>(MAX_AUTHORITY_WEIGHT);
+    let mut signed_weight = zero;
+    let mut total_weight = zero;
+    let mut weights_bounded = builder._true();
+    let mut authority_enabled = builder._true();
+    for i in 0..MAX_NUM_AUTHORITIES {
+        let curr_idx = builder.constant::<U32Variable>(i as u32);
+        let at_end = builder.is_equal(curr_idx, num_active_authorities);
+        let not_at_end = builder.not(at_end);
+        authority_enabled = builder.and(authority_enabled, not_at_end);
 
-        let encoded_precommit = justification_data.signed_message;
-        if encoded_precommit.len() != ENCODED_PRECOMMIT_LENGTH {
-            panic!("Encoded precommit is not the correct length");
-        }
+        let enabled_weight = builder.select(authority_enabled, weights[i], zero);
+        let is_weight_bounded = builder.lte(enabled_weight, max_weight);
+        weights_bounded = builder.and(weights_bounded, is_weight_bounded);
+        total_weight = builder.add(total_weight, enabled_weight);
 
-        for i in 0..justification_data.num_authorities {
-            // Skip if the validator didn't sign.
-            if !justification_data.validator_signed[i] {
-                continue;
-            }
-            verify_signature(
-                justification_data.pubkeys[i].as_bytes(),
-                &encoded_precommit,
-                &justification_data.signatures[i],
-            );
+        let enabled_and_signed = builder.and(authority_enabled, validator_signed[i]);
+        let enabled_signed_weight = builder.select(enabled_and_signed, weights[i], zero);
+        signed_weight = builder.add(signed_weight, enabled_signed_weight);
+    }
+
+    // Check the voting weight of the validators that signed is greater than the threshold.
+    let scaled_signed_weight = builder.mul(signed_weight, threshold_denominator);
+    let scaled_threshold = builder.mul(total_weight, threshold_numerator);
+    let is_above_threshold = builder.gt(scaled_signed_weight, scaled_threshold);
+    builder.and(weights_bounded, is_above_threshold)
+}
+
+/// Returns true if each validator is marked as signed exactly when its signature is not
+/// DUMMY_SIGNATURE. Validators that did not sign (including the padding validators) carry
+/// DUMMY_SIGNATURE, so a validator marked as signed must carry a real signature.
+fn is_signed_consistent_with_signatures<
+    L: PlonkParameters<D>,
+    const D: usize,
+    const MAX_NUM_AUTHORITIES: usize,
+>(
+    builder: &mut CircuitBuilder<L, D>,
+    validator_signed: &ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>,
+    signatures: &ArrayVariable<EDDSASignatureVariable, MAX_NUM_AUTHORITIES>,
+) -> BoolVariable {
+    let dummy_signature = builder
+        .constant::<EDDSASignatureVariable>(signature_to_value_type(&DUMMY_SIGNATURE).unwrap());
+    let mut is_consistent = builder._true();
+    for i in 0..MAX_NUM_AUTHORITIES {
+        let is_dummy = builder.is_equal(signatures[i].clone(), dummy_signature.clone());
+        let is_real = builder.not(is_dummy);
+        let matches_signed = builder.is_equal(validator_signed[i], is_real);
+        is_consistent = builder.and(is_consistent, matches_signed);
+    }
+    is_consistent
+}
+
+/// Returns the number of validators that signed among the first num_active_authorities validators.
+/// Signed padding validators are not counted.
+fn count_active_signed<L: PlonkParameters<D>, const D: usize, const MAX_NUM_AUTHORITIES: usize>(
+    builder: &mut CircuitBuilder<L, D>,
+    num_active_authorities: U32Variable,
+    validator_signed: &ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>,
+) -> Variable {
+    let mut num_signed = builder.zero::<Variable>();
+    let mut authority_enabled = builder._true();
+    for i in 0..MAX_NUM_AUTHORITIES {
+        let curr_idx = builder.constant::<U32Variable>(i as u32);
+        let at_end = builder.is_equal(curr_idx, num_active_authorities);
+        let not_at_end = builder.not(at_end);
+        authority_enabled = builder.and(authority_enabled, not_at_end);
+
+        let enabled_and_signed = builder.and(authority_enabled, validator_signed[i]);
+        num_signed = builder.add(num_signed, enabled_and_signed.variable);
+    }
+    num_signed
+}
+
+/// Toggles for the individual checks run by `verify_simple_justification`. All checks are enabled
+/// by default.
+///
+/// WARNING: Disabling any check makes the justification verification unsound. Lenient options are
+/// only meant to isolate a failing check while debugging a circuit, and must never be used in a
+/// production circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationOptions {
+    /// Verify the authority set commitment matches the authority set in the justification.
+    pub verify_authority_set_commitment: bool,
+    /// Verify the precommit matches the block number, block hash and authority set id.
+    pub verify_precommit: bool,
+    /// Verify the signatures on the precommit from each validator marked as signed.
+    pub verify_signatures: bool,
+    /// Verify more than 2/3 of the validators signed the precommit.
+    pub verify_voting_threshold: bool,
+}
+
+impl Default for VerificationOptions {
+    fn default() -> Self {
+        Self::strict()
+    }
+}
+
+impl VerificationOptions {
+    /// All checks enabled. This is the only configuration that is safe for production.
+    pub const fn strict() -> Self {
+        Self {
+            verify_authority_set_commitment: true,
+            verify_precommit: true,
+            verify_signatures: true,
+            verify_voting_threshold: true,
         }
+    }
 
-        output_stream.write_value::<JustificationVariable<NUM_AUTHORITIES>>(JustificationStruct {
-            encoded_precommit: encoded_precommit.try_into().unwrap(),
-            validator_signed: justification_data.validator_signed,
-            signatures: justification_data
-                .signatures
-                .iter()
-                .map(|sig| EDDSASignatureVariableValue {
-                    r: CompressedEdwardsY::from_slice(&sig[0..32]).unwrap(),
-                    s: U256::from_little_endian(&sig[32..64]),
-                })
-                .collect(),
-            pubkeys: justification_data.pubkeys,
-            num_authorities: justification_data.num_authorities as u32,
-        });
+    /// Returns true if all checks are enabled.
+    pub fn is_strict(&self) -> bool {
+        *self == Self::strict()
     }
 }
 
+/// Values from a justification that are constrained by `verify_simple_justification`.
+#[derive(Debug, Clone)]
+pub struct VerifiedJustification {
+    /// Number of authorities in the authority set that signed the justification. This is bound to
+    /// the authority set commitment, and is the size the voting threshold is computed over.
+    pub num_active_authorities: U32Variable,
+    /// Number of the num_active_authorities authorities that signed the justification.
+    pub num_signed: Variable,
+    /// GRANDPA round of the justification. Matches the round in the signed precommit if the
+    /// precommit is verified. Circuits that bind the justification to a round check this against
+    /// the expected round with `verify_justification_round`.
+    pub round: U64Variable,
+    /// True if the justification was fetched successfully. If false, the checks on the
+    /// justification were skipped, and the caller must either assert success or discard the
+    /// result.
+    pub success: BoolVariable,
+}
+
 pub trait GrandpaJustificationVerifier {
     /// Compute the authority set commitment of an authority set. This is the chained SHA256 hash of
     /// the first num_active_authorities public keys. The ordering of the authorities is the same as
@@ -90,19 +462,49 @@ pub trait GrandpaJustificationVerifier {
     ///
     /// Specifically for a chained hash of 3 public keys, the chained hash takes the form:
     ///     SHA256(SHA256(SHA256(pubkey[0]) || pubkey[1]) || pubkey[2])...
+    ///
+    /// For a single authority the commitment is SHA256(pubkey[0]). The chained hash of an empty
+    /// authority set is not a 32-byte hash, so num_active_authorities must be at least 1.
+    ///
+    /// Note: The commitment is over the compressed public keys, which both the justification and
+    /// the rotate paths already carry as `CompressedEdwardsYVariable`, so no point is compressed in
+    /// circuit. The pubkeys are only decompressed inside `curta_eddsa_verify_sigs_conditional`.
     fn compute_authority_set_commitment<const MAX_NUM_AUTHORITIES: usize>(
         &mut self,
         num_active_authorities: Variable,
         authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
     ) -> Bytes32Variable;
 
-    /// Verify the number of validators that signed is > the threshold.
+    /// Compute the authority set commitment as in `compute_authority_set_commitment`, chained with
+    /// the hash function selected by hash. `compute_authority_set_hash_with` computes the same
+    /// commitment off-circuit. The contract stores the SHA256 commitment.
+    fn compute_authority_set_commitment_with_hash<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        num_active_authorities: Variable,
+        authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+        hash: CommitmentHash,
+    ) -> Bytes32Variable;
+
+    /// Compute the authority set commitment of an authority set with voting weights. Authorities
+    /// with a weight of 1 are hashed as in `compute_authority_set_commitment`, and authorities with
+    /// any other weight are hashed with their little-endian encoded weight appended to the public
+    /// key. If all weights are 1, the commitment is the same as `compute_authority_set_commitment`.
+    fn compute_weighted_authority_set_commitment<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        num_active_authorities: Variable,
+        authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+        weights: &ArrayVariable<U64Variable, MAX_NUM_AUTHORITIES>,
+    ) -> Bytes32Variable;
+
+    /// Verify the voting weight of the validators that signed is > the threshold of the total
+    /// voting weight of the first num_active_authorities validators.
     fn verify_voting_threshold<const MAX_NUM_AUTHORITIES: usize>(
         &mut self,
         num_active_authorities: U32Variable,
         validator_signed: &ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>,
-        threshold_numerator: U32Variable,
-        threshold_denominator: U32Variable,
+        weights: &ArrayVariable<U64Variable, MAX_NUM_AUTHORITIES>,
+        threshold_numerator: U64Variable,
+        threshold_denominator: U64Variable,
     );
 
     /// Verify a simple justification on a block from the specified authority set.
@@ -113,13 +515,55 @@ pub trait GrandpaJustificationVerifier {
     ///     1) Authority set commitment matches the authority set.
     ///     2) Specified precommit message matches the block #, authority set id, and block hash.
     ///     3) Signatures on the precommit message are valid from each validator marked as signed.
-    ///     4) More than 2/3 of the validators have signed the precommit message.
+    ///     4) Validators with more than 2/3 of the voting weight have signed the precommit message.
+    ///
+    /// If the justification could not be fetched, all checks are skipped and `success` is false in
+    /// the returned `VerifiedJustification`.
+    ///
+    /// Individual checks can be disabled with `options` for debugging, see `VerificationOptions`.
     fn verify_simple_justification<const MAX_NUM_AUTHORITIES: usize>(
         &mut self,
         block_number: U32Variable,
         block_hash: Bytes32Variable,
         authority_set_id: U64Variable,
         authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+    ) -> VerifiedJustification;
+
+    /// Verify a simple justification as in `verify_simple_justification`, fetched by the hint from
+    /// source instead of the Avail node at AVAIL_URL.
+    fn verify_simple_justification_from<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+        source: DataSource,
+    ) -> VerifiedJustification;
+
+    /// Verify a simple justification as in `verify_simple_justification`, on a justification that is
+    /// already a witness of the circuit instead of one fetched by `HintSimpleJustification`. All
+    /// checks are run unconditionally, so `success` is always true in the returned
+    /// `VerifiedJustification`.
+    fn verify_simple_justification_with_witness<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        justification: &JustificationVariable<MAX_NUM_AUTHORITIES>,
+        options: VerificationOptions,
+    ) -> VerifiedJustification;
+
+    /// Verify a justification verified by `verify_simple_justification` (or one of its variants) is
+    /// from the GRANDPA round expected_round. The round is bound to the signed precommit when the
+    /// precommit is verified, so this rejects a justification replayed from another round. The check
+    /// is skipped if the justification was not fetched successfully.
+    fn verify_justification_round(
+        &mut self,
+        justification: &VerifiedJustification,
+        expected_round: U64Variable,
     );
 }
 
@@ -128,6 +572,19 @@ impl<L: PlonkParameters<D>, const D: usize> GrandpaJustificationVerifier for Cir
         &mut self,
         num_active_authorities: Variable,
         authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+    ) -> Bytes32Variable {
+        self.compute_authority_set_commitment_with_hash(
+            num_active_authorities,
+            authority_set_signers,
+            CommitmentHash::Sha256,
+        )
+    }
+
+    fn compute_authority_set_commitment_with_hash<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        num_active_authorities: Variable,
+        authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+        hash: CommitmentHash,
     ) -> Bytes32Variable {
         let false_v = self._false();
         let zero = self.zero();
@@ -135,53 +592,91 @@ impl<L: PlonkParameters<D>, const D: usize> GrandpaJustificationVerifier for Cir
         // Assert there is at least 1 authority.
         self.assert_is_equal(invalid_num_authorities, false_v);
 
-        let mut authority_enabled = self._true();
-
-        let mut commitment_so_far = self.curta_sha256(&authority_set_signers[0].0.as_bytes());
+        match hash {
+            CommitmentHash::Sha256 => {
+                sha256_authority_set_commitment(self, num_active_authorities, authority_set_signers)
+            }
+            CommitmentHash::Poseidon => poseidon_authority_set_commitment(
+                self,
+                num_active_authorities,
+                authority_set_signers,
+            ),
+        }
+    }
 
-        for i in 1..MAX_NUM_AUTHORITIES {
-            let curr_idx = self.constant::<Variable>(L::Field::from_canonical_usize(i));
-            let at_end = self.is_equal(curr_idx, num_active_authorities);
-            let not_at_end = self.not(at_end);
+    fn compute_weighted_authority_set_commitment<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        num_active_authorities: Variable,
+        authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+        weights: &ArrayVariable<U64Variable, MAX_NUM_AUTHORITIES>,
+    ) -> Bytes32Variable {
+        let false_v = self._false();
+        let zero = self.zero();
+        let invalid_num_authorities = self.is_equal(num_active_authorities, zero);
+        // Assert there is at least 1 authority.
+        self.assert_is_equal(invalid_num_authorities, false_v);
 
-            // Upon reaching the last validator, turn enabled to false to ensure that the commitment_so_far is not updated.
-            // This is because the authority set commitment is the chained hash of the first num_active_authorities public keys.
-            authority_enabled = self.and(authority_enabled, not_at_end);
+        let unit_weight = self.constant::<U64Variable>(1u64);
 
+        let mut authority_enabled = self._true();
+        let mut commitment_so_far: Option<Bytes32Variable> = None;
+        for i in 0..MAX_NUM_AUTHORITIES {
             let mut input_to_hash = Vec::new();
-            input_to_hash.extend_from_slice(&commitment_so_far.as_bytes());
+            if let Some(commitment_so_far) = commitment_so_far {
+                input_to_hash.extend_from_slice(&commitment_so_far.as_bytes());
+            }
             input_to_hash.extend_from_slice(&authority_set_signers[i].0.as_bytes());
 
-            // Compute the chained hash of the authority set commitment.
-            let chained_hash = self.curta_sha256(&input_to_hash);
+            // Authorities with a weight of 1 are hashed without the weight, so the commitment of an
+            // authority set with unit weights matches compute_authority_set_commitment. The weight
+            // is always appended, and only hashed if it is not 1, so each authority is hashed once.
+            let unweighted_length = self.constant::<U32Variable>(input_to_hash.len() as u32);
+            let weighted_length =
+                self.constant::<U32Variable>((input_to_hash.len() + WEIGHT_LENGTH) as u32);
+            // The weight is encoded as little-endian, as in the encoded authority set.
+            let mut weight_bytes = weights[i].encode(self);
+            weight_bytes.reverse();
+            input_to_hash.extend_from_slice(&weight_bytes);
+            let is_unit_weight = self.is_equal(weights[i], unit_weight);
+            let input_length = self.select(is_unit_weight, unweighted_length, weighted_length);
+            let chained_hash = self.curta_sha256_variable::<WEIGHTED_COMMITMENT_SHA256_CHUNKS>(
+                &input_to_hash,
+                input_length,
+            );
 
-            // Update the commitment_so_far if this authority is enabled.
-            commitment_so_far = self.select(authority_enabled, chained_hash, commitment_so_far);
+            commitment_so_far = Some(match commitment_so_far {
+                None => chained_hash,
+                Some(commitment_so_far) => {
+                    // Upon reaching the last validator, turn enabled to false to ensure that the
+                    // commitment_so_far is not updated.
+                    let curr_idx = self.constant::<Variable>(L::Field::from_canonical_usize(i));
+                    let at_end = self.is_equal(curr_idx, num_active_authorities);
+                    let not_at_end = self.not(at_end);
+                    authority_enabled = self.and(authority_enabled, not_at_end);
+                    self.select(authority_enabled, chained_hash, commitment_so_far)
+                }
+            });
         }
-        commitment_so_far
+        commitment_so_far.unwrap()
     }
 
     fn verify_voting_threshold<const MAX_NUM_AUTHORITIES: usize>(
         &mut self,
         num_active_authorities: U32Variable,
         validator_signed: &ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>,
-        threshold_numerator: U32Variable,
-        threshold_denominator: U32Variable,
+        weights: &ArrayVariable<U64Variable, MAX_NUM_AUTHORITIES>,
+        threshold_numerator: U64Variable,
+        threshold_denominator: U64Variable,
     ) {
         let true_v = self._true();
-        let mut num_signed: U32Variable = self.zero();
-        for i in 0..MAX_NUM_AUTHORITIES {
-            // 1 if validator signed, 0 otherwise. BoolVariable is already range-checked (as a bool), so using unsafe
-            // to convert to U32Variable is safe.
-            let val_signed_u32 =
-                U32Variable::from_variables_unsafe(&[validator_signed[i].variable]);
-            num_signed = self.add(num_signed, val_signed_u32);
-        }
-
-        // Verify the number of validators that signed is greater than to the threshold.
-        let scaled_num_signed = self.mul(num_signed, threshold_denominator);
-        let scaled_threshold = self.mul(num_active_authorities, threshold_numerator);
-        let is_valid_num_signed = self.gt(scaled_num_signed, scaled_threshold);
+        let is_valid_num_signed = is_voting_threshold_met(
+            self,
+            num_active_authorities,
+            validator_signed,
+            weights,
+            threshold_numerator,
+            threshold_denominator,
+        );
         self.assert_is_equal(is_valid_num_signed, true_v);
     }
 
@@ -191,83 +686,817 @@ impl<L: PlonkParameters<D>, const D: usize> GrandpaJustificationVerifier for Cir
     ///     1) Authority set commitment matches the authority set.
     ///     2) Specified precommit message matches the block #, authority set id, and block hash.
     ///     3) Signatures on the precommit message are valid from each validator marked as signed.
-    ///     4) More than 2/3 of the validators have signed the precommit message.
+    ///     4) Validators with more than 2/3 of the voting weight have signed the precommit message.
     fn verify_simple_justification<const MAX_NUM_AUTHORITIES: usize>(
         &mut self,
         block_number: U32Variable,
         block_hash: Bytes32Variable,
         authority_set_id: U64Variable,
         authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+    ) -> VerifiedJustification {
+        self.verify_simple_justification_from(
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            options,
+            DataSource::default(),
+        )
+    }
+
+    fn verify_simple_justification_from<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+        source: DataSource,
+    ) -> VerifiedJustification {
+        verify_simple_justification_with(
+            self,
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            options,
+            source,
+            verify_precommit_signatures,
+        )
+    }
+
+    fn verify_simple_justification_with_witness<const MAX_NUM_AUTHORITIES: usize>(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        justification: &JustificationVariable<MAX_NUM_AUTHORITIES>,
+        options: VerificationOptions,
+    ) -> VerifiedJustification {
+        let success = self._true();
+        verify_justification_witness(
+            self,
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            justification,
+            success,
+            options,
+            verify_precommit_signatures,
+        )
+    }
+
+    fn verify_justification_round(
+        &mut self,
+        justification: &VerifiedJustification,
+        expected_round: U64Variable,
     ) {
-        let mut input_stream = VariableStream::new();
-        input_stream.write(&block_number);
-        input_stream.write(&authority_set_id);
-        let output_stream = self.async_hint(
-            input_stream,
-            HintSimpleJustification::<MAX_NUM_AUTHORITIES> {},
-        );
+        let round_match = self.is_equal(justification.round, expected_round);
+        assert_if_success(self, round_match, justification.success);
+    }
+}
+
+/// Chained SHA256 hash of the first num_active_authorities public keys, see
+/// `compute_authority_set_commitment`. num_active_authorities must be at least 1.
+fn sha256_authority_set_commitment<
+    L: PlonkParameters<D>,
+    const D: usize,
+    const MAX_NUM_AUTHORITIES: usize,
+>(
+    builder: &mut CircuitBuilder<L, D>,
+    num_active_authorities: Variable,
+    authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+) -> Bytes32Variable {
+    let mut authority_enabled = builder._true();
+
+    // The first authority is always active, as num_active_authorities is at least 1.
+    let mut commitment_so_far = builder.curta_sha256(&authority_set_signers[0].0.as_bytes());
+
+    for i in 1..MAX_NUM_AUTHORITIES {
+        let curr_idx = builder.constant::<Variable>(L::Field::from_canonical_usize(i));
+        let at_end = builder.is_equal(curr_idx, num_active_authorities);
+        let not_at_end = builder.not(at_end);
 
-        // justification is untrusted, and must be linked to the trusted authority_set_hash.
-        let justification = output_stream.read::<JustificationVariable<MAX_NUM_AUTHORITIES>>(self);
+        // Upon reaching the last validator, turn enabled to false to ensure that the commitment_so_far is not updated.
+        // This is because the authority set commitment is the chained hash of the first num_active_authorities public keys.
+        authority_enabled = builder.and(authority_enabled, not_at_end);
 
-        // Verify the authority set commitment is valid.
-        let computed_authority_set_commitment = self.compute_authority_set_commitment(
+        let mut input_to_hash = Vec::new();
+        input_to_hash.extend_from_slice(&commitment_so_far.as_bytes());
+        input_to_hash.extend_from_slice(&authority_set_signers[i].0.as_bytes());
+
+        // Compute the chained hash of the authority set commitment.
+        let chained_hash = builder.curta_sha256(&input_to_hash);
+
+        // Update the commitment_so_far if this authority is enabled.
+        commitment_so_far = builder.select(authority_enabled, chained_hash, commitment_so_far);
+    }
+    commitment_so_far
+}
+
+/// Chained Poseidon hash of the first num_active_authorities public keys, as computed off-circuit by
+/// `compute_poseidon_authority_set_hash`. num_active_authorities must be at least 1.
+fn poseidon_authority_set_commitment<
+    L: PlonkParameters<D>,
+    const D: usize,
+    const MAX_NUM_AUTHORITIES: usize,
+>(
+    builder: &mut CircuitBuilder<L, D>,
+    num_active_authorities: Variable,
+    authority_set_signers: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+) -> Bytes32Variable {
+    let mut authority_enabled = builder._true();
+    let mut commitment_so_far: Vec<Variable> = Vec::new();
+    for i in 0..MAX_NUM_AUTHORITIES {
+        // Pack the pubkey into the little-endian u32 of each 4 bytes.
+        let pubkey_bytes = authority_set_signers[i].0.as_bytes();
+        let mut input_to_hash = commitment_so_far
+            .iter()
+            .map(|element| element.0)
+            .collect::<Vec<_>>();
+        for chunk in pubkey_bytes.chunks(4) {
+            let le_bits = chunk
+                .iter()
+                .flat_map(|byte| {
+                    let mut bits = byte.as_bool_targets();
+                    bits.reverse();
+                    bits
+                })
+                .collect::<Vec<_>>();
+            input_to_hash.push(builder.api.le_sum(le_bits.iter()));
+        }
+        let chained_hash = builder
+            .api
+            .hash_n_to_hash_no_pad::<PoseidonHash>(input_to_hash)
+            .elements
+            .map(Variable);
+
+        if i == 0 {
+            // The first authority is always active, as num_active_authorities is at least 1.
+            commitment_so_far = chained_hash.to_vec();
+            continue;
+        }
+        // Upon reaching the last validator, turn enabled to false to ensure that the
+        // commitment_so_far is not updated.
+        let curr_idx = builder.constant::<Variable>(L::Field::from_canonical_usize(i));
+        let at_end = builder.is_equal(curr_idx, num_active_authorities);
+        let not_at_end = builder.not(at_end);
+        authority_enabled = builder.and(authority_enabled, not_at_end);
+        commitment_so_far = chained_hash
+            .iter()
+            .zip(commitment_so_far.iter())
+            .map(|(chained, so_far)| builder.select(authority_enabled, *chained, *so_far))
+            .collect();
+    }
+
+    // Encode each field element as 8 big-endian bytes. The bits of an element are only unique if
+    // they are less than the field order 2^64 - 2^32 + 1, so assert the bits are not an element
+    // with the high 32 bits set and non-zero low 32 bits.
+    let false_v = builder._false();
+    let max_high = builder.constant::<Variable>(L::Field::from_canonical_usize(u32::MAX as usize));
+    let mut commitment_bytes = Vec::with_capacity(32);
+    for element in commitment_so_far {
+        let le_bits = builder.api.split_le(element.0, 64);
+        let low = Variable(builder.api.le_sum(le_bits[0..32].iter()));
+        let high = Variable(builder.api.le_sum(le_bits[32..64].iter()));
+        let is_max_high = builder.is_equal(high, max_high);
+        let is_zero_low = builder.is_zero(low);
+        let is_nonzero_low = builder.not(is_zero_low);
+        let is_non_canonical = builder.and(is_max_high, is_nonzero_low);
+        builder.assert_is_equal(is_non_canonical, false_v);
+
+        for byte_le_bits in le_bits.chunks(8).rev() {
+            let be_bits = byte_le_bits
+                .iter()
+                .rev()
+                .map(|bit| BoolVariable::from_variables_unsafe(&[Variable(bit.target)]))
+                .collect::<Vec<_>>();
+            commitment_bytes.push(ByteVariable(be_bits.try_into().unwrap()));
+        }
+    }
+    Bytes32Variable::from(commitment_bytes.as_slice())
+}
+
+/// Verifies the signatures on the precommit of each validator marked as signed in the justification,
+/// if the justification was fetched successfully.
+fn verify_precommit_signatures<
+    L: PlonkParameters<D>,
+    const D: usize,
+    const MAX_NUM_AUTHORITIES: usize,
+>(
+    builder: &mut CircuitBuilder<L, D>,
+    justification: &JustificationVariable<MAX_NUM_AUTHORITIES>,
+    success: BoolVariable,
+) {
+    // `curta_eddsa_verify_sigs_conditional` requires the message for each signature, but because the
+    // signed message is the same for all validators, pass a constant array with the same message.
+    let message_byte_lengths = builder.constant::<ArrayVariable<U32Variable, MAX_NUM_AUTHORITIES>>(
+        vec![ENCODED_PRECOMMIT_LENGTH as u32; MAX_NUM_AUTHORITIES],
+    );
+    // Note: Sharing one precommit guarantees that all votes occurred during the same round of
+    // consensus.
+    let messages = vec![justification.encoded_precommit.clone(); MAX_NUM_AUTHORITIES];
+    // Only verify the signatures if the justification was fetched successfully.
+    let verify_signature = justification
+        .validator_signed
+        .as_vec()
+        .iter()
+        .map(|signed| builder.and(*signed, success))
+        .collect::<Vec<_>>();
+    builder.curta_eddsa_verify_sigs_conditional(
+        verify_signature.into(),
+        Some(message_byte_lengths),
+        messages.into(),
+        justification.signatures.clone(),
+        justification.pubkeys.clone(),
+    );
+}
+
+/// Verifies a simple justification as in `verify_simple_justification`, with the signatures on the
+/// precommit verified by verify_signatures, which is passed the justification and whether it was
+/// fetched successfully. Signatures must only be verified if the justification was fetched
+/// successfully.
+pub(crate) fn verify_simple_justification_with<
+    L,
+    const D: usize,
+    const MAX_NUM_AUTHORITIES: usize,
+    F,
+>(
+    builder: &mut CircuitBuilder<L, D>,
+    block_number: U32Variable,
+    block_hash: Bytes32Variable,
+    authority_set_id: U64Variable,
+    authority_set_hash: Bytes32Variable,
+    options: VerificationOptions,
+    source: DataSource,
+    verify_signatures: F,
+) -> VerifiedJustification
+where
+    L: PlonkParameters<D>,
+    F: FnOnce(&mut CircuitBuilder<L, D>, &JustificationVariable<MAX_NUM_AUTHORITIES>, BoolVariable),
+{
+    let mut input_stream = VariableStream::new();
+    input_stream.write(&block_number);
+    input_stream.write(&authority_set_id);
+    let output_stream = builder.async_hint(
+        input_stream,
+        HintSimpleJustification::<MAX_NUM_AUTHORITIES> { source },
+    );
+
+    // The error code is 0 if the justification was fetched successfully. If not, justification
+    // is a placeholder and the checks below are skipped.
+    let error_code = output_stream.read::<U32Variable>(builder);
+    let zero_v = builder.zero::<U32Variable>();
+    let success = builder.is_equal(error_code, zero_v);
+
+    // justification is untrusted, and must be linked to the trusted authority_set_hash.
+    let justification = output_stream.read::<JustificationVariable<MAX_NUM_AUTHORITIES>>(builder);
+
+    verify_justification_witness(
+        builder,
+        block_number,
+        block_hash,
+        authority_set_id,
+        authority_set_hash,
+        &justification,
+        success,
+        options,
+        verify_signatures,
+    )
+}
+
+/// Runs the checks of `verify_simple_justification` on the justification, if success is true. The
+/// signatures on the precommit are verified by verify_signatures, as in
+/// `verify_simple_justification_with`.
+pub(crate) fn verify_justification_witness<L, const D: usize, const MAX_NUM_AUTHORITIES: usize, F>(
+    builder: &mut CircuitBuilder<L, D>,
+    block_number: U32Variable,
+    block_hash: Bytes32Variable,
+    authority_set_id: U64Variable,
+    authority_set_hash: Bytes32Variable,
+    justification: &JustificationVariable<MAX_NUM_AUTHORITIES>,
+    success: BoolVariable,
+    options: VerificationOptions,
+    verify_signatures: F,
+) -> VerifiedJustification
+where
+    L: PlonkParameters<D>,
+    F: FnOnce(&mut CircuitBuilder<L, D>, &JustificationVariable<MAX_NUM_AUTHORITIES>, BoolVariable),
+{
+    if !options.is_strict() {
+        warn!(
+            "verify_simple_justification is running with checks disabled ({:?}). The circuit is UNSAFE for production!",
+            options
+        );
+    }
+
+    // Verify the authority set commitment is valid. The commitment binds the weights of the
+    // authorities.
+    if options.verify_authority_set_commitment {
+        let computed_authority_set_commitment = builder.compute_weighted_authority_set_commitment(
             justification.num_authorities.variable,
             &justification.pubkeys,
+            &justification.weights,
         );
-        self.assert_is_equal(authority_set_hash, computed_authority_set_commitment);
-
-        // Verify the correctness of the encoded_precommit message.
-        let decoded_precommit = self.decode_precommit(justification.encoded_precommit);
-        self.assert_is_equal(decoded_precommit.block_number, block_number);
-        self.assert_is_equal(decoded_precommit.authority_set_id, authority_set_id);
-        self.assert_is_equal(decoded_precommit.block_hash, block_hash);
-
-        // Verify the signatures of the validators on the encoded_precommit message.
-        // `curta_eddsa_verify_sigs_conditional` requires the message for each signature, but because
-        // the signed message is the same for all validators, pass a constant array with the same message.
-        let message_byte_lengths = self
-            .constant::<ArrayVariable<U32Variable, MAX_NUM_AUTHORITIES>>(vec![
-                ENCODED_PRECOMMIT_LENGTH
-                    as u32;
-                MAX_NUM_AUTHORITIES
-            ]);
-        // Note: Sharing one precommit guarantees that all votes occurred during the same round of consensus.
-        let messages = vec![justification.encoded_precommit; MAX_NUM_AUTHORITIES];
-        self.curta_eddsa_verify_sigs_conditional(
-            justification.validator_signed.clone(),
-            Some(message_byte_lengths),
-            messages.into(),
-            justification.signatures,
-            justification.pubkeys,
+        let commitment_match =
+            builder.is_equal(authority_set_hash, computed_authority_set_commitment);
+        assert_if_success(builder, commitment_match, success);
+    }
+
+    // Verify the correctness of the encoded_precommit message.
+    if options.verify_precommit {
+        let decoded_precommit = builder.decode_precommit(justification.encoded_precommit.clone());
+        let block_number_match = builder.is_equal(decoded_precommit.block_number, block_number);
+        assert_if_success(builder, block_number_match, success);
+        let authority_set_id_match =
+            builder.is_equal(decoded_precommit.authority_set_id, authority_set_id);
+        assert_if_success(builder, authority_set_id_match, success);
+        let block_hash_match = builder.is_equal(decoded_precommit.block_hash, block_hash);
+        assert_if_success(builder, block_hash_match, success);
+        // Bind the round of the justification to the round in the signed precommit, so it can be
+        // checked against an expected round with `verify_justification_round`.
+        let round_match =
+            builder.is_equal(decoded_precommit.justification_round, justification.round);
+        assert_if_success(builder, round_match, success);
+    }
+
+    // Verify the signatures of the validators on the encoded_precommit message.
+    if options.verify_signatures {
+        // A validator marked as signed must not carry DUMMY_SIGNATURE, and a validator that did
+        // not sign must carry it.
+        let signatures_consistent = is_signed_consistent_with_signatures(
+            builder,
+            &justification.validator_signed,
+            &justification.signatures,
         );
+        assert_if_success(builder, signatures_consistent, success);
 
-        // Verify more than 2/3 of the validators have signed the message.
-        let two_v = self.constant::<U32Variable>(2u32);
-        let three_v = self.constant::<U32Variable>(3u32);
+        verify_signatures(builder, justification, success);
+    }
+
+    // Verify validators with more than 2/3 of the voting weight have signed the message.
+    if options.verify_voting_threshold {
+        let two_v = builder.constant::<U64Variable>(2u64);
+        let three_v = builder.constant::<U64Variable>(3u64);
 
-        // Note: All validators have a voting power of 1 in Avail, verify > 2/3 of the validators have signed.
+        // Note: All validators currently have a voting power of 1 in Avail, in which case this
+        // verifies > 2/3 of the validators have signed.
         // Spec: https://github.com/availproject/polkadot-sdk/blob/70e569d5112f879001a987e94402ff70f9683cb5/substrate/frame/grandpa/src/lib.rs#L585
-        self.verify_voting_threshold(
+        let is_valid_num_signed = is_voting_threshold_met(
+            builder,
             justification.num_authorities,
             &justification.validator_signed,
+            &justification.weights,
             two_v,
             three_v,
-        )
+        );
+        assert_if_success(builder, is_valid_num_signed, success);
+    }
+
+    let num_signed = count_active_signed(
+        builder,
+        justification.num_authorities,
+        &justification.validator_signed,
+    );
+
+    VerifiedJustification {
+        num_active_authorities: justification.num_authorities,
+        num_signed,
+        round: justification.round,
+        success,
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub mod tests {
     use std::env;
 
-    use plonky2x::prelude::DefaultBuilder;
+    use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+    use ethers::types::H256;
+    use plonky2x::prelude::{DefaultBuilder, GoldilocksField};
 
     use super::*;
+    use crate::input::{
+        compute_authority_set_commitment, compute_authority_set_hash,
+        compute_authority_set_hash_with, compute_weighted_authority_set_hash, decode_precommit,
+    };
+
+    /// Converts the value type of `EDDSASignatureVariable` back to the signature bytes.
+    fn value_type_to_signature(
+        signature: &EDDSASignatureVariableValue<GoldilocksField>,
+    ) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[0..32].copy_from_slice(signature.r.as_bytes());
+        signature.s.to_little_endian(&mut bytes[32..64]);
+        bytes
+    }
+
+    #[test]
+    fn test_signature_to_value_type_small_s() {
+        // A signature whose s is a small value, so all but the lowest byte of s are zero.
+        let mut signature = DUMMY_SIGNATURE;
+        signature[32..64].copy_from_slice(&[0u8; 32]);
+        signature[32] = 5;
+        let value = signature_to_value_type::<GoldilocksField>(&signature).unwrap();
+        assert_eq!(value.s, U256::from(5));
+        assert_eq!(value_type_to_signature(&value), signature);
+
+        // s = 0.
+        signature[32] = 0;
+        let value = signature_to_value_type::<GoldilocksField>(&signature).unwrap();
+        assert_eq!(value.s, U256::zero());
+        assert_eq!(value_type_to_signature(&value), signature);
+    }
+
+    #[test]
+    fn test_signature_to_value_type_invalid_length() {
+        assert_eq!(
+            signature_to_value_type::<GoldilocksField>(&DUMMY_SIGNATURE[..32]).err(),
+            Some(SignatureError::InvalidLength { length: 32 })
+        );
+        assert_eq!(
+            signature_to_value_type::<GoldilocksField>(&[]).err(),
+            Some(SignatureError::InvalidLength { length: 0 })
+        );
+    }
+
+    #[test]
+    fn test_signature_to_value_type_verifies() {
+        let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = Keypair { secret, public };
+        let message = [1u8; ENCODED_PRECOMMIT_LENGTH];
+        let signature = keypair.sign(&message).to_bytes();
+
+        // The signature is unchanged by the conversion, and still verifies.
+        let value = signature_to_value_type::<GoldilocksField>(&signature).unwrap();
+        let converted_signature = value_type_to_signature(&value);
+        assert_eq!(converted_signature, signature);
+        assert!(is_signature_valid(
+            public.as_bytes(),
+            &message,
+            &converted_signature
+        ));
+    }
+
+    #[test]
+    fn test_check_circuit_justification() {
+        const NUM_AUTHORITIES: usize = 4;
+        let dummy = dummy_justification::<NUM_AUTHORITIES, GoldilocksField>();
+
+        let mut justification = CircuitJustification {
+            authority_set_id: 10,
+            signed_message: dummy.encoded_precommit.to_vec(),
+            validator_signed: vec![false; NUM_AUTHORITIES],
+            pubkeys: dummy.pubkeys.clone(),
+            signatures: vec![DUMMY_SIGNATURE; NUM_AUTHORITIES],
+            weights: vec![1; NUM_AUTHORITIES],
+            num_authorities: 1,
+            current_authority_set_hash: vec![],
+            round: 0,
+        };
+        assert_eq!(check_circuit_justification(&justification, 10), Ok(()));
+
+        assert_eq!(
+            check_circuit_justification(&justification, 11),
+            Err(JustificationError::AuthoritySetIdMismatch)
+        );
+
+        // A validator marked as signed with a signature that does not sign the precommit.
+        justification.validator_signed[0] = true;
+        assert_eq!(
+            check_circuit_justification(&justification, 10),
+            Err(JustificationError::InvalidSignature)
+        );
+
+        justification.signed_message.pop();
+        assert_eq!(
+            check_circuit_justification(&justification, 10),
+            Err(JustificationError::InvalidPrecommitLength)
+        );
+    }
+
+    /// Proves the commitment computed by commit over the first num_active_authorities of 4
+    /// authorities, with pubkeys [1; 32], [2; 32], [3; 32], and [4; 32].
+    fn prove_authority_set_commitment(
+        num_active_authorities: usize,
+        commit: impl FnOnce(
+            &mut DefaultBuilder,
+            Variable,
+            &ArrayVariable<CompressedEdwardsYVariable, 4>,
+        ) -> Bytes32Variable,
+    ) -> H256 {
+        let mut builder = DefaultBuilder::new();
+        let num_authorities = builder.read::<Variable>();
+        let pubkeys = builder.constant::<ArrayVariable<CompressedEdwardsYVariable, 4>>(
+            (1..=4u8).map(|i| CompressedEdwardsY([i; 32])).collect(),
+        );
+        let commitment = commit(&mut builder, num_authorities, &pubkeys);
+        builder.write(commitment);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<Variable>(GoldilocksField::from_canonical_usize(
+            num_active_authorities,
+        ));
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        output.read::<Bytes32Variable>()
+    }
+
+    #[test]
+    fn test_weighted_authority_set_commitment() {
+        let pubkeys = (1..=3u8)
+            .map(|i| CompressedEdwardsY([i; 32]))
+            .collect::<Vec<_>>();
+        let commitment = prove_authority_set_commitment(3, |builder, num_authorities, pubkeys| {
+            builder.compute_authority_set_commitment(num_authorities, pubkeys)
+        });
+
+        // With unit weights, the weighted commitment matches the unweighted commitment. The weight of
+        // the padding authority is not part of the commitment.
+        let weighted_commitment =
+            prove_authority_set_commitment(3, |builder, num_authorities, pubkeys| {
+                let weights = builder.constant::<ArrayVariable<U64Variable, 4>>(vec![1, 1, 1, 7]);
+                builder.compute_weighted_authority_set_commitment(
+                    num_authorities,
+                    pubkeys,
+                    &weights,
+                )
+            });
+        assert_eq!(weighted_commitment, commitment);
+        assert_eq!(
+            weighted_commitment.0.to_vec(),
+            compute_authority_set_hash(&pubkeys)
+        );
+
+        // With non-unit weights, the weights are bound to the commitment.
+        let weighted_commitment =
+            prove_authority_set_commitment(3, |builder, num_authorities, pubkeys| {
+                let weights = builder.constant::<ArrayVariable<U64Variable, 4>>(vec![1, 5, 1, 1]);
+                builder.compute_weighted_authority_set_commitment(
+                    num_authorities,
+                    pubkeys,
+                    &weights,
+                )
+            });
+        assert_ne!(weighted_commitment, commitment);
+        assert_eq!(
+            weighted_commitment.0.to_vec(),
+            compute_weighted_authority_set_hash(&pubkeys, &[1, 5, 1])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_weighted_authority_set_commitment_no_authorities() {
+        // Only the weighted commitment is built, so its own assert must reject 0 authorities.
+        prove_authority_set_commitment(0, |builder, num_authorities, pubkeys| {
+            let weights = builder.constant::<ArrayVariable<U64Variable, 4>>(vec![1; 4]);
+            builder.compute_weighted_authority_set_commitment(num_authorities, pubkeys, &weights)
+        });
+    }
+
+    #[test]
+    fn test_authority_set_commitment_num_authorities() {
+        let pubkeys = (1..=4u8)
+            .map(|i| CompressedEdwardsY([i; 32]))
+            .collect::<Vec<_>>();
+
+        for num_active_authorities in 1..=pubkeys.len() {
+            let commitment = prove_authority_set_commitment(
+                num_active_authorities,
+                |builder, num_authorities, pubkeys| {
+                    builder.compute_authority_set_commitment(num_authorities, pubkeys)
+                },
+            );
+            let pubkey_bytes = pubkeys[..num_active_authorities]
+                .iter()
+                .map(|pubkey| pubkey.0)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                commitment.0,
+                compute_authority_set_commitment(&pubkey_bytes)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_authority_set_commitment_no_authorities() {
+        prove_authority_set_commitment(0, |builder, num_authorities, pubkeys| {
+            builder.compute_authority_set_commitment(num_authorities, pubkeys)
+        });
+    }
+
+    #[test]
+    fn test_poseidon_authority_set_commitment() {
+        let pubkeys = (1..=4u8)
+            .map(|i| CompressedEdwardsY([i; 32]))
+            .collect::<Vec<_>>();
+
+        for num_active_authorities in [1, 2, 4] {
+            let commitment = prove_authority_set_commitment(
+                num_active_authorities,
+                |builder, num_authorities, pubkeys| {
+                    builder.compute_authority_set_commitment_with_hash(
+                        num_authorities,
+                        pubkeys,
+                        CommitmentHash::Poseidon,
+                    )
+                },
+            );
+            assert_eq!(
+                commitment.0.to_vec(),
+                compute_authority_set_hash_with(
+                    &pubkeys[..num_active_authorities],
+                    CommitmentHash::Poseidon
+                )
+            );
+            assert_ne!(
+                commitment.0.to_vec(),
+                compute_authority_set_hash(&pubkeys[..num_active_authorities])
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_poseidon_authority_set_commitment_no_authorities() {
+        prove_authority_set_commitment(0, |builder, num_authorities, pubkeys| {
+            builder.compute_authority_set_commitment_with_hash(
+                num_authorities,
+                pubkeys,
+                CommitmentHash::Poseidon,
+            )
+        });
+    }
+
+    fn prove_signed_consistent(validator_signed: Vec<bool>, signatures: Vec<[u8; 64]>) -> bool {
+        const MAX_NUM_AUTHORITIES: usize = 3;
+        let mut builder = DefaultBuilder::new();
+
+        let validator_signed_var =
+            builder.read::<ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>>();
+        let signatures_var =
+            builder.read::<ArrayVariable<EDDSASignatureVariable, MAX_NUM_AUTHORITIES>>();
+        let is_consistent = is_signed_consistent_with_signatures(
+            &mut builder,
+            &validator_signed_var,
+            &signatures_var,
+        );
+        builder.write(is_consistent);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>>(validator_signed);
+        input.write::<ArrayVariable<EDDSASignatureVariable, MAX_NUM_AUTHORITIES>>(
+            signatures
+                .iter()
+                .map(|signature| signature_to_value_type(signature).unwrap())
+                .collect(),
+        );
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        output.read::<BoolVariable>()
+    }
+
+    #[test]
+    fn test_signed_consistent_with_signatures() {
+        let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = Keypair { secret, public };
+        let signature = keypair.sign(&[1u8; ENCODED_PRECOMMIT_LENGTH]).to_bytes();
+
+        // Signed validators carry real signatures, and the rest carry DUMMY_SIGNATURE.
+        assert!(prove_signed_consistent(
+            vec![true, false, false],
+            vec![signature, DUMMY_SIGNATURE, DUMMY_SIGNATURE]
+        ));
+
+        // A validator marked as signed with DUMMY_SIGNATURE.
+        assert!(!prove_signed_consistent(
+            vec![true, true, false],
+            vec![signature, DUMMY_SIGNATURE, DUMMY_SIGNATURE]
+        ));
+
+        // A validator not marked as signed with a real signature.
+        assert!(!prove_signed_consistent(
+            vec![true, false, false],
+            vec![signature, signature, DUMMY_SIGNATURE]
+        ));
+    }
+
+    fn prove_voting_threshold(validator_signed: Vec<bool>, weights: Vec<u64>) -> bool {
+        const MAX_NUM_AUTHORITIES: usize = 5;
+        let mut builder = DefaultBuilder::new();
+
+        let validator_signed_var =
+            builder.read::<ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>>();
+        let weights_var = builder.read::<ArrayVariable<U64Variable, MAX_NUM_AUTHORITIES>>();
+        // The last authority is padding.
+        let num_active_authorities = builder.constant::<U32Variable>(4u32);
+        let two_v = builder.constant::<U64Variable>(2u64);
+        let three_v = builder.constant::<U64Variable>(3u64);
+        let threshold_met = is_voting_threshold_met(
+            &mut builder,
+            num_active_authorities,
+            &validator_signed_var,
+            &weights_var,
+            two_v,
+            three_v,
+        );
+        builder.write(threshold_met);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>>(validator_signed);
+        input.write::<ArrayVariable<U64Variable, MAX_NUM_AUTHORITIES>>(weights);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        output.read::<BoolVariable>()
+    }
+
+    #[test]
+    fn test_voting_threshold_weighted() {
+        // With unit weights, more than 2/3 of the active validators must sign.
+        let unit_weights = vec![1; 5];
+        assert!(prove_voting_threshold(
+            vec![true, true, true, false, false],
+            unit_weights.clone()
+        ));
+        assert!(!prove_voting_threshold(
+            vec![true, true, false, false, false],
+            unit_weights.clone()
+        ));
+        // A signed padding validator is not counted.
+        assert!(!prove_voting_threshold(
+            vec![true, true, false, false, true],
+            unit_weights
+        ));
+
+        // With weights, the signed weight must be more than 2/3 of the total weight of 8.
+        let weights = vec![5, 1, 1, 1, 100];
+        assert!(!prove_voting_threshold(
+            vec![true, false, false, false, false],
+            weights.clone()
+        ));
+        assert!(prove_voting_threshold(
+            vec![true, true, false, false, false],
+            weights.clone()
+        ));
+        assert!(!prove_voting_threshold(
+            vec![false, true, true, true, true],
+            weights
+        ));
+
+        // An active weight above MAX_AUTHORITY_WEIGHT fails the threshold, even if it is met.
+        let max_weight = MAX_AUTHORITY_WEIGHT;
+        assert!(prove_voting_threshold(
+            vec![true, true, true, false, false],
+            vec![max_weight, max_weight, max_weight, 1, 1]
+        ));
+        assert!(!prove_voting_threshold(
+            vec![true, true, true, true, false],
+            vec![max_weight + 1, 1, 1, 1, 1]
+        ));
+        // The padding validator's weight is not bounded, as it is not counted.
+        assert!(prove_voting_threshold(
+            vec![true, true, true, false, false],
+            vec![1, 1, 1, 1, u64::MAX]
+        ));
+    }
+
+    #[test]
+    fn test_count_active_signed() {
+        const NUM_AUTHORITIES: usize = 4;
+        let mut builder = DefaultBuilder::new();
+        let num_active_authorities = builder.read::<U32Variable>();
+        let validator_signed = builder.read::<ArrayVariable<BoolVariable, NUM_AUTHORITIES>>();
+        let num_signed =
+            count_active_signed(&mut builder, num_active_authorities, &validator_signed);
+        builder.write(num_signed);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<U32Variable>(3);
+        input.write::<ArrayVariable<BoolVariable, NUM_AUTHORITIES>>(vec![true, false, true, true]);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        // The signed padding validator is not counted.
+        assert_eq!(
+            output.read::<Variable>(),
+            GoldilocksField::from_canonical_usize(2)
+        );
+    }
 
     #[test]
     #[cfg_attr(feature = "ci", ignore)]
-    fn test_verify_simple_justification() {
+    fn test_verify_simple_justification_fetch_failed() {
         env::set_var("RUST_LOG", "debug");
         dotenv::dotenv().ok();
         env_logger::try_init().unwrap_or_default();
@@ -280,41 +1509,351 @@ mod tests {
         let authority_set_id = builder.read::<U64Variable>();
         let authority_set_hash = builder.read::<Bytes32Variable>();
 
-        builder.verify_simple_justification::<NUM_AUTHORITIES>(
+        let verified_justification = builder.verify_simple_justification::<NUM_AUTHORITIES>(
             block_number,
             block_hash,
             authority_set_id,
             authority_set_hash,
+            VerificationOptions::default(),
         );
+        builder.write(verified_justification.success);
+
+        let circuit = builder.build();
+
+        // Block 4320 is not an epoch end block, and its justification is not stored in Redis, so
+        // the hint fails to fetch it.
+        let mut input = circuit.input();
+        input.write::<U32Variable>(4320u32);
+        input.write::<Bytes32Variable>(H256::zero());
+        input.write::<U64Variable>(0u64);
+        input.write::<Bytes32Variable>(H256::zero());
+
+        // The hint returns cleanly, and the proof is generated with success = false.
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        let success = output.read::<BoolVariable>();
+        assert!(!success);
+    }
+
+    #[test]
+    fn test_verify_simple_justification() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
+
+        // The fixture is a justification for block 272535 signed by 4 deterministic test keys, so
+        // the hint serves it without an Avail node.
+        let fixtures_path = concat!(env!("CARGO_MANIFEST_DIR"), "/circuits/fixtures/272535.json");
+
+        const NUM_AUTHORITIES: usize = 8;
+        let mut builder = DefaultBuilder::new();
+
+        let block_number = builder.read::<U32Variable>();
+        let block_hash = builder.read::<Bytes32Variable>();
+        let authority_set_id = builder.read::<U64Variable>();
+        let authority_set_hash = builder.read::<Bytes32Variable>();
+
+        let verified_justification = builder.verify_simple_justification_from::<NUM_AUTHORITIES>(
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            VerificationOptions::default(),
+            DataSource::fixtures(fixtures_path),
+        );
+        builder.write(verified_justification.success);
+        builder.write(verified_justification.num_signed);
+        builder.write(verified_justification.round);
 
         log::debug!("Building circuit");
         let circuit = builder.build();
         log::debug!("Done building circuit");
 
+        let target_block = 272535u32;
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let justification = rt.block_on(async {
+            MockDataFetcher::from_file(fixtures_path)
+                .get_justification_from_block::<NUM_AUTHORITIES>(target_block)
+                .await
+                .unwrap()
+        });
+        let (block_hash, _, round, authority_set_id) =
+            decode_precommit(justification.signed_message.clone());
+
         let mut input = circuit.input();
+        input.write::<U32Variable>(target_block);
+        input.write::<Bytes32Variable>(block_hash);
+        input.write::<U64Variable>(authority_set_id);
+        input.write::<Bytes32Variable>(H256::from_slice(&justification.current_authority_set_hash));
+
+        log::debug!("Generating proof");
+        let (proof, mut output) = circuit.prove(&input);
+        log::debug!("Done generating proof");
+        circuit.verify(&proof, &input, &output);
+
+        assert!(output.read::<BoolVariable>());
+        assert_eq!(
+            output.read::<Variable>(),
+            GoldilocksField::from_canonical_usize(4)
+        );
+        assert_eq!(output.read::<U64Variable>(), round);
+    }
+
+    /// Returns the justification for block 272535 signed by 4 deterministic test keys, as a witness
+    /// of the circuit.
+    fn fixture_justification_witness<const NUM_AUTHORITIES: usize>(
+    ) -> JustificationStruct<NUM_AUTHORITIES, GoldilocksField> {
+        let fixtures_path = concat!(env!("CARGO_MANIFEST_DIR"), "/circuits/fixtures/272535.json");
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let justification = rt.block_on(async {
+            MockDataFetcher::from_file(fixtures_path)
+                .get_justification_from_block::<NUM_AUTHORITIES>(272535)
+                .await
+                .unwrap()
+        });
+        JustificationStruct {
+            encoded_precommit: justification.signed_message.try_into().unwrap(),
+            validator_signed: justification.validator_signed,
+            signatures: justification
+                .signatures
+                .iter()
+                .map(|signature| signature_to_value_type(signature).unwrap())
+                .collect(),
+            pubkeys: justification.pubkeys,
+            weights: justification.weights,
+            num_authorities: justification.num_authorities as u32,
+            round: justification.round,
+        }
+    }
+
+    /// Verifies the justification witness against the block, authority set and commitment in its
+    /// precommit, and that it is from expected_round. Returns the success flag and signed count.
+    fn prove_justification_witness<const NUM_AUTHORITIES: usize>(
+        justification: JustificationStruct<NUM_AUTHORITIES, GoldilocksField>,
+        expected_round: u64,
+    ) -> (bool, GoldilocksField) {
+        let mut builder = DefaultBuilder::new();
+        let block_number = builder.read::<U32Variable>();
+        let block_hash = builder.read::<Bytes32Variable>();
+        let authority_set_id = builder.read::<U64Variable>();
+        let authority_set_hash = builder.read::<Bytes32Variable>();
+        let justification_var = builder.read::<JustificationVariable<NUM_AUTHORITIES>>();
+        let expected_round_var = builder.read::<U64Variable>();
+
+        let verified_justification = builder
+            .verify_simple_justification_with_witness::<NUM_AUTHORITIES>(
+                block_number,
+                block_hash,
+                authority_set_id,
+                authority_set_hash,
+                &justification_var,
+                VerificationOptions::default(),
+            );
+        builder.verify_justification_round(&verified_justification, expected_round_var);
+        builder.write(verified_justification.success);
+        builder.write(verified_justification.num_signed);
+        let circuit = builder.build();
 
-        // target_block is an era end block in epoch 0 with 5 authorities.
+        let (block_hash, block_number, _, authority_set_id) =
+            decode_precommit(justification.encoded_precommit.to_vec());
+        let authority_set_hash = H256::from_slice(&compute_weighted_authority_set_hash(
+            &justification.pubkeys[..justification.num_authorities as usize],
+            &justification.weights[..justification.num_authorities as usize],
+        ));
+        let mut input = circuit.input();
+        input.write::<U32Variable>(block_number);
+        input.write::<Bytes32Variable>(block_hash);
+        input.write::<U64Variable>(authority_set_id);
+        input.write::<Bytes32Variable>(authority_set_hash);
+        input.write::<JustificationVariable<NUM_AUTHORITIES>>(justification);
+        input.write::<U64Variable>(expected_round);
+
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        (output.read::<BoolVariable>(), output.read::<Variable>())
+    }
+
+    #[test]
+    fn test_verify_simple_justification_with_witness() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
+
+        // The justification is passed to the circuit directly, without the hint.
+        let justification = fixture_justification_witness::<8>();
+        let round = justification.round;
+        let (success, num_signed) = prove_justification_witness(justification, round);
+        assert!(success);
+        assert_eq!(num_signed, GoldilocksField::from_canonical_usize(4));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_verify_justification_round_mismatch() {
+        // The justification is valid, but is not from the expected round.
+        let justification = fixture_justification_witness::<8>();
+        let round = justification.round;
+        prove_justification_witness(justification, round + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_verify_simple_justification_mismatched_round() {
+        // The justification claims a different round than the round in its signed precommit, so
+        // the precommit check rejects it even though the expected round matches the claim.
+        let mut justification = fixture_justification_witness::<8>();
+        justification.round += 1;
+        let round = justification.round;
+        prove_justification_witness(justification, round);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    #[should_panic]
+    fn test_verify_simple_justification_wrong_block_hash() {
+        env::set_var("RUST_LOG", "debug");
+        dotenv::dotenv().ok();
+        env_logger::try_init().unwrap_or_default();
+
+        const NUM_AUTHORITIES: usize = 8;
+        let mut builder = DefaultBuilder::new();
+
+        let block_number = builder.read::<U32Variable>();
+        let block_hash = builder.read::<Bytes32Variable>();
+        let authority_set_id = builder.read::<U64Variable>();
+        let authority_set_hash = builder.read::<Bytes32Variable>();
+
+        builder.verify_simple_justification::<NUM_AUTHORITIES>(
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            VerificationOptions::default(),
+        );
+
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+
+        // The signatures in the justification for target_block are valid, but they are over the
+        // precommit of target_block's real header hash, so binding the precommit to a different
+        // block hash must fail.
         let target_block = 4321u32;
-        let target_header = "c70877fed9ae5a040edb11e8800b3df53ec4c9ec67d07b5655a300ae11727dc1"
-            .parse()
-            .unwrap();
+        let wrong_target_header =
+            "0000000000000000000000000000000000000000000000000000000000000001"
+                .parse()
+                .unwrap();
         let authority_set_id = 0u64;
         let authority_set_hash = "54eb3049b763a6a84c391d53ffb5e93515a171b2dbaaa6a900ec09e3b6bb8dfb"
             .parse()
             .unwrap();
 
         input.write::<U32Variable>(target_block);
+        input.write::<Bytes32Variable>(wrong_target_header);
+        input.write::<U64Variable>(authority_set_id);
+        input.write::<Bytes32Variable>(authority_set_hash);
+
+        let (proof, output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_verify_simple_justification_without_threshold_check() {
+        env::set_var("RUST_LOG", "debug");
+        dotenv::dotenv().ok();
+        env_logger::try_init().unwrap_or_default();
+
+        const NUM_AUTHORITIES: usize = 8;
+        let mut builder = DefaultBuilder::new();
+
+        let block_number = builder.read::<U32Variable>();
+        let block_hash = builder.read::<Bytes32Variable>();
+        let authority_set_id = builder.read::<U64Variable>();
+        let authority_set_hash = builder.read::<Bytes32Variable>();
+
+        // Disable the threshold check. The remaining checks still bind the justification to the
+        // inputs, so a wrong authority set hash must still fail to prove.
+        let options = VerificationOptions {
+            verify_voting_threshold: false,
+            ..VerificationOptions::default()
+        };
+        assert!(!options.is_strict());
+
+        builder.verify_simple_justification::<NUM_AUTHORITIES>(
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            options,
+        );
+
+        let circuit = builder.build();
+
+        let target_block = 4321u32;
+        let target_header: H256 =
+            "c70877fed9ae5a040edb11e8800b3df53ec4c9ec67d07b5655a300ae11727dc1"
+                .parse()
+                .unwrap();
+        let authority_set_id = 0u64;
+        let authority_set_hash: H256 =
+            "54eb3049b763a6a84c391d53ffb5e93515a171b2dbaaa6a900ec09e3b6bb8dfb"
+                .parse()
+                .unwrap();
 
+        let mut input = circuit.input();
+        input.write::<U32Variable>(target_block);
         input.write::<Bytes32Variable>(target_header);
+        input.write::<U64Variable>(authority_set_id);
+        input.write::<Bytes32Variable>(authority_set_hash);
+        let (proof, output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
 
+        // The authority set commitment check still runs with the threshold check disabled.
+        let mut input = circuit.input();
+        input.write::<U32Variable>(target_block);
+        input.write::<Bytes32Variable>(target_header);
         input.write::<U64Variable>(authority_set_id);
+        input.write::<Bytes32Variable>(H256::zero());
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| circuit.prove(&input)));
+        assert!(result.is_err());
+    }
 
-        input.write::<Bytes32Variable>(authority_set_hash);
+    /// Builds the circuit defined by define, and proves it on the justification of block 4321,
+    /// which define reads as the block number, block hash, authority set id, and authority set
+    /// hash. Logs the degree of the circuit and the time taken to build and prove it for config.
+    pub fn bench_justification(config: &str, define: impl FnOnce(&mut DefaultBuilder)) {
+        let mut builder = DefaultBuilder::new();
+        let start = Instant::now();
+        define(&mut builder);
+        let circuit = builder.build();
+        let build_time = start.elapsed();
 
-        log::debug!("Generating proof");
-        let (proof, output) = circuit.prove(&input);
-        log::debug!("Done generating proof");
+        let mut input = circuit.input();
+        // Block 4321 is an epoch end block, so it's not reliant on a stored justification.
+        input.write::<U32Variable>(4321u32);
+        input.write::<Bytes32Variable>(
+            "c70877fed9ae5a040edb11e8800b3df53ec4c9ec67d07b5655a300ae11727dc1"
+                .parse()
+                .unwrap(),
+        );
+        input.write::<U64Variable>(0u64);
+        input.write::<Bytes32Variable>(
+            "54eb3049b763a6a84c391d53ffb5e93515a171b2dbaaa6a900ec09e3b6bb8dfb"
+                .parse()
+                .unwrap(),
+        );
 
+        let start = Instant::now();
+        let (proof, output) = circuit.prove(&input);
+        let prove_time = start.elapsed();
         circuit.verify(&proof, &input, &output);
+
+        log::info!(
+            "{}: degree_bits={}, built in {:?}, proved in {:?}",
+            config,
+            circuit.data.common.degree_bits(),
+            build_time,
+            prove_time
+        );
     }
 }
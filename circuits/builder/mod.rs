@@ -2,4 +2,6 @@ pub mod decoder;
 pub mod header;
 pub mod justification;
 pub mod rotate;
+pub mod signature_chunks;
+pub mod signature_slots;
 pub mod subchain_verification;
@@ -1,39 +1,52 @@
+use ethers::types::H256;
 use plonky2x::frontend::curta::ec::point::CompressedEdwardsYVariable;
 use plonky2x::frontend::uint::uint64::U64Variable;
 use plonky2x::prelude::{
-    ArrayVariable, ByteVariable, Bytes32Variable, CircuitBuilder, Field, PlonkParameters, Variable,
+    ArrayVariable, BoolVariable, ByteVariable, Bytes32Variable, BytesVariable, CircuitBuilder,
+    EvmVariable, Field, PlonkParameters, U32Variable, Variable,
 };
 
 use super::decoder::DecodingMethods;
-use super::header::HeaderMethods;
-use crate::builder::justification::GrandpaJustificationVerifier;
+use super::header::{decode_block_number, HeaderMethods};
+use crate::builder::justification::{GrandpaJustificationVerifier, VerificationOptions};
 use crate::consts::{
-    BASE_PREFIX_LENGTH, DELAY_LENGTH, MAX_COMPACT_UINT_BYTES, MAX_PREFIX_LENGTH, PUBKEY_LENGTH,
-    VALIDATOR_LENGTH, WEIGHT_LENGTH,
+    BASE_PREFIX_LENGTH, DELAY_LENGTH, DIGEST_CONSENSUS_TAG, DIGEST_ITEM_PREFIX_LENGTH,
+    DIGEST_OTHER_TAG, DIGEST_PRE_RUNTIME_TAG, DIGEST_RUNTIME_ENVIRONMENT_UPDATED_TAG,
+    DIGEST_SEAL_TAG, FORCED_CHANGE_FLAG, FORCED_CHANGE_MEDIAN_LENGTH, HASH_SIZE,
+    MAX_COMPACT_UINT_BYTES, MAX_DIGEST_ITEMS, MAX_PREFIX_LENGTH, PUBKEY_LENGTH,
+    SCHEDULED_CHANGE_FLAG, VALIDATOR_LENGTH, WEIGHT_LENGTH,
 };
+use crate::input::types::DataSource;
+use crate::rotate::not_epoch_end_rotate;
 use crate::vars::*;
 
 pub trait RotateMethods {
     /// Verifies the prefix bytes before the encoded authority set length are valid, according to the spec
     /// for the epoch end header. The purpose of this function is to ensure that it is difficult for
     /// a malicious prover to witness an incorrect new authority set by using a fake start_position
-    /// from a header correctly signed by the current authority set.
+    /// from a header correctly signed by the current authority set. The log must be a ForcedChange
+    /// if is_forced_change is true, and a ScheduledChange otherwise.
     fn verify_prefix_epoch_end_header<const PREFIX_LENGTH: usize>(
         &mut self,
         subarray: &ArrayVariable<ByteVariable, PREFIX_LENGTH>,
+        is_forced_change: BoolVariable,
     );
 
-    /// Returns the length of the compact encoding of the new authority set length.
+    /// Returns the length of the compact encoding of the new authority set length. In a ForcedChange,
+    /// the compact encoding follows the median last finalized block number.
     fn get_new_authority_set_size_encoded_byte_length(
         &mut self,
         subarray: &ArrayVariable<ByteVariable, MAX_PREFIX_LENGTH>,
         expected_num_authorities: &Variable,
+        is_forced_change: BoolVariable,
     ) -> Variable;
 
-    /// Verifies the epoch end header has a valid encoding, and that the new_pubkeys match the header's
-    /// encoded pubkeys. The purpose of this function is to ensure that it is difficult for
+    /// Verifies the epoch end header has a valid encoding, and that the new_pubkeys and new_weights
+    /// match the header's encoded pubkeys and weights. The purpose of this function is to ensure that it is difficult for
     /// a malicious prover to prove an incorrect new authority set from a correctly signed header by
-    /// adding constraints on the encoding of the new authority set.
+    /// adding constraints on the encoding of the new authority set. The delay encoded in the header
+    /// must be forced_change_delay for a ForcedChange, and zero for a ScheduledChange.
+    #[allow(clippy::too_many_arguments)]
     fn verify_epoch_end_header<
         const MAX_HEADER_SIZE: usize,
         const MAX_AUTHORITY_SET_SIZE: usize,
@@ -45,10 +58,37 @@ pub trait RotateMethods {
         num_authorities: &Variable,
         start_position: &Variable,
         new_pubkeys: &ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>,
+        new_weights: &ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>,
+        is_forced_change: BoolVariable,
+        forced_change_delay: U32Variable,
     );
 
-    // Verify the justification from the current authority set on the epoch end header and extract
-    // the new authority set commitment.
+    /// Computes the weighted authority set commitment of the new authority set from the epoch end
+    /// header. num_new_authorities is the size of the new authority set encoded in the epoch end
+    /// header, which is distinct from the size of the outgoing authority set that signed the header.
+    fn compute_new_authority_set_commitment<const MAX_AUTHORITY_SET_SIZE: usize>(
+        &mut self,
+        num_new_authorities: Variable,
+        new_pubkeys: &ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>,
+        new_weights: &ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>,
+    ) -> Bytes32Variable;
+
+    /// Returns true if the header has a GRANDPA ScheduledChange or ForcedChange consensus log, and
+    /// the position of the first such log in the header. The digest logs are decoded from the header
+    /// bytes, so unlike the is_epoch_end witnessed by the hint, the result is bound to header_hash.
+    /// The header must have at most MAX_DIGEST_ITEMS digest logs.
+    fn find_authority_set_change_log<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+        header_hash: Bytes32Variable,
+    ) -> (BoolVariable, Variable);
+
+    // Verify the justification from the current authority set on the target header and extract the
+    // new authority set commitment. Returns the new authority set commitment, the number of active
+    // authorities of the current authority set that signed the target header, and is_epoch_end. The
+    // target header is the epoch end header if the current authority set has ended, and a later
+    // header signed by it otherwise. is_epoch_end is true iff the target header has an authority set
+    // change log, and if it is false the returned commitment is zero.
     fn rotate<
         const MAX_HEADER_SIZE: usize,
         const MAX_AUTHORITY_SET_SIZE: usize,
@@ -58,13 +98,36 @@ pub trait RotateMethods {
         current_authority_set_id: U64Variable,
         current_authority_set_hash: Bytes32Variable,
         rotate: RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>,
-    ) -> Bytes32Variable;
+    ) -> (Bytes32Variable, Variable, BoolVariable);
+
+    /// Rotate as in `rotate`, with the justification on the epoch end header fetched by the hint from
+    /// source instead of the Avail node at AVAIL_URL.
+    fn rotate_from<
+        const MAX_HEADER_SIZE: usize,
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_SUBARRAY_SIZE: usize,
+    >(
+        &mut self,
+        current_authority_set_id: U64Variable,
+        current_authority_set_hash: Bytes32Variable,
+        rotate: RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>,
+        source: DataSource,
+    ) -> (Bytes32Variable, Variable, BoolVariable);
+
+    /// Returns the block number from which the new authority set is active, which is the delay
+    /// encoded in the epoch end header after the epoch end block. The delay is only trusted once
+    /// verified by `verify_epoch_end_header`.
+    fn activation_block_number<const MAX_HEADER_SIZE: usize, const MAX_AUTHORITY_SET_SIZE: usize>(
+        &mut self,
+        rotate: &RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>,
+    ) -> U32Variable;
 }
 
 impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L, D> {
     fn verify_prefix_epoch_end_header<const PREFIX_LENGTH: usize>(
         &mut self,
         subarray: &ArrayVariable<ByteVariable, PREFIX_LENGTH>,
+        is_forced_change: BoolVariable,
     ) {
         // Digest Spec: https://github.com/availproject/avail/blob/188c20d6a1577670da65e0c6e1c2a38bea8239bb/avail-subxt/src/api_dev.rs#L30820-L30842
         // Skip 1 byte.
@@ -85,10 +148,16 @@ impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L,
 
         // Skip 2 bytes.
 
-        // Verify subarray[8] is 0x01, denoting a ScheduledChange.
-        let scheduled_change_enum_flag = self.constant::<ByteVariable>(1u8);
-        let header_schedule_change_flag = subarray[8];
-        self.assert_is_equal(header_schedule_change_flag, scheduled_change_enum_flag);
+        // Verify subarray[8] is 0x01, denoting a ScheduledChange, or 0x02, denoting a ForcedChange.
+        let scheduled_change_enum_flag = self.constant::<ByteVariable>(SCHEDULED_CHANGE_FLAG);
+        let forced_change_enum_flag = self.constant::<ByteVariable>(FORCED_CHANGE_FLAG);
+        let expected_change_flag = self.select(
+            is_forced_change,
+            forced_change_enum_flag,
+            scheduled_change_enum_flag,
+        );
+        let header_change_flag = subarray[8];
+        self.assert_is_equal(header_change_flag, expected_change_flag);
     }
 
     /// Returns the length of the compact encoding of the new authority set length.
@@ -96,27 +165,28 @@ impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L,
         &mut self,
         subarray: &ArrayVariable<ByteVariable, MAX_PREFIX_LENGTH>,
         expected_num_authorities: &Variable,
+        is_forced_change: BoolVariable,
     ) -> Variable {
         // Verify the bytes starting at the base prefix length are the compact encoding of the
-        // length of the new authority set.
-        let encoded_num_authorities_size_bytes =
-            ArrayVariable::<ByteVariable, MAX_COMPACT_UINT_BYTES>::from(
-                subarray[BASE_PREFIX_LENGTH..BASE_PREFIX_LENGTH + MAX_COMPACT_UINT_BYTES].to_vec(),
-            );
-        let (num_authorities, compress_mode) =
-            self.decode_compact_int(encoded_num_authorities_size_bytes);
+        // length of the new authority set. In a ForcedChange, the compact encoding starts after the
+        // median last finalized block number.
+        let subarray_bytes =
+            BytesVariable::<MAX_PREFIX_LENGTH>(subarray.as_vec().try_into().unwrap());
+        let scheduled_change_start =
+            self.constant::<Variable>(L::Field::from_canonical_usize(BASE_PREFIX_LENGTH));
+        let forced_change_start = self.constant::<Variable>(L::Field::from_canonical_usize(
+            BASE_PREFIX_LENGTH + FORCED_CHANGE_MEDIAN_LENGTH,
+        ));
+        let size_start = self.select(
+            is_forced_change,
+            forced_change_start,
+            scheduled_change_start,
+        );
+        let (num_authorities, byte_length) = self.decode_compact_u32(&subarray_bytes, size_start);
         self.assert_is_equal(*expected_num_authorities, num_authorities.variable);
 
         // Number of bytes in the compact encoding of the new authority set length.
-        let all_possible_lengths = vec![
-            self.constant::<Variable>(L::Field::from_canonical_usize(1)),
-            self.constant::<Variable>(L::Field::from_canonical_usize(2)),
-            self.constant::<Variable>(L::Field::from_canonical_usize(4)),
-            self.constant::<Variable>(L::Field::from_canonical_usize(5)),
-        ];
-
-        // Select the correct length of the compact encoding of the new authority set length.
-        self.select_array_random_gate(&all_possible_lengths, compress_mode)
+        byte_length
     }
 
     fn verify_epoch_end_header<
@@ -130,6 +200,9 @@ impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L,
         num_authorities: &Variable,
         start_position: &Variable,
         new_pubkeys: &ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>,
+        new_weights: &ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>,
+        is_forced_change: BoolVariable,
+        forced_change_delay: U32Variable,
     ) {
         let false_v = self._false();
         let true_v = self._true();
@@ -151,29 +224,39 @@ impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L,
         );
 
         // Verify the prefix bytes before the encoded authority set are valid, according to the spec.
-        self.verify_prefix_epoch_end_header(&prefix_subarray);
+        self.verify_prefix_epoch_end_header(&prefix_subarray, is_forced_change);
 
         // Returns the byte length of the compact encoding of the new authority set length.
-        let encoded_num_authorities_byte_len =
-            self.get_new_authority_set_size_encoded_byte_length(&prefix_subarray, num_authorities);
-
-        // Note: All validators have a voting power of 1 in Avail.
-        // Spec: https://github.com/availproject/polkadot-sdk/blob/70e569d5112f879001a987e94402ff70f9683cb5/substrate/frame/grandpa/src/lib.rs#L585
-        let expected_weight_bytes = self.constant::<ArrayVariable<ByteVariable, WEIGHT_LENGTH>>(
-            [1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8].to_vec(),
+        let encoded_num_authorities_byte_len = self.get_new_authority_set_size_encoded_byte_length(
+            &prefix_subarray,
+            num_authorities,
+            is_forced_change,
         );
-        // Expected delay for the authority set.
-        let expected_delay_bytes =
-            self.constant::<ArrayVariable<ByteVariable, 4>>([0u8, 0u8, 0u8, 0u8].to_vec());
+
+        // Expected delay for the authority set. A ScheduledChange has no delay, and the delay of a
+        // ForcedChange is forced_change_delay, encoded as a little-endian u32.
+        let zero_delay = self.constant::<U32Variable>(0);
+        let is_zero_delay = self.is_equal(forced_change_delay, zero_delay);
+        let delay_check = self.or(is_zero_delay, is_forced_change);
+        self.assert_is_equal(delay_check, true_v);
+        let mut delay_bytes = forced_change_delay.encode(self);
+        delay_bytes.reverse();
+        let expected_delay_bytes = ArrayVariable::<ByteVariable, DELAY_LENGTH>::from(delay_bytes);
 
         let pubkey_len = self.constant::<Variable>(L::Field::from_canonical_usize(PUBKEY_LENGTH));
         let weight_len = self.constant::<Variable>(L::Field::from_canonical_usize(WEIGHT_LENGTH));
         let base_prefix_len =
             self.constant::<Variable>(L::Field::from_canonical_usize(BASE_PREFIX_LENGTH));
-
-        // Get to the start of the encoded authority set. The cursor is the base prefix length
-        // plus the length of the compact encoding of the new authority set length.
+        let forced_change_median_len =
+            self.constant::<Variable>(L::Field::from_canonical_usize(FORCED_CHANGE_MEDIAN_LENGTH));
+        let zero = self.zero::<Variable>();
+        let median_len = self.select(is_forced_change, forced_change_median_len, zero);
+
+        // Get to the start of the encoded authority set. The cursor is the base prefix length, plus
+        // the length of the median last finalized block number for a ForcedChange, plus the length
+        // of the compact encoding of the new authority set length.
         cursor = self.add(cursor, base_prefix_len);
+        cursor = self.add(cursor, median_len);
         cursor = self.add(cursor, encoded_num_authorities_byte_len);
 
         let enc_validator_subarray = self.get_fixed_subarray::<MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>(
@@ -199,11 +282,17 @@ impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L,
             cursor = self.add(cursor, pubkey_len);
 
             // Verify the correctness of the extracted weight for each enabled validator and
-            // increment the cursor by the weight length.
+            // increment the cursor by the weight length. The weight is encoded as a little-endian
+            // u64.
+            // Spec: https://github.com/availproject/polkadot-sdk/blob/70e569d5112f879001a987e94402ff70f9683cb5/substrate/frame/grandpa/src/lib.rs#L585
             let extracted_weight = ArrayVariable::<ByteVariable, WEIGHT_LENGTH>::from(
                 enc_validator_subarray[idx + PUBKEY_LENGTH..idx + VALIDATOR_LENGTH].to_vec(),
             );
-            let weight_match = self.is_equal(extracted_weight, expected_weight_bytes.clone());
+            let mut weight_bytes = new_weights[i].encode(self);
+            weight_bytes.reverse();
+            let expected_weight_bytes =
+                ArrayVariable::<ByteVariable, WEIGHT_LENGTH>::from(weight_bytes);
+            let weight_match = self.is_equal(extracted_weight, expected_weight_bytes);
             let weight_check = self.or(weight_match, validator_disabled);
             self.assert_is_equal(weight_check, true_v);
             cursor = self.add(cursor, weight_len);
@@ -226,6 +315,148 @@ impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L,
         }
     }
 
+    fn compute_new_authority_set_commitment<const MAX_AUTHORITY_SET_SIZE: usize>(
+        &mut self,
+        num_new_authorities: Variable,
+        new_pubkeys: &ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>,
+        new_weights: &ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>,
+    ) -> Bytes32Variable {
+        // Assert the new authority set fits in new_pubkeys, otherwise the commitment would silently
+        // be computed over a truncated authority set.
+        let true_v = self._true();
+        let max_authority_set_size =
+            self.constant::<Variable>(L::Field::from_canonical_usize(MAX_AUTHORITY_SET_SIZE));
+        let is_valid_size = self.lte(num_new_authorities, max_authority_set_size);
+        self.assert_is_equal(is_valid_size, true_v);
+
+        self.compute_weighted_authority_set_commitment(
+            num_new_authorities,
+            new_pubkeys,
+            new_weights,
+        )
+    }
+
+    fn find_authority_set_change_log<const MAX_HEADER_SIZE: usize>(
+        &mut self,
+        header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+        header_hash: Bytes32Variable,
+    ) -> (BoolVariable, Variable) {
+        // Digest Spec: https://github.com/paritytech/polkadot-sdk/blob/master/substrate/primitives/runtime/src/generic/digest.rs
+        let true_v = self._true();
+        let zero = self.zero::<Variable>();
+        let one = self.one::<Variable>();
+
+        // The digest follows the parent hash, the block number, the state root and the extrinsics
+        // root, and starts with the compact encoding of the number of digest logs.
+        let (_, block_number_length) = decode_block_number(self, header);
+        let num_logs_bytes = BytesVariable::<{ 2 * MAX_COMPACT_UINT_BYTES }>(
+            header.header_bytes[3 * HASH_SIZE..3 * HASH_SIZE + 2 * MAX_COMPACT_UINT_BYTES]
+                .to_vec()
+                .try_into()
+                .unwrap(),
+        );
+        let (num_logs, num_logs_byte_length) =
+            self.decode_compact_u32(&num_logs_bytes, block_number_length);
+        let max_digest_items =
+            self.constant::<Variable>(L::Field::from_canonical_usize(MAX_DIGEST_ITEMS));
+        let is_valid_num_logs = self.lte(num_logs.variable, max_digest_items);
+        self.assert_is_equal(is_valid_num_logs, true_v);
+
+        let digest_start = self.constant::<Variable>(L::Field::from_canonical_usize(3 * HASH_SIZE));
+        let digest_start = self.add(digest_start, block_number_length);
+        let mut cursor = self.add(digest_start, num_logs_byte_length);
+
+        let other_tag = self.constant::<ByteVariable>(DIGEST_OTHER_TAG);
+        let consensus_tag = self.constant::<ByteVariable>(DIGEST_CONSENSUS_TAG);
+        let seal_tag = self.constant::<ByteVariable>(DIGEST_SEAL_TAG);
+        let pre_runtime_tag = self.constant::<ByteVariable>(DIGEST_PRE_RUNTIME_TAG);
+        let runtime_environment_updated_tag =
+            self.constant::<ByteVariable>(DIGEST_RUNTIME_ENVIRONMENT_UPDATED_TAG);
+        let grandpa_engine_id =
+            self.constant::<ArrayVariable<ByteVariable, 4>>([70u8, 82u8, 78u8, 75u8].to_vec());
+        let scheduled_change_flag = self.constant::<ByteVariable>(SCHEDULED_CHANGE_FLAG);
+        let forced_change_flag = self.constant::<ByteVariable>(FORCED_CHANGE_FLAG);
+        let engine_id_payload_offset = self.constant::<Variable>(L::Field::from_canonical_usize(5));
+        let zero_log = self.constant::<ArrayVariable<ByteVariable, DIGEST_ITEM_PREFIX_LENGTH>>(
+            vec![0u8; DIGEST_ITEM_PREFIX_LENGTH],
+        );
+
+        let mut is_epoch_end = self._false();
+        let mut position = zero;
+        for i in 0..MAX_DIGEST_ITEMS {
+            let idx = self.constant::<Variable>(L::Field::from_canonical_usize(i));
+            let is_active = self.lt(idx, num_logs.variable);
+            let not_active = self.not(is_active);
+
+            // The cursor of an inactive log is past the end of the digest, so read the start of the
+            // header instead.
+            let log_cursor = self.select(is_active, cursor, zero);
+            let log = self.get_fixed_subarray::<MAX_HEADER_SIZE, DIGEST_ITEM_PREFIX_LENGTH>(
+                &header.header_bytes,
+                log_cursor,
+                &header_hash.as_bytes(),
+            );
+
+            // Verify the tag of an active log is one of the digest log variants.
+            let is_other = self.is_equal(log[0], other_tag);
+            let is_consensus = self.is_equal(log[0], consensus_tag);
+            let is_seal = self.is_equal(log[0], seal_tag);
+            let is_pre_runtime = self.is_equal(log[0], pre_runtime_tag);
+            let is_runtime_environment_updated =
+                self.is_equal(log[0], runtime_environment_updated_tag);
+            let has_engine_id = self.or(is_consensus, is_seal);
+            let has_engine_id = self.or(has_engine_id, is_pre_runtime);
+            let has_payload = self.or(has_engine_id, is_other);
+            let is_valid_tag = self.or(has_payload, is_runtime_environment_updated);
+            let tag_check = self.or(is_valid_tag, not_active);
+            self.assert_is_equal(tag_check, true_v);
+
+            // The payload is a compact encoded length followed by the payload bytes, and follows the
+            // engine id in a PreRuntime, Consensus or Seal log. A RuntimeEnvironmentUpdated log has
+            // no payload, so the length is decoded from zero bytes instead.
+            let payload_length_offset = self.select(has_engine_id, engine_id_payload_offset, one);
+            let decode_payload_length = self.and(is_active, has_payload);
+            let payload_length_bytes =
+                self.select(decode_payload_length, log.clone(), zero_log.clone());
+            let (payload_length, payload_length_byte_length) = self.decode_compact_u32(
+                &BytesVariable::<DIGEST_ITEM_PREFIX_LENGTH>(
+                    payload_length_bytes.as_vec().try_into().unwrap(),
+                ),
+                payload_length_offset,
+            );
+            let payload_start = self.add(payload_length_offset, payload_length_byte_length);
+            let payload_log_length = self.add(payload_start, payload_length.variable);
+            let log_length = self.select(has_payload, payload_log_length, one);
+
+            // A GRANDPA consensus log whose payload starts with the ScheduledChange or ForcedChange
+            // flag is an authority set change log.
+            let engine_id = ArrayVariable::<ByteVariable, 4>::from(log[1..5].to_vec());
+            let is_grandpa = self.is_equal(engine_id, grandpa_engine_id.clone());
+            let change_flag = self.select_array_random_gate(&log.as_vec(), payload_start);
+            let is_scheduled_change = self.is_equal(change_flag, scheduled_change_flag);
+            let is_forced_change = self.is_equal(change_flag, forced_change_flag);
+            let is_change = self.or(is_scheduled_change, is_forced_change);
+            let is_empty_payload = self.is_zero(payload_length.variable);
+            let is_non_empty_payload = self.not(is_empty_payload);
+            let is_change_log = self.and(is_active, is_consensus);
+            let is_change_log = self.and(is_change_log, is_grandpa);
+            let is_change_log = self.and(is_change_log, is_change);
+            let is_change_log = self.and(is_change_log, is_non_empty_payload);
+
+            // The first authority set change log is the one that enacts the new authority set, as in
+            // `decode_authority_set_change`.
+            let is_not_found = self.not(is_epoch_end);
+            let is_first_change_log = self.and(is_change_log, is_not_found);
+            position = self.select(is_first_change_log, cursor, position);
+            is_epoch_end = self.or(is_epoch_end, is_change_log);
+
+            let next_cursor = self.add(cursor, log_length);
+            cursor = self.select(is_active, next_cursor, cursor);
+        }
+
+        (is_epoch_end, position)
+    }
+
     fn rotate<
         const MAX_HEADER_SIZE: usize,
         const MAX_AUTHORITY_SET_SIZE: usize,
@@ -235,58 +466,340 @@ impl<L: PlonkParameters<D>, const D: usize> RotateMethods for CircuitBuilder<L,
         current_authority_set_id: U64Variable,
         current_authority_set_hash: Bytes32Variable,
         rotate: RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>,
-    ) -> Bytes32Variable {
+    ) -> (Bytes32Variable, Variable, BoolVariable) {
+        self.rotate_from::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, MAX_SUBARRAY_SIZE>(
+            current_authority_set_id,
+            current_authority_set_hash,
+            rotate,
+            DataSource::default(),
+        )
+    }
+
+    fn rotate_from<
+        const MAX_HEADER_SIZE: usize,
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_SUBARRAY_SIZE: usize,
+    >(
+        &mut self,
+        current_authority_set_id: U64Variable,
+        current_authority_set_hash: Bytes32Variable,
+        rotate: RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>,
+        source: DataSource,
+    ) -> (Bytes32Variable, Variable, BoolVariable) {
         assert_eq!(
             MAX_SUBARRAY_SIZE,
             MAX_AUTHORITY_SET_SIZE * VALIDATOR_LENGTH + DELAY_LENGTH,
             "MAX_SUBARRAY_SIZE must be equal to MAX_AUTHORITY_SET_SIZE * VALIDATOR_LENGTH + DELAY_LENGTH."
         );
 
-        // Hash the header at epoch_end_block.
+        // Hash the header at epoch_end_block. The new authority set is read at start_position, so
+        // the padding past the end of the hashed header must be zero.
         let target_header_hash = self.hash_encoded_header::<MAX_HEADER_SIZE>(&rotate.target_header);
+        self.verify_header_padding(&rotate.target_header);
 
-        // Verify the justification from the current authority set on the epoch end header.
+        // Verify the justification from the current authority set on the target header.
         // Note: current_authority_set_id and current_authority_set_hash are trusted at this point.
-        self.verify_simple_justification::<MAX_AUTHORITY_SET_SIZE>(
-            rotate.epoch_end_block_number,
-            target_header_hash,
-            current_authority_set_id,
-            current_authority_set_hash,
-        );
-
-        // Verify the epoch end header and the new authority set are valid.
+        // The quorum of the justification is computed over the outgoing authority set, whose size is
+        // bound to current_authority_set_hash. It is unrelated to the size of the new authority set.
+        let verified_justification = self
+            .verify_simple_justification_from::<MAX_AUTHORITY_SET_SIZE>(
+                rotate.epoch_end_block_number,
+                target_header_hash,
+                current_authority_set_id,
+                current_authority_set_hash,
+                VerificationOptions::default(),
+                source,
+            );
+        let true_v = self._true();
+        self.assert_is_equal(verified_justification.success, true_v);
+        let num_outgoing_authorities = verified_justification.num_active_authorities;
+
+        // The target header is the epoch end header iff it has an authority set change log. The
+        // is_epoch_end witnessed by the hint must match, and if the authority set has ended, the
+        // start position must be the byte before the change log, as expected by
+        // verify_prefix_epoch_end_header.
+        // Note: is_epoch_end is trusted at this point.
+        let (is_epoch_end, change_log_position) =
+            self.find_authority_set_change_log(&rotate.target_header, target_header_hash);
+        self.assert_is_equal(rotate.is_epoch_end, is_epoch_end);
+        let one = self.one::<Variable>();
+        let start_log_position = self.add(rotate.next_authority_set_start_position, one);
+        let is_change_log_start = self.is_equal(start_log_position, change_log_position);
+        let not_epoch_end = self.not(is_epoch_end);
+        let start_position_check = self.or(is_change_log_start, not_epoch_end);
+        self.assert_is_equal(start_position_check, true_v);
+
+        // The size of the new authority set is encoded in the epoch end header.
+        let num_new_authorities = rotate.target_header_num_authorities;
+        self.watch(&num_outgoing_authorities, "num_outgoing_authorities");
+        self.watch(&num_new_authorities, "num_new_authorities");
+
+        // Verify the epoch end header and the new authority set are valid. If the authority set has
+        // not ended, the checks run on the placeholder header of `not_epoch_end_rotate`, which
+        // satisfies them.
         // Note: The target_header and target_header_hash are trusted at this point.
+        let placeholder_header = self.constant::<EncodedHeaderVariable<MAX_HEADER_SIZE>>(
+            not_epoch_end_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, L::Field>()
+                .target_header,
+        );
+        let epoch_end_header = self.select(is_epoch_end, rotate.target_header, placeholder_header);
         self.verify_epoch_end_header::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, MAX_SUBARRAY_SIZE>(
-            &rotate.target_header,
+            &epoch_end_header,
             target_header_hash,
-            &rotate.target_header_num_authorities,
+            &num_new_authorities,
             &rotate.next_authority_set_start_position,
             &rotate.new_pubkeys,
+            &rotate.new_weights,
+            rotate.is_forced_change,
+            rotate.forced_change_delay,
         );
 
         // Compute the authority set commitment of the new authority set. The order of the validators
         // in the authority set commitment matches the order of the encoded validator data in the epoch end header.
-        // Note: target_header_num_authorities and next_authority_set_start_position are trusted at this point.
-        self.compute_authority_set_commitment(
-            rotate.target_header_num_authorities,
+        // Note: num_new_authorities and next_authority_set_start_position are trusted at this point.
+        let new_authority_set_hash = self.compute_new_authority_set_commitment(
+            num_new_authorities,
             &rotate.new_pubkeys,
+            &rotate.new_weights,
+        );
+
+        // If the authority set has not ended, there is no new authority set.
+        let zero_commitment = self.constant::<Bytes32Variable>(H256::zero());
+        let new_authority_set_hash =
+            self.select(is_epoch_end, new_authority_set_hash, zero_commitment);
+        (
+            new_authority_set_hash,
+            verified_justification.num_signed,
+            is_epoch_end,
         )
     }
+
+    fn activation_block_number<
+        const MAX_HEADER_SIZE: usize,
+        const MAX_AUTHORITY_SET_SIZE: usize,
+    >(
+        &mut self,
+        rotate: &RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>,
+    ) -> U32Variable {
+        // The delay is zero for a ScheduledChange, so the new authority set is active from the epoch
+        // end block.
+        self.add(rotate.epoch_end_block_number, rotate.forced_change_delay)
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use std::env;
 
-    use plonky2x::frontend::curta::ec::point::CompressedEdwardsYVariable;
+    use avail_subxt::config::substrate::DigestItem;
+    use codec::{Compact, Encode};
+    use ethers::types::H256;
+    use plonky2x::frontend::curta::ec::point::{CompressedEdwardsY, CompressedEdwardsYVariable};
+    use plonky2x::frontend::uint::uint64::U64Variable;
+    use plonky2x::prelude::plonky2::field::types::PrimeField64;
     use plonky2x::prelude::{
-        ArrayVariable, Bytes32Variable, DefaultBuilder, U32Variable, Variable, VariableStream,
+        ArrayVariable, BoolVariable, Bytes32Variable, DefaultBuilder, Field, GoldilocksField,
+        U32Variable, Variable, VariableStream,
     };
 
+    use crate::builder::justification::GrandpaJustificationVerifier;
     use crate::builder::rotate::RotateMethods;
-    use crate::consts::{DELAY_LENGTH, MAX_HEADER_SIZE, MAX_PREFIX_LENGTH, VALIDATOR_LENGTH};
+    use crate::consts::{
+        DELAY_LENGTH, FORCED_CHANGE_FLAG, MAX_DIGEST_ITEMS, MAX_HEADER_SIZE, MAX_PREFIX_LENGTH,
+        PAUSE_FLAG, SCHEDULED_CHANGE_FLAG, VALIDATOR_LENGTH,
+    };
+    use crate::input::compute_authority_set_hash;
+    use crate::input::types::DataSource;
     use crate::rotate::RotateHint;
-    use crate::vars::EncodedHeaderVariable;
+    use crate::vars::{EncodedHeader, EncodedHeaderVariable};
+
+    const BABE_ENGINE_ID: [u8; 4] = *b"BABE";
+    const GRANDPA_ENGINE_ID: [u8; 4] = *b"FRNK";
+
+    /// Encodes a header of block 645120 with the digest logs. Returns the encoded header, and the
+    /// position of each log in it.
+    fn encode_header_with_logs(logs: &[DigestItem]) -> (Vec<u8>, Vec<usize>) {
+        // Parent hash, number, state root and extrinsics root, followed by the digest.
+        let mut header_bytes = vec![0u8; 32];
+        header_bytes.extend(Compact(645120u32).encode());
+        header_bytes.extend_from_slice(&[0u8; 64]);
+        header_bytes.extend(Compact(logs.len() as u32).encode());
+        let mut positions = Vec::new();
+        for log in logs {
+            positions.push(header_bytes.len());
+            header_bytes.extend(log.encode());
+        }
+        (header_bytes, positions)
+    }
+
+    /// A GRANDPA authority set change log of a single authority.
+    fn authority_set_change_log(change_flag: u8) -> DigestItem {
+        let mut log = vec![change_flag];
+        if change_flag == FORCED_CHANGE_FLAG {
+            log.extend_from_slice(&4321u32.to_le_bytes());
+        }
+        log.extend(Compact(1u32).encode());
+        log.extend_from_slice(&[1u8; 32]);
+        log.extend_from_slice(&1u64.to_le_bytes());
+        log.extend_from_slice(&[0u8; DELAY_LENGTH]);
+        DigestItem::Consensus(GRANDPA_ENGINE_ID, log)
+    }
+
+    /// Proves find_authority_set_change_log on a header with the digest logs, and returns
+    /// is_epoch_end and the position of the change log.
+    fn prove_find_authority_set_change_log(logs: &[DigestItem]) -> (bool, usize) {
+        type F = GoldilocksField;
+        const HEADER_LENGTH: usize = 1024;
+
+        let mut builder = DefaultBuilder::new();
+        let header = builder.read::<EncodedHeaderVariable<HEADER_LENGTH>>();
+        // Note: In prod, get_fixed_subarray uses the header_hash as the seed for randomness. The
+        // below is unsafe, but it's fine for testing purposes.
+        let header_hash = builder.read::<Bytes32Variable>();
+        let (is_epoch_end, position) = builder.find_authority_set_change_log(&header, header_hash);
+        builder.write(is_epoch_end);
+        builder.write(position);
+        let circuit = builder.build();
+
+        let (mut header_bytes, _) = encode_header_with_logs(logs);
+        let header_size = header_bytes.len() as u32;
+        header_bytes.resize(HEADER_LENGTH, 0);
+        let mut input = circuit.input();
+        input.write::<EncodedHeaderVariable<HEADER_LENGTH>>(EncodedHeader::<HEADER_LENGTH, F> {
+            header_bytes,
+            header_size,
+        });
+        input.write::<Bytes32Variable>(H256([1u8; 32]));
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        let is_epoch_end = output.read::<BoolVariable>();
+        let position = output.read::<Variable>().to_canonical_u64() as usize;
+        (is_epoch_end, position)
+    }
+
+    #[test]
+    fn test_find_authority_set_change_log() {
+        // An epoch end header has a BABE PreRuntime log, a BABE NextEpochData log, whose payload
+        // length is a 2 byte compact, a GRANDPA ScheduledChange log and a BABE Seal log.
+        let logs = vec![
+            DigestItem::PreRuntime(BABE_ENGINE_ID, vec![2; 16]),
+            DigestItem::Consensus(BABE_ENGINE_ID, vec![1; 200]),
+            authority_set_change_log(SCHEDULED_CHANGE_FLAG),
+            DigestItem::Seal(BABE_ENGINE_ID, vec![3; 64]),
+        ];
+        let (_, positions) = encode_header_with_logs(&logs);
+        assert_eq!(
+            prove_find_authority_set_change_log(&logs),
+            (true, positions[2])
+        );
+
+        let logs = vec![
+            DigestItem::PreRuntime(BABE_ENGINE_ID, vec![2; 16]),
+            authority_set_change_log(FORCED_CHANGE_FLAG),
+            DigestItem::Seal(BABE_ENGINE_ID, vec![3; 64]),
+        ];
+        let (_, positions) = encode_header_with_logs(&logs);
+        assert_eq!(
+            prove_find_authority_set_change_log(&logs),
+            (true, positions[1])
+        );
+    }
+
+    #[test]
+    fn test_find_authority_set_change_log_mid_epoch() {
+        // A mid-epoch header has no GRANDPA consensus log.
+        let logs = vec![
+            DigestItem::PreRuntime(BABE_ENGINE_ID, vec![2; 16]),
+            DigestItem::Seal(BABE_ENGINE_ID, vec![3; 64]),
+        ];
+        assert!(!prove_find_authority_set_change_log(&logs).0);
+
+        // A GRANDPA Pause log does not change the authority set, and a ScheduledChange flag in the
+        // payload of any other log is not a change log.
+        let logs = vec![
+            DigestItem::Other(vec![SCHEDULED_CHANGE_FLAG]),
+            DigestItem::RuntimeEnvironmentUpdated,
+            DigestItem::Consensus(GRANDPA_ENGINE_ID, vec![PAUSE_FLAG, 0, 0, 0, 0]),
+            DigestItem::Consensus(BABE_ENGINE_ID, vec![SCHEDULED_CHANGE_FLAG]),
+            DigestItem::Seal(GRANDPA_ENGINE_ID, vec![SCHEDULED_CHANGE_FLAG]),
+        ];
+        assert!(!prove_find_authority_set_change_log(&logs).0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_find_authority_set_change_log_too_many_logs() {
+        let logs = vec![DigestItem::Other(vec![0]); MAX_DIGEST_ITEMS + 1];
+        prove_find_authority_set_change_log(&logs);
+    }
+
+    fn prove_new_authority_set_commitment(
+        num_outgoing_authorities: usize,
+        num_new_authorities: usize,
+    ) {
+        const MAX_AUTHORITY_SET_SIZE: usize = 4;
+
+        let mut builder = DefaultBuilder::new();
+
+        // The outgoing and new authority sets share the same pubkeys, but have different sizes.
+        let pubkeys = (0..MAX_AUTHORITY_SET_SIZE)
+            .map(|i| CompressedEdwardsY([i as u8 + 1; 32]))
+            .collect::<Vec<_>>();
+        let pubkeys_var = builder
+            .constant::<ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>>(
+                pubkeys.clone(),
+            );
+        let num_outgoing_authorities_var = builder.constant::<Variable>(
+            GoldilocksField::from_canonical_usize(num_outgoing_authorities),
+        );
+        let num_new_authorities_var = builder
+            .constant::<Variable>(GoldilocksField::from_canonical_usize(num_new_authorities));
+
+        let outgoing_commitment =
+            builder.compute_authority_set_commitment(num_outgoing_authorities_var, &pubkeys_var);
+        let weights_var = builder.constant::<ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>>(
+            vec![1; MAX_AUTHORITY_SET_SIZE],
+        );
+        let new_commitment = builder.compute_new_authority_set_commitment(
+            num_new_authorities_var,
+            &pubkeys_var,
+            &weights_var,
+        );
+        builder.write(outgoing_commitment);
+        builder.write(new_commitment);
+
+        let circuit = builder.build();
+        let input = circuit.input();
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        let outgoing_commitment = output.read::<Bytes32Variable>();
+        let new_commitment = output.read::<Bytes32Variable>();
+
+        // The new authority set commitment is computed over num_new_authorities.
+        assert_eq!(
+            outgoing_commitment.as_bytes(),
+            compute_authority_set_hash(&pubkeys[..num_outgoing_authorities]).as_slice()
+        );
+        assert_eq!(
+            new_commitment.as_bytes(),
+            compute_authority_set_hash(&pubkeys[..num_new_authorities]).as_slice()
+        );
+        assert_ne!(outgoing_commitment, new_commitment);
+    }
+
+    #[test]
+    fn test_new_authority_set_commitment_differing_sizes() {
+        prove_new_authority_set_commitment(2, 3);
+        prove_new_authority_set_commitment(4, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_authority_set_commitment_exceeds_max_size() {
+        prove_new_authority_set_commitment(2, 5);
+    }
 
     #[test]
     #[cfg_attr(feature = "ci", ignore)]
@@ -302,7 +815,9 @@ pub mod tests {
         let epoch_end_block_number = builder.read::<U32Variable>();
 
         // Fetch the header at epoch_end_block.
-        let header_fetcher = RotateHint::<MAX_HEADER_LENGTH, NUM_AUTHORITIES> {};
+        let header_fetcher = RotateHint::<MAX_HEADER_LENGTH, NUM_AUTHORITIES> {
+            source: DataSource::default(),
+        };
         let mut input_stream = VariableStream::new();
         input_stream.write(&epoch_end_block_number);
         let output_stream = builder.async_hint(input_stream, header_fetcher);
@@ -325,10 +840,15 @@ pub mod tests {
             target_header_dummy_hash,
         );
 
-        builder.verify_prefix_epoch_end_header(&prefix_subarray);
+        let is_forced_change = builder._false();
+        builder.verify_prefix_epoch_end_header(&prefix_subarray, is_forced_change);
 
         let _encoded_num_authorities_byte_len = builder
-            .get_new_authority_set_size_encoded_byte_length(&prefix_subarray, &num_authorities);
+            .get_new_authority_set_size_encoded_byte_length(
+                &prefix_subarray,
+                &num_authorities,
+                is_forced_change,
+            );
 
         let circuit = builder.build();
         let mut input = circuit.input();
@@ -355,7 +875,9 @@ pub mod tests {
         let epoch_end_block_number = builder.read::<U32Variable>();
 
         // Fetch the header at epoch_end_block.
-        let header_fetcher = RotateHint::<MAX_HEADER_LENGTH, NUM_AUTHORITIES> {};
+        let header_fetcher = RotateHint::<MAX_HEADER_LENGTH, NUM_AUTHORITIES> {
+            source: DataSource::default(),
+        };
         let mut input_stream = VariableStream::new();
         input_stream.write(&epoch_end_block_number);
         let output_stream = builder.async_hint(input_stream, header_fetcher);
@@ -372,6 +894,11 @@ pub mod tests {
         // Note: In prod, get_fixed_subarray uses the header_hash as the seed for randomness. The
         // below is unsafe, but it's fine for testing purposes.
         let target_header_hash = expected_new_authority_set_hash;
+        let is_forced_change = builder._false();
+        let forced_change_delay = builder.zero::<U32Variable>();
+        // All validators have a voting weight of 1 in Avail.
+        let new_weights = builder
+            .constant::<ArrayVariable<U64Variable, NUM_AUTHORITIES>>(vec![1; NUM_AUTHORITIES]);
 
         builder.verify_epoch_end_header::<MAX_HEADER_LENGTH, NUM_AUTHORITIES, MAX_SUBARRAY_SIZE>(
             &target_header,
@@ -379,6 +906,9 @@ pub mod tests {
             &num_authorities,
             &start_position,
             &new_pubkeys,
+            &new_weights,
+            is_forced_change,
+            forced_change_delay,
         );
 
         let circuit = builder.build();
@@ -407,7 +937,9 @@ pub mod tests {
         let epoch_end_block_number = builder.read::<U32Variable>();
 
         // Fetch the header at epoch_end_block.
-        let header_fetcher = RotateHint::<MAX_HEADER_LENGTH, NUM_AUTHORITIES> {};
+        let header_fetcher = RotateHint::<MAX_HEADER_LENGTH, NUM_AUTHORITIES> {
+            source: DataSource::default(),
+        };
         let mut input_stream = VariableStream::new();
         input_stream.write(&epoch_end_block_number);
         let output_stream = builder.async_hint(input_stream, header_fetcher);
@@ -424,6 +956,11 @@ pub mod tests {
         // Note: In prod, get_fixed_subarray uses the header_hash as the seed for randomness. The
         // below is unsafe, but it's fine for testing purposes.
         let target_header_hash = expected_new_authority_set_hash;
+        let is_forced_change = builder._false();
+        let forced_change_delay = builder.zero::<U32Variable>();
+        // All validators have a voting weight of 1 in Avail.
+        let new_weights = builder
+            .constant::<ArrayVariable<U64Variable, NUM_AUTHORITIES>>(vec![1; NUM_AUTHORITIES]);
 
         builder.verify_epoch_end_header::<MAX_HEADER_LENGTH, NUM_AUTHORITIES, MAX_SUBARRAY_SIZE>(
             &target_header,
@@ -431,6 +968,9 @@ pub mod tests {
             &num_authorities,
             &start_position,
             &new_pubkeys,
+            &new_weights,
+            is_forced_change,
+            forced_change_delay,
         );
 
         let circuit = builder.build();
@@ -0,0 +1,474 @@
+use async_trait::async_trait;
+use itertools::Itertools;
+use log::debug;
+use plonky2x::backend::circuit::{Circuit, PlonkParameters};
+use plonky2x::frontend::curta::ec::point::CompressedEdwardsYVariable;
+use plonky2x::frontend::ecc::curve25519::ed25519::eddsa::EDDSASignatureVariable;
+use plonky2x::frontend::hint::asynchronous::hint::AsyncHint;
+use plonky2x::frontend::uint::uint64::U64Variable;
+use plonky2x::frontend::vars::{U32Variable, ValueStream, VariableStream};
+use plonky2x::prelude::{
+    ArrayVariable, BoolVariable, ByteVariable, Bytes32Variable, BytesVariable, CircuitBuilder,
+    CircuitVariable,
+};
+use serde::{Deserialize, Serialize};
+
+use super::justification::{
+    assert_if_success, fetch_justification_struct, verify_simple_justification_with,
+    VerificationOptions, VerifiedJustification,
+};
+use crate::consts::ENCODED_PRECOMMIT_LENGTH;
+use crate::input::types::DataSource;
+use crate::vars::JustificationVariable;
+
+/// Shared by every chunk of a justification. All chunks verify their signatures against the same
+/// encoded_precommit, so all votes are from the same round of consensus.
+#[derive(Clone, Debug, CircuitVariable)]
+pub struct SignatureChunkCtx {
+    pub block_number: U32Variable,
+    pub authority_set_id: U64Variable,
+    pub encoded_precommit: BytesVariable<ENCODED_PRECOMMIT_LENGTH>,
+    /// True if the outer justification was fetched successfully. If false, no signatures are
+    /// verified.
+    pub success: BoolVariable,
+}
+
+#[derive(Clone, Debug, CircuitVariable)]
+pub struct SignatureChunkVariable {
+    /// Merkle root of the (pubkey, validator_signed) leaves of the authorities whose signatures were
+    /// verified.
+    pub signers_commitment: Bytes32Variable,
+}
+
+/// Fetch the chunk [chunk_start, chunk_start + CHUNK_SIZE) of the simple justification for a block
+/// from source. On failure, the chunk is taken from `dummy_justification`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HintSignatureChunk<const MAX_NUM_AUTHORITIES: usize, const CHUNK_SIZE: usize> {
+    pub source: DataSource,
+}
+
+#[async_trait]
+impl<
+        const MAX_NUM_AUTHORITIES: usize,
+        const CHUNK_SIZE: usize,
+        L: PlonkParameters<D>,
+        const D: usize,
+    > AsyncHint<L, D> for HintSignatureChunk<MAX_NUM_AUTHORITIES, CHUNK_SIZE>
+{
+    async fn hint(
+        &self,
+        input_stream: &mut ValueStream<L, D>,
+        output_stream: &mut ValueStream<L, D>,
+    ) {
+        let block_number = input_stream.read_value::<U32Variable>();
+        let authority_set_id = input_stream.read_value::<U64Variable>();
+        let chunk_start = input_stream.read_value::<U32Variable>() as usize;
+
+        debug!(
+            "HintSignatureChunk: fetching authorities [{}, {}) of the justification for block_number={}",
+            chunk_start,
+            chunk_start + CHUNK_SIZE,
+            block_number
+        );
+
+        let (_, justification) = fetch_justification_struct::<MAX_NUM_AUTHORITIES, L::Field>(
+            &self.source,
+            block_number,
+            authority_set_id,
+        )
+        .await;
+        let chunk = chunk_start..chunk_start + CHUNK_SIZE;
+
+        output_stream.write_value::<ArrayVariable<BoolVariable, CHUNK_SIZE>>(
+            justification.validator_signed[chunk.clone()].to_vec(),
+        );
+        output_stream.write_value::<ArrayVariable<EDDSASignatureVariable, CHUNK_SIZE>>(
+            justification.signatures[chunk.clone()].to_vec(),
+        );
+        output_stream.write_value::<ArrayVariable<CompressedEdwardsYVariable, CHUNK_SIZE>>(
+            justification.pubkeys[chunk].to_vec(),
+        );
+    }
+}
+
+/// Commitment to a leaf of the signers tree: SHA256(pubkey || validator_signed), with
+/// validator_signed encoded as a single byte.
+fn signer_leaf<L: PlonkParameters<D>, const D: usize>(
+    builder: &mut CircuitBuilder<L, D>,
+    pubkey: &CompressedEdwardsYVariable,
+    validator_signed: BoolVariable,
+) -> Bytes32Variable {
+    let zero_byte = builder.constant::<ByteVariable>(0);
+    let one_byte = builder.constant::<ByteVariable>(1);
+    let signed_byte = builder.select(validator_signed, one_byte, zero_byte);
+    let mut input_to_hash = pubkey.0.as_bytes().to_vec();
+    input_to_hash.push(signed_byte);
+    builder.curta_sha256(&input_to_hash)
+}
+
+/// Root of the tree SHA256(left || right) over the leaves. The number of leaves must be a power of 2.
+fn signers_root<L: PlonkParameters<D>, const D: usize>(
+    builder: &mut CircuitBuilder<L, D>,
+    leaves: Vec<Bytes32Variable>,
+) -> Bytes32Variable {
+    let mut level = leaves;
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| hash_pair(builder, pair[0], pair[1]))
+            .collect();
+    }
+    level[0]
+}
+
+fn hash_pair<L: PlonkParameters<D>, const D: usize>(
+    builder: &mut CircuitBuilder<L, D>,
+    left: Bytes32Variable,
+    right: Bytes32Variable,
+) -> Bytes32Variable {
+    let mut input_to_hash = left.as_bytes().to_vec();
+    input_to_hash.extend_from_slice(&right.as_bytes());
+    builder.curta_sha256(&input_to_hash)
+}
+
+/// Number of chunks the authorities are split into. MAX_NUM_AUTHORITIES must be a multiple of
+/// CHUNK_SIZE, and the number of chunks a power of 2 that is at least 2.
+fn num_chunks<const MAX_NUM_AUTHORITIES: usize, const CHUNK_SIZE: usize>() -> usize {
+    assert!(
+        MAX_NUM_AUTHORITIES % CHUNK_SIZE == 0,
+        "MAX_NUM_AUTHORITIES must be a multiple of CHUNK_SIZE!"
+    );
+    let num_chunks = MAX_NUM_AUTHORITIES / CHUNK_SIZE;
+    assert!(
+        num_chunks >= 2 && num_chunks.is_power_of_two(),
+        "Number of chunks must be a power of 2 that is at least 2!"
+    );
+    num_chunks
+}
+
+pub trait ChunkedSignatureVerifier<L: PlonkParameters<D>, const D: usize> {
+    /// Verify a simple justification as in `verify_simple_justification`, with the signatures
+    /// verified in chunks of CHUNK_SIZE authorities. Each chunk is verified in a separate proof,
+    /// and the chunks are aggregated into a commitment to the authorities whose signatures were
+    /// verified, which is checked against the authorities in the justification.
+    ///
+    /// The circuit C must register `HintSignatureChunk<MAX_NUM_AUTHORITIES, CHUNK_SIZE>` and the
+    /// `MapReduceGenerator` for `SignatureChunkCtx` with a batch size of 1.
+    fn verify_simple_justification_chunked<
+        C: Circuit,
+        const MAX_NUM_AUTHORITIES: usize,
+        const CHUNK_SIZE: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+    ) -> VerifiedJustification
+    where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<<L as PlonkParameters<D>>::Field>;
+
+    /// Verify a simple justification as in `verify_simple_justification_chunked`, with the
+    /// justification and its chunks fetched by the hints from source instead of the Avail node at
+    /// AVAIL_URL.
+    fn verify_simple_justification_chunked_from<
+        C: Circuit,
+        const MAX_NUM_AUTHORITIES: usize,
+        const CHUNK_SIZE: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+        source: DataSource,
+    ) -> VerifiedJustification
+    where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<<L as PlonkParameters<D>>::Field>;
+}
+
+impl<L: PlonkParameters<D>, const D: usize> ChunkedSignatureVerifier<L, D>
+    for CircuitBuilder<L, D>
+{
+    fn verify_simple_justification_chunked<
+        C: Circuit,
+        const MAX_NUM_AUTHORITIES: usize,
+        const CHUNK_SIZE: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+    ) -> VerifiedJustification
+    where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<<L as PlonkParameters<D>>::Field>,
+    {
+        self.verify_simple_justification_chunked_from::<C, MAX_NUM_AUTHORITIES, CHUNK_SIZE>(
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            options,
+            DataSource::default(),
+        )
+    }
+
+    fn verify_simple_justification_chunked_from<
+        C: Circuit,
+        const MAX_NUM_AUTHORITIES: usize,
+        const CHUNK_SIZE: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+        source: DataSource,
+    ) -> VerifiedJustification
+    where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<<L as PlonkParameters<D>>::Field>,
+    {
+        let num_chunks = num_chunks::<MAX_NUM_AUTHORITIES, CHUNK_SIZE>();
+
+        verify_simple_justification_with(
+            self,
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            options,
+            source.clone(),
+            |builder, justification: &JustificationVariable<MAX_NUM_AUTHORITIES>, success| {
+                let ctx = SignatureChunkCtx {
+                    block_number,
+                    authority_set_id,
+                    encoded_precommit: justification.encoded_precommit.clone(),
+                    success,
+                };
+                let chunk_starts = (0..num_chunks)
+                    .map(|i| (i * CHUNK_SIZE) as u32)
+                    .collect_vec();
+
+                let output = builder.mapreduce::<SignatureChunkCtx, U32Variable, SignatureChunkVariable, C, 1, _, _>(
+                    ctx,
+                    chunk_starts,
+                    |map_ctx, chunk_start, builder| {
+                        // Map Stage
+                        // 1. Fetch the authorities in the chunk.
+                        // 2. Verify the signatures of the authorities marked as signed on the
+                        //  shared encoded_precommit.
+                        // 3. Commit to the pubkeys and validator_signed of the chunk, which are
+                        //  linked to the justification in the outer circuit.
+                        let mut input_stream = VariableStream::new();
+                        input_stream.write(&map_ctx.block_number);
+                        input_stream.write(&map_ctx.authority_set_id);
+                        input_stream.write(&chunk_start[0]);
+                        let output_stream = builder.async_hint(
+                            input_stream,
+                            HintSignatureChunk::<MAX_NUM_AUTHORITIES, CHUNK_SIZE> {
+                                source: source.clone(),
+                            },
+                        );
+                        let validator_signed =
+                            output_stream.read::<ArrayVariable<BoolVariable, CHUNK_SIZE>>(builder);
+                        let signatures = output_stream
+                            .read::<ArrayVariable<EDDSASignatureVariable, CHUNK_SIZE>>(builder);
+                        let pubkeys = output_stream
+                            .read::<ArrayVariable<CompressedEdwardsYVariable, CHUNK_SIZE>>(builder);
+
+                        let message_byte_lengths = builder
+                            .constant::<ArrayVariable<U32Variable, CHUNK_SIZE>>(vec![
+                                ENCODED_PRECOMMIT_LENGTH as u32;
+                                CHUNK_SIZE
+                            ]);
+                        let messages = vec![map_ctx.encoded_precommit.clone(); CHUNK_SIZE];
+                        let verify_signature = validator_signed
+                            .as_vec()
+                            .iter()
+                            .map(|signed| builder.and(*signed, map_ctx.success))
+                            .collect::<Vec<_>>();
+                        builder.curta_eddsa_verify_sigs_conditional(
+                            verify_signature.into(),
+                            Some(message_byte_lengths),
+                            messages.into(),
+                            signatures,
+                            pubkeys.clone(),
+                        );
+
+                        let leaf = pubkeys
+                            .as_vec()
+                            .iter()
+                            .zip(validator_signed.as_vec())
+                            .map(|(pubkey, signed)| signer_leaf(builder, pubkey, signed))
+                            .collect_vec();
+                        SignatureChunkVariable {
+                            signers_commitment: signers_root(builder, leaf),
+                        }
+                    },
+                    |_, left, right, builder| {
+                        // Reduce Stage
+                        // 1. Combine the commitments of the left and right chunks. Chunks are
+                        //  reduced in order, so the result is the root over all chunks.
+                        SignatureChunkVariable {
+                            signers_commitment: hash_pair(
+                                builder,
+                                left.signers_commitment,
+                                right.signers_commitment,
+                            ),
+                        }
+                    },
+                );
+
+                // The verified chunks must be the authorities in the justification, with the same
+                // validator_signed.
+                let leaves = justification
+                    .pubkeys
+                    .as_vec()
+                    .iter()
+                    .zip(justification.validator_signed.as_vec())
+                    .map(|(pubkey, signed)| signer_leaf(builder, pubkey, signed))
+                    .collect_vec();
+                let chunk_roots = leaves
+                    .chunks(CHUNK_SIZE)
+                    .map(|chunk| signers_root(builder, chunk.to_vec()))
+                    .collect_vec();
+                let expected_commitment = signers_root(builder, chunk_roots);
+                let commitment_match =
+                    builder.is_equal(output.signers_commitment, expected_commitment);
+                assert_if_success(builder, commitment_match, success);
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use plonky2x::frontend::mapreduce::generator::MapReduceGenerator;
+    use plonky2x::prelude::{DefaultBuilder, HintRegistry};
+
+    use super::*;
+    use crate::builder::justification::tests::bench_justification;
+    use crate::builder::justification::{GrandpaJustificationVerifier, HintSimpleJustification};
+
+    #[test]
+    fn test_num_chunks() {
+        assert_eq!(num_chunks::<128, 32>(), 4);
+        assert_eq!(num_chunks::<76, 19>(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Number of chunks must be a power of 2")]
+    fn test_num_chunks_not_power_of_two() {
+        num_chunks::<96, 32>();
+    }
+
+    /// Verifies the justification of a block, with the signatures verified in a single proof if
+    /// CHUNK_SIZE is MAX_NUM_AUTHORITIES, and in chunks of CHUNK_SIZE otherwise.
+    #[derive(Debug, Clone)]
+    struct JustificationBenchCircuit<const MAX_NUM_AUTHORITIES: usize, const CHUNK_SIZE: usize> {}
+
+    impl<const MAX_NUM_AUTHORITIES: usize, const CHUNK_SIZE: usize> Circuit
+        for JustificationBenchCircuit<MAX_NUM_AUTHORITIES, CHUNK_SIZE>
+    {
+        fn define<L: PlonkParameters<D>, const D: usize>(builder: &mut CircuitBuilder<L, D>)
+        where
+            <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+            plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+        {
+            let block_number = builder.read::<U32Variable>();
+            let block_hash = builder.read::<Bytes32Variable>();
+            let authority_set_id = builder.read::<U64Variable>();
+            let authority_set_hash = builder.read::<Bytes32Variable>();
+
+            let verified_justification = if CHUNK_SIZE == MAX_NUM_AUTHORITIES {
+                builder.verify_simple_justification::<MAX_NUM_AUTHORITIES>(
+                    block_number,
+                    block_hash,
+                    authority_set_id,
+                    authority_set_hash,
+                    VerificationOptions::default(),
+                )
+            } else {
+                builder
+                    .verify_simple_justification_chunked::<Self, MAX_NUM_AUTHORITIES, CHUNK_SIZE>(
+                        block_number,
+                        block_hash,
+                        authority_set_id,
+                        authority_set_hash,
+                        VerificationOptions::default(),
+                    )
+            };
+            let true_v = builder._true();
+            builder.assert_is_equal(verified_justification.success, true_v);
+        }
+
+        fn register_generators<L: PlonkParameters<D>, const D: usize>(
+            generator_registry: &mut HintRegistry<L, D>,
+        ) where
+            <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+            plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+        {
+            generator_registry
+                .register_async_hint::<HintSimpleJustification<MAX_NUM_AUTHORITIES>>();
+            generator_registry
+                .register_async_hint::<HintSignatureChunk<MAX_NUM_AUTHORITIES, CHUNK_SIZE>>();
+
+            let mr_id = MapReduceGenerator::<
+                L,
+                SignatureChunkCtx,
+                U32Variable,
+                SignatureChunkVariable,
+                Self,
+                1,
+                D,
+            >::id();
+            generator_registry.register_simple::<MapReduceGenerator<
+                L,
+                SignatureChunkCtx,
+                U32Variable,
+                SignatureChunkVariable,
+                Self,
+                1,
+                D,
+            >>(mr_id);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_bench_chunked_signature_verification() {
+        env::set_var("RUST_LOG", "info");
+        env_logger::try_init().unwrap_or_default();
+
+        // 76 authorities, in 4 chunks of 19.
+        bench_justification(
+            "MAX_NUM_AUTHORITIES=76 CHUNK_SIZE=76",
+            JustificationBenchCircuit::<76, 76>::define,
+        );
+        bench_justification(
+            "MAX_NUM_AUTHORITIES=76 CHUNK_SIZE=19",
+            JustificationBenchCircuit::<76, 19>::define,
+        );
+
+        // 128 authorities, in 4 chunks of 32.
+        bench_justification(
+            "MAX_NUM_AUTHORITIES=128 CHUNK_SIZE=128",
+            JustificationBenchCircuit::<128, 128>::define,
+        );
+        bench_justification(
+            "MAX_NUM_AUTHORITIES=128 CHUNK_SIZE=32",
+            JustificationBenchCircuit::<128, 32>::define,
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use plonky2x::backend::circuit::PlonkParameters;
+use plonky2x::frontend::curta::ec::point::CompressedEdwardsYVariable;
+use plonky2x::frontend::ecc::curve25519::ed25519::eddsa::EDDSASignatureVariable;
+use plonky2x::frontend::hint::simple::hint::Hint;
+use plonky2x::frontend::uint::uint64::U64Variable;
+use plonky2x::frontend::vars::{U32Variable, ValueStream, VariableStream};
+use plonky2x::prelude::{ArrayVariable, BoolVariable, Bytes32Variable, CircuitBuilder, Variable};
+use serde::{Deserialize, Serialize};
+
+use super::justification::{
+    assert_if_success, verify_simple_justification_with, VerificationOptions, VerifiedJustification,
+};
+use crate::consts::ENCODED_PRECOMMIT_LENGTH;
+use crate::input::types::DataSource;
+use crate::vars::JustificationVariable;
+
+/// Computes the indices of the validators that signed, in ascending order, padded with 0 to
+/// MAX_NUM_SIGNATURES. If more than MAX_NUM_SIGNATURES validators signed, the indices are truncated
+/// and the circuit rejects the justification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedIndicesHint<const MAX_NUM_AUTHORITIES: usize, const MAX_NUM_SIGNATURES: usize> {}
+
+impl<
+        const MAX_NUM_AUTHORITIES: usize,
+        const MAX_NUM_SIGNATURES: usize,
+        L: PlonkParameters<D>,
+        const D: usize,
+    > Hint<L, D> for SignedIndicesHint<MAX_NUM_AUTHORITIES, MAX_NUM_SIGNATURES>
+{
+    fn hint(&self, input_stream: &mut ValueStream<L, D>, output_stream: &mut ValueStream<L, D>) {
+        let validator_signed =
+            input_stream.read_value::<ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>>();
+
+        let mut signed_indices = validator_signed
+            .iter()
+            .enumerate()
+            .filter(|(_, signed)| **signed)
+            .map(|(i, _)| i as u32)
+            .collect::<Vec<_>>();
+        signed_indices.resize(MAX_NUM_SIGNATURES, 0);
+
+        output_stream.write_value::<ArrayVariable<U32Variable, MAX_NUM_SIGNATURES>>(signed_indices);
+    }
+}
+
+/// The signatures to verify, one per slot. Each enabled slot holds the pubkey and signature of a
+/// different validator that signed, and every validator that signed has a slot.
+pub(crate) struct SignatureSlots<const MAX_NUM_SIGNATURES: usize> {
+    pub enabled: Vec<BoolVariable>,
+    pub pubkeys: ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_SIGNATURES>,
+    pub signatures: ArrayVariable<EDDSASignatureVariable, MAX_NUM_SIGNATURES>,
+}
+
+/// Packs the pubkeys and signatures of the validators that signed into MAX_NUM_SIGNATURES slots.
+/// The slot indices are computed in a hint, and constrained to be strictly ascending indices of
+/// validators that signed, with as many enabled slots as validators that signed. Asserts (if
+/// success is true) that at most MAX_NUM_SIGNATURES validators signed.
+pub(crate) fn select_signature_slots<
+    L: PlonkParameters<D>,
+    const D: usize,
+    const MAX_NUM_AUTHORITIES: usize,
+    const MAX_NUM_SIGNATURES: usize,
+>(
+    builder: &mut CircuitBuilder<L, D>,
+    validator_signed: &ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>,
+    pubkeys: &ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>,
+    signatures: &ArrayVariable<EDDSASignatureVariable, MAX_NUM_AUTHORITIES>,
+    success: BoolVariable,
+) -> SignatureSlots<MAX_NUM_SIGNATURES> {
+    let mut input_stream = VariableStream::new();
+    input_stream.write(validator_signed);
+    let output_stream = builder.hint(
+        input_stream,
+        SignedIndicesHint::<MAX_NUM_AUTHORITIES, MAX_NUM_SIGNATURES> {},
+    );
+    let signed_indices =
+        output_stream.read::<ArrayVariable<U32Variable, MAX_NUM_SIGNATURES>>(builder);
+
+    let mut num_signed = builder.zero::<Variable>();
+    for signed in validator_signed.as_vec() {
+        num_signed = builder.add(num_signed, signed.variable);
+    }
+    let num_signed = U32Variable::from_variables_unsafe(&[num_signed]);
+
+    // Every validator that signed must have a slot.
+    let max_num_signatures = builder.constant::<U32Variable>(MAX_NUM_SIGNATURES as u32);
+    let too_many_signatures = builder.lt(max_num_signatures, num_signed);
+    let signatures_fit = builder.not(too_many_signatures);
+    assert_if_success(builder, signatures_fit, success);
+
+    let max_num_authorities = builder.constant::<U32Variable>(MAX_NUM_AUTHORITIES as u32);
+    let mut enabled = Vec::new();
+    let mut slot_pubkeys = Vec::new();
+    let mut slot_signatures = Vec::new();
+    for i in 0..MAX_NUM_SIGNATURES {
+        // The first num_signed slots are enabled.
+        let slot_idx = builder.constant::<U32Variable>(i as u32);
+        let slot_enabled = builder.lt(slot_idx, num_signed);
+
+        // An enabled slot is a validator that signed. The indices of the enabled slots are strictly
+        // ascending, so no validator has more than one slot. With num_signed enabled slots, each
+        // validator that signed has exactly one slot.
+        let index = signed_indices[i];
+        let in_range = builder.lt(index, max_num_authorities);
+        let signed = builder.select_array_random_gate(&validator_signed.as_vec(), index.variable);
+        let mut is_valid_slot = builder.and(in_range, signed);
+        if i > 0 {
+            let ascending = builder.lt(signed_indices[i - 1], index);
+            is_valid_slot = builder.and(is_valid_slot, ascending);
+        }
+        let slot_disabled = builder.not(slot_enabled);
+        let slot_check = builder.or(is_valid_slot, slot_disabled);
+        assert_if_success(builder, slot_check, success);
+
+        slot_pubkeys.push(builder.select_array_random_gate(&pubkeys.as_vec(), index.variable));
+        slot_signatures
+            .push(builder.select_array_random_gate(&signatures.as_vec(), index.variable));
+        enabled.push(builder.and(slot_enabled, success));
+    }
+
+    SignatureSlots {
+        enabled,
+        pubkeys: slot_pubkeys.into(),
+        signatures: slot_signatures.into(),
+    }
+}
+
+pub trait SlottedSignatureVerifier {
+    /// Verify a simple justification as in `verify_simple_justification`, verifying the signatures
+    /// in MAX_NUM_SIGNATURES slots instead of one per authority. The EdDSA verification cost scales
+    /// with MAX_NUM_SIGNATURES, so validators that did not sign and the padding up to
+    /// MAX_NUM_AUTHORITIES are not verified. Packing the signatures into slots costs a random
+    /// access over the authorities per slot.
+    ///
+    /// If more than MAX_NUM_SIGNATURES validators signed, the justification is rejected, so
+    /// MAX_NUM_SIGNATURES must be at least the size of the largest authority set to be proven.
+    ///
+    /// The circuit must register `SignedIndicesHint<MAX_NUM_AUTHORITIES, MAX_NUM_SIGNATURES>`.
+    fn verify_simple_justification_slotted<
+        const MAX_NUM_AUTHORITIES: usize,
+        const MAX_NUM_SIGNATURES: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+    ) -> VerifiedJustification;
+
+    /// Verify a simple justification as in `verify_simple_justification_slotted`, fetched by the
+    /// hint from source instead of the Avail node at AVAIL_URL.
+    fn verify_simple_justification_slotted_from<
+        const MAX_NUM_AUTHORITIES: usize,
+        const MAX_NUM_SIGNATURES: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+        source: DataSource,
+    ) -> VerifiedJustification;
+}
+
+impl<L: PlonkParameters<D>, const D: usize> SlottedSignatureVerifier for CircuitBuilder<L, D> {
+    fn verify_simple_justification_slotted<
+        const MAX_NUM_AUTHORITIES: usize,
+        const MAX_NUM_SIGNATURES: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+    ) -> VerifiedJustification {
+        self.verify_simple_justification_slotted_from::<MAX_NUM_AUTHORITIES, MAX_NUM_SIGNATURES>(
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            options,
+            DataSource::default(),
+        )
+    }
+
+    fn verify_simple_justification_slotted_from<
+        const MAX_NUM_AUTHORITIES: usize,
+        const MAX_NUM_SIGNATURES: usize,
+    >(
+        &mut self,
+        block_number: U32Variable,
+        block_hash: Bytes32Variable,
+        authority_set_id: U64Variable,
+        authority_set_hash: Bytes32Variable,
+        options: VerificationOptions,
+        source: DataSource,
+    ) -> VerifiedJustification {
+        assert!(
+            MAX_NUM_SIGNATURES <= MAX_NUM_AUTHORITIES,
+            "MAX_NUM_SIGNATURES must be at most MAX_NUM_AUTHORITIES!"
+        );
+
+        verify_simple_justification_with(
+            self,
+            block_number,
+            block_hash,
+            authority_set_id,
+            authority_set_hash,
+            options,
+            source,
+            |builder, justification: &JustificationVariable<MAX_NUM_AUTHORITIES>, success| {
+                let slots = select_signature_slots::<L, D, MAX_NUM_AUTHORITIES, MAX_NUM_SIGNATURES>(
+                    builder,
+                    &justification.validator_signed,
+                    &justification.pubkeys,
+                    &justification.signatures,
+                    success,
+                );
+
+                let message_byte_lengths = builder
+                    .constant::<ArrayVariable<U32Variable, MAX_NUM_SIGNATURES>>(vec![
+                        ENCODED_PRECOMMIT_LENGTH
+                            as u32;
+                        MAX_NUM_SIGNATURES
+                    ]);
+                let messages = vec![justification.encoded_precommit.clone(); MAX_NUM_SIGNATURES];
+                builder.curta_eddsa_verify_sigs_conditional(
+                    slots.enabled.into(),
+                    Some(message_byte_lengths),
+                    messages.into(),
+                    slots.signatures,
+                    slots.pubkeys,
+                );
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use plonky2x::frontend::curta::ec::point::CompressedEdwardsY;
+    use plonky2x::prelude::DefaultBuilder;
+
+    use super::*;
+    use crate::builder::justification::tests::bench_justification;
+    use crate::builder::justification::{signature_to_value_type, GrandpaJustificationVerifier};
+
+    /// Proves select_signature_slots with 4 authorities and 2 slots, and returns the enabled flags
+    /// and the pubkeys of the slots. Authority i has the pubkey [i; 32].
+    fn prove_signature_slots(validator_signed: Vec<bool>) -> (Vec<bool>, Vec<CompressedEdwardsY>) {
+        const MAX_NUM_AUTHORITIES: usize = 4;
+        const MAX_NUM_SIGNATURES: usize = 2;
+        let mut builder = DefaultBuilder::new();
+
+        let validator_signed_var =
+            builder.read::<ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>>();
+        let pubkeys = builder
+            .constant::<ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_AUTHORITIES>>(
+                (0..MAX_NUM_AUTHORITIES as u8)
+                    .map(|i| CompressedEdwardsY([i; 32]))
+                    .collect(),
+            );
+        let signatures = builder
+            .constant::<ArrayVariable<EDDSASignatureVariable, MAX_NUM_AUTHORITIES>>(
+                (0..MAX_NUM_AUTHORITIES as u8)
+                    .map(|i| signature_to_value_type(&[i; 64]).unwrap())
+                    .collect(),
+            );
+        let success = builder._true();
+        let slots = select_signature_slots::<_, 2, MAX_NUM_AUTHORITIES, MAX_NUM_SIGNATURES>(
+            &mut builder,
+            &validator_signed_var,
+            &pubkeys,
+            &signatures,
+            success,
+        );
+        for enabled in slots.enabled {
+            builder.write(enabled);
+        }
+        builder.write(slots.pubkeys);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<ArrayVariable<BoolVariable, MAX_NUM_AUTHORITIES>>(validator_signed);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        let enabled = (0..MAX_NUM_SIGNATURES)
+            .map(|_| output.read::<BoolVariable>())
+            .collect();
+        let pubkeys =
+            output.read::<ArrayVariable<CompressedEdwardsYVariable, MAX_NUM_SIGNATURES>>();
+        (enabled, pubkeys)
+    }
+
+    #[test]
+    fn test_select_signature_slots() {
+        let (enabled, pubkeys) = prove_signature_slots(vec![false, true, false, true]);
+        assert_eq!(enabled, vec![true, true]);
+        assert_eq!(
+            pubkeys,
+            vec![CompressedEdwardsY([1; 32]), CompressedEdwardsY([3; 32])]
+        );
+
+        // Slots past the number of validators that signed are disabled.
+        let (enabled, pubkeys) = prove_signature_slots(vec![false, false, true, false]);
+        assert_eq!(enabled, vec![true, false]);
+        assert_eq!(pubkeys[0], CompressedEdwardsY([2; 32]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_select_signature_slots_too_many_signatures() {
+        prove_signature_slots(vec![true, true, true, false]);
+    }
+
+    /// Verifies the justification read from the builder with the signatures verified per
+    /// authority, or in MAX_NUM_SIGNATURES slots if MAX_NUM_SIGNATURES is less than
+    /// MAX_NUM_AUTHORITIES.
+    fn define_slotted_justification<
+        const MAX_NUM_AUTHORITIES: usize,
+        const MAX_NUM_SIGNATURES: usize,
+    >(
+        builder: &mut DefaultBuilder,
+    ) {
+        let block_number = builder.read::<U32Variable>();
+        let block_hash = builder.read::<Bytes32Variable>();
+        let authority_set_id = builder.read::<U64Variable>();
+        let authority_set_hash = builder.read::<Bytes32Variable>();
+        let verified_justification = if MAX_NUM_SIGNATURES == MAX_NUM_AUTHORITIES {
+            builder.verify_simple_justification::<MAX_NUM_AUTHORITIES>(
+                block_number,
+                block_hash,
+                authority_set_id,
+                authority_set_hash,
+                VerificationOptions::default(),
+            )
+        } else {
+            builder.verify_simple_justification_slotted::<MAX_NUM_AUTHORITIES, MAX_NUM_SIGNATURES>(
+                block_number,
+                block_hash,
+                authority_set_id,
+                authority_set_hash,
+                VerificationOptions::default(),
+            )
+        };
+        let true_v = builder._true();
+        builder.assert_is_equal(verified_justification.success, true_v);
+    }
+
+    /// Compares the size and proving time of verifying the signatures per authority against
+    /// verifying them in slots, for an authority set much smaller than MAX_NUM_AUTHORITIES.
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_bench_slotted_signature_verification() {
+        env::set_var("RUST_LOG", "info");
+        env_logger::try_init().unwrap_or_default();
+
+        bench_justification(
+            "MAX_NUM_AUTHORITIES=256 MAX_NUM_SIGNATURES=256",
+            define_slotted_justification::<256, 256>,
+        );
+        bench_justification(
+            "MAX_NUM_AUTHORITIES=256 MAX_NUM_SIGNATURES=76",
+            define_slotted_justification::<256, 76>,
+        );
+    }
+}
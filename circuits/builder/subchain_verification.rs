@@ -1,5 +1,4 @@
 use async_trait::async_trait;
-use codec::Encode;
 use ethers::types::H256;
 use itertools::Itertools;
 use log::debug;
@@ -16,7 +15,7 @@ use serde::{Deserialize, Serialize};
 use crate::builder::decoder::DecodingMethods;
 use crate::builder::header::HeaderMethods;
 use crate::consts::{HEADERS_PER_MAP, MAX_HEADER_SIZE};
-use crate::input::RpcDataFetcher;
+use crate::input::{encode_header, RpcDataFetcher};
 use crate::vars::{EncodedHeader, EncodedHeaderVariable, SubchainVerificationVariable};
 
 #[derive(Clone, Debug, CircuitVariable)]
@@ -39,6 +38,24 @@ pub trait SubChainVerifier<L: PlonkParameters<D>, const D: usize> {
     where
         <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
         plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<<L as PlonkParameters<D>>::Field>;
+
+    /// Verify a chain of headers from a trusted start header, and compute the state and data merkle
+    /// root commitments over the range (start_header, end_header]. headers are the headers after
+    /// start_header, and the end header is headers[num_headers - 1]. The headers at index >=
+    /// num_headers are padding, and their leaves in the merkle commitments are zero.
+    ///
+    /// Specifically, this verifies that:
+    ///     1) Each header's parent hash is the hash of the previous header.
+    ///     2) Each header's block number is one more than the previous header's block number.
+    ///
+    /// Unlike `verify_subchain`, the headers are provided by the caller rather than fetched in a
+    /// map reduce, so this is only suited to short subchains. MAX_NUM_HEADERS must be a power of 2.
+    fn verify_header_subchain<const MAX_HEADER_SIZE: usize, const MAX_NUM_HEADERS: usize>(
+        &mut self,
+        start_header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+        headers: &ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>,
+        num_headers: U32Variable,
+    ) -> SubchainVerificationVariable;
 }
 
 #[derive(Clone, Debug, CircuitVariable)]
@@ -309,6 +326,77 @@ impl<L: PlonkParameters<D>, const D: usize> SubChainVerifier<L, D> for CircuitBu
             data_root_merkle_root: output.data_merkle_root,
         }
     }
+
+    fn verify_header_subchain<const MAX_HEADER_SIZE: usize, const MAX_NUM_HEADERS: usize>(
+        &mut self,
+        start_header: &EncodedHeaderVariable<MAX_HEADER_SIZE>,
+        headers: &ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>,
+        num_headers: U32Variable,
+    ) -> SubchainVerificationVariable {
+        let true_v = self._true();
+        let false_v = self._false();
+        let one_u32 = self.one::<U32Variable>();
+
+        // Assert the subchain has an end header, and fits in headers, otherwise the subchain would
+        // silently be truncated.
+        let no_headers = self.is_zero(num_headers.variable);
+        self.assert_is_equal(no_headers, false_v);
+        let max_num_headers = self.constant::<U32Variable>(MAX_NUM_HEADERS as u32);
+        let too_many_headers = self.lt(max_num_headers, num_headers);
+        self.assert_is_equal(too_many_headers, false_v);
+
+        let start_hash = self.hash_encoded_header(start_header);
+        let start_header_variable =
+            self.decode_header::<MAX_HEADER_SIZE>(start_header, &start_hash);
+
+        let mut prev_hash = start_hash;
+        let mut prev_block_number = start_header_variable.block_number;
+        let mut end_header_hash = start_hash;
+        let mut block_state_roots = Vec::new();
+        let mut block_data_roots = Vec::new();
+
+        // in_padding is true for all headers at index >= num_headers.
+        let mut in_padding = self._false();
+        for i in 0..MAX_NUM_HEADERS {
+            let idx = self.constant::<U32Variable>(i as u32);
+            let is_chain_end = self.is_equal(idx, num_headers);
+            in_padding = self.or(in_padding, is_chain_end);
+
+            let hash = self.hash_encoded_header(&headers[i]);
+            let header_variable = self.decode_header::<MAX_HEADER_SIZE>(&headers[i], &hash);
+            block_state_roots.push(header_variable.state_root);
+            block_data_roots.push(header_variable.data_root);
+
+            // If this is not a padding header, it must be linked to the previous header.
+            let hashes_linked = self.is_equal(header_variable.parent_hash, prev_hash);
+            let expected_block_number = self.add(prev_block_number, one_u32);
+            let nums_sequential =
+                self.is_equal(header_variable.block_number, expected_block_number);
+            let header_correctly_linked = self.and(hashes_linked, nums_sequential);
+            let link_check = self.or(in_padding, header_correctly_linked);
+            self.assert_is_equal(link_check, true_v);
+
+            prev_hash = hash;
+            prev_block_number = header_variable.block_number;
+            end_header_hash = self.select(in_padding, end_header_hash, hash);
+        }
+
+        // Calculate the state and data merkle roots. The leaves of the padding headers are zero.
+        let state_root_merkle_root = self.get_root_from_hashed_leaves::<MAX_NUM_HEADERS>(
+            ArrayVariable::<Bytes32Variable, MAX_NUM_HEADERS>::new(block_state_roots),
+            num_headers.variable,
+        );
+        let data_root_merkle_root = self.get_root_from_hashed_leaves::<MAX_NUM_HEADERS>(
+            ArrayVariable::<Bytes32Variable, MAX_NUM_HEADERS>::new(block_data_roots),
+            num_headers.variable,
+        );
+
+        SubchainVerificationVariable {
+            target_header_hash: end_header_hash,
+            state_root_merkle_root,
+            data_root_merkle_root,
+        }
+    }
 }
 
 // Fetch a range of headers with a hint. Used to generate a data commitment for header_range.
@@ -339,39 +427,20 @@ impl<
             start_block, last_block
         );
 
-        let mut headers = Vec::new();
-        if last_block >= start_block {
-            headers.extend({
-                let mut data_fetcher = RpcDataFetcher::new().await;
-                data_fetcher
-                    .get_block_headers_range(start_block, last_block)
-                    .await
-            });
-        }
-
-        // Pad `headers` to the correct length for `EncodedHeader` variables.
         let mut header_variables = Vec::new();
-        for (i, header) in headers.iter().enumerate() {
-            let mut header_bytes = header.encode();
-            let header_size = header_bytes.len();
-            if header_size > HEADER_LENGTH {
-                panic!(
-                    "Block {}'s header size is {}, which is greater than the maximum header size of {} bytes.",
-                    start_block + i as u32,
-                    header_size,
-                    HEADER_LENGTH
-                );
-            }
-            header_bytes.resize(HEADER_LENGTH, 0);
-            let header_variable = EncodedHeader {
-                header_bytes,
-                header_size: header_size as u32,
-            };
-            header_variables.push(header_variable);
+        if last_block >= start_block {
+            let mut data_fetcher = RpcDataFetcher::new().await;
+            let headers = data_fetcher
+                .get_headers_range(start_block, last_block)
+                .await
+                .expect("Failed to fetch header range");
+            header_variables.extend(headers.iter().map(|header| {
+                encode_header::<HEADER_LENGTH, L::Field>(header).expect("Failed to encode header")
+            }));
         }
 
         // Pad `header_variables` with empty headers to ensure its length is NUM_HEADERS.
-        for _i in headers.len()..NUM_HEADERS {
+        for _i in header_variables.len()..NUM_HEADERS {
             let header_variable = EncodedHeader {
                 header_bytes: vec![0u8; HEADER_LENGTH],
                 header_size: 0u32,
@@ -389,8 +458,9 @@ impl<
 mod tests {
     use std::env;
 
+    use avail_subxt::config::Header;
     use plonky2x::frontend::mapreduce::generator::MapReduceGenerator;
-    use plonky2x::prelude::{DefaultBuilder, DefaultParameters, HintRegistry};
+    use plonky2x::prelude::{DefaultBuilder, DefaultParameters, GoldilocksField, HintRegistry};
 
     use super::*;
     use crate::consts::{BLAKE2B_CHUNK_SIZE_BYTES, MAX_HEADER_CHUNK_SIZE};
@@ -489,4 +559,141 @@ mod tests {
             D,
         >();
     }
+
+    /// Fetches the headers of the blocks in [start_block, start_block + num_headers].
+    fn fetch_chain(start_block: u32, num_headers: usize) -> Vec<avail_subxt::primitives::Header> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut data_fetcher = RpcDataFetcher::new().await;
+            data_fetcher
+                .get_block_headers_range(start_block, start_block + num_headers as u32)
+                .await
+        })
+    }
+
+    /// Proves verify_header_subchain from the first header of chain over the next num_headers
+    /// headers of chain, padded to MAX_NUM_HEADERS, after tamper is applied to the encoded headers.
+    /// Returns the target header hash, and the state and data root commitments.
+    fn prove_header_subchain<const MAX_NUM_HEADERS: usize>(
+        chain: &[avail_subxt::primitives::Header],
+        num_headers: usize,
+        tamper: fn(&mut Vec<EncodedHeader<MAX_HEADER_SIZE, GoldilocksField>>),
+    ) -> (H256, H256, H256) {
+        env::set_var("RUST_LOG", "debug");
+        dotenv::dotenv().ok();
+        env_logger::try_init().unwrap_or_default();
+
+        let mut builder = DefaultBuilder::new();
+        let start_header = builder.read::<EncodedHeaderVariable<MAX_HEADER_SIZE>>();
+        let headers = builder
+            .read::<ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>>();
+        let num_headers_var = builder.read::<U32Variable>();
+        let subchain_output = builder.verify_header_subchain::<MAX_HEADER_SIZE, MAX_NUM_HEADERS>(
+            &start_header,
+            &headers,
+            num_headers_var,
+        );
+        builder.write::<Bytes32Variable>(subchain_output.target_header_hash);
+        builder.write::<Bytes32Variable>(subchain_output.state_root_merkle_root);
+        builder.write::<Bytes32Variable>(subchain_output.data_root_merkle_root);
+        let circuit = builder.build();
+
+        let mut header_values = chain[1..num_headers + 1]
+            .iter()
+            .map(|header| encode_header::<MAX_HEADER_SIZE, GoldilocksField>(header).unwrap())
+            .collect::<Vec<_>>();
+        header_values.resize(
+            MAX_NUM_HEADERS,
+            EncodedHeader {
+                header_bytes: vec![0u8; MAX_HEADER_SIZE],
+                header_size: 0,
+            },
+        );
+        tamper(&mut header_values);
+
+        let mut input = circuit.input();
+        input.write::<EncodedHeaderVariable<MAX_HEADER_SIZE>>(encode_header(&chain[0]).unwrap());
+        input.write::<ArrayVariable<EncodedHeaderVariable<MAX_HEADER_SIZE>, MAX_NUM_HEADERS>>(
+            header_values,
+        );
+        input.write::<U32Variable>(num_headers as u32);
+
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        (
+            output.read::<Bytes32Variable>(),
+            output.read::<Bytes32Variable>(),
+            output.read::<Bytes32Variable>(),
+        )
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_verify_header_subchain() {
+        const MAX_NUM_HEADERS: usize = 16;
+        let chain = fetch_chain(397855, MAX_NUM_HEADERS);
+
+        // A full 16 block subchain, and a subchain of 12 blocks padded to 16 headers.
+        for num_headers in [MAX_NUM_HEADERS, 12] {
+            let (target_header_hash, state_root_merkle_root, data_root_merkle_root) =
+                prove_header_subchain::<MAX_NUM_HEADERS>(&chain, num_headers, |_| {});
+
+            // The commitments are over the headers after the start header, with zero leaves for
+            // the padding headers.
+            let mut state_root_leaves = Vec::new();
+            let mut data_root_leaves = Vec::new();
+            for header in &chain[1..num_headers + 1] {
+                state_root_leaves.push(header.state_root.0.to_vec());
+                data_root_leaves.push(header.data_root().0.to_vec());
+            }
+            state_root_leaves.resize(MAX_NUM_HEADERS, [0u8; 32].to_vec());
+            data_root_leaves.resize(MAX_NUM_HEADERS, [0u8; 32].to_vec());
+
+            assert_eq!(
+                target_header_hash,
+                H256::from_slice(&chain[num_headers].hash().0)
+            );
+            assert_eq!(
+                state_root_merkle_root.0.to_vec(),
+                RpcDataFetcher::get_merkle_root(state_root_leaves)
+            );
+            assert_eq!(
+                data_root_merkle_root.0.to_vec(),
+                RpcDataFetcher::get_merkle_root(data_root_leaves)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_verify_header_subchain_compact_boundary() {
+        const MAX_NUM_HEADERS: usize = 8;
+
+        // The block numbers cross from the single-byte to the two-byte compact encoding at 64, and
+        // from the two-byte to the four-byte compact encoding at 16384.
+        for start_block in [60u32, 16380] {
+            let chain = fetch_chain(start_block, MAX_NUM_HEADERS);
+            let (target_header_hash, _, _) =
+                prove_header_subchain::<MAX_NUM_HEADERS>(&chain, MAX_NUM_HEADERS, |_| {});
+            assert_eq!(
+                target_header_hash,
+                H256::from_slice(&chain[MAX_NUM_HEADERS].hash().0)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_verify_header_subchain_wrong_parent() {
+        const MAX_NUM_HEADERS: usize = 8;
+        let chain = fetch_chain(272355, MAX_NUM_HEADERS);
+
+        // Header 2 points at the parent of header 1 instead of header 1, as a header from a fork
+        // would.
+        prove_header_subchain::<MAX_NUM_HEADERS>(&chain, MAX_NUM_HEADERS, |headers| {
+            let grandparent_hash = headers[1].header_bytes[0..32].to_vec();
+            headers[2].header_bytes[0..32].copy_from_slice(&grandparent_hash);
+        });
+    }
 }
@@ -15,6 +15,11 @@ pub const BLAKE2B_CHUNK_SIZE_BYTES: usize = 128;
 // (Data limit is 512KB).
 pub const MAX_HEADER_SIZE: usize = MAX_HEADER_CHUNK_SIZE * BLAKE2B_CHUNK_SIZE_BYTES;
 
+// Number of blake2b chunks needed to hash a header of max_header_length bytes.
+pub const fn header_chunk_size(max_header_length: usize) -> usize {
+    (max_header_length + BLAKE2B_CHUNK_SIZE_BYTES - 1) / BLAKE2B_CHUNK_SIZE_BYTES
+}
+
 // Digest byte size.
 pub const HASH_SIZE: usize = 32;
 
@@ -27,19 +32,61 @@ pub const PUBKEY_LENGTH: usize = 32;
 // Length of the weight of an Avail validator.
 pub const WEIGHT_LENGTH: usize = 8;
 
+// Max voting weight of an authority in the justification circuit. Bounding the weights bounds the
+// sums of at most MAX_AUTHORITY_SET_SIZE weights, so scaling them by the voting threshold can't
+// overflow a u64.
+pub const MAX_AUTHORITY_WEIGHT: u64 = u32::MAX as u64;
+
+// Max number of SHA256 chunks hashed per authority by the weighted authority set commitment. The
+// chained hash input is the previous commitment, the pubkey and the weight, which is 72 bytes.
+pub const WEIGHTED_COMMITMENT_SHA256_CHUNKS: usize = 2;
+
 // Length of the delay in an Avail header.
 pub const DELAY_LENGTH: usize = 4;
 
 // The base length of the prefix excluding the variable length encoded new authority set length.
 pub const BASE_PREFIX_LENGTH: usize = 9;
 
+// Length of the median last finalized block number in a ForcedChange log, which precedes the encoded
+// new authority set length.
+pub const FORCED_CHANGE_MEDIAN_LENGTH: usize = 4;
+
+// The GRANDPA consensus log flags that enact a new authority set.
+pub const SCHEDULED_CHANGE_FLAG: u8 = 1;
+pub const FORCED_CHANGE_FLAG: u8 = 2;
+
+// The GRANDPA consensus log flags that pause and resume finality, without changing the authority set.
+pub const PAUSE_FLAG: u8 = 4;
+pub const RESUME_FLAG: u8 = 5;
+
+// The tags of the header digest log variants.
+// Spec: https://github.com/paritytech/polkadot-sdk/blob/master/substrate/primitives/runtime/src/generic/digest.rs
+pub const DIGEST_OTHER_TAG: u8 = 0;
+pub const DIGEST_CONSENSUS_TAG: u8 = 4;
+pub const DIGEST_SEAL_TAG: u8 = 5;
+pub const DIGEST_PRE_RUNTIME_TAG: u8 = 6;
+pub const DIGEST_RUNTIME_ENVIRONMENT_UPDATED_TAG: u8 = 8;
+
+// Max number of digest logs in a header decoded by the rotate circuit. An epoch end header has a
+// BABE PreRuntime, a BABE NextEpochData, a GRANDPA ScheduledChange and a BABE Seal log.
+pub const MAX_DIGEST_ITEMS: usize = 8;
+
+// Length of the start of a digest log read by the rotate circuit: the tag, the consensus engine id,
+// the compact encoded payload length and the first byte of the payload.
+pub const DIGEST_ITEM_PREFIX_LENGTH: usize = 1 + 4 + MAX_COMPACT_UINT_BYTES + 1;
+
 // The maximum length of the prefix before the encoded new authority set. This is the maximum length
-// including the encoded new authority set length.
-pub const MAX_PREFIX_LENGTH: usize = BASE_PREFIX_LENGTH + MAX_COMPACT_UINT_BYTES;
+// including the median last finalized block number of a ForcedChange and the encoded new authority
+// set length.
+pub const MAX_PREFIX_LENGTH: usize =
+    BASE_PREFIX_LENGTH + FORCED_CHANGE_MEDIAN_LENGTH + MAX_COMPACT_UINT_BYTES;
 
 // Length of the justification encoded precommit message.  This is what is
 // signed by the authorities.
 // Link: https://github.com/availproject/avail/blob/188c20d6a1577670da65e0c6e1c2a38bea8239bb/avail-subxt/src/api_dev.rs#L30549-L30557.
+// The message is (message type: u8, block hash: H256, block number: u32, round: u64,
+// authority set id: u64). The round and authority set id are fixed-width u64s, not compact
+// integers, so the length does not grow with the round or authority set id.
 pub const ENCODED_PRECOMMIT_LENGTH: usize = 53;
 
 // The maximum size of the subarray is the max length of the encoded
@@ -52,5 +99,17 @@ pub const MAX_AUTHORITY_SET_SIZE: usize = 300;
 // Max number of headers this circuit currently supports. This is one era.
 pub const MAX_NUM_HEADERS: usize = 256;
 
+// Number of justifications the step circuit verifies in a single proof.
+pub const MAX_NUM_STEPS: usize = 4;
+
 // Can need up to 5 bytes to represent a compact u32.
 pub const MAX_COMPACT_UINT_BYTES: usize = 5;
+
+// Can need up to 9 bytes to represent a compact u64: the prefix byte and 8 bytes in "BigInt" mode.
+pub const MAX_COMPACT_U64_BYTES: usize = 9;
+
+// Max number of header requests in flight at once when fetching a range of headers.
+pub const MAX_CONCURRENT_HEADER_REQUESTS: usize = 16;
+
+// Websocket url of the Avail node to connect to if AVAIL_URL is not set.
+pub const DEFAULT_AVAIL_URL: &str = "wss://kate.avail.tools:443/ws";
@@ -25,7 +25,11 @@ impl Program for DummyRotate {
                 .to_vec()
         });
 
-        new_authority_set_hash
+        // The output is (new_authority_set_hash, next_authority_set_id), matching RotateCircuit.
+        let next_authority_set_id = authority_set_id + 1;
+        let mut output = new_authority_set_hash;
+        output.extend_from_slice(&next_authority_set_id.to_be_bytes());
+        output
     }
 }
 
@@ -47,9 +51,10 @@ mod tests {
         let output = DummyRotate::run(input_bytes);
 
         // Assert that the output matches.
-        let expected_output =
-            hex::decode("21969829db96b6cc8171290a231a150fbf4b11911eea1edb7b1d785716797a7f")
-                .unwrap();
+        let expected_output = hex::decode(
+            "21969829db96b6cc8171290a231a150fbf4b11911eea1edb7b1d785716797a7f0000000000000076",
+        )
+        .unwrap();
         assert_eq!(output, expected_output);
     }
 }
@@ -3,12 +3,16 @@ use plonky2x::frontend::mapreduce::generator::MapReduceGenerator;
 use plonky2x::frontend::uint::uint64::U64Variable;
 use plonky2x::frontend::vars::U32Variable;
 use plonky2x::prelude::{Bytes32Variable, CircuitBuilder, PlonkParameters};
+use serde_json::Value;
 
-use crate::builder::justification::{GrandpaJustificationVerifier, HintSimpleJustification};
+use crate::builder::justification::{
+    GrandpaJustificationVerifier, HintSimpleJustification, VerificationOptions,
+};
 use crate::builder::subchain_verification::{
     HeaderRangeFetcherHint, MapReduceSubchainVariable, SubChainVerifier, SubchainVerificationCtx,
 };
 use crate::consts::HEADERS_PER_MAP;
+use crate::io_schema::{io_schema, IoField};
 
 #[derive(Clone, Debug)]
 pub struct HeaderRangeCircuit<
@@ -17,6 +21,34 @@ pub struct HeaderRangeCircuit<
     const MAX_NUM_HEADERS: usize,
 > {}
 
+impl<
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_HEADER_SIZE: usize,
+        const MAX_NUM_HEADERS: usize,
+    > HeaderRangeCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_NUM_HEADERS>
+{
+    /// Public inputs, in the order they are read in `define`.
+    pub const INPUTS: [IoField; 5] = [
+        IoField::new("trusted_block", "uint32", 4),
+        IoField::new("trusted_header_hash", "bytes32", 32),
+        IoField::new("authority_set_id", "uint64", 8),
+        IoField::new("authority_set_hash", "bytes32", 32),
+        IoField::new("target_block", "uint32", 4),
+    ];
+
+    /// Public outputs, in the order they are written in `define`.
+    pub const OUTPUTS: [IoField; 3] = [
+        IoField::new("target_header_hash", "bytes32", 32),
+        IoField::new("state_root_merkle_root", "bytes32", 32),
+        IoField::new("data_root_merkle_root", "bytes32", 32),
+    ];
+
+    /// Schema of the public inputs and outputs. Must be kept in sync with `define`.
+    pub fn io_schema() -> Value {
+        io_schema("header_range", &Self::INPUTS, &Self::OUTPUTS)
+    }
+}
+
 impl<
         const MAX_AUTHORITY_SET_SIZE: usize,
         const MAX_HEADER_SIZE: usize,
@@ -46,12 +78,15 @@ impl<
 
         // Note: target_header_hash and target_block are trusted at this point.
         // Verify that there is a valid justification on target_header_hash by the authority set at authority_set_id.
-        builder.verify_simple_justification::<MAX_AUTHORITY_SET_SIZE>(
+        let verified_justification = builder.verify_simple_justification::<MAX_AUTHORITY_SET_SIZE>(
             target_block,
             subchain_output.target_header_hash,
             authority_set_id,
             authority_set_hash,
+            VerificationOptions::default(),
         );
+        let true_v = builder._true();
+        builder.assert_is_equal(verified_justification.success, true_v);
 
         builder.evm_write::<Bytes32Variable>(subchain_output.target_header_hash);
         builder.evm_write::<Bytes32Variable>(subchain_output.state_root_merkle_root);
@@ -93,12 +128,38 @@ impl<
 mod tests {
     use std::env;
 
+    use ethers::types::H256;
     use ethers::utils::hex;
     use plonky2x::backend::circuit::PublicInput;
-    use plonky2x::prelude::{DefaultBuilder, GateRegistry, HintRegistry};
+    use plonky2x::prelude::{
+        DefaultBuilder, EvmVariable, GateRegistry, GoldilocksField, HintRegistry,
+    };
 
     use super::*;
     use crate::consts::{MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_NUM_HEADERS};
+    use crate::input::{compute_weighted_authority_set_hash, RpcDataFetcher};
+
+    #[test]
+    fn test_header_range_io_schema() {
+        type HeaderRange =
+            HeaderRangeCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_NUM_HEADERS>;
+
+        let schema = HeaderRange::io_schema();
+
+        // The widths in the schema must match the encoding of the variables read and written in
+        // define.
+        let u32_length = U32Variable::encode_value::<GoldilocksField>(0).len();
+        let u64_length = U64Variable::encode_value::<GoldilocksField>(0).len();
+        let bytes32_length = Bytes32Variable::encode_value::<GoldilocksField>(H256::zero()).len();
+        assert_eq!(
+            schema["inputs_byte_length"],
+            u32_length + bytes32_length + u64_length + bytes32_length + u32_length
+        );
+        assert_eq!(schema["outputs_byte_length"], 3 * bytes32_length);
+
+        assert_eq!(schema["inputs"][4]["name"], "target_block");
+        assert_eq!(schema["inputs"][4]["offset"], 76);
+    }
 
     #[test]
     #[cfg_attr(feature = "ci", ignore)]
@@ -306,4 +367,109 @@ mod tests {
         println!("state root merkle root {:?}", state_root_merkle_root);
         println!("data root merkle root {:?}", data_root_merkle_root);
     }
+
+    /// Proves the header range circuit over (trusted_block, target_block], with the justification on
+    /// target_block verified by the authority set at authority_set_id. Checks the outputs against the
+    /// target header hash and the merkle roots computed from the fetched headers.
+    fn prove_header_range_from_chain(trusted_block: u32, target_block: u32, authority_set_id: u64) {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (trusted_header, authority_set_hash, target_header, state_root, data_root) = rt
+            .block_on(async {
+                let mut fetcher = RpcDataFetcher::new().await;
+                let (authorities, weights) = fetcher
+                    .get_authorities_for_set_id(authority_set_id, target_block)
+                    .await
+                    .unwrap();
+                let (state_root, data_root) = fetcher
+                    .get_merkle_root_commitments(trusted_block, target_block)
+                    .await;
+                (
+                    fetcher.get_block_hash(trusted_block).await,
+                    H256::from_slice(&compute_weighted_authority_set_hash(&authorities, &weights)),
+                    fetcher.get_block_hash(target_block).await,
+                    H256::from_slice(&state_root),
+                    H256::from_slice(&data_root),
+                )
+            });
+
+        let mut builder = DefaultBuilder::new();
+        HeaderRangeCircuit::<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_NUM_HEADERS>::define(
+            &mut builder,
+        );
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.evm_write::<U32Variable>(trusted_block);
+        input.evm_write::<Bytes32Variable>(trusted_header);
+        input.evm_write::<U64Variable>(authority_set_id);
+        input.evm_write::<Bytes32Variable>(authority_set_hash);
+        input.evm_write::<U32Variable>(target_block);
+
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        assert_eq!(output.evm_read::<Bytes32Variable>(), target_header);
+        assert_eq!(output.evm_read::<Bytes32Variable>(), state_root);
+        assert_eq!(output.evm_read::<Bytes32Variable>(), data_root);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_header_range_epoch_boundary() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
+
+        // The epoch end block of authority set 200, and the first block after it with a stored
+        // justification, within MAX_NUM_HEADERS of the trusted block.
+        let authority_set_id = 200u64;
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (epoch_end_block, target_block) = rt.block_on(async {
+            let mut fetcher = RpcDataFetcher::new().await;
+            let epoch_end_block = fetcher.last_justified_block(authority_set_id).await;
+            let target_block = fetcher
+                .redis_client
+                .get_blocks_in_range(
+                    &fetcher.avail_chain_id,
+                    epoch_end_block + 1,
+                    epoch_end_block + MAX_NUM_HEADERS as u32 - 4,
+                )
+                .await
+                .first()
+                .copied()
+                .expect("No stored justification after the epoch end block");
+
+            // The epoch end block enacts the next authority set.
+            assert_eq!(
+                fetcher.get_authority_set_id(epoch_end_block - 1).await,
+                authority_set_id
+            );
+            assert_eq!(
+                fetcher.get_authority_set_id(epoch_end_block).await,
+                authority_set_id + 1
+            );
+            (epoch_end_block, target_block)
+        });
+        let trusted_block = epoch_end_block - 4;
+
+        // The epoch end block is signed by the outgoing authority set.
+        prove_header_range_from_chain(trusted_block, epoch_end_block, authority_set_id);
+
+        // A block after the epoch end block is signed by the new authority set, and the range spans
+        // the rotation.
+        prove_header_range_from_chain(trusted_block, target_block, authority_set_id + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_header_range_epoch_boundary_new_authority_set() {
+        // The epoch end block is not signed by the authority set it enacts.
+        let authority_set_id = 200u64;
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let epoch_end_block = rt.block_on(async {
+            let mut fetcher = RpcDataFetcher::new().await;
+            fetcher.last_justified_block(authority_set_id).await
+        });
+        prove_header_range_from_chain(epoch_end_block - 4, epoch_end_block, authority_set_id + 1);
+    }
 }
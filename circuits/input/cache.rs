@@ -0,0 +1,357 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use ethers::types::H256;
+
+use super::types::{HeaderRotateData, SimpleJustificationData};
+
+/// Default number of justifications kept in the in-process justification cache.
+pub const DEFAULT_JUSTIFICATION_CACHE_CAPACITY: usize = 16;
+
+/// Default number of rotate headers kept in the in-process header cache.
+pub const DEFAULT_HEADER_CACHE_CAPACITY: usize = 16;
+
+/// Default number of authority set hashes kept in the in-process authority set hash cache.
+pub const DEFAULT_AUTHORITY_SET_HASH_CACHE_CAPACITY: usize = 16;
+
+/// Key of a value fetched for a block. The caches are shared by all RpcDataFetchers in the process,
+/// so the key also identifies the chain and node the value was fetched from, and the const generics
+/// it was fetched with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockCacheKey {
+    pub avail_chain_id: String,
+    pub avail_url: String,
+    /// The const generics the value was fetched with, e.g. HEADER_LENGTH and VALIDATOR_SET_SIZE_MAX.
+    pub sizes: Vec<usize>,
+    pub block_number: u32,
+}
+
+/// Cache of values fetched for a block, keyed by `BlockCacheKey`. Each value is stored with the
+/// authority set id it was fetched for, and is only returned for that authority set id.
+pub type BlockCache<V> = LruCache<BlockCacheKey, (u64, V)>;
+
+/// Cache of justification data, keyed by block number.
+pub type JustificationCache = BlockCache<SimpleJustificationData>;
+
+/// Cache of rotate header data, keyed by epoch end block number.
+pub type HeaderRotateCache = BlockCache<HeaderRotateData>;
+
+/// Cache of authority set hashes, keyed by chain id and authority set id. The authority set is
+/// constant for an authority set id of a chain, so the hash never goes stale. An authority set change
+/// (including a forced change) always advances the authority set id, so the new authority set is a
+/// cache miss.
+pub type AuthoritySetHashCache = LruCache<(String, u64), H256>;
+
+/// Least recently used cache with a fixed capacity.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, V>,
+    /// Keys ordered from least recently used to most recently used.
+    order: VecDeque<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Cache capacity must be positive");
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self, key: &K) {
+        if let Some(index) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(index).unwrap();
+            self.order.push_back(key);
+        }
+    }
+
+    /// Gets the value for the key, marking it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let value = self.entries.get(key).cloned();
+        if value.is_some() {
+            self.touch(key);
+        }
+        value
+    }
+
+    /// Inserts the value for the key, evicting the least recently used entry if the cache is full.
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.entries.insert(key.clone(), value).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            let evicted = self.order.pop_front().unwrap();
+            self.entries.remove(&evicted);
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let value = self.entries.remove(key);
+        if value.is_some() {
+            self.order.retain(|k| k != key);
+        }
+        value
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+impl<V: Clone> BlockCache<V> {
+    /// Gets the value cached for the block. If the value was cached for a different authority set
+    /// id, it is stale and is evicted.
+    pub fn get_for_authority_set(
+        &mut self,
+        key: &BlockCacheKey,
+        authority_set_id: u64,
+    ) -> Option<V> {
+        match self.get(key) {
+            Some((cached_authority_set_id, value))
+                if cached_authority_set_id == authority_set_id =>
+            {
+                Some(value)
+            }
+            Some(_) => {
+                self.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert_for_authority_set(
+        &mut self,
+        key: BlockCacheKey,
+        authority_set_id: u64,
+        value: V,
+    ) {
+        self.insert(key, (authority_set_id, value));
+    }
+}
+
+/// Gets the value for the block from the cache, or fetches and caches it on a miss. The cache is
+/// not locked while fetching, so it can be shared across concurrent async hint invocations.
+pub async fn get_or_fetch<V, E, F, Fut>(
+    cache: &Mutex<BlockCache<V>>,
+    key: BlockCacheKey,
+    authority_set_id: u64,
+    fetch: F,
+) -> Result<V, E>
+where
+    V: Clone,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<V, E>>,
+{
+    if let Some(value) = cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_for_authority_set(&key, authority_set_id)
+    {
+        return Ok(value);
+    }
+    let value = fetch().await?;
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert_for_authority_set(key, authority_set_id, value.clone());
+    Ok(value)
+}
+
+/// Reads a cache capacity from the environment variable. A capacity of 0 disables the cache.
+fn capacity_from_env(var: &str, default: usize) -> usize {
+    match env::var(var) {
+        Ok(capacity) if !capacity.is_empty() => capacity
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a non-negative integer", var)),
+        _ => default,
+    }
+}
+
+/// The justification cache shared by all RpcDataFetchers in the process, so repeated fetches within
+/// a proving run hit memory. The capacity is read from JUSTIFICATION_CACHE_CAPACITY, and a capacity
+/// of 0 disables the cache.
+pub fn shared_justification_cache() -> Option<Arc<Mutex<JustificationCache>>> {
+    static CACHE: OnceLock<Option<Arc<Mutex<JustificationCache>>>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            let capacity = capacity_from_env(
+                "JUSTIFICATION_CACHE_CAPACITY",
+                DEFAULT_JUSTIFICATION_CACHE_CAPACITY,
+            );
+            (capacity > 0).then(|| Arc::new(Mutex::new(LruCache::new(capacity))))
+        })
+        .clone()
+}
+
+/// The rotate header cache shared by all RpcDataFetchers in the process. The capacity is read from
+/// HEADER_CACHE_CAPACITY, and a capacity of 0 disables the cache.
+pub fn shared_header_rotate_cache() -> Option<Arc<Mutex<HeaderRotateCache>>> {
+    static CACHE: OnceLock<Option<Arc<Mutex<HeaderRotateCache>>>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            let capacity =
+                capacity_from_env("HEADER_CACHE_CAPACITY", DEFAULT_HEADER_CACHE_CAPACITY);
+            (capacity > 0).then(|| Arc::new(Mutex::new(LruCache::new(capacity))))
+        })
+        .clone()
+}
+
+/// The authority set hash cache shared by all RpcDataFetchers in the process. The capacity is read
+/// from AUTHORITY_SET_HASH_CACHE_CAPACITY, and a capacity of 0 disables the cache.
+pub fn shared_authority_set_hash_cache() -> Option<Arc<Mutex<AuthoritySetHashCache>>> {
+    static CACHE: OnceLock<Option<Arc<Mutex<AuthoritySetHashCache>>>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            let capacity = capacity_from_env(
+                "AUTHORITY_SET_HASH_CACHE_CAPACITY",
+                DEFAULT_AUTHORITY_SET_HASH_CACHE_CAPACITY,
+            );
+            (capacity > 0).then(|| Arc::new(Mutex::new(LruCache::new(capacity))))
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn block_key(avail_chain_id: &str, sizes: &[usize], block_number: u32) -> BlockCacheKey {
+        BlockCacheKey {
+            avail_chain_id: avail_chain_id.to_string(),
+            avail_url: "ws://localhost:9944".to_string(),
+            sizes: sizes.to_vec(),
+            block_number,
+        }
+    }
+
+    #[test]
+    fn test_lru_cache_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+
+        // Using 1 makes 2 the least recently used entry.
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "c");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&3), Some("c"));
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch_hits_cache() {
+        let cache = Mutex::new(JustificationCache::new(2));
+        let rpc_calls = AtomicUsize::new(0);
+        let mock_fetch = || async {
+            rpc_calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, String>(SimpleJustificationData {
+                pubkeys: vec![],
+                signatures: vec![],
+                validator_signed: vec![],
+                signed_message: vec![1u8; 53],
+                weights: vec![],
+                voting_weight: 0,
+                num_authorities: 0,
+                authority_set_id: 5,
+                round: 1,
+                authority_set_hash: None,
+            })
+        };
+
+        let key = block_key("couscous", &[8], 100);
+        let first = get_or_fetch(&cache, key.clone(), 5, mock_fetch)
+            .await
+            .unwrap();
+        let second = get_or_fetch(&cache, key.clone(), 5, mock_fetch)
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(rpc_calls.load(Ordering::SeqCst), 1);
+
+        // A different authority set id for the same block invalidates the cached value.
+        get_or_fetch(&cache, key, 6, mock_fetch).await.unwrap();
+        assert_eq!(rpc_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_block_cache_invalidates_on_authority_set_change() {
+        let mut cache = BlockCache::new(2);
+        let key = block_key("couscous", &[8], 100);
+        cache.insert_for_authority_set(key.clone(), 5, "header");
+        assert_eq!(cache.get_for_authority_set(&key, 5), Some("header"));
+
+        // A stale entry is evicted on lookup.
+        assert_eq!(cache.get_for_authority_set(&key, 6), None);
+        assert!(cache.is_empty());
+        assert_eq!(cache.get_for_authority_set(&key, 5), None);
+
+        cache.insert_for_authority_set(key.clone(), 5, "header");
+        cache.insert_for_authority_set(block_key("couscous", &[8], 101), 5, "header");
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(
+            cache.get_for_authority_set(&block_key("couscous", &[8], 101), 5),
+            None
+        );
+    }
+
+    #[test]
+    fn test_block_cache_keyed_by_chain_and_sizes() {
+        let mut cache = BlockCache::new(4);
+        cache.insert_for_authority_set(block_key("couscous", &[8], 100), 5, "couscous");
+
+        // The same block of another chain, or fetched with other const generics, is a miss.
+        assert_eq!(
+            cache.get_for_authority_set(&block_key("goldberg", &[8], 100), 5),
+            None
+        );
+        assert_eq!(
+            cache.get_for_authority_set(&block_key("couscous", &[16], 100), 5),
+            None
+        );
+        assert_eq!(
+            cache.get_for_authority_set(&block_key("couscous", &[8], 100), 5),
+            Some("couscous")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch_does_not_cache_errors() {
+        let cache = Mutex::new(BlockCache::<u32>::new(2));
+        let rpc_calls = AtomicUsize::new(0);
+        let failing_fetch = || async {
+            rpc_calls.fetch_add(1, Ordering::SeqCst);
+            Err::<u32, _>("not found")
+        };
+
+        let key = block_key("couscous", &[8], 1);
+        assert!(get_or_fetch(&cache, key.clone(), 0, failing_fetch)
+            .await
+            .is_err());
+        assert!(get_or_fetch(&cache, key, 0, failing_fetch).await.is_err());
+        assert_eq!(rpc_calls.load(Ordering::SeqCst), 2);
+        assert!(cache.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fs;
+
+use async_trait::async_trait;
+use avail_subxt::primitives::Header;
+use codec::Decode;
+use plonky2x::frontend::curta::ec::point::CompressedEdwardsY;
+use serde::{Deserialize, Serialize};
+use sp_core::Bytes;
+
+use super::types::{
+    ChainStatus, FetchError, HeaderRotateData, SimpleJustificationData, StoredJustificationData,
+};
+use super::{check_simple_justification, header_rotate_data, DataFetcher};
+
+/// A stored justification, with the id of the authority set that signed it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JustificationFixture {
+    pub authority_set_id: u64,
+    pub justification: StoredJustificationData,
+}
+
+/// Chain data served by a `MockDataFetcher`, keyed by block number, or by authority set id for the
+/// epoch end blocks.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub justifications: HashMap<u32, JustificationFixture>,
+    /// Authorities in storage order.
+    #[serde(default)]
+    pub authorities: HashMap<u32, Vec<Bytes>>,
+    /// SCALE-encoded headers.
+    #[serde(default)]
+    pub headers: HashMap<u32, Bytes>,
+    /// Epoch end block of each authority set id that has ended.
+    #[serde(default)]
+    pub epoch_end_blocks: HashMap<u64, u32>,
+    /// Status of the chain reported by `health_check`.
+    #[serde(default)]
+    pub chain_status: Option<ChainStatus>,
+}
+
+/// Data fetcher serving fixtures instead of fetching from an Avail node and Redis, so the hints can
+/// run offline with `DataSource::fixtures`. Justifications and rotate headers are checked the same way as by `RpcDataFetcher`.
+#[derive(Clone, Default)]
+pub struct MockDataFetcher {
+    pub fixtures: Fixtures,
+}
+
+impl MockDataFetcher {
+    pub fn new(fixtures: Fixtures) -> Self {
+        MockDataFetcher { fixtures }
+    }
+
+    /// Loads the fixtures from the JSON file at path.
+    pub fn from_file(path: &str) -> Self {
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read fixtures {}: {}", path, e));
+        let fixtures = serde_json::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse fixtures {}: {}", path, e));
+        MockDataFetcher { fixtures }
+    }
+
+    pub fn get_header(&self, block_number: u32) -> Result<Header, FetchError> {
+        let encoded = self.fixtures.headers.get(&block_number).ok_or_else(|| {
+            FetchError::Rpc(format!("No header fixture for block {}", block_number))
+        })?;
+        Header::decode(&mut &encoded.0[..]).map_err(|e| {
+            FetchError::Rpc(format!(
+                "Failed to decode header fixture for block {}: {}",
+                block_number, e
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl DataFetcher for MockDataFetcher {
+    async fn try_get_simple_justification<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        block_number: u32,
+    ) -> Result<SimpleJustificationData, FetchError> {
+        let fixture = self
+            .fixtures
+            .justifications
+            .get(&block_number)
+            .cloned()
+            .ok_or(FetchError::NoJustificationStored { block_number })?;
+        let data =
+            SimpleJustificationData::from_stored(fixture.justification, fixture.authority_set_id);
+        check_simple_justification::<VALIDATOR_SET_SIZE_MAX>(
+            block_number,
+            data,
+            fixture.authority_set_id,
+        )
+    }
+
+    async fn get_header_rotate<const HEADER_LENGTH: usize, const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        epoch_end_block: u32,
+    ) -> Result<HeaderRotateData, FetchError> {
+        if !self.is_epoch_end_block(epoch_end_block).await {
+            return Err(FetchError::NotEpochEndBlock {
+                block_number: epoch_end_block,
+            });
+        }
+        let header = self.get_header(epoch_end_block)?;
+        let new_authorities = self.get_authorities(epoch_end_block).await;
+        header_rotate_data::<HEADER_LENGTH, VALIDATOR_SET_SIZE_MAX>(
+            epoch_end_block,
+            &header,
+            &new_authorities,
+        )
+    }
+
+    async fn get_authorities(&mut self, block_number: u32) -> Vec<CompressedEdwardsY> {
+        self.fixtures
+            .authorities
+            .get(&block_number)
+            .unwrap_or_else(|| panic!("No authorities fixture for block {}", block_number))
+            .iter()
+            .map(|pubkey| CompressedEdwardsY::from_slice(&pubkey.0).unwrap())
+            .collect()
+    }
+
+    async fn last_justified_block(&mut self, target_authority_set_id: u64) -> u32 {
+        self.fixtures
+            .epoch_end_blocks
+            .get(&target_authority_set_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    async fn is_epoch_end_block(&mut self, block_number: u32) -> bool {
+        block_number != 0
+            && self
+                .fixtures
+                .epoch_end_blocks
+                .values()
+                .any(|epoch_end_block| *epoch_end_block == block_number)
+    }
+
+    async fn latest_justified_block(&mut self, authority_set_id: u64) -> Option<u32> {
+        self.fixtures
+            .justifications
+            .iter()
+            .filter(|(_, fixture)| fixture.authority_set_id == authority_set_id)
+            .map(|(block_number, _)| *block_number)
+            .max()
+    }
+
+    async fn try_get_header(&mut self, block_number: u32) -> Result<Header, FetchError> {
+        self.get_header(block_number)
+    }
+
+    async fn health_check(&mut self) -> Result<ChainStatus, FetchError> {
+        self.fixtures
+            .chain_status
+            .ok_or_else(|| FetchError::Rpc("No chain status fixture".to_string()))?
+            .check_synced()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::verify_justification_signatures;
+
+    const FIXTURES_272535: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/circuits/fixtures/272535.json");
+
+    #[tokio::test]
+    async fn test_mock_simple_justification() {
+        let mut fetcher = MockDataFetcher::from_file(FIXTURES_272535);
+
+        let data = fetcher
+            .try_get_simple_justification::<8>(272535)
+            .await
+            .unwrap();
+        assert_eq!(data.authority_set_id, 1);
+        assert_eq!(data.num_authorities, 4);
+
+        let stored = fetcher.fixtures.justifications[&272535]
+            .justification
+            .clone();
+        assert_eq!(verify_justification_signatures(&stored), Ok(4));
+
+        let justification = fetcher
+            .get_justification_from_block::<8>(272535)
+            .await
+            .unwrap();
+        assert_eq!(justification.pubkeys.len(), 8);
+        assert_eq!(justification.num_authorities, 4);
+    }
+
+    #[tokio::test]
+    async fn test_mock_simple_justification_errors() {
+        let mut fetcher = MockDataFetcher::from_file(FIXTURES_272535);
+
+        assert_eq!(
+            fetcher.try_get_simple_justification::<8>(272536).await,
+            Err(FetchError::NoJustificationStored {
+                block_number: 272536
+            })
+        );
+        assert_eq!(
+            fetcher.try_get_simple_justification::<2>(272535).await,
+            Err(FetchError::TooManyAuthorities {
+                block_number: 272535,
+                num_authorities: 4,
+                max_num_authorities: 2,
+            })
+        );
+
+        // A corrupt stored signature is reported with the index of its validator.
+        fetcher
+            .fixtures
+            .justifications
+            .get_mut(&272535)
+            .unwrap()
+            .justification
+            .signatures[2]
+            .pop();
+        assert_eq!(
+            fetcher.try_get_simple_justification::<8>(272535).await,
+            Err(FetchError::InvalidSignatureLength {
+                block_number: 272535,
+                index: 2,
+                length: 63,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_health_check() {
+        let mut fetcher = MockDataFetcher::default();
+        assert!(matches!(
+            fetcher.health_check().await,
+            Err(FetchError::Rpc(_))
+        ));
+
+        let synced = ChainStatus {
+            finalized_block_number: 272535,
+            best_block_number: 272537,
+            is_syncing: false,
+            peers: 8,
+        };
+        fetcher.fixtures.chain_status = Some(synced);
+        assert_eq!(fetcher.health_check().await, Ok(synced));
+
+        // A node that is still syncing is not healthy, even if it has finalized blocks.
+        fetcher.fixtures.chain_status = Some(ChainStatus {
+            is_syncing: true,
+            ..synced
+        });
+        assert_eq!(
+            fetcher.health_check().await,
+            Err(FetchError::NodeNotSynced {
+                finalized_block_number: 272535,
+                best_block_number: 272537,
+            })
+        );
+
+        // A warp syncing node has not finalized a block after genesis yet.
+        fetcher.fixtures.chain_status = Some(ChainStatus {
+            finalized_block_number: 0,
+            best_block_number: 0,
+            is_syncing: false,
+            peers: 8,
+        });
+        assert_eq!(
+            fetcher.health_check().await,
+            Err(FetchError::NodeNotSynced {
+                finalized_block_number: 0,
+                best_block_number: 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_not_epoch_end_block() {
+        let mut fetcher = MockDataFetcher::default();
+        fetcher.fixtures.epoch_end_blocks.insert(0, 4321);
+
+        assert_eq!(fetcher.last_justified_block(0).await, 4321);
+        assert_eq!(fetcher.last_justified_block(1).await, 0);
+        assert!(fetcher.is_epoch_end_block(4321).await);
+        assert!(!fetcher.is_epoch_end_block(0).await);
+        assert_eq!(
+            fetcher.get_header_rotate::<1024, 8>(4320).await.err(),
+            Some(FetchError::NotEpochEndBlock { block_number: 4320 })
+        );
+    }
+}
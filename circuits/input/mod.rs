@@ -1,41 +1,72 @@
+pub mod cache;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod retry;
 pub mod types;
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
 use alloy_sol_types::{sol, SolType};
 use anyhow::Error;
+use async_trait::async_trait;
 use avail_subxt::avail::Client;
 use avail_subxt::config::substrate::DigestItem;
 use avail_subxt::primitives::Header;
 use avail_subxt::subxt_rpc::RpcParams;
 use avail_subxt::{api, build_client};
 use codec::{Compact, Decode, Encode};
+use deadpool_redis::{Config as RedisPoolConfig, Connection, Pool, PoolConfig, Runtime};
 use ed25519_dalek::{PublicKey, Signature, Verifier};
 use ethers::types::H256;
 use futures::future::join_all;
-use log::{debug, info};
+use log::{debug, info, warn};
 use plonky2x::frontend::curta::ec::point::CompressedEdwardsY;
 use plonky2x::frontend::ecc::curve25519::ed25519::eddsa::{DUMMY_PUBLIC_KEY, DUMMY_SIGNATURE};
-use redis::aio::Connection;
+use plonky2x::prelude::plonky2::field::types::{Field, PrimeField64};
+use plonky2x::prelude::plonky2::hash::poseidon::PoseidonHash;
+use plonky2x::prelude::plonky2::plonk::config::Hasher;
+use plonky2x::prelude::{GoldilocksField, RichField};
 use redis::{AsyncCommands, JsonAsyncCommands};
 use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
 use tokio::time::sleep;
 
+use self::cache::{
+    get_or_fetch, shared_authority_set_hash_cache, shared_header_rotate_cache,
+    shared_justification_cache, AuthoritySetHashCache, BlockCacheKey, HeaderRotateCache,
+    JustificationCache,
+};
+use self::retry::{connect_in_order, parse_urls, retry_with_backoff, RetryPolicy};
 use self::types::{
-    CircuitJustification, EncodedFinalityProof, FinalityProof, GrandpaJustification,
-    HeaderRotateData, SignerMessage, SimpleJustificationData, StoredJustificationData,
+    AuthoritySetChangeLog, ChainStatus, CircuitJustification, CommitmentHash, EncodedFinalityProof,
+    FetchError, FinalityProof, GrandpaJustification, HeaderRotateData, SignerMessage,
+    SimpleJustificationData, StoredJustificationData, VerifyError,
 };
 use crate::consts::{
-    BASE_PREFIX_LENGTH, DELAY_LENGTH, HASH_SIZE, MAX_NUM_HEADERS, PUBKEY_LENGTH, VALIDATOR_LENGTH,
-    WEIGHT_LENGTH,
+    BASE_PREFIX_LENGTH, DATA_ROOT_OFFSET_FROM_END, DEFAULT_AVAIL_URL, DELAY_LENGTH,
+    ENCODED_PRECOMMIT_LENGTH, FORCED_CHANGE_FLAG, FORCED_CHANGE_MEDIAN_LENGTH, HASH_SIZE,
+    MAX_CONCURRENT_HEADER_REQUESTS, MAX_NUM_HEADERS, PAUSE_FLAG, PUBKEY_LENGTH, RESUME_FLAG,
+    SCHEDULED_CHANGE_FLAG, VALIDATOR_LENGTH,
 };
-
+use crate::vars::EncodedHeader;
+
+/// Client for the justifications and data commitments stored in Redis. Connections are taken from a
+/// pool shared by all RedisClients in the process, so cloning the client or creating a new one
+/// reuses the pooled connections instead of reconnecting.
+///
+/// All keys are prefixed with the Avail chain id passed to each method (AVAIL_CHAIN_ID for the
+/// indexer and operator), e.g. `<avail_chain_id>:justification:<block_number>`, so indexers for
+/// different chains can share one Redis instance.
 #[derive(Clone)]
 pub struct RedisClient {
-    pub redis: redis::Client,
+    pub pool: Pool,
+    /// Time to live of stored justifications, in seconds. If None, justifications never expire.
+    pub justification_ttl: Option<u64>,
 }
 
 pub struct DataCommitmentRange {
@@ -49,18 +80,64 @@ type DataCommitmentRangeTuple = sol! { tuple(uint32, uint32, bytes32) };
 impl RedisClient {
     const MAX_RECONNECT_ATTEMPTS: usize = 3;
     const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+    /// Default time to live of stored justifications. Conservative, so that justifications of blocks
+    /// that have not been proven yet do not expire.
+    pub const DEFAULT_JUSTIFICATION_TTL_SECONDS: u64 = 30 * 24 * 60 * 60;
+    /// Default maximum number of connections in the Redis connection pool.
+    pub const DEFAULT_MAX_CONNECTIONS: usize = 16;
 
     pub async fn new() -> Self {
         dotenv::dotenv().ok();
 
-        let redis_url = env::var("REDIS_URL").expect("REDIS_URL must be set");
-        let redis = redis::Client::open(redis_url).expect("Redis client not created");
-        RedisClient { redis }
+        let pool = Self::shared_pool();
+        // A TTL of 0 disables expiry.
+        let justification_ttl = env::var("JUSTIFICATION_TTL_SECONDS")
+            .ok()
+            .filter(|ttl| !ttl.is_empty())
+            .map(|ttl| {
+                ttl.parse::<u64>()
+                    .expect("JUSTIFICATION_TTL_SECONDS must be a number of seconds")
+            })
+            .unwrap_or(Self::DEFAULT_JUSTIFICATION_TTL_SECONDS);
+        RedisClient {
+            pool,
+            justification_ttl: Some(justification_ttl).filter(|ttl| *ttl > 0),
+        }
+    }
+
+    /// The connection pool shared by all RedisClients in the process. Connects to REDIS_URL, with at
+    /// most REDIS_MAX_CONNECTIONS connections. When all connections are in use, callers wait for a
+    /// connection to be returned to the pool.
+    fn shared_pool() -> Pool {
+        static POOL: OnceLock<Pool> = OnceLock::new();
+        POOL.get_or_init(|| {
+            let redis_url = env::var("REDIS_URL").expect("REDIS_URL must be set");
+            let max_connections = env::var("REDIS_MAX_CONNECTIONS")
+                .ok()
+                .filter(|max_connections| !max_connections.is_empty())
+                .map(|max_connections| {
+                    max_connections
+                        .parse::<usize>()
+                        .expect("REDIS_MAX_CONNECTIONS must be a number")
+                })
+                .unwrap_or(Self::DEFAULT_MAX_CONNECTIONS);
+            assert!(
+                max_connections > 0,
+                "REDIS_MAX_CONNECTIONS must be positive"
+            );
+
+            let mut config = RedisPoolConfig::from_url(redis_url);
+            config.pool = Some(PoolConfig::new(max_connections));
+            config
+                .create_pool(Some(Runtime::Tokio1))
+                .expect("Redis pool not created")
+        })
+        .clone()
     }
 
     pub async fn get_connection(&mut self) -> Result<Connection, String> {
         for i in 0..Self::MAX_RECONNECT_ATTEMPTS {
-            match self.redis.get_async_connection().await {
+            match self.pool.get().await {
                 Ok(con) => return Ok(con),
                 Err(e) => {
                     // Log the retry attempt and error.
@@ -78,12 +155,47 @@ impl RedisClient {
         Err("Failed to connect to Redis after multiple attempts!".to_string())
     }
 
-    /// Stores justification data in Redis. Errors if setting the key fails.
+    /// Returns true if a justification is stored in Redis for the block.
+    pub async fn justification_exists(&mut self, avail_chain_id: &str, block_number: u32) -> bool {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!("{}:justification:{}", avail_chain_id, block_number);
+
+        con.exists(key).await.expect("Failed to check key")
+    }
+
+    /// Stores justification data in Redis. GRANDPA can emit more than one justification for a block,
+    /// so if a justification is already stored for the block, it is only overwritten as in
+    /// `should_replace_justification`. Returns true if the justification was stored. Errors if
+    /// setting the key fails.
     pub async fn add_justification(
         &mut self,
         avail_chain_id: &str,
         justification: StoredJustificationData,
-    ) {
+    ) -> bool {
+        if self
+            .justification_exists(avail_chain_id, justification.block_number)
+            .await
+        {
+            if let Ok(stored) = self
+                .get_justification(avail_chain_id, justification.block_number)
+                .await
+            {
+                if !should_replace_justification(&stored, &justification) {
+                    debug!(
+                        "Justification for block {} with {} signatures in round {:?} is already stored, skipping",
+                        justification.block_number,
+                        stored.num_signatures(),
+                        stored.round
+                    );
+                    return false;
+                }
+            }
+        }
+
         let mut con = match self.get_connection().await {
             Ok(con) => con,
             Err(e) => panic!("{}", e),
@@ -96,10 +208,17 @@ impl RedisClient {
 
         // Justification is stored as a JSON object.
         let _: () = con
-            .json_set(justification_key, "$", &justification)
+            .json_set(&justification_key, "$", &justification)
             .await
             .expect("Failed to set key");
 
+        if let Some(ttl) = self.justification_ttl {
+            let _: () = con
+                .expire(&justification_key, ttl as usize)
+                .await
+                .expect("Failed to set expiry on key");
+        }
+
         let sorted_block_key = format!("{}:justification:blocks", avail_chain_id);
 
         // Add the block number to a sorted set, so we can query for all blocks with justifications.
@@ -115,7 +234,8 @@ impl RedisClient {
         debug!(
             "Added justification for block {:?}",
             justification.block_number
-        )
+        );
+        true
     }
 
     /// Gets justification data from Redis. Errors if getting the key fails.
@@ -132,8 +252,10 @@ impl RedisClient {
         let key = format!("{}:justification:{}", avail_chain_id, block_number);
 
         // Result is always stored as serialized bytes: https://github.com/redis-rs/redis-rs#json-support.
-        let serialized_justification: Vec<u8> =
-            con.json_get(key, "$").await.expect("Failed to get key");
+        let serialized_justification: Vec<u8> = con
+            .json_get(key, "$")
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get justification: {}", e))?;
 
         match serde_json::from_slice::<Vec<StoredJustificationData>>(&serialized_justification) {
             Ok(justification) => Ok(justification[0].clone()),
@@ -144,7 +266,8 @@ impl RedisClient {
         }
     }
 
-    /// Gets all blocks in range [start, end] (inclusive) that have justifications in Redis.
+    /// Gets all blocks in range [start, end] (inclusive) that have justifications in Redis. Blocks
+    /// whose justification has expired are removed from the sorted set of blocks, and skipped.
     pub async fn get_blocks_in_range(
         &mut self,
         avail_chain_id: &str,
@@ -158,9 +281,252 @@ impl RedisClient {
 
         let key = format!("{}:justification:blocks", avail_chain_id);
 
-        con.zrangebyscore(key, start, end)
+        let blocks: Vec<u32> = con
+            .zrangebyscore(&key, start, end)
+            .await
+            .expect("Failed to get keys");
+        if blocks.is_empty() {
+            return blocks;
+        }
+
+        // A justification key expires without its block being removed from the sorted set, so
+        // check the justification of each block is still stored.
+        let mut pipe = redis::pipe();
+        for block in blocks.iter() {
+            pipe.exists(format!("{}:justification:{}", avail_chain_id, block));
+        }
+        let exists: Vec<bool> = pipe
+            .query_async(&mut con)
+            .await
+            .expect("Failed to check keys");
+        let (stored, expired): (Vec<_>, Vec<_>) = blocks
+            .into_iter()
+            .zip(exists)
+            .partition(|(_, exists)| *exists);
+
+        if !expired.is_empty() {
+            let expired = expired
+                .into_iter()
+                .map(|(block, _)| block)
+                .collect::<Vec<_>>();
+            debug!(
+                "Removing {} blocks with expired justifications from {}",
+                expired.len(),
+                key
+            );
+            let _: () = con
+                .zrem(&key, expired)
+                .await
+                .expect("Failed to remove keys from sorted set");
+        }
+        stored.into_iter().map(|(block, _)| block).collect()
+    }
+
+    /// Gets the stored justifications for all blocks in range [start, end] (inclusive), in ascending
+    /// block order. The justifications are fetched in a single pipelined call, and blocks without a
+    /// stored justification (e.g. expired) are skipped. A justification that fails to deserialize
+    /// is logged and skipped, so the rest of the range is still returned. An empty range (no
+    /// stored blocks, or start > end) returns an empty Vec.
+    pub async fn get_justifications_in_range(
+        &mut self,
+        avail_chain_id: &str,
+        start: u32,
+        end: u32,
+    ) -> Vec<StoredJustificationData> {
+        if start > end {
+            return Vec::new();
+        }
+        let blocks = self.get_blocks_in_range(avail_chain_id, start, end).await;
+        if blocks.is_empty() {
+            return Vec::new();
+        }
+
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let mut pipe = redis::pipe();
+        for block in blocks.iter() {
+            pipe.cmd("JSON.GET")
+                .arg(format!("{}:justification:{}", avail_chain_id, block))
+                .arg("$");
+        }
+        // A missing key is returned as nil.
+        let serialized_justifications: Vec<Option<Vec<u8>>> = pipe
+            .query_async(&mut con)
+            .await
+            .expect("Failed to get justifications");
+
+        let mut justifications = blocks
+            .iter()
+            .zip(serialized_justifications)
+            .filter_map(|(block, serialized_justification)| {
+                let serialized_justification = serialized_justification?;
+                match serde_json::from_slice::<Vec<StoredJustificationData>>(
+                    &serialized_justification,
+                ) {
+                    Ok(justification) => justification.into_iter().next(),
+                    Err(e) => {
+                        warn!(
+                            "Skipping justification for block {}, failed to deserialize: {}",
+                            block, e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        justifications.sort_by_key(|justification| justification.block_number);
+        justifications
+    }
+
+    /// Deletes all stored justifications for blocks below the watermark block number. The caller
+    /// must only pass a watermark below which all justifications have been consumed by a prover.
+    /// Returns the number of justifications deleted.
+    pub async fn prune_older_than(&mut self, avail_chain_id: &str, block_number: u32) -> usize {
+        if block_number == 0 {
+            return 0;
+        }
+
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let sorted_block_key = format!("{}:justification:blocks", avail_chain_id);
+
+        let blocks: Vec<u32> = con
+            .zrangebyscore(&sorted_block_key, 0, block_number - 1)
+            .await
+            .expect("Failed to get keys");
+        if blocks.is_empty() {
+            return 0;
+        }
+
+        let justification_keys: Vec<String> = blocks
+            .iter()
+            .map(|block| format!("{}:justification:{}", avail_chain_id, block))
+            .collect();
+        let _: () = con
+            .del(justification_keys)
+            .await
+            .expect("Failed to delete keys");
+        let _: () = con
+            .zrembyscore(&sorted_block_key, 0, block_number - 1)
             .await
-            .expect("Failed to get keys")
+            .expect("Failed to remove keys from sorted set");
+
+        debug!(
+            "Pruned {} justifications below block {}",
+            blocks.len(),
+            block_number
+        );
+        blocks.len()
+    }
+
+    /// Gets the prune watermark, the block number below which all justifications have been
+    /// consumed by a prover. Returns None if no watermark has been set.
+    pub async fn get_prune_watermark(&mut self, avail_chain_id: &str) -> Option<u32> {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!("{}:justification:watermark", avail_chain_id);
+
+        con.get(key).await.expect("Failed to get key")
+    }
+
+    /// Sets the prune watermark, the block number below which all justifications have been consumed
+    /// by a prover. Errors if setting the key fails.
+    pub async fn set_prune_watermark(&mut self, avail_chain_id: &str, block_number: u32) {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!("{}:justification:watermark", avail_chain_id);
+
+        let _: () = con.set(key, block_number).await.expect("Failed to set key");
+    }
+
+    /// Gets the indexer cursor, the block number of the last justification saved by the indexer.
+    /// Returns None if the indexer has not saved a justification yet.
+    pub async fn get_indexer_cursor(&mut self, avail_chain_id: &str) -> Option<u32> {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!("{}:indexer:cursor", avail_chain_id);
+
+        con.get(key).await.expect("Failed to get key")
+    }
+
+    /// Sets the indexer cursor, the block number of the last justification saved by the indexer.
+    pub async fn set_indexer_cursor(&mut self, avail_chain_id: &str, block_number: u32) {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!("{}:indexer:cursor", avail_chain_id);
+
+        let _: () = con.set(key, block_number).await.expect("Failed to set key");
+    }
+
+    /// Gets the last justification round stored for an authority set id. Returns None if no round
+    /// has been stored for the authority set id.
+    pub async fn get_last_justification_round(
+        &mut self,
+        avail_chain_id: &str,
+        authority_set_id: u64,
+    ) -> Option<u64> {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!(
+            "{}:justification:round:{}",
+            avail_chain_id, authority_set_id
+        );
+
+        con.get(key).await.expect("Failed to get key")
+    }
+
+    /// Stores the last justification round for an authority set id. Errors if setting the key fails.
+    pub async fn set_last_justification_round(
+        &mut self,
+        avail_chain_id: &str,
+        authority_set_id: u64,
+        round: u64,
+    ) {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!(
+            "{}:justification:round:{}",
+            avail_chain_id, authority_set_id
+        );
+
+        let _: () = con.set(key, round).await.expect("Failed to set key");
+    }
+
+    /// Increments the count of justifications received with a round that did not increase within
+    /// the same authority set. Returns the updated count.
+    pub async fn increment_round_regressions(&mut self, avail_chain_id: &str) -> u64 {
+        let mut con = match self.get_connection().await {
+            Ok(con) => con,
+            Err(e) => panic!("{}", e),
+        };
+
+        let key = format!("{}:metrics:justification_round_regressions", avail_chain_id);
+
+        con.incr(key, 1).await.expect("Failed to increment key")
     }
 
     /// Stores data commitment range data in Redis. Errors if setting the key fails.
@@ -199,28 +565,144 @@ impl RedisClient {
     }
 }
 
+/// Returns true if the Ed25519 signature is valid. Returns false if the signature is not valid, or
+/// the pubkey or signature are malformed.
+pub fn is_signature_valid(
+    pubkey_bytes: &[u8],
+    signed_message: &[u8],
+    signature: &[u8; 64],
+) -> bool {
+    let Ok(pubkey_dalek) = PublicKey::from_bytes(pubkey_bytes) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_bytes(signature) else {
+        return false;
+    };
+    pubkey_dalek.verify(signed_message, &signature).is_ok()
+}
+
 /// This function is useful for verifying that a Ed25519 signature is valid, it will panic if the signature is not valid
 pub fn verify_signature(pubkey_bytes: &[u8], signed_message: &[u8], signature: &[u8; 64]) {
-    let pubkey_dalek = PublicKey::from_bytes(pubkey_bytes).unwrap();
-    let verified = pubkey_dalek.verify(signed_message, &Signature::from_bytes(signature).unwrap());
-    if verified.is_err() {
+    if !is_signature_valid(pubkey_bytes, signed_message, signature) {
         panic!("Signature is not valid");
     }
 }
 
-// Compute the chained hash of the authority set.
+/// Verifies the signatures of a stored justification on its signed message, without any RPC or
+/// async runtime, and returns the number of valid signatures. Authorities that did not sign, or
+/// whose signature is the dummy signature, are not counted. Errors with the index of the first
+/// authority that is marked as signed and has an invalid signature.
+pub fn verify_justification_signatures(
+    data: &StoredJustificationData,
+) -> Result<usize, VerifyError> {
+    if data.pubkeys.len() != data.num_authorities
+        || data.signatures.len() != data.num_authorities
+        || data.validator_signed.len() != data.num_authorities
+    {
+        return Err(VerifyError::LengthMismatch {
+            num_authorities: data.num_authorities,
+            num_pubkeys: data.pubkeys.len(),
+            num_signatures: data.signatures.len(),
+            num_validator_signed: data.validator_signed.len(),
+        });
+    }
+
+    let mut num_valid = 0;
+    for i in 0..data.num_authorities {
+        if !data.validator_signed[i] || data.signatures[i] == DUMMY_SIGNATURE {
+            continue;
+        }
+        let is_valid = <[u8; 64]>::try_from(data.signatures[i].as_slice())
+            .map(|signature| is_signature_valid(&data.pubkeys[i], &data.signed_message, &signature))
+            .unwrap_or(false);
+        if !is_valid {
+            return Err(VerifyError::InvalidSignature { index: i });
+        }
+        num_valid += 1;
+    }
+    Ok(num_valid)
+}
+
+// Compute the chained hash of the authority set, which is the weighted hash with all weights 1.
 pub fn compute_authority_set_hash(authorities: &[CompressedEdwardsY]) -> Vec<u8> {
+    compute_weighted_authority_set_hash(authorities, &vec![1; authorities.len()])
+}
+
+/// Compute the authority set commitment SHA256(...SHA256(SHA256(pk0) || pk1)... || pkn) of the
+/// public keys, as the circuit does in `compute_authority_set_commitment`. This is the commitment
+/// stored by the indexer for an authority set with all weights 1. The circuit rejects an empty
+/// authority set, so this panics if pubkeys is empty.
+pub fn compute_authority_set_commitment(pubkeys: &[[u8; 32]]) -> [u8; 32] {
+    assert!(
+        !pubkeys.is_empty(),
+        "Authority set commitment of an empty authority set"
+    );
+    let authorities = pubkeys
+        .iter()
+        .map(|pubkey| CompressedEdwardsY(*pubkey))
+        .collect::<Vec<_>>();
+    compute_authority_set_hash(&authorities).try_into().unwrap()
+}
+
+/// Compute the chained hash of the authority set with the hash function, as the circuit does in
+/// `compute_authority_set_commitment_with_hash`.
+pub fn compute_authority_set_hash_with(
+    authorities: &[CompressedEdwardsY],
+    hash: CommitmentHash,
+) -> Vec<u8> {
+    match hash {
+        CommitmentHash::Sha256 => compute_authority_set_hash(authorities),
+        CommitmentHash::Poseidon => compute_poseidon_authority_set_hash(authorities),
+    }
+}
+
+/// Compute the chained Poseidon hash of the authority set. Each pubkey is packed into 8 field
+/// elements, the little-endian u32 of each 4 bytes, which are appended to the 4 field elements of
+/// the hash so far. The hash is encoded as the 8 big-endian bytes of each field element.
+pub fn compute_poseidon_authority_set_hash(authorities: &[CompressedEdwardsY]) -> Vec<u8> {
+    let mut hash_so_far: Vec<GoldilocksField> = Vec::new();
+    for authority in authorities {
+        let mut input = hash_so_far.clone();
+        input.extend(authority.as_bytes().chunks(4).map(|chunk| {
+            GoldilocksField::from_canonical_u32(u32::from_le_bytes(chunk.try_into().unwrap()))
+        }));
+        hash_so_far = PoseidonHash::hash_no_pad(&input).elements.to_vec();
+    }
+    hash_so_far
+        .iter()
+        .flat_map(|element| element.to_canonical_u64().to_be_bytes())
+        .collect()
+}
+
+// Compute the chained SHA256 hash of an authority set with voting weights. Authorities with a
+// weight of 1 are hashed by their public key alone, and authorities with any other weight are
+// hashed with their little-endian encoded weight appended to the public key. This is the only
+// off-circuit implementation of the chained SHA256 commitment, which the other helpers call.
+pub fn compute_weighted_authority_set_hash(
+    authorities: &[CompressedEdwardsY],
+    weights: &[u64],
+) -> Vec<u8> {
+    assert_eq!(authorities.len(), weights.len());
     let mut hash_so_far = Vec::new();
     for i in 0..authorities.len() {
-        let authority = authorities[i];
         let mut hasher = sha2::Sha256::new();
         hasher.update(hash_so_far);
-        hasher.update(authority.as_bytes());
+        hasher.update(authorities[i].as_bytes());
+        if weights[i] != 1 {
+            hasher.update(weights[i].to_le_bytes());
+        }
         hash_so_far = hasher.finalize().to_vec();
     }
     hash_so_far
 }
 
+// Returns true if the signed voting weight is more than 2/3 of the total voting weight. This is the
+// threshold the justification circuit checks. The weights are scaled as u128s, so they can't
+// overflow.
+pub fn is_voting_threshold_met(signed_weight: u64, total_weight: u64) -> bool {
+    signed_weight as u128 * 3 > total_weight as u128 * 2
+}
+
 pub fn decode_precommit(precommit: Vec<u8>) -> (H256, u32, u64, u64) {
     // The first byte should be a 1.
     assert_eq!(precommit[0], 1);
@@ -251,93 +733,960 @@ pub fn decode_precommit(precommit: Vec<u8>) -> (H256, u32, u64, u64) {
     )
 }
 
-#[derive(Clone)]
-pub struct RpcDataFetcher {
-    pub client: Client,
-    pub avail_url: String,
-    pub avail_chain_id: String,
-    pub redis_client: RedisClient,
-    pub save: Option<String>,
+/// Builds the justification data for a block from the valid signatures of the justification.
+///
+/// The pubkeys, signatures and validator_signed flags are in the order of the authority set in
+/// storage, regardless of the order of the precommits in the justification. This is the same order
+/// as the encoded authority set in the epoch end header, which the authority set commitment is
+/// computed over, so the pubkeys must not be re-sorted.
+pub fn order_by_authority_set(
+    block_number: u32,
+    signed_message: Vec<u8>,
+    authorities: &[CompressedEdwardsY],
+    weights: &[u64],
+    pubkey_to_signature: &HashMap<Vec<u8>, Vec<u8>>,
+) -> StoredJustificationData {
+    assert_eq!(authorities.len(), weights.len());
+    let mut pubkeys = Vec::new();
+    let mut signatures = Vec::new();
+    let mut validator_signed = Vec::new();
+    for authority_pubkey in authorities.iter() {
+        pubkeys.push(authority_pubkey.0.to_vec());
+        if let Some(signature) = pubkey_to_signature.get(&authority_pubkey.0.to_vec()) {
+            signatures.push(signature.to_vec());
+            validator_signed.push(true);
+        } else {
+            // Push a dummy signature, since this validator did not sign.
+            signatures.push(DUMMY_SIGNATURE.to_vec());
+            validator_signed.push(false);
+        }
+    }
+
+    StoredJustificationData {
+        block_number,
+        signed_message,
+        pubkeys,
+        signatures,
+        validator_signed,
+        num_authorities: authorities.len(),
+        weights: weights.to_vec(),
+        round: None,
+        authority_set_hash: compute_weighted_authority_set_hash(authorities, weights)
+            .try_into()
+            .ok(),
+    }
 }
 
-impl RpcDataFetcher {
-    const MAX_RECONNECT_ATTEMPTS: usize = 3;
-    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Form the message signed by the validators in a justification.
+/// Spec: https://github.com/availproject/polkadot-sdk/blob/70e569d5112f879001a987e94402ff70f9683cb5/substrate/primitives/consensus/grandpa/src/lib.rs#L434-L458
+pub fn encode_signed_message(
+    justification: &GrandpaJustification,
+    authority_set_id: u64,
+) -> Vec<u8> {
+    Encode::encode(&(
+        &SignerMessage::PrecommitMessage(justification.commit.precommits[0].clone().precommit),
+        &justification.round,
+        &authority_set_id,
+    ))
+}
 
-    pub async fn new() -> Self {
-        dotenv::dotenv().ok();
+/// Returns the authority set id in the message signed by the precommits of the justification, out of
+/// the candidate authority set ids. The authority set id is not part of the justification, so it is
+/// recovered by checking which candidate the precommit signatures are valid for.
+pub fn signed_authority_set_id(
+    justification: &GrandpaJustification,
+    candidates: &[u64],
+) -> Option<u64> {
+    candidates.iter().copied().find(|authority_set_id| {
+        let signed_message = encode_signed_message(justification, *authority_set_id);
+        justification.commit.precommits.iter().any(|precommit| {
+            is_signature_valid(&precommit.id.0, &signed_message, &precommit.signature.0)
+        })
+    })
+}
 
-        let url = env::var("AVAIL_URL").expect("AVAIL_URL must be set");
-        let client = build_client(url.as_str(), false).await.unwrap();
-        let redis_client = RedisClient::new().await;
-        RpcDataFetcher {
-            client: client.0,
-            avail_url: url,
-            avail_chain_id: env::var("AVAIL_CHAIN_ID").expect("AVAIL_CHAIN_ID must be set"),
-            redis_client,
-            save: None,
+/// Processes a justification signed by `authorities` into the justification data for the block.
+/// Precommits with an invalid signature are ignored. This includes precommits signed in a
+/// different round than the justification's round, as the round is part of the signed message.
+/// Errors if the authority set is empty, or if the authorities with a valid signature do not have
+/// more than 2/3 of the voting power.
+pub fn process_justification(
+    block_number: u32,
+    justification: &GrandpaJustification,
+    authority_set_id: u64,
+    authorities: &[CompressedEdwardsY],
+    weights: &[u64],
+) -> Result<StoredJustificationData, Error> {
+    check_authority_set_not_empty(authorities, block_number)?;
+
+    let signed_message = encode_signed_message(justification, authority_set_id);
+
+    // Create map from pubkey to signature for the precommits of authorities with a valid signature.
+    // A valid signature from a pubkey that is not in the authority set does not count.
+    let authority_pubkeys = authorities
+        .iter()
+        .map(|authority| authority.0.to_vec())
+        .collect::<HashSet<_>>();
+    let mut pubkey_to_signature = HashMap::new();
+    for precommit in justification.commit.precommits.iter() {
+        let pubkey = precommit.id.0.to_vec();
+        if !authority_pubkeys.contains(&pubkey) {
+            debug!(
+                "Ignoring precommit for block {} from {:?}, which is not in authority set {}",
+                block_number,
+                H256::from(precommit.id.0),
+                authority_set_id
+            );
+            continue;
+        }
+        let signature = precommit.signature.0;
+        if is_signature_valid(&pubkey, &signed_message, &signature) {
+            pubkey_to_signature.insert(pubkey, signature.to_vec());
         }
     }
+    if pubkey_to_signature.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No valid signatures from authority set {} for block {}",
+            authority_set_id,
+            block_number
+        ));
+    }
 
-    async fn refresh_ws_connection(&mut self) -> Result<(), String> {
-        for _ in 0..Self::MAX_RECONNECT_ATTEMPTS {
-            match self.client.rpc().system_health().await {
-                Ok(_) => return Ok(()),
-                Err(_) => match build_client(self.avail_url.as_str(), false).await {
-                    Ok(new_client) => {
-                        self.client = new_client.0;
-                        return Ok(());
-                    }
-                    Err(_) => {
-                        debug!("Failed to connect to client, retrying...");
-                        tokio::time::sleep(Self::RECONNECT_DELAY).await;
-                    }
-                },
-            }
-        }
-        Err("Failed to connect to Avail client after multiple attempts!".to_string())
+    let data = StoredJustificationData {
+        round: Some(justification.round),
+        ..order_by_authority_set(
+            block_number,
+            signed_message,
+            authorities,
+            weights,
+            &pubkey_to_signature,
+        )
+    };
+
+    // Check that the validators that signed the justification have more than 2/3 of the voting
+    // weight.
+    let total_weight: u64 = weights.iter().sum();
+    let signed_weight: u64 = data
+        .validator_signed
+        .iter()
+        .zip(weights.iter())
+        .filter(|(signed, _)| **signed)
+        .map(|(_, weight)| weight)
+        .sum();
+    if !is_voting_threshold_met(signed_weight, total_weight) {
+        return Err(anyhow::anyhow!(
+            "Not enough voting power for block {}: signed weight {} of total weight {}",
+            block_number,
+            signed_weight,
+            total_weight
+        ));
     }
 
-    pub async fn check_data_commitment(&mut self, block: u32) {
-        self.refresh_ws_connection()
-            .await
-            .expect("Failed to establish connection to Avail WS.");
+    Ok(data)
+}
 
-        let header = self.get_header(block).await;
-        let data_root = header.data_root().0.to_vec();
-        println!("data_root {:?}", data_root);
+/// Verifies the votes ancestries of the justification. Precommits are for the commit target or one
+/// of its descendants, and votes_ancestries has the headers linking each precommit target back to the
+/// commit target. Errors if a precommit target does not reach the commit target by following the
+/// parent hashes of the headers in votes_ancestries, or if a header in votes_ancestries is unused.
+pub fn verify_votes_ancestries(justification: &GrandpaJustification) -> Result<(), Error> {
+    let ancestries = votes_ancestries_links(justification);
+    let precommit_targets = justification
+        .commit
+        .precommits
+        .iter()
+        .map(|precommit| precommit.precommit.target_hash.0)
+        .collect::<Vec<_>>();
+    check_votes_ancestries(
+        justification.commit.target_hash.0,
+        &precommit_targets,
+        &ancestries,
+    )
+}
 
-        let encoded_header_bytes = header.encode();
-        println!("encoded_header_bytes {:?}", encoded_header_bytes);
+/// Returns the (hash, parent_hash) links of the votes ancestries, for the hashes of the headers in the
+/// votes ancestries of the justification.
+fn votes_ancestries_links(justification: &GrandpaJustification) -> Vec<([u8; 32], [u8; 32])> {
+    justification
+        .votes_ancestries
+        .iter()
+        .map(|header| {
+            (
+                Encode::using_encoded(header, sp_core::blake2_256),
+                header.parent_hash.0,
+            )
+        })
+        .collect()
+}
 
-        // Find the data_root in the header.
-        let mut data_root_index = -1;
-        for i in 0..(encoded_header_bytes.len() - HASH_SIZE) + 1 {
-            if encoded_header_bytes[i..i + HASH_SIZE] == data_root[..] {
-                data_root_index = i as i32;
-                break;
-            }
+/// Follows the parent links from the precommit target back to the commit target, and returns the
+/// ancestries on the path. Errors if the path breaks before reaching the commit target, or if the
+/// links contain a cycle.
+fn ancestry_path(
+    commit_target: [u8; 32],
+    precommit_target: [u8; 32],
+    parents: &HashMap<[u8; 32], [u8; 32]>,
+) -> Result<Vec<[u8; 32]>, Error> {
+    let mut path = Vec::new();
+    let mut current = precommit_target;
+    while current != commit_target {
+        let Some(parent) = parents.get(&current) else {
+            return Err(anyhow::anyhow!(
+                "Precommit target {} is not a descendant of commit target {}",
+                hex::encode(precommit_target),
+                hex::encode(commit_target)
+            ));
+        };
+        // Each step follows a distinct ancestry, so a path longer than the ancestries is a cycle.
+        if path.len() == parents.len() {
+            return Err(anyhow::anyhow!("Votes ancestries contain a cycle"));
         }
+        path.push(current);
+        current = *parent;
+    }
+    Ok(path)
+}
 
-        println!("data_root_index {:?}", data_root_index);
+/// Checks that each precommit target reaches the commit target through the (hash, parent_hash)
+/// links of the votes ancestries, and that every votes ancestry is on one of these paths.
+fn check_votes_ancestries(
+    commit_target: [u8; 32],
+    precommit_targets: &[[u8; 32]],
+    ancestries: &[([u8; 32], [u8; 32])],
+) -> Result<(), Error> {
+    let parents = ancestries.iter().copied().collect::<HashMap<_, _>>();
+    let mut visited = HashSet::new();
+    for precommit_target in precommit_targets {
+        visited.extend(ancestry_path(commit_target, *precommit_target, &parents)?);
+    }
+    if visited.len() != parents.len() {
+        return Err(anyhow::anyhow!(
+            "{} of {} votes ancestries are not ancestors of a precommit target",
+            parents.len() - visited.len(),
+            parents.len()
+        ));
     }
+    Ok(())
+}
 
-    /// Finds all blocks with valid justifications. This includes justifications in Redis and epoch
-    /// end blocks within the given range of block numbers. Includes start and end blocks.
-    pub async fn find_justifications_in_range(
-        &mut self,
-        start_block: u32,
-        end_block: u32,
-    ) -> Vec<u32> {
-        self.refresh_ws_connection()
-            .await
-            .expect("Failed to establish connection to Avail WS.");
-        info!(
-            "Finding justifications in range [{}, {}].",
-            start_block, end_block
-        );
-        // Query Redis for all keys in the range [start_block, end_block].
-        let redis_blocks: Vec<u32> = self
+/// Returns whether each precommit target reaches the commit target through the (hash, parent_hash)
+/// links of the votes ancestries.
+fn justified_precommit_targets(
+    commit_target: [u8; 32],
+    precommit_targets: &[[u8; 32]],
+    ancestries: &[([u8; 32], [u8; 32])],
+) -> Vec<bool> {
+    let parents = ancestries.iter().copied().collect::<HashMap<_, _>>();
+    precommit_targets
+        .iter()
+        .map(|precommit_target| ancestry_path(commit_target, *precommit_target, &parents).is_ok())
+        .collect()
+}
+
+/// Drops the precommits of the justification whose target is not the commit target or one of its
+/// descendants linked by the votes ancestries, so they are not counted as votes for the commit
+/// target. Returns the number of dropped precommits.
+pub fn drop_unjustified_precommits(justification: &mut GrandpaJustification) -> usize {
+    let precommit_targets = justification
+        .commit
+        .precommits
+        .iter()
+        .map(|precommit| precommit.precommit.target_hash.0)
+        .collect::<Vec<_>>();
+    let justified = justified_precommit_targets(
+        justification.commit.target_hash.0,
+        &precommit_targets,
+        &votes_ancestries_links(justification),
+    );
+    let num_precommits = justification.commit.precommits.len();
+    let mut justified = justified.into_iter();
+    justification
+        .commit
+        .precommits
+        .retain(|_| justified.next().unwrap());
+    num_precommits - justification.commit.precommits.len()
+}
+
+/// Errors if the authority set is empty, which can happen if the RPC returns an empty authority set
+/// or the authority set is queried before genesis. An empty authority set would lead to a degenerate
+/// authority set commitment and voting threshold.
+pub fn check_authority_set_not_empty(
+    authorities: &[CompressedEdwardsY],
+    block_number: u32,
+) -> Result<(), Error> {
+    if authorities.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Authority set at block {} is empty",
+            block_number
+        ));
+    }
+    Ok(())
+}
+
+/// Returns true if the value of a GRANDPA consensus log is a ScheduledChange or a ForcedChange, which
+/// enact a new authority set.
+pub fn is_authority_set_change(value: &[u8]) -> bool {
+    matches!(
+        value.first(),
+        Some(&SCHEDULED_CHANGE_FLAG) | Some(&FORCED_CHANGE_FLAG)
+    )
+}
+
+/// Returns true if the header has a GRANDPA ScheduledChange or ForcedChange consensus log, which
+/// specifies the new authority set in an epoch end block.
+pub fn has_scheduled_change(header: &Header) -> bool {
+    header.digest.logs.iter().any(|log| match log {
+        DigestItem::Consensus(consensus_id, value) => {
+            *consensus_id == [70, 82, 78, 75] && is_authority_set_change(value)
+        }
+        _ => false,
+    })
+}
+
+/// Decodes the GRANDPA ScheduledChange or ForcedChange log from the digest logs of an epoch end
+/// header. Errors if there is no such log, or if the log is malformed. A Pause or Resume log does not
+/// change the authority set, so a header with only a Pause or Resume log is an error.
+pub fn decode_authority_set_change(logs: &[DigestItem]) -> Result<AuthoritySetChangeLog, Error> {
+    let mut offset = 0;
+    let mut pause_or_resume = None;
+    for log in logs {
+        if let DigestItem::Consensus(consensus_id, value) = log {
+            if *consensus_id == [70, 82, 78, 75] && is_authority_set_change(value) {
+                let is_forced_change = value[0] == FORCED_CHANGE_FLAG;
+
+                // A `ForcedChange` is prefixed with the median last finalized block number.
+                let mut cursor = 1;
+                if is_forced_change {
+                    cursor += FORCED_CHANGE_MEDIAN_LENGTH;
+                }
+
+                // The bytes after the prefix are the compact encoded number of authorities.
+                // Follows the encoding format: https://docs.substrate.io/reference/scale-codec/#fn-1
+                let mut encoded_authorities = &value[cursor.min(value.len())..];
+                let Compact(num_authorities) = Compact::<u32>::decode(&mut encoded_authorities)
+                    .map_err(|e| {
+                        anyhow::anyhow!("Failed to decode number of authorities: {}", e)
+                    })?;
+                let authorities_length = num_authorities as usize * VALIDATOR_LENGTH;
+                if encoded_authorities.len() != authorities_length + DELAY_LENGTH {
+                    return Err(anyhow::anyhow!(
+                        "Authority set change log has {} bytes for {} authorities",
+                        encoded_authorities.len(),
+                        num_authorities
+                    ));
+                }
+
+                let (authorities_bytes, delay) = encoded_authorities.split_at(authorities_length);
+                let mut pubkeys = Vec::new();
+                let mut weights = Vec::new();
+                for authority_chunk in authorities_bytes.chunks_exact(VALIDATOR_LENGTH) {
+                    pubkeys.push(authority_chunk[..PUBKEY_LENGTH].try_into().unwrap());
+                    weights.push(u64::from_le_bytes(
+                        authority_chunk[PUBKEY_LENGTH..].try_into().unwrap(),
+                    ));
+                }
+
+                return Ok(AuthoritySetChangeLog {
+                    offset,
+                    is_forced_change,
+                    pubkeys,
+                    weights,
+                    delay: u32::from_le_bytes(delay.try_into().unwrap()),
+                });
+            }
+            if *consensus_id == [70, 82, 78, 75]
+                && matches!(value.first(), Some(&PAUSE_FLAG) | Some(&RESUME_FLAG))
+            {
+                pause_or_resume = Some(if value[0] == PAUSE_FLAG {
+                    "Pause"
+                } else {
+                    "Resume"
+                });
+            }
+        }
+        offset += log.encode().len();
+    }
+
+    match pause_or_resume {
+        Some(log) => Err(anyhow::anyhow!(
+            "Header has a GRANDPA {} log, which does not change the authority set",
+            log
+        )),
+        None => Err(anyhow::anyhow!(
+            "Header has no GRANDPA ScheduledChange or ForcedChange log"
+        )),
+    }
+}
+
+/// Returns true if the encoded header ends with the header's data root, which is where the circuit
+/// decodes the data root from. This holds for header extensions whose Kate commitment is the last
+/// field and ends with the data root, and does not depend on the length of the other extension
+/// fields (app lookup, rows, cols and commitment).
+pub fn is_data_root_at_end(header: &Header, encoded_header: &[u8]) -> bool {
+    encoded_header.len() >= DATA_ROOT_OFFSET_FROM_END
+        && encoded_header[encoded_header.len() - DATA_ROOT_OFFSET_FROM_END..][..HASH_SIZE]
+            == header.data_root().0
+}
+
+/// Encodes a header, padded with zeros to HEADER_LENGTH. Errors if the encoded header is larger than
+/// HEADER_LENGTH, or if its header extension does not end with the data root, in which case the
+/// circuit would decode the wrong data root.
+pub fn encode_header<const HEADER_LENGTH: usize, F: RichField>(
+    header: &Header,
+) -> Result<EncodedHeader<HEADER_LENGTH, F>, Error> {
+    let mut header_bytes = header.encode();
+    let header_size = header_bytes.len();
+    if header_size > HEADER_LENGTH {
+        return Err(anyhow::anyhow!(
+            "Block {}'s header size is {}, which is greater than the maximum header size of {} bytes.",
+            header.number,
+            header_size,
+            HEADER_LENGTH
+        ));
+    }
+    if !is_data_root_at_end(header, &header_bytes) {
+        return Err(anyhow::anyhow!(
+            "Block {}'s header does not end with its data root, so its header extension is not supported.",
+            header.number
+        ));
+    }
+    header_bytes.resize(HEADER_LENGTH, 0);
+    Ok(EncodedHeader {
+        header_bytes,
+        header_size: header_size as u32,
+    })
+}
+
+/// Checks that the simple justification for block_number fits in VALIDATOR_SET_SIZE_MAX, that the
+/// precommit is correctly encoded and signed by the authority set expected_authority_set_id, that
+/// every signature is 64 bytes, that the stored authority set commitment (if any) matches the
+/// pubkeys and weights, and that it has at least 2/3 of the voting weight.
+pub fn check_simple_justification<const VALIDATOR_SET_SIZE_MAX: usize>(
+    block_number: u32,
+    data: SimpleJustificationData,
+    expected_authority_set_id: u64,
+) -> Result<SimpleJustificationData, FetchError> {
+    // The justification would be truncated to VALIDATOR_SET_SIZE_MAX, so the circuit would
+    // verify the wrong authority set.
+    if data.num_authorities as usize > VALIDATOR_SET_SIZE_MAX {
+        return Err(FetchError::TooManyAuthorities {
+            block_number,
+            num_authorities: data.num_authorities as usize,
+            max_num_authorities: VALIDATOR_SET_SIZE_MAX,
+        });
+    }
+
+    if data.signed_message.len() != ENCODED_PRECOMMIT_LENGTH {
+        return Err(FetchError::PrecommitLengthMismatch {
+            block_number,
+            length: data.signed_message.len(),
+        });
+    }
+
+    // A corrupt stored signature would fail the conversion to the circuit's signature type.
+    if let Some((index, signature)) = data
+        .signatures
+        .iter()
+        .enumerate()
+        .find(|(_, signature)| signature.len() != 64)
+    {
+        return Err(FetchError::InvalidSignatureLength {
+            block_number,
+            index,
+            length: signature.len(),
+        });
+    }
+
+    // A corrupt stored justification would otherwise only be caught by the circuit, against the
+    // authority set hash from the contract.
+    if let Some(expected) = data.authority_set_hash {
+        let actual = compute_weighted_authority_set_hash(&data.pubkeys, &data.weights);
+        if actual != expected {
+            return Err(FetchError::AuthoritySetHashMismatch {
+                block_number,
+                expected: H256::from(expected),
+                actual: H256::from_slice(&actual),
+            });
+        }
+    }
+
+    let (_, _, _, actual) = decode_precommit(data.signed_message.clone());
+    debug!(
+        "Justification for block {} is signed by authority set {}, expected authority set {}",
+        block_number, actual, expected_authority_set_id
+    );
+    if actual != expected_authority_set_id {
+        return Err(FetchError::AuthoritySetMismatch {
+            block_number,
+            expected: expected_authority_set_id,
+            actual,
+        });
+    }
+
+    let total_weight: u64 = data.weights.iter().sum();
+    if !is_voting_threshold_met(data.voting_weight, total_weight) {
+        return Err(FetchError::NotEnoughVotingWeight { block_number });
+    }
+
+    Ok(data)
+}
+
+/// Pads the simple justification to VALIDATOR_SET_SIZE_MAX authorities for the circuit. Returns a
+/// CircuitJustification with the encoded precommit that all authorities sign, the validator
+/// signatures, and the authority set's pubkeys.
+pub fn circuit_justification<const VALIDATOR_SET_SIZE_MAX: usize>(
+    data: SimpleJustificationData,
+) -> CircuitJustification {
+    // The authority set id is derived from storage at the parent of block_number, not from the
+    // signed message, so the circuit checks the signed message against it.
+    let current_authority_set_id = data.authority_set_id;
+    let current_authority_set_hash =
+        compute_weighted_authority_set_hash(&data.pubkeys, &data.weights);
+
+    let mut padded_pubkeys = Vec::new();
+    let mut padded_signatures = Vec::new();
+    let mut padded_validator_signed = Vec::new();
+    let mut padded_weights = Vec::new();
+    for i in 0..data.num_authorities as usize {
+        padded_pubkeys.push(data.pubkeys[i]);
+        padded_signatures.push(data.signatures[i].as_slice().try_into().unwrap());
+        padded_validator_signed.push(data.validator_signed[i]);
+        padded_weights.push(data.weights[i]);
+    }
+
+    for _ in data.num_authorities as usize..VALIDATOR_SET_SIZE_MAX {
+        padded_validator_signed.push(false);
+        // Push a dummy pubkey, signature and weight, to pad the array to VALIDATOR_SET_SIZE_MAX.
+        padded_pubkeys.push(CompressedEdwardsY::from_slice(&DUMMY_PUBLIC_KEY).unwrap());
+        padded_signatures.push(DUMMY_SIGNATURE);
+        padded_weights.push(1);
+    }
+
+    CircuitJustification {
+        authority_set_id: current_authority_set_id,
+        signed_message: data.signed_message,
+        validator_signed: padded_validator_signed,
+        pubkeys: padded_pubkeys,
+        signatures: padded_signatures,
+        weights: padded_weights,
+        num_authorities: data.num_authorities as usize,
+        current_authority_set_hash,
+        round: data.round,
+    }
+}
+
+/// Computes the data to prove the new authority set enacted by the header of epoch_end_block, from
+/// the header and the new authorities. Errors with NotEpochEndBlock if the header has no valid
+/// authority set change log, and with a typed FetchError if the header or its log does not match
+/// the circuit or the new authorities.
+pub fn header_rotate_data<const HEADER_LENGTH: usize, const VALIDATOR_SET_SIZE_MAX: usize>(
+    epoch_end_block: u32,
+    header: &Header,
+    new_authorities: &[CompressedEdwardsY],
+) -> Result<HeaderRotateData, FetchError> {
+    let not_epoch_end_block = FetchError::NotEpochEndBlock {
+        block_number: epoch_end_block,
+    };
+
+    let mut header_bytes = header.encode();
+    let header_size = header_bytes.len();
+    if header_size > HEADER_LENGTH {
+        return Err(FetchError::HeaderTooLarge {
+            block_number: epoch_end_block,
+            header_size,
+            max_header_size: HEADER_LENGTH,
+        });
+    }
+    if !is_data_root_at_end(header, &header_bytes) {
+        return Err(FetchError::DataRootNotAtEnd {
+            block_number: epoch_end_block,
+        });
+    }
+    header_bytes.resize(HEADER_LENGTH, 0);
+
+    let num_authorities = new_authorities.len();
+    let encoded_num_authorities_len = Compact(num_authorities as u32).encode().len();
+
+    let mut position = 0;
+    let number_encoded = Compact(epoch_end_block).encode();
+    // Skip past parent_hash, number, state_root, extrinsics_root.
+    position += HASH_SIZE + number_encoded.len() + HASH_SIZE + HASH_SIZE;
+
+    // Skip past the logs before the authority set change log.
+    let change = decode_authority_set_change(&header.digest.logs).map_err(|e| {
+        warn!(
+            "Block {} has no valid authority set change log: {}",
+            epoch_end_block, e
+        );
+        not_epoch_end_block
+    })?;
+    position += change.offset;
+    let is_forced_change = change.is_forced_change;
+    let forced_change_delay = change.delay;
+
+    // Check the pubkeys in the encoded log are the new authorities.
+    if change.pubkeys.len() != num_authorities
+        || change
+            .pubkeys
+            .iter()
+            .zip(new_authorities.iter())
+            .any(|(pubkey, authority)| *pubkey != authority.0)
+    {
+        return Err(FetchError::AuthoritySetChangeMismatch {
+            block_number: epoch_end_block,
+        });
+    }
+
+    // The delay of a `ScheduledChange` is [0, 0, 0, 0], and the delay of a `ForcedChange` is the
+    // number of blocks after which the change is enacted.
+    if !is_forced_change && forced_change_delay != 0 {
+        return Err(FetchError::InvalidScheduledChangeDelay {
+            block_number: epoch_end_block,
+            delay: forced_change_delay,
+        });
+    }
+
+    // The commitment binds the weight of each authority. If all weights are 1, it is the same as
+    // the unweighted commitment.
+    let new_authority_set_hash =
+        compute_weighted_authority_set_hash(new_authorities, &change.weights);
+    let mut padded_pubkeys = Vec::new();
+    let mut padded_weights = change.weights.clone();
+    for i in 0..new_authorities.len() {
+        padded_pubkeys.push(CompressedEdwardsY::from_slice(&new_authorities[i].0).unwrap());
+    }
+    for _ in new_authorities.len()..VALIDATOR_SET_SIZE_MAX {
+        // Pad the arrays with dummy pubkeys and weights to VALIDATOR_SET_SIZE_MAX.
+        padded_pubkeys.push(CompressedEdwardsY::from_slice(&DUMMY_PUBLIC_KEY).unwrap());
+        padded_weights.push(1);
+    }
+
+    // skip 1 byte, 1 consensus id, 4 consensus engine id, skip 2 bytes,
+    // 1 scheduled change or forced change, 4 median last finalized block number bytes for a
+    // forced change, variable length compact encoding of the number of authorities.
+    let mut prefix_length = BASE_PREFIX_LENGTH + encoded_num_authorities_len;
+    if is_forced_change {
+        prefix_length += FORCED_CHANGE_MEDIAN_LENGTH;
+    }
+    // The end position is the position + prefix_length + encoded pubkeys len + 4 delay bytes.
+    let end_position = position + prefix_length + ((32 + 8) * new_authorities.len()) + 4;
+
+    Ok(HeaderRotateData {
+        header_bytes,
+        header_size,
+        num_authorities: new_authorities.len(),
+        start_position: position,
+        end_position,
+        new_authority_set_hash,
+        padded_pubkeys,
+        padded_weights,
+        is_forced_change,
+        forced_change_delay,
+    })
+}
+
+/// Fetches the items for block numbers in [start_block_number, end_block_number] concurrently, with
+/// at most max_in_flight fetches in flight at once. Returns the items ordered by block number, or
+/// the error of the lowest block number that failed.
+pub async fn fetch_range_concurrently<T, F, Fut>(
+    start_block_number: u32,
+    end_block_number: u32,
+    max_in_flight: usize,
+    fetch: F,
+) -> Result<Vec<T>, Error>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let semaphore = Semaphore::new(max_in_flight);
+    let futures = (start_block_number..=end_block_number).map(|block_number| {
+        let semaphore = &semaphore;
+        let fetch = &fetch;
+        async move {
+            let _permit = semaphore.acquire().await.expect("Semaphore closed");
+            fetch(block_number).await
+        }
+    });
+
+    // join_all returns the results in the order of the futures, so in order of block number.
+    join_all(futures).await.into_iter().collect()
+}
+
+/// Returns true if justification should overwrite the justification stored for the same block. A
+/// finalized block is not justified again in an earlier round, so a justification with a lower round
+/// than the stored one is a stale justification replayed by the RPC, and never overwrites it.
+/// Otherwise, the justification only overwrites the stored one if it has strictly more validator
+/// signatures. Justifications stored before the round was stored are only compared by signatures.
+pub fn should_replace_justification(
+    stored: &StoredJustificationData,
+    justification: &StoredJustificationData,
+) -> bool {
+    if let (Some(stored_round), Some(round)) = (stored.round, justification.round) {
+        if round < stored_round {
+            warn!(
+                "Justification for block {} has round {}, but a justification with round {} is already stored.",
+                justification.block_number, round, stored_round
+            );
+            return false;
+        }
+    }
+    justification.num_signatures() > stored.num_signatures()
+}
+
+/// Within an authority set, GRANDPA rounds increase monotonically. Returns true (and logs a
+/// warning) if `round` is lower than or equal to the last round seen for the authority set, which
+/// indicates a replayed justification or a fork.
+pub fn is_round_regression(
+    last_round: Option<u64>,
+    round: u64,
+    authority_set_id: u64,
+    block_number: u32,
+) -> bool {
+    match last_round {
+        Some(last_round) if round <= last_round => {
+            warn!(
+                "Justification for block {} has round {} in authority set {}, but round {} was already seen.",
+                block_number, round, authority_set_id, last_round
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Source of the data for the justification and rotate hints. Implemented by `RpcDataFetcher`,
+/// which fetches from an Avail node and Redis, and by `MockDataFetcher`, which serves fixtures.
+#[async_trait]
+pub trait DataFetcher: Send {
+    /// Fetches the simple justification for block_number, checked as in
+    /// `check_simple_justification`.
+    async fn try_get_simple_justification<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        block_number: u32,
+    ) -> Result<SimpleJustificationData, FetchError>;
+
+    /// Fetches the data to prove the new authority set enacted by epoch_end_block. Errors with
+    /// NotEpochEndBlock if epoch_end_block is not an epoch end block.
+    async fn get_header_rotate<const HEADER_LENGTH: usize, const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        epoch_end_block: u32,
+    ) -> Result<HeaderRotateData, FetchError>;
+
+    /// Fetches the authorities at block_number, in storage order.
+    async fn get_authorities(&mut self, block_number: u32) -> Vec<CompressedEdwardsY>;
+
+    /// Returns the epoch end block of target_authority_set_id, or 0 if the authority set has not
+    /// ended.
+    async fn last_justified_block(&mut self, target_authority_set_id: u64) -> u32;
+
+    /// Returns true if block_number is an epoch end block.
+    async fn is_epoch_end_block(&mut self, block_number: u32) -> bool;
+
+    /// Returns the latest block with a stored justification signed by authority_set_id, or None if
+    /// there is no such block. Used to prove that an authority set has not ended.
+    async fn latest_justified_block(&mut self, authority_set_id: u64) -> Option<u32>;
+
+    /// Fetches the header of block_number.
+    async fn try_get_header(&mut self, block_number: u32) -> Result<Header, FetchError>;
+
+    /// Fetches the status of the chain, checked to be synced as in `ChainStatus::check_synced`.
+    async fn health_check(&mut self) -> Result<ChainStatus, FetchError>;
+
+    /// Fetches the simple justification for block_number, padded to VALIDATOR_SET_SIZE_MAX
+    /// authorities for the circuit.
+    async fn get_justification_from_block<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        block_number: u32,
+    ) -> Result<CircuitJustification, FetchError> {
+        let data = self
+            .try_get_simple_justification::<VALIDATOR_SET_SIZE_MAX>(block_number)
+            .await?;
+        Ok(circuit_justification::<VALIDATOR_SET_SIZE_MAX>(data))
+    }
+}
+
+#[derive(Clone)]
+pub struct RpcDataFetcher {
+    pub client: Client,
+    /// Url of the Avail node the client is connected to.
+    pub avail_url: String,
+    /// Urls of the Avail nodes to connect to, in order of preference.
+    pub avail_urls: Vec<String>,
+    pub avail_chain_id: String,
+    pub redis_client: RedisClient,
+    pub save: Option<String>,
+    /// Retry policy for transient errors from RPC calls to the Avail node.
+    pub retry_policy: RetryPolicy,
+    /// Cache of fetched justification data, shared across fetchers in the process. None if
+    /// disabled.
+    pub justification_cache: Option<Arc<Mutex<JustificationCache>>>,
+    /// Cache of fetched rotate header data, shared across fetchers in the process. None if
+    /// disabled.
+    pub header_rotate_cache: Option<Arc<Mutex<HeaderRotateCache>>>,
+    /// Cache of authority set hashes by chain id and authority set id, shared across fetchers in the
+    /// process. None if disabled.
+    pub authority_set_hash_cache: Option<Arc<Mutex<AuthoritySetHashCache>>>,
+}
+
+impl RpcDataFetcher {
+    const MAX_RECONNECT_ATTEMPTS: usize = 3;
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    /// Connects to the Avail node at AVAIL_URL, or at DEFAULT_AVAIL_URL if AVAIL_URL is not set.
+    /// AVAIL_URL can be a comma-separated list of urls, which are used as fallbacks in order.
+    pub async fn new() -> Self {
+        dotenv::dotenv().ok();
+
+        let url = env::var("AVAIL_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| {
+                warn!("AVAIL_URL is not set, defaulting to {}", DEFAULT_AVAIL_URL);
+                DEFAULT_AVAIL_URL.to_string()
+            });
+        Self::new_with_url(&url).await
+    }
+
+    /// Connects to the Avail node at the websocket url (or comma-separated list of urls), instead
+    /// of AVAIL_URL.
+    pub async fn new_with_url(url: &str) -> Self {
+        dotenv::dotenv().ok();
+
+        let avail_urls = parse_urls(url);
+        let (index, client) = connect_in_order(&avail_urls, 0, |url| build_client(url, false))
+            .await
+            .expect("Failed to connect to any Avail url");
+        let redis_client = RedisClient::new().await;
+        RpcDataFetcher {
+            client: client.0,
+            avail_url: avail_urls[index].clone(),
+            avail_urls,
+            avail_chain_id: env::var("AVAIL_CHAIN_ID").expect("AVAIL_CHAIN_ID must be set"),
+            redis_client,
+            save: None,
+            retry_policy: RetryPolicy::default(),
+            justification_cache: shared_justification_cache(),
+            header_rotate_cache: shared_header_rotate_cache(),
+            authority_set_hash_cache: shared_authority_set_hash_cache(),
+        }
+    }
+
+    /// Connects to the Avail node at the websocket url if specified, otherwise at AVAIL_URL.
+    pub async fn new_with_optional_url(url: Option<&str>) -> Self {
+        match url {
+            Some(url) => Self::new_with_url(url).await,
+            None => Self::new().await,
+        }
+    }
+
+    /// Clears the in-memory justification, header and authority set hash caches, so that
+    /// subsequent calls fetch from the Avail node and Redis.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.justification_cache {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+        if let Some(cache) = &self.header_rotate_cache {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+        if let Some(cache) = &self.authority_set_hash_cache {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+
+    /// Returns the key of the value fetched for block_number with the const generics sizes, in the
+    /// caches shared across fetchers.
+    fn block_cache_key(&self, sizes: &[usize], block_number: u32) -> BlockCacheKey {
+        BlockCacheKey {
+            avail_chain_id: self.avail_chain_id.clone(),
+            avail_url: self.avail_url.clone(),
+            sizes: sizes.to_vec(),
+            block_number,
+        }
+    }
+
+    /// Rebuilds the client, connecting to the current url and falling back to the other urls in
+    /// order. Used when the connection is unhealthy, or a subscription on the client has dropped.
+    pub async fn reconnect(&mut self) -> Result<(), String> {
+        let current_index = self
+            .avail_urls
+            .iter()
+            .position(|url| *url == self.avail_url)
+            .unwrap_or(0);
+        let (index, new_client) = connect_in_order(&self.avail_urls, current_index, |url| {
+            build_client(url, false)
+        })
+        .await
+        .map_err(|e| format!("Failed to connect to any Avail url: {:?}", e))?;
+        if self.avail_urls[index] != self.avail_url {
+            info!(
+                "Switched Avail url from {} to {}",
+                self.avail_url, self.avail_urls[index]
+            );
+        }
+        self.avail_url = self.avail_urls[index].clone();
+        self.client = new_client.0;
+        Ok(())
+    }
+
+    /// Checks the connection to the Avail node is healthy. If not, reconnects to the current url,
+    /// falling back to the other urls in order.
+    async fn refresh_ws_connection(&mut self) -> Result<(), String> {
+        for _ in 0..Self::MAX_RECONNECT_ATTEMPTS {
+            if self.client.rpc().system_health().await.is_ok() {
+                return Ok(());
+            }
+
+            match self.reconnect().await {
+                Ok(()) => return Ok(()),
+                Err(_) => {
+                    debug!("Failed to connect to client, retrying...");
+                    tokio::time::sleep(Self::RECONNECT_DELAY).await;
+                }
+            }
+        }
+        Err("Failed to connect to Avail client after multiple attempts!".to_string())
+    }
+
+    pub async fn check_data_commitment(&mut self, block: u32) {
+        self.refresh_ws_connection()
+            .await
+            .expect("Failed to establish connection to Avail WS.");
+
+        let header = self.get_header(block).await;
+        let data_root = header.data_root().0.to_vec();
+        println!("data_root {:?}", data_root);
+
+        let encoded_header_bytes = header.encode();
+        println!("encoded_header_bytes {:?}", encoded_header_bytes);
+
+        // Find the data_root in the header.
+        let mut data_root_index = -1;
+        for i in 0..(encoded_header_bytes.len() - HASH_SIZE) + 1 {
+            if encoded_header_bytes[i..i + HASH_SIZE] == data_root[..] {
+                data_root_index = i as i32;
+                break;
+            }
+        }
+
+        println!("data_root_index {:?}", data_root_index);
+    }
+
+    /// Finds all blocks with valid justifications. This includes justifications in Redis and epoch
+    /// end blocks within the given range of block numbers. Includes start and end blocks.
+    pub async fn find_justifications_in_range(
+        &mut self,
+        start_block: u32,
+        end_block: u32,
+    ) -> Vec<u32> {
+        self.refresh_ws_connection()
+            .await
+            .expect("Failed to establish connection to Avail WS.");
+        info!(
+            "Finding justifications in range [{}, {}].",
+            start_block, end_block
+        );
+        // Query Redis for all keys in the range [start_block, end_block].
+        let redis_blocks: Vec<u32> = self
             .redis_client
             .get_blocks_in_range(&self.avail_chain_id, start_block, end_block)
             .await;
@@ -413,12 +1762,44 @@ impl RpcDataFetcher {
         epoch_end_block_number
     }
 
-    pub async fn get_block_hash(&self, block_number: u32) -> H256 {
-        let block_hash = self
-            .client
-            .rpc()
-            .block_hash(Some(block_number.into()))
+    /// Returns true if block_number is an epoch end block: the authority set id changes at the block,
+    /// and its header has a ScheduledChange consensus log with the new authority set.
+    pub async fn is_epoch_end_block(&mut self, block_number: u32) -> bool {
+        if block_number == 0 {
+            return false;
+        }
+        let authority_set_id = self.get_authority_set_id(block_number).await;
+        let prev_authority_set_id = self.get_authority_set_id(block_number - 1).await;
+        if authority_set_id != prev_authority_set_id + 1 {
+            return false;
+        }
+        has_scheduled_change(&self.get_header(block_number).await)
+    }
+
+    /// Returns the latest block with a justification stored in Redis that is signed by
+    /// authority_set_id, or None if there is no such block. The authority set signs the blocks after
+    /// the epoch end block of the previous authority set, up to its own epoch end block.
+    pub async fn latest_justified_block(&mut self, authority_set_id: u64) -> Option<u32> {
+        let start_block = match authority_set_id {
+            0 => 1,
+            _ => self.last_justified_block(authority_set_id - 1).await + 1,
+        };
+        let end_block = match self.last_justified_block(authority_set_id).await {
+            0 => self.get_head().await.number,
+            epoch_end_block => epoch_end_block,
+        };
+        let blocks = self
+            .redis_client
+            .get_blocks_in_range(&self.avail_chain_id, start_block, end_block)
             .await;
+        blocks.into_iter().max()
+    }
+
+    pub async fn get_block_hash(&self, block_number: u32) -> H256 {
+        let block_hash = retry_with_backoff(&self.retry_policy, || {
+            self.client.rpc().block_hash(Some(block_number.into()))
+        })
+        .await;
         block_hash.unwrap().unwrap()
     }
 
@@ -522,9 +1903,71 @@ impl RpcDataFetcher {
         headers
     }
 
+    /// Fetches the headers for blocks in [start_block_number, end_block_number], with at most
+    /// MAX_CONCURRENT_HEADER_REQUESTS requests in flight. Returns the headers ordered by block number,
+    /// or an error identifying the first block whose header could not be found.
+    pub async fn get_headers_range(
+        &mut self,
+        start_block_number: u32,
+        end_block_number: u32,
+    ) -> Result<Vec<Header>, Error> {
+        self.refresh_ws_connection()
+            .await
+            .expect("Failed to establish connection to Avail WS.");
+
+        let fetcher = &*self;
+        fetch_range_concurrently(
+            start_block_number,
+            end_block_number,
+            MAX_CONCURRENT_HEADER_REQUESTS,
+            |block_number| fetcher.try_get_header(block_number),
+        )
+        .await
+    }
+
+    /// Fetches the header for block_number. Errors if the block hash or header can't be found.
+    pub async fn try_get_header(&self, block_number: u32) -> Result<Header, Error> {
+        let block_hash = retry_with_backoff(&self.retry_policy, || {
+            self.client.rpc().block_hash(Some(block_number.into()))
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get block hash for block {}: {}", block_number, e))?
+        .ok_or_else(|| anyhow::anyhow!("Block hash not found for block {}", block_number))?;
+
+        retry_with_backoff(&self.retry_policy, || {
+            self.client.rpc().header(Some(block_hash))
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get header for block {}: {}", block_number, e))?
+        .ok_or_else(|| anyhow::anyhow!("Header not found for block {}", block_number))
+    }
+
+    /// Fetches the GRANDPA justification the Avail node stores for the block. Returns None if the
+    /// node has no justification for the block, as nodes only keep the justifications of some
+    /// blocks, such as epoch end blocks.
+    pub async fn get_stored_justification(
+        &self,
+        block_number: u32,
+    ) -> Option<GrandpaJustification> {
+        let block_hash = self.get_block_hash(block_number).await;
+        let block = retry_with_backoff(&self.retry_policy, || {
+            self.client.rpc().block(Some(block_hash))
+        })
+        .await
+        .ok()??;
+        block
+            .justifications?
+            .into_iter()
+            .find(|(engine_id, _)| *engine_id == *b"FRNK")
+            .and_then(|(_, encoded)| GrandpaJustification::decode(&mut encoded.as_slice()).ok())
+    }
+
     pub async fn get_header(&self, block_number: u32) -> Header {
         let block_hash = self.get_block_hash(block_number).await;
-        let header_result = self.client.rpc().header(Some(block_hash)).await;
+        let header_result = retry_with_backoff(&self.retry_policy, || {
+            self.client.rpc().header(Some(block_hash))
+        })
+        .await;
         header_result.unwrap().unwrap()
     }
 
@@ -537,39 +1980,117 @@ impl RpcDataFetcher {
         header.unwrap().unwrap()
     }
 
-    pub async fn get_authority_set_id(&mut self, block_number: u32) -> u64 {
+    /// Fetches the finalized and best block numbers and the sync state of the Avail node. Errors
+    /// with NodeNotSynced if the node is still syncing, as justifications fetched from a node that
+    /// has not imported the chain are incomplete.
+    pub async fn health_check(&mut self) -> Result<ChainStatus, FetchError> {
         self.refresh_ws_connection()
             .await
-            .expect("Failed to establish connection to Avail WS.");
-        let block_hash = self.get_block_hash(block_number).await;
-
-        let set_id_key = api::storage().grandpa().current_set_id();
-        self.client
-            .storage()
-            .at(block_hash)
-            .fetch(&set_id_key)
+            .map_err(FetchError::Rpc)?;
+        let health = self
+            .client
+            .rpc()
+            .system_health()
             .await
-            .unwrap()
-            .unwrap()
-    }
-
-    // This function returns the authorities (as AffinePoint and public key bytes) for a given block number
-    // by fetching the "authorities_bytes" from storage and decoding the bytes to a VersionedAuthorityList.
-    pub async fn get_authorities(&mut self, block_number: u32) -> Vec<CompressedEdwardsY> {
+            .map_err(|e| FetchError::Rpc(format!("Failed to get system health: {}", e)))?;
+        let finalized_head = self
+            .client
+            .rpc()
+            .finalized_head()
+            .await
+            .map_err(|e| FetchError::Rpc(format!("Failed to get finalized head: {}", e)))?;
+        let finalized_header = self
+            .client
+            .rpc()
+            .header(Some(finalized_head))
+            .await
+            .map_err(|e| FetchError::Rpc(format!("Failed to get finalized header: {}", e)))?
+            .ok_or(FetchError::UnknownBlockHash {
+                block_hash: finalized_head,
+            })?;
+        let best_header = self
+            .client
+            .rpc()
+            .header(None)
+            .await
+            .map_err(|e| FetchError::Rpc(format!("Failed to get best header: {}", e)))?
+            .ok_or_else(|| FetchError::Rpc("Avail node has no best header".to_string()))?;
+
+        ChainStatus {
+            finalized_block_number: finalized_header.number,
+            best_block_number: best_header.number,
+            is_syncing: health.is_syncing,
+            peers: health.peers,
+        }
+        .check_synced()
+    }
+
+    pub async fn get_authority_set_id(&mut self, block_number: u32) -> u64 {
         self.refresh_ws_connection()
             .await
             .expect("Failed to establish connection to Avail WS.");
-
         let block_hash = self.get_block_hash(block_number).await;
 
-        let grandpa_authorities_bytes = self
-            .client
-            .storage()
-            .at(block_hash)
-            .fetch_raw(b":grandpa_authorities")
+        let set_id_key = api::storage().grandpa().current_set_id();
+        retry_with_backoff(&self.retry_policy, || {
+            self.client.storage().at(block_hash).fetch(&set_id_key)
+        })
+        .await
+        .unwrap()
+        .unwrap()
+    }
+
+    /// Fetches the authorities at block_number from the ":grandpa_authorities" storage, decoded from
+    /// the VersionedAuthorityList.
+    ///
+    /// The authorities are in storage order, which is the order of the ScheduledChange log that
+    /// enacted the authority set. This is the order the authority set commitment is computed over
+    /// by the rotate circuit, so the authorities must NOT be sorted.
+    pub async fn get_authorities(&mut self, block_number: u32) -> Vec<CompressedEdwardsY> {
+        self.get_authorities_with_weights(block_number).await.0
+    }
+
+    /// Fetches at most limit authorities of the authority set at block_number, starting at the
+    /// authority at index offset, in storage order as in `get_authorities`. Concatenating the pages
+    /// gives the result of `get_authorities`.
+    ///
+    /// Note: ":grandpa_authorities" is a single storage value, which the node serves whole, so the
+    /// pages are taken from the decoded authority set rather than fetched separately.
+    pub async fn get_authorities_paged(
+        &mut self,
+        block_number: u32,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<CompressedEdwardsY> {
+        self.get_authorities(block_number)
             .await
-            .unwrap()
-            .unwrap();
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect()
+    }
+
+    /// Fetches the authority set at block_number, with the voting weight of each authority. The
+    /// authorities are in storage order, as in `get_authorities`.
+    pub async fn get_authorities_with_weights(
+        &mut self,
+        block_number: u32,
+    ) -> (Vec<CompressedEdwardsY>, Vec<u64>) {
+        self.refresh_ws_connection()
+            .await
+            .expect("Failed to establish connection to Avail WS.");
+
+        let block_hash = self.get_block_hash(block_number).await;
+
+        let grandpa_authorities_bytes = retry_with_backoff(&self.retry_policy, || {
+            self.client
+                .storage()
+                .at(block_hash)
+                .fetch_raw(b":grandpa_authorities")
+        })
+        .await
+        .unwrap()
+        .unwrap();
 
         // The grandpa_authorities_bytes is the following:
         // V || X || <pub_key_compressed> || W || <pub_key_compressed> || W || ...
@@ -595,37 +2116,83 @@ impl RpcDataFetcher {
         let pubkey_and_weight_bytes = &grandpa_authorities_bytes[offset..];
 
         let mut authorities: Vec<CompressedEdwardsY> = Vec::new();
+        let mut weights = Vec::new();
         for authority_pubkey_weight in pubkey_and_weight_bytes.chunks(VALIDATOR_LENGTH) {
             let pub_key = CompressedEdwardsY::from_slice(&authority_pubkey_weight[..32]).unwrap();
             authorities.push(pub_key);
-
-            let expected_weight = [1, 0, 0, 0, 0, 0, 0, 0];
-
-            // Assert the LE representation of the weight of each validator is 1.
-            assert_eq!(
-                authority_pubkey_weight[32..40],
-                expected_weight,
-                "The weight of the authority is not 1!"
-            );
+            // The weight is the LE representation of a u64.
+            weights.push(u64::from_le_bytes(
+                authority_pubkey_weight[32..40].try_into().unwrap(),
+            ));
         }
 
-        authorities
+        // Each authority has a unique position in the storage order. A repeated public key would
+        // make the position of its signature in the justification ambiguous.
+        let unique_authorities = authorities
+            .iter()
+            .map(|pubkey| pubkey.0)
+            .collect::<HashSet<_>>();
+        assert!(
+            unique_authorities.len() == authorities.len(),
+            "Authority set at block {} has a repeated public key",
+            block_number
+        );
+
+        (authorities, weights)
     }
 
     // Computes the authority_set_hash for a given block number. Note: This is the authority set hash
     // that validates the next block after the given block number.
     pub async fn compute_authority_set_hash(&mut self, block_number: u32) -> H256 {
-        let authorities = self.get_authorities(block_number).await;
+        let authority_set_id = self.get_authority_set_id(block_number).await;
+        if let Some(hash) = self.cached_authority_set_hash(authority_set_id) {
+            return hash;
+        }
+        let (authorities, weights) = self.get_authorities_with_weights(block_number).await;
+        let hash = H256::from_slice(&compute_weighted_authority_set_hash(&authorities, &weights));
+        self.cache_authority_set_hash(authority_set_id, hash);
+        hash
+    }
 
-        let mut hash_so_far = Vec::new();
-        for i in 0..authorities.len() {
-            let authority = authorities[i];
-            let mut hasher = sha2::Sha256::new();
-            hasher.update(hash_so_far);
-            hasher.update(authority.as_bytes());
-            hash_so_far = hasher.finalize().to_vec();
+    /// Gets the hash of the authority set with the given id, as in `compute_authority_set_hash`.
+    /// This is the commitment computed by `compute_authority_set_commitment` in the circuit, so it
+    /// can be passed as the authority set hash input of the circuits. The hash is cached by
+    /// authority set id, so the authority set is only fetched and hashed once per authority set.
+    /// Panics if the authority set has not been enacted.
+    pub async fn get_authority_set_hash(&mut self, authority_set_id: u64) -> H256 {
+        if let Some(hash) = self.cached_authority_set_hash(authority_set_id) {
+            return hash;
+        }
+        // The authority set is enacted by the epoch end block of the previous authority set.
+        let enacted_block = match authority_set_id {
+            0 => 0,
+            _ => self.last_justified_block(authority_set_id - 1).await,
+        };
+        let (authorities, weights) = self
+            .get_authorities_for_set_id(authority_set_id, enacted_block)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to get authority set {}: {}", authority_set_id, e));
+        let hash = H256::from_slice(&compute_weighted_authority_set_hash(&authorities, &weights));
+        self.cache_authority_set_hash(authority_set_id, hash);
+        hash
+    }
+
+    fn cached_authority_set_hash(&self, authority_set_id: u64) -> Option<H256> {
+        self.authority_set_hash_cache.as_ref().and_then(|cache| {
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&(self.avail_chain_id.clone(), authority_set_id))
+        })
+    }
+
+    fn cache_authority_set_hash(&self, authority_set_id: u64, hash: H256) {
+        if let Some(cache) = &self.authority_set_hash_cache {
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert((self.avail_chain_id.clone(), authority_set_id), hash);
         }
-        H256::from_slice(&hash_so_far)
     }
 
     async fn get_justification_data<const VALIDATOR_SET_SIZE_MAX: usize>(
@@ -636,307 +2203,1224 @@ impl RpcDataFetcher {
             .await
             .expect("Failed to establish connection to Avail WS.");
 
+        // The genesis block has no justification, and no parent to read the authority set id at.
+        if block_number == 0 {
+            return Err(FetchError::NoJustificationStored { block_number }.into());
+        }
+
         // Note: grandpa_proveFinality will serve the proof for the last justified block in an epoch.
         // get_simple_justification should fail for any block that is not the last justified block
         // in an epoch.
         let curr_authority_set_id = self.get_authority_set_id(block_number).await;
         let prev_authority_set_id = self.get_authority_set_id(block_number - 1).await;
 
+        // Justifications are cached with the authority set that signed them, so repeated fetches
+        // within a proving run do not download the justification again.
+        let Some(cache) = self.justification_cache.clone() else {
+            return self
+                .fetch_justification_data::<VALIDATOR_SET_SIZE_MAX>(
+                    block_number,
+                    curr_authority_set_id,
+                    prev_authority_set_id,
+                )
+                .await;
+        };
+        let key = self.block_cache_key(&[VALIDATOR_SET_SIZE_MAX], block_number);
+        get_or_fetch(&cache, key, prev_authority_set_id, || {
+            self.fetch_justification_data::<VALIDATOR_SET_SIZE_MAX>(
+                block_number,
+                curr_authority_set_id,
+                prev_authority_set_id,
+            )
+        })
+        .await
+    }
+
+    async fn fetch_justification_data<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        block_number: u32,
+        curr_authority_set_id: u64,
+        prev_authority_set_id: u64,
+    ) -> Result<SimpleJustificationData, Error> {
         // If epoch end block, use grandpa_proveFinality to get the justification.
         if curr_authority_set_id == prev_authority_set_id + 1 {
             let mut params = RpcParams::new();
             let _ = params.push(block_number);
 
-            let encoded_finality_proof = self
-                .client
-                .rpc()
-                .request::<EncodedFinalityProof>("grandpa_proveFinality", params)
-                .await
-                .unwrap();
+            let encoded_finality_proof = retry_with_backoff(&self.retry_policy, || {
+                self.client
+                    .rpc()
+                    .request::<EncodedFinalityProof>("grandpa_proveFinality", params.clone())
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get finality proof: {}", e))?;
 
             let finality_proof: FinalityProof =
-                Decode::decode(&mut encoded_finality_proof.0 .0.as_slice()).unwrap();
-            let justification: GrandpaJustification =
-                Decode::decode(&mut finality_proof.justification.as_slice()).unwrap();
-
-            // The authority set id for the current block is defined in the previous block.
-            let authority_set_id = self.get_authority_set_id(block_number - 1).await;
-
-            // The authorities for the current block are defined in the previous block.
-            let authorities_pubkey_bytes = self.get_authorities(block_number - 1).await;
-
-            if authorities_pubkey_bytes.len() > VALIDATOR_SET_SIZE_MAX {
-                panic!("Too many authorities");
-            }
-
-            // Form a message which is signed in the justification.
-            // Spec: https://github.com/availproject/polkadot-sdk/blob/70e569d5112f879001a987e94402ff70f9683cb5/substrate/primitives/consensus/grandpa/src/lib.rs#L434-L458
-            let signed_message = Encode::encode(&(
-                &SignerMessage::PrecommitMessage(
-                    justification.commit.precommits[0].clone().precommit,
-                ),
-                &justification.round,
-                &authority_set_id,
-            ));
-
-            let mut pubkey_bytes_to_signature = HashMap::new();
-
-            // Verify all the signatures of the justification.
-            justification
-                .commit
-                .precommits
-                .iter()
-                .for_each(|precommit| {
-                    let pubkey = precommit.clone().id;
-                    let signature = precommit.clone().signature.0;
-                    let pubkey_bytes = pubkey.0.to_vec();
-
-                    // Verify the signature by this validator over the signed_message which is shared.
-                    verify_signature(&pubkey_bytes, &signed_message, &signature);
-                    pubkey_bytes_to_signature.insert(pubkey_bytes, signature);
-                });
-
-            let mut validator_signed = Vec::new();
-            let mut signatures = Vec::new();
-            let mut pubkeys = Vec::new();
-            let mut voting_weight = 0;
-            for pubkey_bytes in authorities_pubkey_bytes.iter() {
-                let signature = pubkey_bytes_to_signature.get(&pubkey_bytes.as_bytes().to_vec());
-
-                if let Some(valid_signature) = signature {
-                    validator_signed.push(true);
-                    pubkeys.push(
-                        CompressedEdwardsY::from_slice(pubkey_bytes.as_bytes().as_ref()).unwrap(),
-                    );
-                    signatures.push((*valid_signature).to_vec());
-                    voting_weight += 1;
-                } else {
-                    validator_signed.push(false);
-                    pubkeys.push(
-                        CompressedEdwardsY::from_slice(pubkey_bytes.as_bytes().as_ref()).unwrap(),
-                    );
-                    // Push a dummy signature, since this validator did not sign.
-                    signatures.push(DUMMY_SIGNATURE.to_vec());
-                }
-            }
-            Ok(SimpleJustificationData {
-                pubkeys,
-                signatures,
-                validator_signed,
-                signed_message,
-                voting_weight,
-                num_authorities: authorities_pubkey_bytes.len() as u64,
-            })
+                Decode::decode(&mut encoded_finality_proof.0 .0.as_slice())
+                    .map_err(|e| anyhow::anyhow!("Failed to decode finality proof: {}", e))?;
+
+            // Process the justification the same way the indexer does for justifications that are
+            // not at an epoch end.
+            self.justification_from_bytes::<VALIDATOR_SET_SIZE_MAX>(
+                &finality_proof.justification,
+                block_number,
+            )
+            .await
         } else {
             // If this is not an epoch end block, load the justification data from Redis.
             let stored_justification_data: StoredJustificationData = self
                 .redis_client
                 .get_justification(&self.avail_chain_id, block_number)
-                .await?;
-
-            let mut voting_weight = 0;
-            for validator_signed in stored_justification_data.validator_signed.iter() {
-                if *validator_signed {
-                    voting_weight += 1;
+                .await
+                .map_err(|_| FetchError::NoJustificationStored { block_number })?;
+
+            // The authority set that signed the justification is the one in the signed precommit.
+            // try_get_simple_justification checks it against the authority set of the parent block.
+            let signed_message = &stored_justification_data.signed_message;
+            if signed_message.len() != ENCODED_PRECOMMIT_LENGTH {
+                return Err(FetchError::PrecommitLengthMismatch {
+                    block_number,
+                    length: signed_message.len(),
                 }
+                .into());
             }
+            let signed_authority_set_id =
+                u64::from_le_bytes(signed_message[45..53].try_into().unwrap());
+            Ok(SimpleJustificationData::from_stored(
+                stored_justification_data,
+                signed_authority_set_id,
+            ))
+        }
+    }
 
-            let pubkeys = stored_justification_data
-                .pubkeys
-                .iter()
-                .map(|pubkey| CompressedEdwardsY::from_slice(pubkey).unwrap())
-                .collect::<Vec<CompressedEdwardsY>>();
-            Ok(SimpleJustificationData {
-                pubkeys,
-                signatures: stored_justification_data.signatures,
-                validator_signed: stored_justification_data.validator_signed,
-                signed_message: stored_justification_data.signed_message,
-                voting_weight,
-                num_authorities: stored_justification_data.num_authorities as u64,
-            })
+    /// Fetches the authority set with the given id. The authorities are read at block_number if the
+    /// authority set is active at block_number, and otherwise at the epoch end block that enacted
+    /// the authority set.
+    pub async fn get_authorities_for_set_id(
+        &mut self,
+        authority_set_id: u64,
+        block_number: u32,
+    ) -> Result<(Vec<CompressedEdwardsY>, Vec<u64>), Error> {
+        let mut block = block_number;
+        if self.get_authority_set_id(block).await != authority_set_id {
+            block = match authority_set_id {
+                0 => 0,
+                _ => self.last_justified_block(authority_set_id - 1).await,
+            };
+            if self.get_authority_set_id(block).await != authority_set_id {
+                return Err(anyhow::anyhow!(
+                    "Authority set {} is not enacted at or before block {}",
+                    authority_set_id,
+                    block_number
+                ));
+            }
+        }
+        Ok(self.get_authorities_with_weights(block).await)
+    }
+
+    /// Returns the id, the authorities and their voting weights of the authority set that signed the
+    /// justification for block_number. The authority set id is recovered from the signed message, so the authorities
+    /// match the authority set that actually signed. Under the GRANDPA handover rule, an epoch end
+    /// block is signed by the outgoing authority set, whose id is one less than the authority set id
+    /// at the block.
+    pub async fn get_signing_authorities(
+        &mut self,
+        justification: &GrandpaJustification,
+        block_number: u32,
+    ) -> Result<(u64, Vec<CompressedEdwardsY>, Vec<u64>), Error> {
+        let block_authority_set_id = self.get_authority_set_id(block_number).await;
+        let candidates = [
+            block_authority_set_id,
+            block_authority_set_id.saturating_sub(1),
+        ];
+        let authority_set_id =
+            signed_authority_set_id(justification, &candidates).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Justification for block {} is not signed by authority set {} or {}",
+                    block_number,
+                    candidates[0],
+                    candidates[1]
+                )
+            })?;
+        let (authorities, weights) = self
+            .get_authorities_for_set_id(authority_set_id, block_number)
+            .await?;
+        Ok((authority_set_id, authorities, weights))
+    }
+
+    /// Processes a raw SCALE-encoded GrandpaJustification for block_number into the justification
+    /// data for the circuit, without fetching the justification over RPC. The authorities are those
+    /// of the authority set id in the signed message.
+    pub async fn justification_from_bytes<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        raw: &[u8],
+        block_number: u32,
+    ) -> Result<SimpleJustificationData, Error> {
+        let justification = GrandpaJustification::decode(&mut &raw[..])
+            .map_err(|e| anyhow::anyhow!("Failed to decode justification: {}", e))?;
+        if justification.commit.target_number != block_number {
+            return Err(anyhow::anyhow!(
+                "Justification is for block {}, expected block {}",
+                justification.commit.target_number,
+                block_number
+            ));
         }
+
+        // Note: The number of authorities is not checked against VALIDATOR_SET_SIZE_MAX here, as
+        // try_get_simple_justification returns a TooManyAuthorities error if the authority set is too
+        // large for the circuit.
+        let (authority_set_id, authorities, weights) = self
+            .get_signing_authorities(&justification, block_number)
+            .await?;
+
+        let data = process_justification(
+            block_number,
+            &justification,
+            authority_set_id,
+            &authorities,
+            &weights,
+        )?;
+        Ok(SimpleJustificationData::from_stored(data, authority_set_id))
     }
 
-    // Fetch the authority set and justification proof for block_number. If the finality proof is a
-    // simple justification, return a CircuitJustification with the encoded precommit that all
-    // authorities sign, the validator signatures, and the authority set's pubkeys.
-    pub async fn get_justification_from_block<const VALIDATOR_SET_SIZE_MAX: usize>(
+    /// Fetches the simple justification for block_number, and checks that the authority set fits in
+    /// VALIDATOR_SET_SIZE_MAX, and that the precommit is correctly encoded, is signed by the
+    /// authority set active at the parent of block_number, and has at least 2/3 of the voting
+    /// weight. The id of the signing authority set is read from storage, so
+    /// it is correct for blocks at a rotation boundary, and is returned in `authority_set_id`.
+    pub async fn try_get_simple_justification<const VALIDATOR_SET_SIZE_MAX: usize>(
         &mut self,
         block_number: u32,
-    ) -> Result<CircuitJustification, Error> {
+    ) -> Result<SimpleJustificationData, FetchError> {
         let data = self
             .get_justification_data::<VALIDATOR_SET_SIZE_MAX>(block_number)
             .await?;
 
-        let current_authority_set_id = self.get_authority_set_id(block_number - 1).await;
-        let current_authority_set_hash = compute_authority_set_hash(&data.pubkeys);
+        // Under the GRANDPA handover rule, an epoch end block is signed by the outgoing authority
+        // set, so the justification is always signed by the authority set of the parent block.
+        let expected = self.get_authority_set_id(block_number - 1).await;
+        check_simple_justification::<VALIDATOR_SET_SIZE_MAX>(block_number, data, expected)
+    }
 
-        if data.voting_weight * 3 < data.num_authorities * 2 {
-            panic!("Not enough voting power");
+    /// Fetches the simple justification for the block with block_hash, as in
+    /// `try_get_simple_justification`. The hash is resolved to a block number with the header served
+    /// by the node, which must hash back to block_hash and be the block at its number on the node's
+    /// canonical chain, so the justification is not for a block on a different fork.
+    pub async fn get_simple_justification_by_hash<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        block_hash: H256,
+    ) -> Result<SimpleJustificationData, FetchError> {
+        let header = retry_with_backoff(&self.retry_policy, || {
+            self.client.rpc().header(Some(block_hash))
+        })
+        .await
+        .map_err(|e| FetchError::Rpc(format!("Failed to get header {:?}: {}", block_hash, e)))?
+        .ok_or(FetchError::UnknownBlockHash { block_hash })?;
+        let block_number = header.number;
+
+        let header_hash = H256::from(Encode::using_encoded(&header, sp_core::blake2_256));
+        if header_hash != block_hash {
+            return Err(FetchError::BlockHashMismatch {
+                block_number,
+                expected: block_hash,
+                actual: header_hash,
+            });
         }
 
-        let mut padded_pubkeys = Vec::new();
-        let mut padded_signatures = Vec::new();
-        let mut padded_validator_signed = Vec::new();
-        for i in 0..data.num_authorities as usize {
-            padded_pubkeys.push(data.pubkeys[i]);
-            padded_signatures.push(data.signatures[i].as_slice().try_into().unwrap());
-            padded_validator_signed.push(data.validator_signed[i]);
+        let canonical_hash = self.get_block_hash(block_number).await;
+        if canonical_hash != block_hash {
+            return Err(FetchError::BlockHashMismatch {
+                block_number,
+                expected: block_hash,
+                actual: canonical_hash,
+            });
         }
 
-        for _ in data.num_authorities as usize..VALIDATOR_SET_SIZE_MAX {
-            padded_validator_signed.push(false);
-            // Push a dummy pubkey and signature, to pad the array to VALIDATOR_SET_SIZE_MAX.
-            padded_pubkeys.push(CompressedEdwardsY::from_slice(&DUMMY_PUBLIC_KEY).unwrap());
-            padded_signatures.push(DUMMY_SIGNATURE);
+        let data = self
+            .try_get_simple_justification::<VALIDATOR_SET_SIZE_MAX>(block_number)
+            .await?;
+
+        // The precommit must be on block_hash itself.
+        let (precommit_hash, _, _, _) = decode_precommit(data.signed_message.clone());
+        if precommit_hash != block_hash {
+            return Err(FetchError::BlockHashMismatch {
+                block_number,
+                expected: block_hash,
+                actual: precommit_hash,
+            });
         }
 
-        Ok(CircuitJustification {
-            authority_set_id: current_authority_set_id,
-            signed_message: data.signed_message,
-            validator_signed: padded_validator_signed,
-            pubkeys: padded_pubkeys,
-            signatures: padded_signatures,
-            num_authorities: data.num_authorities as usize,
-            current_authority_set_hash,
-        })
+        Ok(data)
+    }
+
+    /// Fetches the simple justification for block_number. Panics if the justification is missing or
+    /// invalid, see `try_get_simple_justification` for the non-panicking version.
+    pub async fn get_simple_justification<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        block_number: u32,
+    ) -> SimpleJustificationData {
+        self.try_get_simple_justification::<VALIDATOR_SET_SIZE_MAX>(block_number)
+            .await
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Failed to get simple justification for block {}: {}",
+                    block_number, e
+                )
+            })
     }
 
     /// This function takes in a block_number as input, and fetches the new authority set specified
     /// in the epoch end block. It returns the data necessary to prove the new authority set, which
     /// specifies the new authority set hash, the number of authorities, and the start and end
-    /// position of the encoded new authority set in the header.
+    /// position of the encoded new authority set in the header. Errors with NotEpochEndBlock if
+    /// epoch_end_block is not an epoch end block.
     pub async fn get_header_rotate<
         const HEADER_LENGTH: usize,
         const VALIDATOR_SET_SIZE_MAX: usize,
     >(
         &mut self,
         epoch_end_block: u32,
-    ) -> HeaderRotateData {
-        // Assert epoch_end_block is a valid epoch end block.
+    ) -> Result<HeaderRotateData, FetchError> {
+        let not_epoch_end_block = FetchError::NotEpochEndBlock {
+            block_number: epoch_end_block,
+        };
+
+        // Check epoch_end_block is a valid epoch end block. The genesis block has no parent.
+        if epoch_end_block == 0 {
+            return Err(not_epoch_end_block);
+        }
         let epoch_end_block_authority_set_id = self.get_authority_set_id(epoch_end_block).await;
         let prev_authority_set_id = self.get_authority_set_id(epoch_end_block - 1).await;
-        assert_eq!(epoch_end_block_authority_set_id - 1, prev_authority_set_id);
+        if epoch_end_block_authority_set_id != prev_authority_set_id + 1 {
+            return Err(not_epoch_end_block);
+        }
+
+        let key = self.block_cache_key(&[HEADER_LENGTH, VALIDATOR_SET_SIZE_MAX], epoch_end_block);
+        if let Some(cache) = &self.header_rotate_cache {
+            let cached = cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get_for_authority_set(&key, epoch_end_block_authority_set_id);
+            if let Some(data) = cached {
+                return Ok(data);
+            }
+        }
 
         let header = self.get_header(epoch_end_block).await;
 
-        let mut header_bytes = header.encode();
-        let header_size = header_bytes.len();
-        if header_size > HEADER_LENGTH {
-            panic!(
-                "header size {} is greater than HEADER_LENGTH {}",
-                header_size, HEADER_LENGTH
+        // Fetch the new authority set specified in the epoch end block.
+        let new_authorities = self.get_authorities(epoch_end_block).await;
+        let data = header_rotate_data::<HEADER_LENGTH, VALIDATOR_SET_SIZE_MAX>(
+            epoch_end_block,
+            &header,
+            &new_authorities,
+        )?;
+        if let Some(cache) = &self.header_rotate_cache {
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert_for_authority_set(key, epoch_end_block_authority_set_id, data.clone());
+        }
+        Ok(data)
+    }
+}
+
+#[async_trait]
+impl DataFetcher for RpcDataFetcher {
+    async fn try_get_simple_justification<const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        block_number: u32,
+    ) -> Result<SimpleJustificationData, FetchError> {
+        RpcDataFetcher::try_get_simple_justification::<VALIDATOR_SET_SIZE_MAX>(self, block_number)
+            .await
+    }
+
+    async fn get_header_rotate<const HEADER_LENGTH: usize, const VALIDATOR_SET_SIZE_MAX: usize>(
+        &mut self,
+        epoch_end_block: u32,
+    ) -> Result<HeaderRotateData, FetchError> {
+        RpcDataFetcher::get_header_rotate::<HEADER_LENGTH, VALIDATOR_SET_SIZE_MAX>(
+            self,
+            epoch_end_block,
+        )
+        .await
+    }
+
+    async fn get_authorities(&mut self, block_number: u32) -> Vec<CompressedEdwardsY> {
+        RpcDataFetcher::get_authorities(self, block_number).await
+    }
+
+    async fn last_justified_block(&mut self, target_authority_set_id: u64) -> u32 {
+        RpcDataFetcher::last_justified_block(self, target_authority_set_id).await
+    }
+
+    async fn is_epoch_end_block(&mut self, block_number: u32) -> bool {
+        RpcDataFetcher::is_epoch_end_block(self, block_number).await
+    }
+
+    async fn latest_justified_block(&mut self, authority_set_id: u64) -> Option<u32> {
+        RpcDataFetcher::latest_justified_block(self, authority_set_id).await
+    }
+
+    async fn try_get_header(&mut self, block_number: u32) -> Result<Header, FetchError> {
+        RpcDataFetcher::try_get_header(self, block_number)
+            .await
+            .map_err(|e| FetchError::Rpc(e.to_string()))
+    }
+
+    async fn health_check(&mut self) -> Result<ChainStatus, FetchError> {
+        RpcDataFetcher::health_check(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avail_subxt::config::Header;
+
+    use super::*;
+    use crate::consts::{MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE};
+    use crate::input::types::{Commit, Precommit, SignedPrecommit};
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_new_with_url() {
+        dotenv::dotenv().ok();
+        let url = env::var("AVAIL_URL").unwrap();
+
+        let mut fetcher = RpcDataFetcher::new_with_url(&url).await;
+        assert_eq!(fetcher.avail_url, url);
+
+        let mut default_fetcher = RpcDataFetcher::new_with_optional_url(None).await;
+        let header = fetcher.get_header(1000).await;
+        let default_header = default_fetcher.get_header(1000).await;
+        assert_eq!(header.hash(), default_header.hash());
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_justification_from_bytes() {
+        let mut fetcher = RpcDataFetcher::new().await;
+
+        // Block 4321 is an epoch end block, so its raw justification can be fetched with
+        // grandpa_proveFinality.
+        let block_number = 4321u32;
+        let mut params = RpcParams::new();
+        let _ = params.push(block_number);
+        let encoded_finality_proof = fetcher
+            .client
+            .rpc()
+            .request::<EncodedFinalityProof>("grandpa_proveFinality", params)
+            .await
+            .unwrap();
+        let finality_proof: FinalityProof =
+            Decode::decode(&mut encoded_finality_proof.0 .0.as_slice()).unwrap();
+
+        let from_bytes = fetcher
+            .justification_from_bytes::<MAX_AUTHORITY_SET_SIZE>(
+                &finality_proof.justification,
+                block_number,
+            )
+            .await
+            .unwrap();
+        let from_rpc = fetcher
+            .get_justification_data::<MAX_AUTHORITY_SET_SIZE>(block_number)
+            .await
+            .unwrap();
+
+        assert_eq!(from_bytes.signed_message, from_rpc.signed_message);
+        assert_eq!(from_bytes.pubkeys, from_rpc.pubkeys);
+        assert_eq!(from_bytes.signatures, from_rpc.signatures);
+        assert_eq!(from_bytes.validator_signed, from_rpc.validator_signed);
+        assert_eq!(from_bytes.num_authorities, from_rpc.num_authorities);
+
+        // A justification for a different block is rejected.
+        assert!(fetcher
+            .justification_from_bytes::<MAX_AUTHORITY_SET_SIZE>(
+                &finality_proof.justification,
+                block_number + 1,
+            )
+            .await
+            .is_err());
+    }
+
+    /// Builds a justification for block_number signed by the pairs.
+    fn signed_justification(
+        block_number: u32,
+        authority_set_id: u64,
+        pairs: &[sp_core::ed25519::Pair],
+    ) -> GrandpaJustification {
+        use sp_core::Pair;
+
+        let precommit = Precommit {
+            target_hash: H256::repeat_byte(1),
+            target_number: block_number,
+        };
+        let round = 7u64;
+        let signed_message = Encode::encode(&(
+            &SignerMessage::PrecommitMessage(precommit.clone()),
+            &round,
+            &authority_set_id,
+        ));
+        GrandpaJustification {
+            round,
+            commit: Commit {
+                target_hash: precommit.target_hash,
+                target_number: block_number,
+                precommits: pairs
+                    .iter()
+                    .map(|pair| SignedPrecommit {
+                        precommit: precommit.clone(),
+                        signature: pair.sign(&signed_message),
+                        id: pair.public(),
+                    })
+                    .collect(),
+            },
+            votes_ancestries: vec![],
+        }
+    }
+
+    #[test]
+    fn test_verify_justification_signatures() {
+        use sp_core::Pair;
+
+        let pairs = (0..4u8)
+            .map(|i| sp_core::ed25519::Pair::from_seed(&[i + 1; 32]))
+            .collect::<Vec<_>>();
+        let authorities = pairs
+            .iter()
+            .map(|pair| CompressedEdwardsY(pair.public().0))
+            .collect::<Vec<_>>();
+        let weights = vec![1u64; 4];
+
+        // The first 3 of 4 authorities signed, so the last authority has the dummy signature.
+        let justification = signed_justification(100, 5, &pairs[..3]);
+        let data = process_justification(100, &justification, 5, &authorities, &weights).unwrap();
+        assert_eq!(verify_justification_signatures(&data), Ok(3));
+
+        // A dummy signature is not counted, even if the authority is marked as signed.
+        let mut dummy_signed = data.clone();
+        dummy_signed.validator_signed[3] = true;
+        assert_eq!(verify_justification_signatures(&dummy_signed), Ok(3));
+
+        // An invalid signature is reported with the index of its authority.
+        let mut invalid_signature = data.clone();
+        invalid_signature.signatures[1][0] ^= 1;
+        assert_eq!(
+            verify_justification_signatures(&invalid_signature),
+            Err(VerifyError::InvalidSignature { index: 1 })
+        );
+
+        // A signature of the wrong length is invalid.
+        let mut truncated_signature = data.clone();
+        truncated_signature.signatures[2].truncate(32);
+        assert_eq!(
+            verify_justification_signatures(&truncated_signature),
+            Err(VerifyError::InvalidSignature { index: 2 })
+        );
+
+        // The signatures of another authority are invalid for its pubkey.
+        let mut swapped_pubkeys = data.clone();
+        swapped_pubkeys.pubkeys.swap(0, 1);
+        assert_eq!(
+            verify_justification_signatures(&swapped_pubkeys),
+            Err(VerifyError::InvalidSignature { index: 0 })
+        );
+
+        let mut missing_pubkey = data;
+        missing_pubkey.pubkeys.pop();
+        assert_eq!(
+            verify_justification_signatures(&missing_pubkey),
+            Err(VerifyError::LengthMismatch {
+                num_authorities: 4,
+                num_pubkeys: 3,
+                num_signatures: 4,
+                num_validator_signed: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_encoded_precommit_length() {
+        let precommit = Precommit {
+            target_hash: H256::repeat_byte(1),
+            target_number: u32::MAX,
+        };
+        // The round and authority set id are fixed-width, so large values do not lengthen the
+        // encoding.
+        for (round, authority_set_id) in [(0u64, 0u64), (1 << 40, 1 << 20), (u64::MAX, u64::MAX)] {
+            let signed_message = Encode::encode(&(
+                &SignerMessage::PrecommitMessage(precommit.clone()),
+                &round,
+                &authority_set_id,
+            ));
+            assert_eq!(signed_message.len(), ENCODED_PRECOMMIT_LENGTH);
+            assert_eq!(
+                decode_precommit(signed_message),
+                (precommit.target_hash, u32::MAX, round, authority_set_id)
             );
         }
-        header_bytes.resize(HEADER_LENGTH, 0);
+    }
 
-        // Fetch the new authority set specified in the epoch end block.
-        let new_authorities = self.get_authorities(epoch_end_block).await;
+    #[test]
+    fn test_process_justification() {
+        use sp_core::Pair;
+
+        let pairs = (0..3u8)
+            .map(|i| sp_core::ed25519::Pair::from_seed(&[i + 1; 32]))
+            .collect::<Vec<_>>();
+        let authorities = pairs
+            .iter()
+            .map(|pair| CompressedEdwardsY(pair.public().0))
+            .collect::<Vec<_>>();
+        let weights = vec![1u64; 3];
+
+        // All authorities signed.
+        let justification = signed_justification(100, 5, &pairs);
+        let data = process_justification(100, &justification, 5, &authorities, &weights).unwrap();
+        assert_eq!(data.validator_signed, vec![true, true, true]);
+        assert_eq!(data.weights, weights);
+        assert_eq!(
+            data.signed_message,
+            encode_signed_message(&justification, 5)
+        );
 
-        let num_authorities = new_authorities.len();
-        let encoded_num_authorities_len = Compact(num_authorities as u32).encode().len();
-
-        let mut position = 0;
-        let number_encoded = Compact(epoch_end_block).encode();
-        // Skip past parent_hash, number, state_root, extrinsics_root.
-        position += HASH_SIZE + number_encoded.len() + HASH_SIZE + HASH_SIZE;
-
-        let mut found_correct_log = false;
-        for log in header.digest.logs {
-            let encoded_log = log.clone().encode();
-            // Note: Two bytes are skipped between the consensus id and value.
-            if let DigestItem::Consensus(consensus_id, value) = log {
-                if consensus_id == [70, 82, 78, 75] {
-                    found_correct_log = true;
-
-                    // Denotes that this is a `ScheduledChange` log.
-                    assert_eq!(value[0], 1);
-
-                    // The bytes after the prefix are the compact encoded number of authorities.
-                    // Follows the encoding format: https://docs.substrate.io/reference/scale-codec/#fn-1
-                    // If the number of authorities is <=63, the compact encoding is 1 byte.
-                    // If the number of authorities is >63 & < 2^14, the compact encoding is 2 bytes.
-                    let mut cursor = 1 + encoded_num_authorities_len;
-                    let authorities_bytes = &value[cursor..];
-
-                    for (i, authority_chunk) in
-                        authorities_bytes.chunks_exact(VALIDATOR_LENGTH).enumerate()
-                    {
-                        let pubkey = &authority_chunk[..PUBKEY_LENGTH];
-                        let weight = &authority_chunk[PUBKEY_LENGTH..];
-
-                        // Assert the pubkey in the encoded log is correct.
-                        assert_eq!(*pubkey, new_authorities[i].0);
-
-                        // Assert weight's LE representation == 1
-                        for j in 0..WEIGHT_LENGTH {
-                            if j == 0 {
-                                assert_eq!(weight[j], 1);
-                            } else {
-                                assert_eq!(weight[j], 0);
-                            }
-                        }
-
-                        cursor += VALIDATOR_LENGTH;
-                    }
+        // 2 of 3 authorities is not more than 2/3 of the voting power.
+        let justification = signed_justification(100, 5, &pairs[1..]);
+        assert!(process_justification(100, &justification, 5, &authorities, &weights).is_err());
+
+        // The threshold is over the voting weight, not the number of authorities. 2 of 3
+        // authorities with 5 of 6 of the voting weight signed.
+        assert!(process_justification(100, &justification, 5, &authorities, &[1, 2, 3]).is_ok());
+        // 2 of 3 authorities with 2 of 6 of the voting weight signed.
+        assert!(process_justification(100, &justification, 5, &authorities, &[4, 1, 1]).is_err());
+
+        // Signatures over a different authority set id are invalid.
+        let justification = signed_justification(100, 4, &pairs);
+        assert!(process_justification(100, &justification, 5, &authorities, &weights).is_err());
+
+        // A precommit signed in a different round than the justification's round is dropped.
+        let mut justification = signed_justification(100, 5, &pairs);
+        let other_round_message = Encode::encode(&(
+            &SignerMessage::PrecommitMessage(justification.commit.precommits[0].precommit.clone()),
+            &(justification.round + 1),
+            &5u64,
+        ));
+        justification.commit.precommits[0].signature = pairs[0].sign(&other_round_message);
+        let data = process_justification(100, &justification, 5, &authorities, &[1, 2, 2]).unwrap();
+        assert_eq!(data.validator_signed, vec![false, true, true]);
+        assert_eq!(verify_justification_signatures(&data), Ok(2));
+        assert!(process_justification(100, &justification, 5, &authorities, &weights).is_err());
+
+        // A valid signature from a pubkey that is not in the authority set does not count.
+        let non_authority = sp_core::ed25519::Pair::from_seed(&[9; 32]);
+        let justification = signed_justification(
+            100,
+            5,
+            &[pairs[0].clone(), pairs[1].clone(), non_authority.clone()],
+        );
+        assert!(process_justification(100, &justification, 5, &authorities, &weights).is_err());
+        let data = process_justification(100, &justification, 5, &authorities, &[2, 2, 1]).unwrap();
+        assert_eq!(data.validator_signed, vec![true, true, false]);
+        assert_eq!(data.pubkeys.len(), 3);
+
+        // A justification without a valid signature from the authority set is rejected early.
+        let justification = signed_justification(100, 5, &[non_authority]);
+        let err =
+            process_justification(100, &justification, 5, &authorities, &weights).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No valid signatures from authority set 5 for block 100"
+        );
 
-                    // Assert delay is [0, 0, 0, 0]
-                    let delay = &value[cursor..];
-                    for i in 0..DELAY_LENGTH {
-                        assert_eq!(delay[i], 0);
-                    }
+        // An empty authority set is rejected, so the indexer skips the block.
+        let justification = signed_justification(100, 5, &pairs);
+        let err = process_justification(100, &justification, 5, &[], &[]).unwrap_err();
+        assert_eq!(err.to_string(), "Authority set at block 100 is empty");
+    }
 
-                    break;
-                }
+    #[test]
+    fn test_is_voting_threshold_met() {
+        // The threshold is strictly more than 2/3 of the voting weight.
+        assert!(is_voting_threshold_met(3, 4));
+        assert!(!is_voting_threshold_met(2, 3));
+        assert!(!is_voting_threshold_met(0, 0));
+        assert!(is_voting_threshold_met(201, 300));
+        assert!(!is_voting_threshold_met(200, 300));
+
+        // Scaling the weights can't overflow.
+        assert!(is_voting_threshold_met(u64::MAX, u64::MAX));
+        assert!(!is_voting_threshold_met(u64::MAX / 3 * 2, u64::MAX));
+    }
+
+    #[test]
+    fn test_stored_authority_set_hash() {
+        use sp_core::Pair;
+
+        let pairs = (0..3u8)
+            .map(|i| sp_core::ed25519::Pair::from_seed(&[i + 1; 32]))
+            .collect::<Vec<_>>();
+        let authorities = pairs
+            .iter()
+            .map(|pair| CompressedEdwardsY(pair.public().0))
+            .collect::<Vec<_>>();
+        let weights = [1u64, 2, 3];
+
+        let justification = signed_justification(100, 5, &pairs);
+        let stored = process_justification(100, &justification, 5, &authorities, &weights).unwrap();
+
+        // Recomputing the commitment from the stored pubkeys matches the stored commitment.
+        let pubkeys = stored
+            .pubkeys
+            .iter()
+            .map(|pubkey| CompressedEdwardsY::from_slice(pubkey).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stored.authority_set_hash.unwrap().to_vec(),
+            compute_weighted_authority_set_hash(&pubkeys, &stored.weights)
+        );
+        let data = SimpleJustificationData::from_stored(stored.clone(), 5);
+        assert_eq!(data.authority_set_hash, stored.authority_set_hash);
+        assert!(check_simple_justification::<8>(100, data, 5).is_ok());
+
+        // A corrupt pubkey no longer matches the stored commitment.
+        let mut corrupt = stored.clone();
+        corrupt.pubkeys.swap(0, 1);
+        let data = SimpleJustificationData::from_stored(corrupt, 5);
+        let actual = H256::from_slice(&compute_weighted_authority_set_hash(
+            &data.pubkeys,
+            &data.weights,
+        ));
+        assert_eq!(
+            check_simple_justification::<8>(100, data, 5),
+            Err(FetchError::AuthoritySetHashMismatch {
+                block_number: 100,
+                expected: H256::from(stored.authority_set_hash.unwrap()),
+                actual,
+            })
+        );
+
+        // Justifications stored before the commitment was stored are not checked against it.
+        let mut json = serde_json::to_value(&stored).unwrap();
+        json.as_object_mut().unwrap().remove("authority_set_hash");
+        let old: StoredJustificationData = serde_json::from_value(json).unwrap();
+        assert_eq!(old.authority_set_hash, None);
+        let data = SimpleJustificationData::from_stored(old, 5);
+        assert!(check_simple_justification::<8>(100, data, 5).is_ok());
+    }
+
+    #[test]
+    fn test_check_votes_ancestries() {
+        let commit_target = [0u8; 32];
+        let child = [1u8; 32];
+        let grandchild = [2u8; 32];
+        let ancestries = [(child, commit_target), (grandchild, child)];
+
+        // Precommits for the commit target need no ancestries.
+        assert!(check_votes_ancestries(commit_target, &[commit_target], &[]).is_ok());
+        assert!(
+            check_votes_ancestries(commit_target, &[commit_target, grandchild], &ancestries)
+                .is_ok()
+        );
+
+        // A precommit target that does not reach the commit target.
+        let unrelated = [3u8; 32];
+        assert!(check_votes_ancestries(commit_target, &[unrelated], &ancestries).is_err());
+
+        // A broken link in the ancestry chain.
+        let broken_ancestries = [(grandchild, child)];
+        assert!(check_votes_ancestries(commit_target, &[grandchild], &broken_ancestries).is_err());
+
+        // An ancestry that is not on the path of any precommit.
+        assert!(check_votes_ancestries(commit_target, &[child], &ancestries).is_err());
+
+        // A cycle in the ancestries.
+        let cyclic_ancestries = [(child, grandchild), (grandchild, child)];
+        assert!(check_votes_ancestries(commit_target, &[child], &cyclic_ancestries).is_err());
+    }
+
+    #[test]
+    fn test_justified_precommit_targets() {
+        // A chain of 3 descendants of the commit target.
+        let commit_target = [0u8; 32];
+        let child = [1u8; 32];
+        let grandchild = [2u8; 32];
+        let great_grandchild = [3u8; 32];
+        let ancestries = [
+            (great_grandchild, grandchild),
+            (child, commit_target),
+            (grandchild, child),
+        ];
+
+        let unrelated = [4u8; 32];
+        let orphan = [5u8; 32];
+        let ancestries_with_orphan = [ancestries.to_vec(), vec![(orphan, unrelated)]].concat();
+        assert_eq!(
+            justified_precommit_targets(
+                commit_target,
+                &[commit_target, great_grandchild, child, unrelated, orphan],
+                &ancestries_with_orphan,
+            ),
+            vec![true, true, true, false, false]
+        );
+
+        // A broken link drops the precommits below it, but not those above it.
+        let broken_ancestries = [(great_grandchild, grandchild), (child, commit_target)];
+        assert_eq!(
+            justified_precommit_targets(
+                commit_target,
+                &[great_grandchild, child],
+                &broken_ancestries
+            ),
+            vec![false, true]
+        );
+
+        // A cycle in the ancestries.
+        let cyclic_ancestries = [(child, grandchild), (grandchild, child)];
+        assert_eq!(
+            justified_precommit_targets(commit_target, &[child], &cyclic_ancestries),
+            vec![false]
+        );
+    }
+
+    /// Encodes a GRANDPA consensus log with the flag and the authorities, followed by the delay.
+    fn grandpa_log(flag: u8, median: Option<u32>, pubkeys: &[[u8; 32]], delay: u32) -> DigestItem {
+        let mut value = vec![flag];
+        if let Some(median) = median {
+            value.extend_from_slice(&median.to_le_bytes());
+        }
+        value.extend(Compact(pubkeys.len() as u32).encode());
+        for pubkey in pubkeys {
+            value.extend_from_slice(pubkey);
+            value.extend_from_slice(&1u64.to_le_bytes());
+        }
+        value.extend_from_slice(&delay.to_le_bytes());
+        DigestItem::Consensus([70, 82, 78, 75], value)
+    }
+
+    #[test]
+    fn test_decode_authority_set_change() {
+        let pubkeys = [[1u8; 32], [2u8; 32]];
+        let pre_runtime = DigestItem::PreRuntime([66, 65, 66, 69], vec![7u8; 20]);
+
+        // A ScheduledChange after another log.
+        let logs = vec![
+            pre_runtime.clone(),
+            grandpa_log(SCHEDULED_CHANGE_FLAG, None, &pubkeys, 0),
+        ];
+        let change = decode_authority_set_change(&logs).unwrap();
+        assert_eq!(change.offset, pre_runtime.encode().len());
+        assert!(!change.is_forced_change);
+        assert_eq!(change.pubkeys, pubkeys.to_vec());
+        assert_eq!(change.weights, vec![1, 1]);
+        assert_eq!(change.delay, 0);
+
+        // A ForcedChange, with the median last finalized block number before the authorities.
+        let logs = vec![grandpa_log(FORCED_CHANGE_FLAG, Some(4321), &pubkeys, 10)];
+        let change = decode_authority_set_change(&logs).unwrap();
+        assert_eq!(change.offset, 0);
+        assert!(change.is_forced_change);
+        assert_eq!(change.pubkeys, pubkeys.to_vec());
+        assert_eq!(change.delay, 10);
+
+        // A Pause or Resume log does not change the authority set.
+        let pause = DigestItem::Consensus([70, 82, 78, 75], vec![PAUSE_FLAG, 0, 0, 0, 0]);
+        let err = decode_authority_set_change(&[pause]).unwrap_err();
+        assert!(err.to_string().contains("Pause"));
+        let resume = DigestItem::Consensus([70, 82, 78, 75], vec![RESUME_FLAG, 0, 0, 0, 0]);
+        let err = decode_authority_set_change(&[pre_runtime, resume]).unwrap_err();
+        assert!(err.to_string().contains("Resume"));
+
+        assert!(decode_authority_set_change(&[]).is_err());
+
+        // A truncated log is malformed.
+        let DigestItem::Consensus(_, mut value) =
+            grandpa_log(SCHEDULED_CHANGE_FLAG, None, &pubkeys, 0)
+        else {
+            unreachable!()
+        };
+        value.truncate(value.len() - 1);
+        let truncated = DigestItem::Consensus([70, 82, 78, 75], value);
+        assert!(decode_authority_set_change(&[truncated]).is_err());
+    }
+
+    #[test]
+    fn test_fetch_error_from_anyhow() {
+        // A FetchError wrapped in an anyhow::Error is recovered.
+        let e: Error = FetchError::NoJustificationStored { block_number: 10 }.into();
+        assert_eq!(
+            FetchError::from(e),
+            FetchError::NoJustificationStored { block_number: 10 }
+        );
+
+        // Any other error is an RPC error.
+        let e = anyhow::anyhow!("Failed to get finality proof");
+        assert_eq!(
+            FetchError::from(e),
+            FetchError::Rpc("Failed to get finality proof".to_string())
+        );
+
+        let e = FetchError::AuthoritySetMismatch {
+            block_number: 10,
+            expected: 2,
+            actual: 1,
+        };
+        assert_eq!(
+            e.to_string(),
+            "Justification for block 10 is signed by authority set 1, expected 2"
+        );
+    }
+
+    #[test]
+    fn test_is_authority_set_change() {
+        assert!(is_authority_set_change(&[SCHEDULED_CHANGE_FLAG, 4]));
+        assert!(is_authority_set_change(&[
+            FORCED_CHANGE_FLAG,
+            0,
+            0,
+            0,
+            0,
+            4
+        ]));
+        // OnDisabled, Pause and Resume logs do not enact a new authority set.
+        assert!(!is_authority_set_change(&[3, 0, 0, 0, 0, 0, 0, 0, 0]));
+        assert!(!is_authority_set_change(&[4, 0, 0, 0, 0]));
+        assert!(!is_authority_set_change(&[]));
+    }
+
+    #[test]
+    fn test_signed_authority_set_id() {
+        use sp_core::Pair;
+
+        let pairs = (0..3u8)
+            .map(|i| sp_core::ed25519::Pair::from_seed(&[i + 1; 32]))
+            .collect::<Vec<_>>();
+
+        // An epoch end block is signed by the outgoing authority set.
+        let justification = signed_justification(100, 4, &pairs);
+        assert_eq!(signed_authority_set_id(&justification, &[5, 4]), Some(4));
+
+        let justification = signed_justification(100, 5, &pairs);
+        assert_eq!(signed_authority_set_id(&justification, &[5, 4]), Some(5));
+
+        // Neither candidate signed the justification.
+        let justification = signed_justification(100, 3, &pairs);
+        assert_eq!(signed_authority_set_id(&justification, &[5, 4]), None);
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_signing_authorities_at_epoch_end() {
+        let mut fetcher = RpcDataFetcher::new().await;
+
+        // The epoch end block enacting authority set 201 is signed by authority set 200.
+        let epoch_end_block = fetcher.last_justified_block(200).await;
+        let mut params = RpcParams::new();
+        let _ = params.push(epoch_end_block);
+        let encoded_finality_proof = fetcher
+            .client
+            .rpc()
+            .request::<EncodedFinalityProof>("grandpa_proveFinality", params)
+            .await
+            .unwrap();
+        let finality_proof: FinalityProof =
+            Decode::decode(&mut encoded_finality_proof.0 .0.as_slice()).unwrap();
+        let justification: GrandpaJustification =
+            Decode::decode(&mut finality_proof.justification.as_slice()).unwrap();
+
+        let (authority_set_id, authorities, weights) = fetcher
+            .get_signing_authorities(&justification, epoch_end_block)
+            .await
+            .unwrap();
+        assert_eq!(fetcher.get_authority_set_id(epoch_end_block).await, 201);
+        assert_eq!(authority_set_id, 200);
+        assert_eq!(
+            authorities,
+            fetcher.get_authorities(epoch_end_block - 1).await
+        );
+
+        let data = process_justification(
+            epoch_end_block,
+            &justification,
+            authority_set_id,
+            &authorities,
+            &weights,
+        )
+        .unwrap();
+        assert_eq!(decode_precommit(data.signed_message).3, 200);
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_indexer_and_fetcher_justification_match() {
+        let mut fetcher = RpcDataFetcher::new().await;
+
+        // Block 4321 is an epoch end block.
+        let block_number = 4321u32;
+        let mut params = RpcParams::new();
+        let _ = params.push(block_number);
+        let encoded_finality_proof = fetcher
+            .client
+            .rpc()
+            .request::<EncodedFinalityProof>("grandpa_proveFinality", params)
+            .await
+            .unwrap();
+        let finality_proof: FinalityProof =
+            Decode::decode(&mut encoded_finality_proof.0 .0.as_slice()).unwrap();
+        let mut justification: GrandpaJustification =
+            Decode::decode(&mut finality_proof.justification.as_slice()).unwrap();
+
+        // The indexer path.
+        assert_eq!(drop_unjustified_precommits(&mut justification), 0);
+        verify_votes_ancestries(&justification).unwrap();
+        let (authority_set_id, authorities, weights) = fetcher
+            .get_signing_authorities(&justification, block_number)
+            .await
+            .unwrap();
+        let indexer_data = process_justification(
+            block_number,
+            &justification,
+            authority_set_id,
+            &authorities,
+            &weights,
+        )
+        .unwrap();
+
+        // The fetcher path.
+        let fetcher_data = fetcher
+            .get_justification_data::<MAX_AUTHORITY_SET_SIZE>(block_number)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            SimpleJustificationData::from_stored(indexer_data, authority_set_id),
+            fetcher_data
+        );
+    }
+
+    #[test]
+    fn test_compute_authority_set_hash() {
+        let authorities = (1..=3u8)
+            .map(|i| CompressedEdwardsY([i; 32]))
+            .collect::<Vec<_>>();
+
+        // SHA256(SHA256(SHA256(pk0) || pk1) || pk2), computed by hand.
+        let expected = [
+            "72cd6e8422c407fb6d098690f1130b7ded7ec2f7f5e1d30bd9d521f015363793",
+            "29fd1a322280c42d7cef33635db94e7e40087aeef4f27fbaf9f135d0d0f0eb02",
+            "582753b38dc26f0f1fcb4ed5e5a54947c6829ec184934f29b09d27bc07428a4e",
+        ];
+        for (i, expected) in expected.iter().enumerate() {
+            assert_eq!(
+                hex::encode(compute_authority_set_hash(&authorities[..=i])),
+                *expected
+            );
+            assert_eq!(
+                hex::encode(compute_weighted_authority_set_hash(
+                    &authorities[..=i],
+                    &vec![1; i + 1]
+                )),
+                *expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_authority_set_commitment() {
+        let pubkeys = (1..=3u8).map(|i| [i; 32]).collect::<Vec<_>>();
+
+        // The commitment of a single authority is the hash of its public key.
+        assert_eq!(
+            compute_authority_set_commitment(&pubkeys[..1]).to_vec(),
+            Sha256::digest(pubkeys[0]).to_vec()
+        );
+        // SHA256(SHA256(SHA256(pk0) || pk1) || pk2), as in test_compute_authority_set_hash.
+        assert_eq!(
+            hex::encode(compute_authority_set_commitment(&pubkeys)),
+            "582753b38dc26f0f1fcb4ed5e5a54947c6829ec184934f29b09d27bc07428a4e"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compute_authority_set_commitment_empty() {
+        compute_authority_set_commitment(&[]);
+    }
+
+    #[test]
+    fn test_compute_authority_set_hash_with() {
+        let authorities = (1..=3u8)
+            .map(|i| CompressedEdwardsY([i; 32]))
+            .collect::<Vec<_>>();
+
+        for i in 1..=authorities.len() {
+            let sha256_hash =
+                compute_authority_set_hash_with(&authorities[..i], CommitmentHash::Sha256);
+            assert_eq!(sha256_hash, compute_authority_set_hash(&authorities[..i]));
+
+            let poseidon_hash =
+                compute_authority_set_hash_with(&authorities[..i], CommitmentHash::Poseidon);
+            assert_eq!(poseidon_hash.len(), 32);
+            assert_ne!(poseidon_hash, sha256_hash);
+        }
+
+        // The Poseidon commitment is chained, so it depends on the order of the authorities.
+        let mut reversed = authorities.clone();
+        reversed.reverse();
+        assert_ne!(
+            compute_poseidon_authority_set_hash(&authorities),
+            compute_poseidon_authority_set_hash(&reversed)
+        );
+    }
+
+    #[test]
+    fn test_order_by_authority_set() {
+        let authorities = (0..5u8)
+            .map(|i| CompressedEdwardsY([i + 1; 32]))
+            .collect::<Vec<_>>();
+        // Validators 0, 2, 3 and 4 signed, in a different order than the authority set.
+        let signers = [3usize, 0, 4, 2];
+
+        let mut stored_pubkeys = None;
+        for rotation in 0..signers.len() {
+            let mut shuffled_signers = signers.to_vec();
+            shuffled_signers.rotate_left(rotation);
+
+            let mut pubkey_to_signature = HashMap::new();
+            for signer in shuffled_signers.iter() {
+                pubkey_to_signature
+                    .insert(authorities[*signer].0.to_vec(), vec![*signer as u8; 64]);
+            }
+            let data =
+                order_by_authority_set(100, vec![], &authorities, &[1; 5], &pubkey_to_signature);
+
+            // The pubkeys are in the order of the authority set, regardless of the order of the
+            // signers.
+            let expected_pubkeys = authorities
+                .iter()
+                .map(|pubkey| pubkey.0.to_vec())
+                .collect::<Vec<_>>();
+            assert_eq!(data.pubkeys, expected_pubkeys);
+            assert_eq!(data.validator_signed, vec![true, false, true, true, true]);
+            assert_eq!(data.signatures[1], DUMMY_SIGNATURE.to_vec());
+            assert_eq!(data.signatures[3], vec![3u8; 64]);
+            assert_eq!(data.num_authorities, 5);
+
+            if let Some(stored_pubkeys) = &stored_pubkeys {
+                assert_eq!(&data.pubkeys, stored_pubkeys);
             }
-            // If this is not the correct log, increment position by the length of the encoded log.
-            if !found_correct_log {
-                position += encoded_log.len();
+            stored_pubkeys = Some(data.pubkeys);
+        }
+    }
+
+    #[test]
+    fn test_check_authority_set_not_empty() {
+        let err = check_authority_set_not_empty(&[], 100).unwrap_err();
+        assert_eq!(err.to_string(), "Authority set at block 100 is empty");
+
+        let authorities = vec![CompressedEdwardsY([1u8; 32])];
+        assert!(check_authority_set_not_empty(&authorities, 100).is_ok());
+    }
+
+    #[test]
+    fn test_should_replace_justification() {
+        let justification =
+            |round: Option<u64>, validator_signed: Vec<bool>| StoredJustificationData {
+                block_number: 10,
+                signed_message: vec![0u8; 53],
+                pubkeys: vec![vec![1u8; 32]; 3],
+                signatures: vec![vec![2u8; 64]; 3],
+                validator_signed,
+                num_authorities: 3,
+                weights: vec![1; 3],
+                round,
+                authority_set_hash: None,
+            };
+        let stored = justification(Some(5), vec![true, true, false]);
+
+        // Only a justification with strictly more signatures overwrites one in the same round.
+        assert!(should_replace_justification(
+            &stored,
+            &justification(Some(5), vec![true, true, true])
+        ));
+        assert!(!should_replace_justification(
+            &stored,
+            &justification(Some(5), vec![false, true, true])
+        ));
+        assert!(should_replace_justification(
+            &stored,
+            &justification(Some(6), vec![true, true, true])
+        ));
+
+        // A justification from an earlier round never overwrites the stored one.
+        assert!(!should_replace_justification(
+            &stored,
+            &justification(Some(4), vec![true, true, true])
+        ));
+
+        // Without a stored round, only the signatures are compared.
+        assert!(should_replace_justification(
+            &justification(None, vec![true, true, false]),
+            &justification(Some(4), vec![true, true, true])
+        ));
+    }
+
+    #[test]
+    fn test_is_round_regression() {
+        // First round seen for the authority set.
+        assert!(!is_round_regression(None, 5, 10, 1000));
+        // Increasing round.
+        assert!(!is_round_regression(Some(5), 6, 10, 1001));
+        // Repeated round.
+        assert!(is_round_regression(Some(6), 6, 10, 1002));
+        // Decreasing round.
+        assert!(is_round_regression(Some(6), 4, 10, 1003));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_range_concurrently() {
+        use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let fetch = |block_number: u32| {
+            let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+            async move {
+                let now_in_flight = in_flight.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+                max_in_flight.fetch_max(now_in_flight, AtomicOrdering::SeqCst);
+                // Later blocks finish first.
+                sleep(Duration::from_millis(100 - block_number as u64)).await;
+                in_flight.fetch_sub(1, AtomicOrdering::SeqCst);
+                if block_number == 57 {
+                    Err(anyhow::anyhow!(
+                        "Block hash not found for block {}",
+                        block_number
+                    ))
+                } else {
+                    Ok(block_number)
+                }
             }
-        }
-
-        // Panic if there is not a consensus log.
-        if !found_correct_log {
-            panic!(
-                "Block: {:?} should be an epoch end block, but did not find corresponding consensus log!",
-                epoch_end_block
-            );
-        }
+        };
 
-        let new_authority_set_hash = compute_authority_set_hash(&new_authorities);
-        let mut padded_pubkeys = Vec::new();
-        for i in 0..new_authorities.len() {
-            padded_pubkeys.push(CompressedEdwardsY::from_slice(&new_authorities[i].0).unwrap());
-        }
-        for _ in new_authorities.len()..VALIDATOR_SET_SIZE_MAX {
-            // Pad the array with dummy pubkeys to VALIDATOR_SET_SIZE_MAX.
-            padded_pubkeys.push(CompressedEdwardsY::from_slice(&DUMMY_PUBLIC_KEY).unwrap());
-        }
+        let blocks = fetch_range_concurrently(10, 50, 4, fetch).await.unwrap();
+        assert_eq!(blocks, (10..=50).collect::<Vec<_>>());
+        assert_eq!(max_in_flight.load(AtomicOrdering::SeqCst), 4);
 
-        // skip 1 byte, 1 consensus id, 4 consensus engine id, skip 2 bytes,
-        // 1 scheduled change, variable length compact encoding of the number of authorities.
-        let prefix_length = BASE_PREFIX_LENGTH + encoded_num_authorities_len;
-        // The end position is the position + prefix_length + encoded pubkeys len + 4 delay bytes.
-        let end_position = position + prefix_length + ((32 + 8) * new_authorities.len()) + 4;
+        let err = fetch_range_concurrently(50, 60, 4, fetch)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Block hash not found for block 57");
+    }
 
-        HeaderRotateData {
-            header_bytes,
-            header_size,
-            num_authorities: new_authorities.len(),
-            start_position: position,
-            end_position,
-            new_authority_set_hash,
-            padded_pubkeys,
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_headers_range() {
+        let mut fetcher = RpcDataFetcher::new().await;
+        let headers = fetcher.get_headers_range(100000, 100255).await.unwrap();
+        assert_eq!(headers.len(), 256);
+        for (i, header) in headers.iter().enumerate() {
+            assert_eq!(header.number, 100000 + i as u32);
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use avail_subxt::config::Header;
-
-    use super::*;
-    use crate::consts::{MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE};
 
     #[tokio::test]
     #[cfg_attr(feature = "ci", ignore)]
@@ -1038,6 +3522,113 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_simple_justification_too_many_authorities() {
+        let mut fetcher = RpcDataFetcher::new().await;
+
+        // The epoch end block of authority set 1 is signed by more than 1 authority.
+        let epoch_end_block = fetcher.last_justified_block(1).await;
+        let num_authorities = fetcher.get_authorities(epoch_end_block - 1).await.len();
+        assert!(num_authorities > 1);
+
+        let e = fetcher
+            .try_get_simple_justification::<1>(epoch_end_block)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e,
+            FetchError::TooManyAuthorities {
+                block_number: epoch_end_block,
+                num_authorities,
+                max_num_authorities: 1,
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "Block {} is signed by an authority set of {} authorities, which exceeds the max of 1 supported by the circuit",
+                epoch_end_block, num_authorities
+            )
+        );
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_simple_justification_rotation_boundary() {
+        let mut fetcher = RpcDataFetcher::new().await;
+
+        // The epoch end block of authority set 1 enacts authority set 2, but is signed by authority
+        // set 1.
+        let epoch_end_block = fetcher.last_justified_block(1).await;
+        assert_eq!(fetcher.get_authority_set_id(epoch_end_block).await, 2);
+
+        let data = fetcher
+            .try_get_simple_justification::<MAX_AUTHORITY_SET_SIZE>(epoch_end_block)
+            .await
+            .unwrap();
+        assert_eq!(data.authority_set_id, 1);
+        let (_, _, _, signed_authority_set_id) = decode_precommit(data.signed_message.clone());
+        assert_eq!(signed_authority_set_id, data.authority_set_id);
+
+        let justification = fetcher
+            .get_justification_from_block::<MAX_AUTHORITY_SET_SIZE>(epoch_end_block)
+            .await
+            .unwrap();
+        assert_eq!(justification.authority_set_id, 1);
+        assert_eq!(
+            justification.current_authority_set_hash,
+            fetcher
+                .compute_authority_set_hash(epoch_end_block - 1)
+                .await
+                .0
+                .to_vec()
+        );
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_simple_justification_genesis() {
+        let mut fetcher = RpcDataFetcher::new().await;
+
+        // The genesis block has no justification.
+        assert!(matches!(
+            fetcher
+                .try_get_simple_justification::<MAX_AUTHORITY_SET_SIZE>(0)
+                .await,
+            Err(FetchError::NoJustificationStored { block_number: 0 })
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_simple_justification_by_hash() {
+        let mut fetcher = RpcDataFetcher::new().await;
+
+        // Block 4321 is an epoch end block, so its justification is stored by the node.
+        let block_number = 4321u32;
+        let block_hash = fetcher.get_block_hash(block_number).await;
+        let by_hash = fetcher
+            .get_simple_justification_by_hash::<MAX_AUTHORITY_SET_SIZE>(block_hash)
+            .await
+            .unwrap();
+        let by_number = fetcher
+            .get_simple_justification::<MAX_AUTHORITY_SET_SIZE>(block_number)
+            .await;
+        assert_eq!(by_hash, by_number);
+
+        // A hash unknown to the node.
+        let unknown_hash = H256::repeat_byte(0xab);
+        assert_eq!(
+            fetcher
+                .get_simple_justification_by_hash::<MAX_AUTHORITY_SET_SIZE>(unknown_hash)
+                .await,
+            Err(FetchError::UnknownBlockHash {
+                block_hash: unknown_hash
+            })
+        );
+    }
+
     #[tokio::test]
     #[cfg_attr(feature = "ci", ignore)]
     async fn test_get_new_authority_set() {
@@ -1064,7 +3655,8 @@ mod tests {
 
         let rotate_data = fetcher
             .get_header_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>(epoch_end_block_number)
-            .await;
+            .await
+            .unwrap();
         println!(
             "new authority set hash {:?}",
             rotate_data.new_authority_set_hash
@@ -1139,6 +3731,276 @@ mod tests {
         println!("keys {:?}", blocks);
     }
 
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_justifications_in_range() {
+        // Requires an ephemeral Redis with RedisJSON at REDIS_URL.
+        let mut redis_client = RedisClient::new().await;
+        let avail_chain_id = format!("test-justifications-in-range-{}", std::process::id());
+
+        let justification = |block_number: u32| StoredJustificationData {
+            block_number,
+            signed_message: vec![block_number as u8; 53],
+            pubkeys: vec![vec![1u8; 32]],
+            signatures: vec![vec![2u8; 64]],
+            validator_signed: vec![true],
+            num_authorities: 1,
+            weights: vec![1],
+            round: Some(1),
+            authority_set_hash: None,
+        };
+        // Store the justifications out of order.
+        for block_number in [30, 10, 20] {
+            redis_client
+                .add_justification(&avail_chain_id, justification(block_number))
+                .await;
+        }
+
+        let justifications = redis_client
+            .get_justifications_in_range(&avail_chain_id, 0, 100)
+            .await;
+        let block_numbers = justifications
+            .iter()
+            .map(|justification| justification.block_number)
+            .collect::<Vec<_>>();
+        assert_eq!(block_numbers, vec![10, 20, 30]);
+        assert_eq!(justifications[1].signed_message, vec![20u8; 53]);
+
+        let justifications = redis_client
+            .get_justifications_in_range(&avail_chain_id, 11, 20)
+            .await;
+        assert_eq!(justifications.len(), 1);
+        assert_eq!(justifications[0].block_number, 20);
+
+        assert!(redis_client
+            .get_justifications_in_range(&avail_chain_id, 31, 100)
+            .await
+            .is_empty());
+        assert!(redis_client
+            .get_justifications_in_range(&avail_chain_id, 30, 10)
+            .await
+            .is_empty());
+
+        // A malformed entry is skipped without dropping the rest of the range.
+        let mut con = redis_client.get_connection().await.unwrap();
+        let _: () = con
+            .json_set(
+                format!("{}:justification:{}", avail_chain_id, 25),
+                "$",
+                &serde_json::json!({ "block_number": "not a block number" }),
+            )
+            .await
+            .unwrap();
+        let _: () = con
+            .zadd(format!("{}:justification:blocks", avail_chain_id), 25, 25)
+            .await
+            .unwrap();
+        let justifications = redis_client
+            .get_justifications_in_range(&avail_chain_id, 0, 100)
+            .await;
+        let block_numbers = justifications
+            .iter()
+            .map(|justification| justification.block_number)
+            .collect::<Vec<_>>();
+        assert_eq!(block_numbers, vec![10, 20, 30]);
+
+        assert_eq!(redis_client.prune_older_than(&avail_chain_id, 31).await, 4);
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_add_justification_keeps_most_signatures() {
+        // Requires an ephemeral Redis with RedisJSON at REDIS_URL.
+        let mut redis_client = RedisClient::new().await;
+        let avail_chain_id = format!("test-add-justification-dedup-{}", std::process::id());
+
+        let justification = |validator_signed: Vec<bool>| StoredJustificationData {
+            block_number: 10,
+            signed_message: vec![0u8; 53],
+            pubkeys: vec![vec![1u8; 32]; 3],
+            signatures: vec![vec![2u8; 64]; 3],
+            validator_signed,
+            num_authorities: 3,
+            weights: vec![1; 3],
+            round: Some(1),
+            authority_set_hash: None,
+        };
+
+        assert!(!redis_client.justification_exists(&avail_chain_id, 10).await);
+        assert!(
+            redis_client
+                .add_justification(&avail_chain_id, justification(vec![true, true, false]))
+                .await
+        );
+        assert!(redis_client.justification_exists(&avail_chain_id, 10).await);
+
+        // A justification with fewer or as many signatures does not overwrite the stored one.
+        assert!(
+            !redis_client
+                .add_justification(&avail_chain_id, justification(vec![true, false, false]))
+                .await
+        );
+        assert!(
+            !redis_client
+                .add_justification(&avail_chain_id, justification(vec![false, true, true]))
+                .await
+        );
+        let stored = redis_client
+            .get_justification(&avail_chain_id, 10)
+            .await
+            .unwrap();
+        assert_eq!(stored.validator_signed, vec![true, true, false]);
+
+        // A justification with more signatures overwrites the stored one.
+        assert!(
+            redis_client
+                .add_justification(&avail_chain_id, justification(vec![true, true, true]))
+                .await
+        );
+        let stored = redis_client
+            .get_justification(&avail_chain_id, 10)
+            .await
+            .unwrap();
+        assert_eq!(stored.validator_signed, vec![true, true, true]);
+
+        redis_client.prune_older_than(&avail_chain_id, 11).await;
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_justifications_namespaced_by_chain_id() {
+        // Requires an ephemeral Redis with RedisJSON at REDIS_URL.
+        let mut redis_client = RedisClient::new().await;
+        let chain_a = format!("test-namespace-a-{}", std::process::id());
+        let chain_b = format!("test-namespace-b-{}", std::process::id());
+
+        let justification = |signed_message: Vec<u8>| StoredJustificationData {
+            block_number: 10,
+            signed_message,
+            pubkeys: vec![vec![1u8; 32]],
+            signatures: vec![vec![2u8; 64]],
+            validator_signed: vec![true],
+            num_authorities: 1,
+            weights: vec![1],
+            round: Some(1),
+            authority_set_hash: None,
+        };
+
+        // The same block is stored for both chains, without either overwriting the other.
+        assert!(
+            redis_client
+                .add_justification(&chain_a, justification(vec![0xa; 53]))
+                .await
+        );
+        assert!(
+            redis_client
+                .add_justification(&chain_b, justification(vec![0xb; 53]))
+                .await
+        );
+        let stored_a = redis_client.get_justification(&chain_a, 10).await.unwrap();
+        let stored_b = redis_client.get_justification(&chain_b, 10).await.unwrap();
+        assert_eq!(stored_a.signed_message, vec![0xa; 53]);
+        assert_eq!(stored_b.signed_message, vec![0xb; 53]);
+
+        // Pruning one chain leaves the other untouched.
+        assert_eq!(redis_client.prune_older_than(&chain_a, 11).await, 1);
+        assert!(!redis_client.justification_exists(&chain_a, 10).await);
+        assert!(redis_client.justification_exists(&chain_b, 10).await);
+
+        redis_client.prune_older_than(&chain_b, 11).await;
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_justification_ttl() {
+        // Requires an ephemeral Redis with RedisJSON at REDIS_URL.
+        let mut redis_client = RedisClient::new().await;
+        redis_client.justification_ttl = Some(1);
+        let avail_chain_id = format!("test-justification-ttl-{}", std::process::id());
+
+        redis_client
+            .add_justification(
+                &avail_chain_id,
+                StoredJustificationData {
+                    block_number: 10,
+                    signed_message: vec![0u8; 53],
+                    pubkeys: vec![vec![1u8; 32]],
+                    signatures: vec![vec![2u8; 64]],
+                    validator_signed: vec![true],
+                    num_authorities: 1,
+                    weights: vec![1],
+                    round: Some(1),
+                    authority_set_hash: None,
+                },
+            )
+            .await;
+        assert!(redis_client
+            .get_justification(&avail_chain_id, 10)
+            .await
+            .is_ok());
+
+        sleep(Duration::from_secs(2)).await;
+        assert!(redis_client
+            .get_justification(&avail_chain_id, 10)
+            .await
+            .is_err());
+        assert!(redis_client
+            .get_justifications_in_range(&avail_chain_id, 0, 100)
+            .await
+            .is_empty());
+
+        // The expired block is removed from the sorted set of blocks.
+        let mut con = redis_client.get_connection().await.unwrap();
+        let num_blocks: usize = con
+            .zcard(format!("{}:justification:blocks", avail_chain_id))
+            .await
+            .unwrap();
+        assert_eq!(num_blocks, 0);
+        assert!(redis_client
+            .get_blocks_in_range(&avail_chain_id, 0, 100)
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_redis_pool_concurrent_reads() {
+        // Requires an ephemeral Redis with RedisJSON at REDIS_URL.
+        let mut redis_client = RedisClient::new().await;
+        let avail_chain_id = format!("test-redis-pool-{}", std::process::id());
+
+        redis_client
+            .add_justification(
+                &avail_chain_id,
+                StoredJustificationData {
+                    block_number: 10,
+                    signed_message: vec![0u8; 53],
+                    pubkeys: vec![vec![1u8; 32]],
+                    signatures: vec![vec![2u8; 64]],
+                    validator_signed: vec![true],
+                    num_authorities: 1,
+                    weights: vec![1],
+                    round: Some(1),
+                    authority_set_hash: None,
+                },
+            )
+            .await;
+
+        // Many more concurrent reads than pooled connections. Each read waits for a pooled
+        // connection instead of opening a new one.
+        let reads = (0..1000).map(|_| {
+            let mut redis_client = redis_client.clone();
+            let avail_chain_id = avail_chain_id.clone();
+            tokio::spawn(async move { redis_client.get_justification(&avail_chain_id, 10).await })
+        });
+        for read in join_all(reads).await {
+            assert_eq!(read.unwrap().unwrap().block_number, 10);
+        }
+        assert!(redis_client.pool.status().size <= redis_client.pool.status().max_size);
+
+        redis_client.prune_older_than(&avail_chain_id, 11).await;
+    }
+
     #[tokio::test]
     #[cfg_attr(feature = "ci", ignore)]
     async fn test_get_header_rotate() {
@@ -1157,9 +4019,10 @@ mod tests {
             }
             log::debug!("epoch_end_block {:?}", epoch_end_block);
 
-            let _ = data_fetcher
+            data_fetcher
                 .get_header_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>(epoch_end_block)
-                .await;
+                .await
+                .unwrap();
 
             let num_authorities = data_fetcher.get_authorities(epoch_end_block).await.len();
             println!("num authorities {:?}", num_authorities);
@@ -1168,6 +4031,261 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_encode_header_data_root_at_end() {
+        dotenv::dotenv().ok();
+
+        let fetcher = RpcDataFetcher::new().await;
+
+        // Blocks from across the chain's history, including epoch end blocks whose digests have a
+        // ScheduledChange log, so the headers differ in the length of the digest and of the header
+        // extension.
+        for block_number in [1u32, 4321, 272355, 317857, 529200, 645570] {
+            let header = fetcher.get_header(block_number).await;
+            let encoded_header = header.encode();
+            assert!(is_data_root_at_end(&header, &encoded_header));
+
+            let padded_header = encode_header::<MAX_HEADER_SIZE, GoldilocksField>(&header).unwrap();
+            let header_size = padded_header.header_size as usize;
+            assert_eq!(header_size, encoded_header.len());
+            assert_eq!(
+                padded_header.header_bytes[header_size - DATA_ROOT_OFFSET_FROM_END..header_size],
+                header.data_root().0
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_header_rotate_not_epoch_end_block() {
+        dotenv::dotenv().ok();
+
+        let mut data_fetcher = RpcDataFetcher::new().await;
+
+        // The authority set id does not change at the blocks around the epoch end block, or at the
+        // genesis block.
+        let epoch_end_block = data_fetcher.last_justified_block(200).await;
+        for block_number in [0, epoch_end_block - 1, epoch_end_block + 1] {
+            let e = data_fetcher
+                .get_header_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>(block_number)
+                .await
+                .unwrap_err();
+            assert_eq!(e, FetchError::NotEpochEndBlock { block_number });
+            assert_eq!(
+                e.to_string(),
+                format!("Block {} is not an epoch end block", block_number)
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_header_rotate_data_errors() {
+        dotenv::dotenv().ok();
+
+        let mut data_fetcher = RpcDataFetcher::new().await;
+
+        let epoch_end_block = data_fetcher.last_justified_block(200).await;
+        let header = data_fetcher.get_header(epoch_end_block).await;
+        let authorities = data_fetcher.get_authorities(epoch_end_block).await;
+        let rotate_data = |header: &avail_subxt::primitives::Header,
+                           authorities: &[CompressedEdwardsY]| {
+            header_rotate_data::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>(
+                epoch_end_block,
+                header,
+                authorities,
+            )
+        };
+        assert!(rotate_data(&header, &authorities).is_ok());
+
+        // The header does not fit in a circuit with a smaller max header size.
+        assert_eq!(
+            header_rotate_data::<32, MAX_AUTHORITY_SET_SIZE>(
+                epoch_end_block,
+                &header,
+                &authorities
+            )
+            .unwrap_err(),
+            FetchError::HeaderTooLarge {
+                block_number: epoch_end_block,
+                header_size: header.encode().len(),
+                max_header_size: 32,
+            }
+        );
+
+        // The authorities in the log are not the new authorities.
+        let mismatch = FetchError::AuthoritySetChangeMismatch {
+            block_number: epoch_end_block,
+        };
+        assert_eq!(
+            rotate_data(&header, &authorities[1..]).unwrap_err(),
+            mismatch
+        );
+        let mut swapped_authorities = authorities.clone();
+        swapped_authorities.swap(0, 1);
+        assert_eq!(
+            rotate_data(&header, &swapped_authorities).unwrap_err(),
+            mismatch
+        );
+
+        // A ScheduledChange log with a nonzero delay.
+        let change = decode_authority_set_change(&header.digest.logs).unwrap();
+        let pubkeys = authorities.iter().map(|a| a.0).collect::<Vec<_>>();
+        let mut delayed_header = header.clone();
+        delayed_header.digest.logs = vec![grandpa_log(SCHEDULED_CHANGE_FLAG, None, &pubkeys, 5)];
+        assert!(!change.is_forced_change);
+        assert_eq!(
+            rotate_data(&delayed_header, &authorities).unwrap_err(),
+            FetchError::InvalidScheduledChangeDelay {
+                block_number: epoch_end_block,
+                delay: 5,
+            }
+        );
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_authorities_paged() {
+        dotenv::dotenv().ok();
+
+        let mut data_fetcher = RpcDataFetcher::new().await;
+
+        let block_number = 272355;
+        let authorities = data_fetcher.get_authorities(block_number).await;
+        for limit in [1, 7, authorities.len()] {
+            let mut paged_authorities = Vec::new();
+            for offset in (0..authorities.len()).step_by(limit) {
+                let page = data_fetcher
+                    .get_authorities_paged(block_number, offset, limit)
+                    .await;
+                assert_eq!(page.len(), limit.min(authorities.len() - offset));
+                paged_authorities.extend(page);
+            }
+            assert_eq!(paged_authorities, authorities);
+        }
+
+        // A page past the end of the authority set is empty.
+        assert!(data_fetcher
+            .get_authorities_paged(block_number, authorities.len(), 10)
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_authorities_canonical_order() {
+        dotenv::dotenv().ok();
+
+        let mut data_fetcher = RpcDataFetcher::new().await;
+
+        // The epoch end block enacting authority set 201.
+        let epoch_end_block = data_fetcher.last_justified_block(200).await;
+        let rotate_data = data_fetcher
+            .get_header_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>(epoch_end_block)
+            .await
+            .unwrap();
+
+        // The fetched authorities are in the order of the ScheduledChange log, which is the order
+        // the new authority set commitment is computed over.
+        let authorities = data_fetcher.get_authorities(epoch_end_block).await;
+        assert_eq!(
+            authorities,
+            rotate_data.padded_pubkeys[..rotate_data.num_authorities]
+        );
+        assert_eq!(
+            compute_authority_set_hash(&authorities),
+            rotate_data.new_authority_set_hash
+        );
+
+        // The order is stable for the rest of the epoch.
+        assert_eq!(
+            data_fetcher.get_authorities(epoch_end_block + 1).await,
+            authorities
+        );
+
+        // Sorting the authorities is not the canonical order, and changes the commitment.
+        let mut sorted_authorities = authorities.clone();
+        sorted_authorities.sort_by_key(|pubkey| pubkey.0);
+        if sorted_authorities != authorities {
+            assert_ne!(
+                compute_authority_set_hash(&sorted_authorities),
+                rotate_data.new_authority_set_hash
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_header_rotate_cached() {
+        dotenv::dotenv().ok();
+
+        let mut data_fetcher = RpcDataFetcher::new().await;
+        data_fetcher.clear_cache();
+
+        let epoch_end_block = data_fetcher.last_justified_block(200).await;
+        let fetched = data_fetcher
+            .get_header_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>(epoch_end_block)
+            .await
+            .unwrap();
+        let cached = data_fetcher
+            .get_header_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>(epoch_end_block)
+            .await
+            .unwrap();
+        assert_eq!(fetched.header_bytes, cached.header_bytes);
+        assert_eq!(
+            fetched.new_authority_set_hash,
+            cached.new_authority_set_hash
+        );
+
+        data_fetcher.clear_cache();
+        if let Some(cache) = &data_fetcher.header_rotate_cache {
+            assert!(cache.lock().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_get_authority_set_hash_cached() {
+        dotenv::dotenv().ok();
+
+        let mut data_fetcher = RpcDataFetcher::new().await;
+        data_fetcher.clear_cache();
+
+        // The epoch end block of authority set 200 enacts authority set 201.
+        let epoch_end_block = data_fetcher.last_justified_block(200).await;
+        let outgoing_hash = data_fetcher
+            .compute_authority_set_hash(epoch_end_block - 1)
+            .await;
+        let incoming_hash = data_fetcher
+            .compute_authority_set_hash(epoch_end_block)
+            .await;
+        assert_ne!(outgoing_hash, incoming_hash);
+
+        // The hashes are cached by authority set id, and served without fetching the authorities.
+        if let Some(cache) = &data_fetcher.authority_set_hash_cache {
+            let mut cache = cache.lock().unwrap();
+            let chain_id = data_fetcher.avail_chain_id.clone();
+            assert_eq!(cache.get(&(chain_id.clone(), 200)), Some(outgoing_hash));
+            assert_eq!(cache.get(&(chain_id, 201)), Some(incoming_hash));
+        }
+        assert_eq!(
+            data_fetcher.get_authority_set_hash(200).await,
+            outgoing_hash
+        );
+        assert_eq!(
+            data_fetcher.get_authority_set_hash(201).await,
+            incoming_hash
+        );
+
+        // Without the cache, the hash is recomputed from the authority set.
+        data_fetcher.clear_cache();
+        assert_eq!(
+            data_fetcher.get_authority_set_hash(201).await,
+            incoming_hash
+        );
+    }
+
     #[tokio::test]
     #[cfg_attr(feature = "ci", ignore)]
     async fn test_system_chain() {
@@ -0,0 +1,236 @@
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::debug;
+use tokio::time::sleep;
+
+/// Retry policy for transient RPC errors, with exponential backoff between attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first attempt.
+    pub max_attempts: usize,
+    /// Delay before the first retry. The delay doubles after each retry.
+    pub base_delay: Duration,
+    /// Maximum delay between attempts.
+    pub max_delay: Duration,
+    /// If true, a random delay of up to half the backoff delay is added to each delay.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the retry after `attempt` failed attempts (starting at 1).
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31) as u32;
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.max_delay);
+        if !self.jitter || delay.is_zero() {
+            return delay;
+        }
+        // Use the subsecond nanos of the current time as a cheap source of randomness.
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos() as u64;
+        let max_jitter = (delay.as_nanos() / 2) as u64;
+        delay + Duration::from_nanos(nanos % (max_jitter + 1))
+    }
+}
+
+/// Runs op until it succeeds or policy.max_attempts attempts have failed. An `Err` from op is a
+/// transient error and is retried. Logical errors (e.g. a block that does not exist) should be
+/// returned as an `Ok` value by op (e.g. `Ok(None)`), so they are not retried.
+pub async fn retry_with_backoff<T, E, F, Fut>(policy: &RetryPolicy, mut op: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(result) => return Ok(result),
+            Err(e) if attempt < policy.max_attempts => {
+                let delay = policy.delay(attempt);
+                debug!(
+                    "Attempt {} failed with error: {}. Retrying in {:?}...",
+                    attempt, e, delay
+                );
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Parses a comma-separated list of urls, ignoring whitespace and empty entries.
+pub fn parse_urls(urls: &str) -> Vec<String> {
+    urls.split(',')
+        .map(|url| url.trim())
+        .filter(|url| !url.is_empty())
+        .map(|url| url.to_string())
+        .collect()
+}
+
+/// Tries to connect to each of the urls in order, starting at index start and wrapping around.
+/// Returns the index of the first url that connects with the connection, or the last error if
+/// none of the urls connect.
+pub async fn connect_in_order<'a, T, E, F, Fut>(
+    urls: &'a [String],
+    start: usize,
+    mut connect: F,
+) -> Result<(usize, T), E>
+where
+    E: Debug,
+    F: FnMut(&'a str) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    assert!(!urls.is_empty(), "No urls to connect to");
+    let mut last_error = None;
+    for offset in 0..urls.len() {
+        let index = (start + offset) % urls.len();
+        match connect(urls[index].as_str()).await {
+            Ok(connection) => return Ok((index, connection)),
+            Err(e) => {
+                debug!("Failed to connect to {}: {:?}", urls[index], e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn test_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_urls() {
+        assert_eq!(parse_urls("wss://a"), vec!["wss://a".to_string()]);
+        assert_eq!(
+            parse_urls("wss://a, wss://b,,"),
+            vec!["wss://a".to_string(), "wss://b".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_in_order_skips_dead_endpoint() {
+        let urls = parse_urls("wss://dead,wss://live");
+        let attempted = std::cell::RefCell::new(Vec::new());
+        let connect = |url: &str| {
+            attempted.borrow_mut().push(url.to_string());
+            let url = url.to_string();
+            async move {
+                if url == "wss://dead" {
+                    Err("connection refused")
+                } else {
+                    Ok(url)
+                }
+            }
+        };
+
+        // The dead endpoint is tried first, then the live endpoint.
+        let (index, connection) = connect_in_order(&urls, 0, connect).await.unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(connection, "wss://live");
+        assert_eq!(*attempted.borrow(), vec!["wss://dead", "wss://live"]);
+
+        // Rotating past the live endpoint wraps around to it after the dead endpoint.
+        attempted.borrow_mut().clear();
+        let (index, _) = connect_in_order(&urls, 2, connect).await.unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(*attempted.borrow(), vec!["wss://dead", "wss://live"]);
+
+        // All endpoints dead.
+        let dead_urls = parse_urls("wss://dead,wss://dead");
+        assert_eq!(
+            connect_in_order(&dead_urls, 0, connect).await,
+            Err("connection refused")
+        );
+    }
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let policy = test_policy();
+        assert_eq!(policy.delay(1), Duration::from_millis(1));
+        assert_eq!(policy.delay(2), Duration::from_millis(2));
+        assert_eq!(policy.delay(3), Duration::from_millis(4));
+        // Capped at max_delay.
+        assert_eq!(policy.delay(10), Duration::from_millis(4));
+
+        let policy = RetryPolicy {
+            jitter: true,
+            ..test_policy()
+        };
+        let delay = policy.delay(2);
+        assert!(delay >= Duration::from_millis(2) && delay <= Duration::from_millis(3));
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_fails_twice_then_succeeds() {
+        let attempts = Cell::new(0);
+        let result = retry_with_backoff(&test_policy(), || {
+            attempts.set(attempts.get() + 1);
+            let attempt = attempts.get();
+            async move {
+                if attempt <= 2 {
+                    Err("transport error")
+                } else {
+                    Ok(Some(attempt))
+                }
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(Some(3)));
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_does_not_retry_not_found() {
+        let attempts = Cell::new(0);
+        let result: Result<Option<u32>, &str> = retry_with_backoff(&test_policy(), || {
+            attempts.set(attempts.get() + 1);
+            async { Ok(None) }
+        })
+        .await;
+        assert_eq!(result, Ok(None));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_gives_up() {
+        let attempts = Cell::new(0);
+        let result: Result<u32, &str> = retry_with_backoff(&test_policy(), || {
+            attempts.set(attempts.get() + 1);
+            async { Err("transport error") }
+        })
+        .await;
+        assert_eq!(result, Err("transport error"));
+        assert_eq!(attempts.get(), 5);
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use avail_subxt::primitives::Header;
 use codec::{Decode, Encode};
 use ethers::types::H256;
@@ -7,6 +9,9 @@ use serde::{Deserialize, Serialize};
 use sp_core::ed25519::{Public as EdPublic, Signature};
 use sp_core::{bytes, Bytes};
 
+use crate::consts::ENCODED_PRECOMMIT_LENGTH;
+
+#[derive(Debug, Clone)]
 pub struct HeaderRotateData {
     pub header_bytes: Vec<u8>,
     pub header_size: usize,
@@ -15,6 +20,27 @@ pub struct HeaderRotateData {
     pub end_position: usize,
     pub new_authority_set_hash: Vec<u8>,
     pub padded_pubkeys: Vec<CompressedEdwardsY>,
+    /// Voting weights of the new authorities, padded with 1 to VALIDATOR_SET_SIZE_MAX.
+    pub padded_weights: Vec<u64>,
+    /// True if the new authority set is enacted by a ForcedChange log instead of a ScheduledChange
+    /// log.
+    pub is_forced_change: bool,
+    /// The delay (in blocks) of the ForcedChange. Zero for a ScheduledChange.
+    pub forced_change_delay: u32,
+}
+
+/// A GRANDPA ScheduledChange or ForcedChange consensus log, decoded from the digest of an epoch end
+/// header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthoritySetChangeLog {
+    /// Offset of the log from the start of the digest logs, which is the total encoded length of the
+    /// preceding logs.
+    pub offset: usize,
+    pub is_forced_change: bool,
+    pub pubkeys: Vec<[u8; 32]>,
+    pub weights: Vec<u64>,
+    /// The delay (in blocks) before the new authority set is enacted.
+    pub delay: u32,
 }
 
 // Stores the signed messages, valid signatures and pubkeys for a given block number justification.
@@ -29,6 +55,29 @@ pub struct StoredJustificationData {
     pub signatures: Vec<Vec<u8>>,
     pub validator_signed: Vec<bool>,
     pub num_authorities: usize,
+    /// Voting weight of each authority. Empty for justifications stored before weights were
+    /// stored, whose authorities all have a voting weight of 1.
+    #[serde(default)]
+    pub weights: Vec<u64>,
+    /// GRANDPA round of the justification, which is part of the signed message. None for
+    /// justifications stored before the round was stored.
+    #[serde(default)]
+    pub round: Option<u64>,
+    /// Chained SHA256 commitment of the authority set, as in `compute_weighted_authority_set_hash`,
+    /// computed when the justification was stored. None for justifications stored before the
+    /// commitment was stored.
+    #[serde(default)]
+    pub authority_set_hash: Option<[u8; 32]>,
+}
+
+impl StoredJustificationData {
+    /// Number of validators that signed the justification.
+    pub fn num_signatures(&self) -> usize {
+        self.validator_signed
+            .iter()
+            .filter(|validator_signed| **validator_signed)
+            .count()
+    }
 }
 
 #[derive(Debug)]
@@ -38,17 +87,373 @@ pub struct CircuitJustification {
     pub validator_signed: Vec<bool>,
     pub pubkeys: Vec<CompressedEdwardsY>,
     pub signatures: Vec<[u8; 64]>,
+    pub weights: Vec<u64>,
     pub num_authorities: usize,
     pub current_authority_set_hash: Vec<u8>,
+    /// GRANDPA round of the justification.
+    pub round: u64,
+}
+
+/// Errors returned when fetching the simple justification for a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchError {
+    /// The block is not an epoch end block, and no justification is stored in Redis for it.
+    NoJustificationStored { block_number: u32 },
+    /// The precommit is signed by a different authority set than the one active at the block.
+    AuthoritySetMismatch {
+        block_number: u32,
+        expected: u64,
+        actual: u64,
+    },
+    /// The encoded precommit is not ENCODED_PRECOMMIT_LENGTH bytes.
+    PrecommitLengthMismatch { block_number: u32, length: usize },
+    /// The authorities that signed have at most 2/3 of the voting weight.
+    NotEnoughVotingWeight { block_number: u32 },
+    /// The authority set that signed has more authorities than the circuit supports.
+    TooManyAuthorities {
+        block_number: u32,
+        num_authorities: usize,
+        max_num_authorities: usize,
+    },
+    /// The stored signature of the validator at index is not 64 bytes.
+    InvalidSignatureLength {
+        block_number: u32,
+        index: usize,
+        length: usize,
+    },
+    /// The authority set commitment stored with the justification does not match the commitment
+    /// recomputed from its pubkeys and weights, so the stored justification is corrupt.
+    AuthoritySetHashMismatch {
+        block_number: u32,
+        expected: H256,
+        actual: H256,
+    },
+    /// The block is not an epoch end block: the authority set id does not change at the block, or
+    /// its header has no ScheduledChange or ForcedChange consensus log.
+    NotEpochEndBlock { block_number: u32 },
+    /// The encoded header of the block is longer than the max header length of the circuit.
+    HeaderTooLarge {
+        block_number: u32,
+        header_size: usize,
+        max_header_size: usize,
+    },
+    /// The encoded header of the block does not end with its data root.
+    DataRootNotAtEnd { block_number: u32 },
+    /// The authorities in the authority set change log of the epoch end block do not match the
+    /// authority set stored for the next authority set id.
+    AuthoritySetChangeMismatch { block_number: u32 },
+    /// The ScheduledChange log of the epoch end block has a nonzero delay.
+    InvalidScheduledChangeDelay { block_number: u32, delay: u32 },
+    /// The Avail node has no header with the block hash.
+    UnknownBlockHash { block_hash: H256 },
+    /// The block with the requested hash is not the block at its number on the node's canonical
+    /// chain, or the node served a header that does not hash to the requested hash.
+    BlockHashMismatch {
+        block_number: u32,
+        expected: H256,
+        actual: H256,
+    },
+    /// The Avail node is still syncing (e.g. warp syncing), so it cannot serve justifications for
+    /// the blocks it has not imported.
+    NodeNotSynced {
+        finalized_block_number: u32,
+        best_block_number: u32,
+    },
+    /// Fetching or decoding the justification from the Avail node failed.
+    Rpc(String),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::NoJustificationStored { block_number } => {
+                write!(f, "No justification stored for block {}", block_number)
+            }
+            FetchError::AuthoritySetMismatch {
+                block_number,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Justification for block {} is signed by authority set {}, expected {}",
+                block_number, actual, expected
+            ),
+            FetchError::PrecommitLengthMismatch {
+                block_number,
+                length,
+            } => write!(
+                f,
+                "Precommit for block {} is {} bytes, expected {}",
+                block_number, length, ENCODED_PRECOMMIT_LENGTH
+            ),
+            FetchError::NotEnoughVotingWeight { block_number } => {
+                write!(f, "Not enough voting power for block {}", block_number)
+            }
+            FetchError::TooManyAuthorities {
+                block_number,
+                num_authorities,
+                max_num_authorities,
+            } => write!(
+                f,
+                "Block {} is signed by an authority set of {} authorities, which exceeds the max of {} supported by the circuit",
+                block_number, num_authorities, max_num_authorities
+            ),
+            FetchError::InvalidSignatureLength {
+                block_number,
+                index,
+                length,
+            } => write!(
+                f,
+                "Signature of validator {} for block {} is {} bytes, expected 64",
+                index, block_number, length
+            ),
+            FetchError::AuthoritySetHashMismatch {
+                block_number,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Authority set of the justification for block {} hashes to {:?}, but {:?} is stored",
+                block_number, actual, expected
+            ),
+            FetchError::NotEpochEndBlock { block_number } => {
+                write!(f, "Block {} is not an epoch end block", block_number)
+            }
+            FetchError::HeaderTooLarge {
+                block_number,
+                header_size,
+                max_header_size,
+            } => write!(
+                f,
+                "Header of block {} is {} bytes, which exceeds the max of {}",
+                block_number, header_size, max_header_size
+            ),
+            FetchError::DataRootNotAtEnd { block_number } => {
+                write!(
+                    f,
+                    "Header of block {} does not end with its data root",
+                    block_number
+                )
+            }
+            FetchError::AuthoritySetChangeMismatch { block_number } => write!(
+                f,
+                "Authority set change log of block {} does not match the next authority set",
+                block_number
+            ),
+            FetchError::InvalidScheduledChangeDelay {
+                block_number,
+                delay,
+            } => write!(
+                f,
+                "Scheduled change of block {} has delay {}, expected 0",
+                block_number, delay
+            ),
+            FetchError::UnknownBlockHash { block_hash } => {
+                write!(
+                    f,
+                    "Block hash {:?} is unknown to the Avail node",
+                    block_hash
+                )
+            }
+            FetchError::BlockHashMismatch {
+                block_number,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Block {} has hash {:?}, expected {:?}",
+                block_number, actual, expected
+            ),
+            FetchError::NodeNotSynced {
+                finalized_block_number,
+                best_block_number,
+            } => write!(
+                f,
+                "Avail node is still syncing (finalized block {}, best block {})",
+                finalized_block_number, best_block_number
+            ),
+            FetchError::Rpc(e) => write!(f, "RPC error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Source of the chain data fetched by the hints. The source is serialized with the hints, so it is
+/// fixed when the circuit is built.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataSource {
+    /// Websocket url of the Avail node to fetch from. If None, AVAIL_URL is used.
+    pub avail_url: Option<String>,
+    /// Path of a fixtures file served by a `MockDataFetcher` instead of the Avail node. Only
+    /// available with the mock feature, so a production build always fetches from the Avail node.
+    #[cfg(any(test, feature = "mock"))]
+    #[serde(default)]
+    pub fixtures_path: Option<String>,
+}
+
+impl DataSource {
+    /// Fetch from the Avail node at avail_url, or at AVAIL_URL if None.
+    pub fn avail(avail_url: Option<String>) -> Self {
+        DataSource {
+            avail_url,
+            #[cfg(any(test, feature = "mock"))]
+            fixtures_path: None,
+        }
+    }
+
+    /// Serve the fixtures file at fixtures_path.
+    #[cfg(any(test, feature = "mock"))]
+    pub fn fixtures(fixtures_path: &str) -> Self {
+        DataSource {
+            avail_url: None,
+            fixtures_path: Some(fixtures_path.to_string()),
+        }
+    }
 }
 
+/// Hash function of the chained authority set commitment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommitmentHash {
+    /// SHA256, which is the commitment stored in the contract.
+    #[default]
+    Sha256,
+    /// Poseidon over the Goldilocks field, which is cheaper to compute in circuit, but expensive to
+    /// compute on-chain. Only for commitments that are internal to the circuits.
+    Poseidon,
+}
+
+/// Status of the chain as seen by an Avail node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainStatus {
+    pub finalized_block_number: u32,
+    pub best_block_number: u32,
+    /// True if the node is syncing blocks from its peers.
+    pub is_syncing: bool,
+    pub peers: usize,
+}
+
+impl ChainStatus {
+    /// Errors with NodeNotSynced if the node is syncing, or has not finalized a block after genesis
+    /// (as while warp syncing).
+    pub fn check_synced(self) -> Result<Self, FetchError> {
+        if self.is_syncing || self.finalized_block_number == 0 {
+            return Err(FetchError::NodeNotSynced {
+                finalized_block_number: self.finalized_block_number,
+                best_block_number: self.best_block_number,
+            });
+        }
+        Ok(self)
+    }
+}
+
+impl From<anyhow::Error> for FetchError {
+    /// Recovers a FetchError wrapped in an anyhow::Error. Any other error is an RPC error.
+    fn from(e: anyhow::Error) -> Self {
+        e.downcast::<FetchError>()
+            .unwrap_or_else(|e| FetchError::Rpc(e.to_string()))
+    }
+}
+
+/// Errors returned when verifying the signatures of a stored justification offline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The pubkeys, signatures and validator_signed do not all have num_authorities entries.
+    LengthMismatch {
+        num_authorities: usize,
+        num_pubkeys: usize,
+        num_signatures: usize,
+        num_validator_signed: usize,
+    },
+    /// The authority at index is marked as signed, but its signature on the signed message is
+    /// invalid.
+    InvalidSignature { index: usize },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::LengthMismatch {
+                num_authorities,
+                num_pubkeys,
+                num_signatures,
+                num_validator_signed,
+            } => write!(
+                f,
+                "Justification has {} authorities, but {} pubkeys, {} signatures and {} validator_signed",
+                num_authorities, num_pubkeys, num_signatures, num_validator_signed
+            ),
+            VerifyError::InvalidSignature { index } => {
+                write!(f, "Invalid signature from the authority at index {}", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SimpleJustificationData {
     pub pubkeys: Vec<CompressedEdwardsY>,
     pub signatures: Vec<Vec<u8>>,
     pub validator_signed: Vec<bool>,
     pub signed_message: Vec<u8>,
+    /// Voting weight of each authority.
+    pub weights: Vec<u64>,
+    /// Sum of the voting weights of the authorities that signed.
     pub voting_weight: u64,
     pub num_authorities: u64,
+    /// Id of the authority set that signed the justification. Derived from on-chain storage, as the
+    /// authority set active at the parent of the block, rather than from the signed message.
+    pub authority_set_id: u64,
+    /// GRANDPA round of the justification.
+    pub round: u64,
+    /// Authority set commitment stored with the justification, if any.
+    pub authority_set_hash: Option<[u8; 32]>,
+}
+
+impl SimpleJustificationData {
+    /// Converts stored justification data into the justification data for the circuit, signed by
+    /// the authority set with authority_set_id.
+    pub fn from_stored(data: StoredJustificationData, authority_set_id: u64) -> Self {
+        let weights = if data.weights.is_empty() {
+            vec![1u64; data.num_authorities]
+        } else {
+            data.weights
+        };
+        let voting_weight = data
+            .validator_signed
+            .iter()
+            .zip(weights.iter())
+            .filter(|(validator_signed, _)| **validator_signed)
+            .map(|(_, weight)| weight)
+            .sum();
+        // Justifications stored before the round was stored carry it in the signed message, after
+        // the message type, block hash and block number.
+        let round = data.round.unwrap_or_else(|| {
+            data.signed_message
+                .get(37..45)
+                .map(|round| u64::from_le_bytes(round.try_into().unwrap()))
+                .unwrap_or_default()
+        });
+        let pubkeys = data
+            .pubkeys
+            .iter()
+            .map(|pubkey| CompressedEdwardsY::from_slice(pubkey).unwrap())
+            .collect::<Vec<CompressedEdwardsY>>();
+        SimpleJustificationData {
+            pubkeys,
+            signatures: data.signatures,
+            validator_signed: data.validator_signed,
+            signed_message: data.signed_message,
+            weights,
+            voting_weight,
+            num_authorities: data.num_authorities as u64,
+            authority_set_id,
+            round,
+            authority_set_hash: data.authority_set_hash,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Decode, Encode, Deserialize)]
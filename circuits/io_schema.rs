@@ -0,0 +1,167 @@
+use ethers::types::H256;
+use serde_json::{json, Value};
+
+/// A field of the public inputs or outputs of a circuit. Public inputs and outputs are ABI encode
+/// packed in the order they are read/written with `evm_read`/`evm_write`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoField {
+    pub name: &'static str,
+    /// Solidity type of the field.
+    pub ty: &'static str,
+    /// Byte width of the field when ABI encode packed.
+    pub bytes: usize,
+}
+
+impl IoField {
+    pub const fn new(name: &'static str, ty: &'static str, bytes: usize) -> Self {
+        Self { name, ty, bytes }
+    }
+}
+
+/// A value of an `IoField`, to serialize the expected public inputs or outputs of a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IoValue {
+    Bytes32(H256),
+    Uint64(u64),
+    Uint32(u32),
+    Bool(bool),
+    Bytes(Vec<u8>),
+}
+
+impl IoValue {
+    /// Solidity type of the value.
+    pub fn ty(&self) -> &'static str {
+        match self {
+            IoValue::Bytes32(_) => "bytes32",
+            IoValue::Uint64(_) => "uint64",
+            IoValue::Uint32(_) => "uint32",
+            IoValue::Bool(_) => "bool",
+            IoValue::Bytes(_) => "bytes",
+        }
+    }
+
+    /// ABI encode packed value. Integers are big-endian, and a bool is a single byte.
+    pub fn encode_packed(&self) -> Vec<u8> {
+        match self {
+            IoValue::Bytes32(value) => value.0.to_vec(),
+            IoValue::Uint64(value) => value.to_be_bytes().to_vec(),
+            IoValue::Uint32(value) => value.to_be_bytes().to_vec(),
+            IoValue::Bool(value) => vec![*value as u8],
+            IoValue::Bytes(value) => value.clone(),
+        }
+    }
+}
+
+/// ABI encode packs the values of fields, in the order of fields. This is the layout the contract
+/// decodes the public inputs or outputs with. Panics if the values do not match the types and
+/// widths of fields.
+pub fn encode_packed(fields: &[IoField], values: &[IoValue]) -> Vec<u8> {
+    assert_eq!(
+        fields.len(),
+        values.len(),
+        "Expected {} values, got {}",
+        fields.len(),
+        values.len()
+    );
+    let mut bytes = Vec::with_capacity(byte_length(fields));
+    for (field, value) in fields.iter().zip(values) {
+        assert_eq!(
+            field.ty,
+            value.ty(),
+            "Field {} is a {}, got a {}",
+            field.name,
+            field.ty,
+            value.ty()
+        );
+        let encoded = value.encode_packed();
+        assert_eq!(
+            encoded.len(),
+            field.bytes,
+            "Field {} is {} bytes, got {}",
+            field.name,
+            field.bytes,
+            encoded.len()
+        );
+        bytes.extend(encoded);
+    }
+    bytes
+}
+
+/// Total byte width of the ABI encode packed fields.
+pub fn byte_length(fields: &[IoField]) -> usize {
+    fields.iter().map(|field| field.bytes).sum()
+}
+
+fn fields_to_json(fields: &[IoField]) -> Value {
+    let mut offset = 0;
+    let fields = fields
+        .iter()
+        .enumerate()
+        .map(|(order, field)| {
+            let field_json = json!({
+                "name": field.name,
+                "type": field.ty,
+                "bytes": field.bytes,
+                "order": order,
+                "offset": offset,
+            });
+            offset += field.bytes;
+            field_json
+        })
+        .collect::<Vec<_>>();
+    Value::Array(fields)
+}
+
+/// Machine-readable description of the public input and output layout of a circuit, used to
+/// generate the decoding code in the contracts.
+pub fn io_schema(circuit: &str, inputs: &[IoField], outputs: &[IoField]) -> Value {
+    json!({
+        "circuit": circuit,
+        "encoding": "abi.encodePacked",
+        "inputs": fields_to_json(inputs),
+        "inputs_byte_length": byte_length(inputs),
+        "outputs": fields_to_json(outputs),
+        "outputs_byte_length": byte_length(outputs),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: [IoField; 3] = [
+        IoField::new("hash", "bytes32", 32),
+        IoField::new("id", "uint64", 8),
+        IoField::new("flag", "bool", 1),
+    ];
+
+    #[test]
+    fn test_encode_packed() {
+        let bytes = encode_packed(
+            &FIELDS,
+            &[
+                IoValue::Bytes32(H256::repeat_byte(0xab)),
+                IoValue::Uint64(0x0102),
+                IoValue::Bool(true),
+            ],
+        );
+        let mut expected = vec![0xab; 32];
+        expected.extend([0, 0, 0, 0, 0, 0, 1, 2]);
+        expected.push(1);
+        assert_eq!(bytes, expected);
+        assert_eq!(bytes.len(), byte_length(&FIELDS));
+    }
+
+    #[test]
+    #[should_panic(expected = "Field id is a uint64, got a uint32")]
+    fn test_encode_packed_wrong_type() {
+        encode_packed(
+            &FIELDS,
+            &[
+                IoValue::Bytes32(H256::zero()),
+                IoValue::Uint32(1),
+                IoValue::Bool(false),
+            ],
+        );
+    }
+}
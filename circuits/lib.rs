@@ -1,11 +1,16 @@
 #![allow(clippy::needless_range_loop)]
 #![allow(clippy::too_many_arguments)]
 
+pub mod batch_rotate;
 pub mod builder;
 pub mod consts;
 pub mod dummy_header_range;
 pub mod dummy_rotate;
 pub mod header_range;
 pub mod input;
+pub mod io_schema;
+pub mod prover;
+pub mod resource_report;
 pub mod rotate;
+pub mod step;
 pub mod vars;
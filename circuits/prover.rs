@@ -0,0 +1,290 @@
+use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+use log::{debug, error};
+use plonky2x::backend::circuit::{CircuitBuild, PublicInput, PublicOutput};
+use plonky2x::prelude::plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2x::prelude::plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2x::prelude::PlonkParameters;
+
+/// If set, proofs that fail to generate are dumped to a file in this directory.
+pub const WITNESS_DUMP_DIR_ENV: &str = "WITNESS_DUMP_DIR";
+
+/// Maximum number of proofs generated at the same time. If unset, the number of proofs is unlimited.
+pub const MAX_CONCURRENT_PROOFS_ENV: &str = "MAX_CONCURRENT_PROOFS";
+
+type ProofAndOutput<L, const D: usize> = (
+    ProofWithPublicInputs<<L as PlonkParameters<D>>::Field, <L as PlonkParameters<D>>::Config, D>,
+    PublicOutput<L, D>,
+);
+
+#[derive(Debug, Default)]
+struct ProofLimiterState {
+    running: usize,
+    queued: usize,
+}
+
+/// Semaphore limiting the number of proofs generated at the same time. Proofs over the limit are
+/// queued until a running proof finishes.
+#[derive(Debug)]
+pub struct ProofLimiter {
+    max_concurrent_proofs: usize,
+    state: Mutex<ProofLimiterState>,
+    available: Condvar,
+}
+
+/// Releases the permit of a running proof when dropped, including if proving panics.
+struct ProofPermit<'a> {
+    limiter: &'a ProofLimiter,
+}
+
+impl Drop for ProofPermit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.running -= 1;
+        self.limiter.available.notify_one();
+    }
+}
+
+impl ProofLimiter {
+    pub fn new(max_concurrent_proofs: usize) -> Self {
+        assert!(
+            max_concurrent_proofs > 0,
+            "Maximum concurrent proofs must be positive"
+        );
+        Self {
+            max_concurrent_proofs,
+            state: Mutex::new(ProofLimiterState::default()),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Creates a limiter with the limit in MAX_CONCURRENT_PROOFS, or no limit if it is unset.
+    pub fn from_env() -> Self {
+        match env::var(MAX_CONCURRENT_PROOFS_ENV) {
+            Ok(max) if !max.is_empty() => Self::new(
+                max.parse()
+                    .expect("MAX_CONCURRENT_PROOFS must be a positive integer"),
+            ),
+            _ => Self::new(usize::MAX),
+        }
+    }
+
+    /// The limiter shared by all proofs in the process, configured from MAX_CONCURRENT_PROOFS.
+    pub fn global() -> &'static Self {
+        static LIMITER: OnceLock<ProofLimiter> = OnceLock::new();
+        LIMITER.get_or_init(Self::from_env)
+    }
+
+    /// Number of proofs waiting for a running proof to finish.
+    pub fn queue_depth(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).queued
+    }
+
+    /// Number of proofs currently being generated.
+    pub fn running(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).running
+    }
+
+    fn acquire(&self) -> ProofPermit {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.running >= self.max_concurrent_proofs {
+            state.queued += 1;
+            debug!("Proof queued, queue depth: {}", state.queued);
+            while state.running >= self.max_concurrent_proofs {
+                state = self
+                    .available
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+            state.queued -= 1;
+        }
+        state.running += 1;
+        ProofPermit { limiter: self }
+    }
+
+    /// Runs prove once fewer than the maximum number of proofs are running.
+    pub fn run<T>(&self, prove: impl FnOnce() -> T) -> T {
+        let _permit = self.acquire();
+        prove()
+    }
+}
+
+/// Returns the message of a panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Writes a dump of a failed proof to dump_dir and returns its path. The failure is the panic
+/// message of the prover, which identifies the unsatisfied constraint (e.g. the wire that was set
+/// twice with different values), and the witness is the public input of the proof.
+pub fn write_witness_dump(
+    dump_dir: &Path,
+    label: &str,
+    failure: &str,
+    witness: &impl Debug,
+) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dump_dir)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dump_dir.join(format!("{}-{}.txt", label, timestamp));
+    fs::write(
+        &path,
+        format!(
+            "label: {}\nfailure: {}\nwitness: {:#?}\n",
+            label, failure, witness
+        ),
+    )?;
+    Ok(path)
+}
+
+/// Generates a proof. If proving fails, the failure and the input are dumped to a file in dump_dir
+/// before the panic is resumed.
+pub fn prove_or_dump<L: PlonkParameters<D>, const D: usize>(
+    circuit: &CircuitBuild<L, D>,
+    input: &PublicInput<L, D>,
+    label: &str,
+    dump_dir: &Path,
+) -> ProofAndOutput<L, D>
+where
+    <<L as PlonkParameters<D>>::Config as GenericConfig<D>>::Hasher: AlgebraicHasher<L::Field>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(|| circuit.prove(input))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let failure = panic_message(payload.as_ref());
+            match write_witness_dump(dump_dir, label, &failure, input) {
+                Ok(path) => error!("Proof {} failed, witness dumped to {:?}", label, path),
+                Err(e) => error!("Proof {} failed, failed to dump witness: {}", label, e),
+            }
+            panic::resume_unwind(payload)
+        }
+    }
+}
+
+/// Generates a proof, waiting if MAX_CONCURRENT_PROOFS proofs are already being generated. If
+/// WITNESS_DUMP_DIR is set and proving fails, the failure and the input are dumped to a file in
+/// WITNESS_DUMP_DIR.
+pub fn prove_with_witness_dump<L: PlonkParameters<D>, const D: usize>(
+    circuit: &CircuitBuild<L, D>,
+    input: &PublicInput<L, D>,
+    label: &str,
+) -> ProofAndOutput<L, D>
+where
+    <<L as PlonkParameters<D>>::Config as GenericConfig<D>>::Hasher: AlgebraicHasher<L::Field>,
+{
+    ProofLimiter::global().run(|| match env::var(WITNESS_DUMP_DIR_ENV) {
+        Ok(dump_dir) => prove_or_dump(circuit, input, label, Path::new(&dump_dir)),
+        Err(_) => circuit.prove(input),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::H256;
+    use plonky2x::frontend::curta::ec::point::{CompressedEdwardsY, CompressedEdwardsYVariable};
+    use plonky2x::prelude::{ArrayVariable, Bytes32Variable, DefaultBuilder, Field, Variable};
+
+    use super::*;
+    use crate::builder::justification::GrandpaJustificationVerifier;
+
+    #[test]
+    fn test_proof_limiter_serializes_proofs() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+
+        let limiter = Arc::new(ProofLimiter::new(1));
+        let active = Arc::new(AtomicUsize::new(0));
+        let max_active = Arc::new(AtomicUsize::new(0));
+
+        let handles = (0..2)
+            .map(|_| {
+                let (limiter, active, max_active) =
+                    (limiter.clone(), active.clone(), max_active.clone());
+                thread::spawn(move || {
+                    limiter.run(|| {
+                        let now_active = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_active.fetch_max(now_active, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(100));
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // While the first proof runs, the second proof is queued.
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(limiter.running(), 1);
+        assert_eq!(limiter.queue_depth(), 1);
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(max_active.load(Ordering::SeqCst), 1);
+        assert_eq!(limiter.running(), 0);
+        assert_eq!(limiter.queue_depth(), 0);
+    }
+
+    #[test]
+    fn test_proof_limiter_releases_on_panic() {
+        let limiter = ProofLimiter::new(1);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| limiter.run(|| panic!("failed"))));
+        assert!(result.is_err());
+        assert_eq!(limiter.running(), 0);
+        assert_eq!(limiter.run(|| 1), 1);
+    }
+
+    #[test]
+    fn test_witness_dump_on_commitment_mismatch() {
+        const NUM_AUTHORITIES: usize = 2;
+        let mut builder = DefaultBuilder::new();
+
+        let authority_set_hash = builder.read::<Bytes32Variable>();
+        let pubkeys = builder
+            .constant::<ArrayVariable<CompressedEdwardsYVariable, NUM_AUTHORITIES>>(vec![
+                CompressedEdwardsY([1u8; 32]);
+                NUM_AUTHORITIES
+            ]);
+        let num_authorities = builder.constant::<Variable>(Field::ONE);
+        let commitment = builder.compute_authority_set_commitment(num_authorities, &pubkeys);
+        builder.assert_is_equal(authority_set_hash, commitment);
+
+        let circuit = builder.build();
+
+        // The authority set hash does not match the commitment, so proving fails.
+        let mut input = circuit.input();
+        input.write::<Bytes32Variable>(H256::zero());
+
+        let dump_dir = env::temp_dir().join("vectorx_witness_dump_test");
+        let _ = fs::remove_dir_all(&dump_dir);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            prove_or_dump(&circuit, &input, "commitment_mismatch", &dump_dir)
+        }));
+        assert!(result.is_err());
+
+        let dumps = fs::read_dir(&dump_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(dumps.len(), 1);
+        let dump = fs::read_to_string(&dumps[0]).unwrap();
+        assert!(dump.starts_with("label: commitment_mismatch\nfailure: "));
+        // The failure is the prover's message identifying the unsatisfied constraint.
+        assert!(!dump.contains("failure: unknown panic payload"));
+        assert!(dump.contains("witness: "));
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use plonky2x::backend::circuit::Circuit;
+use plonky2x::frontend::curta::ec::point::CompressedEdwardsYVariable;
+use plonky2x::frontend::uint::uint64::U64Variable;
+use plonky2x::prelude::{ArrayVariable, DefaultBuilder, Variable};
+
+use crate::builder::justification::GrandpaJustificationVerifier;
+use crate::consts::{
+    MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_NUM_HEADERS, MAX_NUM_STEPS, MAX_SUBARRAY_SIZE,
+};
+use crate::header_range::HeaderRangeCircuit;
+use crate::rotate::RotateCircuit;
+use crate::step::StepCircuit;
+
+/// Estimated proving time per gate, in nanoseconds. Proving time is roughly linear in the number of
+/// gates. Can be overridden with PROVING_NS_PER_GATE to calibrate against a measured run.
+pub const DEFAULT_PROVING_NS_PER_GATE: u64 = 2_000;
+
+/// Ratio of peak memory to the size of the low-degree extension of the wire polynomials, which
+/// accounts for the partial products, quotient polynomials and Merkle trees held during proving.
+pub const MEMORY_OVERHEAD_FACTOR: u64 = 3;
+
+/// Size of a Goldilocks field element, in bytes.
+const FIELD_SIZE_BYTES: u64 = 8;
+
+/// Shape of a built circuit, which determines the resources needed to prove it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub name: String,
+    pub degree_bits: usize,
+    pub num_wires: usize,
+    pub rate_bits: usize,
+}
+
+/// Estimated resources needed to prove a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceEstimate {
+    pub name: String,
+    pub num_gates: u64,
+    pub peak_memory_bytes: u64,
+    pub proving_time: Duration,
+}
+
+/// Builds the circuit and returns its shape. Building a production circuit takes minutes.
+pub fn circuit_stats<C: Circuit>(name: &str) -> CircuitStats {
+    builder_stats(name, C::define)
+}
+
+/// Builds a circuit with the constraints added by define and returns its shape.
+fn builder_stats(name: &str, define: impl FnOnce(&mut DefaultBuilder)) -> CircuitStats {
+    let mut builder = DefaultBuilder::new();
+    define(&mut builder);
+    let circuit = builder.build();
+    let common = &circuit.data.common;
+    CircuitStats {
+        name: name.to_string(),
+        degree_bits: common.degree_bits(),
+        num_wires: common.config.num_wires,
+        rate_bits: common.config.fri_config.rate_bits,
+    }
+}
+
+fn header_range_stats() -> CircuitStats {
+    circuit_stats::<HeaderRangeCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_NUM_HEADERS>>(
+        "header_range",
+    )
+}
+
+fn rotate_stats() -> CircuitStats {
+    circuit_stats::<RotateCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>>(
+        "rotate",
+    )
+}
+
+fn step_stats() -> CircuitStats {
+    circuit_stats::<
+        StepCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE, MAX_NUM_STEPS>,
+    >("step")
+}
+
+/// The circuits deployed as entrypoints in succinct.json, with their production parameters.
+pub fn registered_configs() -> Vec<(&'static str, fn() -> CircuitStats)> {
+    vec![
+        ("header_range", header_range_stats as fn() -> CircuitStats),
+        ("rotate", rotate_stats),
+        ("step", step_stats),
+    ]
+}
+
+/// The weighted authority set commitment of MAX_AUTHORITY_SET_SIZE authorities, which rotate and
+/// every justification verification compute. Each authority is hashed once by a variable length
+/// SHA256 of at most 2 chunks, where it used to be hashed by a SHA256 with and without its weight
+/// (4 chunks) and a select of the two hashes. This halves the SHA256 chunks of the commitment, from
+/// 1198 to 600.
+fn weighted_commitment_stats() -> CircuitStats {
+    builder_stats("weighted_commitment", |builder| {
+        let num_authorities = builder.read::<Variable>();
+        let pubkeys =
+            builder.read::<ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>>();
+        let weights = builder.read::<ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>>();
+        let commitment =
+            builder.compute_weighted_authority_set_commitment(num_authorities, &pubkeys, &weights);
+        builder.write(commitment);
+    })
+}
+
+/// Components of the registered circuits, built on their own to track their share of the gates.
+pub fn component_configs() -> Vec<(&'static str, fn() -> CircuitStats)> {
+    vec![(
+        "weighted_commitment",
+        weighted_commitment_stats as fn() -> CircuitStats,
+    )]
+}
+
+/// Estimates the peak memory and proving time of a circuit from its shape. Peak memory is dominated
+/// by the low-degree extension of the wire polynomials, and proving time is linear in the number of
+/// gates.
+pub fn estimate_resources(stats: &CircuitStats, proving_ns_per_gate: u64) -> ResourceEstimate {
+    let num_gates = 1u64 << stats.degree_bits;
+    let lde_size = 1u64 << (stats.degree_bits + stats.rate_bits);
+    let peak_memory_bytes =
+        stats.num_wires as u64 * lde_size * FIELD_SIZE_BYTES * MEMORY_OVERHEAD_FACTOR;
+    ResourceEstimate {
+        name: stats.name.clone(),
+        num_gates,
+        peak_memory_bytes,
+        proving_time: Duration::from_nanos(num_gates * proving_ns_per_gate),
+    }
+}
+
+/// Formats the estimates as a table, one row per circuit.
+pub fn format_report(estimates: &[ResourceEstimate]) -> String {
+    let mut report = format!(
+        "{:<16} {:>12} {:>16} {:>16}\n",
+        "circuit", "gates", "peak memory (GB)", "proving time (s)"
+    );
+    for estimate in estimates {
+        report.push_str(&format!(
+            "{:<16} {:>12} {:>16.1} {:>16.1}\n",
+            estimate.name,
+            estimate.num_gates,
+            estimate.peak_memory_bytes as f64 / (1u64 << 30) as f64,
+            estimate.proving_time.as_secs_f64()
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_resources() {
+        let stats = CircuitStats {
+            name: "rotate".to_string(),
+            degree_bits: 20,
+            num_wires: 135,
+            rate_bits: 3,
+        };
+        let estimate = estimate_resources(&stats, DEFAULT_PROVING_NS_PER_GATE);
+        assert_eq!(estimate.num_gates, 1 << 20);
+        assert_eq!(
+            estimate.peak_memory_bytes,
+            135 * (1 << 23) * FIELD_SIZE_BYTES * MEMORY_OVERHEAD_FACTOR
+        );
+        assert_eq!(
+            estimate.proving_time,
+            Duration::from_nanos((1 << 20) * DEFAULT_PROVING_NS_PER_GATE)
+        );
+
+        let report = format_report(&[estimate]);
+        assert_eq!(report.lines().count(), 2);
+        assert!(report.lines().nth(1).unwrap().starts_with("rotate"));
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_resource_report_registered_configs() {
+        let configs = registered_configs()
+            .into_iter()
+            .chain(component_configs())
+            .collect::<Vec<_>>();
+        let estimates = configs
+            .iter()
+            .map(|(_, stats)| estimate_resources(&stats(), DEFAULT_PROVING_NS_PER_GATE))
+            .collect::<Vec<_>>();
+
+        let report = format_report(&estimates);
+        log::info!("{}", report);
+        for ((name, _), estimate) in configs.iter().zip(estimates.iter()) {
+            assert_eq!(estimate.name, *name);
+            assert!(estimate.num_gates > 0);
+            assert!(estimate.peak_memory_bytes > 0);
+            assert!(!estimate.proving_time.is_zero());
+            assert!(report.contains(name));
+        }
+    }
+}
@@ -1,20 +1,189 @@
+use std::time::Instant;
+
 use async_trait::async_trait;
+use ethers::types::H256;
+use log::{info, warn};
 use plonky2x::backend::circuit::Circuit;
+use plonky2x::frontend::curta::ec::point::CompressedEdwardsY;
+use plonky2x::frontend::ecc::curve25519::ed25519::eddsa::DUMMY_PUBLIC_KEY;
 use plonky2x::frontend::hint::asynchronous::hint::AsyncHint;
 use plonky2x::frontend::uint::uint64::U64Variable;
 use plonky2x::prelude::{
-    Bytes32Variable, CircuitBuilder, Field, PlonkParameters, ValueStream, VariableStream,
+    BoolVariable, Bytes32Variable, CircuitBuilder, CircuitVariable, Field, PlonkParameters,
+    RichField, U32Variable, ValueStream, VariableStream,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::builder::justification::HintSimpleJustification;
 use crate::builder::rotate::RotateMethods;
-use crate::input::RpcDataFetcher;
+use crate::consts::DELAY_LENGTH;
+#[cfg(any(test, feature = "mock"))]
+use crate::input::mock::MockDataFetcher;
+use crate::input::types::DataSource;
+use crate::input::{encode_header, DataFetcher, RpcDataFetcher};
+use crate::io_schema::{encode_packed, io_schema, IoField, IoValue};
 use crate::vars::{EncodedHeader, RotateStruct, RotateVariable};
 
-// Get the data for the rotate circuit.
+/// Placeholder rotate data for a block that is not an epoch end block. The header is a minimal
+/// encoded ScheduledChange log of a single authority, so it satisfies the epoch end header checks,
+/// which `rotate` and `StepCircuit` run on it instead of the header of a block that is not an epoch
+/// end block.
+pub fn not_epoch_end_rotate<
+    const HEADER_LENGTH: usize,
+    const MAX_AUTHORITY_SET_SIZE: usize,
+    F: RichField,
+>() -> RotateStruct<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F> {
+    // Skip 1 byte, consensus flag, consensus engine id, skip 2 bytes, scheduled change flag,
+    // compact encoding of 1 authority.
+    let mut header_bytes = vec![0u8, 4, 70, 82, 78, 75, 0, 0, 1, 4];
+    // The authority's pubkey and weight, followed by the delay.
+    header_bytes.extend_from_slice(&DUMMY_PUBLIC_KEY);
+    header_bytes.extend_from_slice(&1u64.to_le_bytes());
+    header_bytes.extend_from_slice(&[0u8; DELAY_LENGTH]);
+    let header_size = header_bytes.len() as u32;
+    header_bytes.resize(HEADER_LENGTH, 0);
+
+    RotateStruct {
+        // The genesis block has no justification, so the justification hint fails cleanly.
+        epoch_end_block_number: 0,
+        target_header: EncodedHeader {
+            header_bytes,
+            header_size,
+        },
+        target_header_num_authorities: F::ONE,
+        next_authority_set_start_position: F::ZERO,
+        new_pubkeys: vec![
+            CompressedEdwardsY::from_slice(&DUMMY_PUBLIC_KEY).unwrap();
+            MAX_AUTHORITY_SET_SIZE
+        ],
+        new_weights: vec![1; MAX_AUTHORITY_SET_SIZE],
+        is_forced_change: false,
+        forced_change_delay: 0,
+        is_epoch_end: false,
+    }
+}
+
+/// Fetches the rotate data of authority_set_id from source. If the authority set has not ended
+/// yet, returns `not_epoch_end_rotate` with the header of the latest block justified by the
+/// authority set, from which `rotate` proves that the authority set has not ended.
+pub async fn fetch_rotate_struct<
+    const HEADER_LENGTH: usize,
+    const MAX_AUTHORITY_SET_SIZE: usize,
+    F: RichField,
+>(
+    source: &DataSource,
+    authority_set_id: u64,
+) -> RotateStruct<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F> {
+    #[cfg(any(test, feature = "mock"))]
+    if let Some(fixtures_path) = &source.fixtures_path {
+        return fetch_rotate_struct_with::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F, _>(
+            &mut MockDataFetcher::from_file(fixtures_path),
+            authority_set_id,
+        )
+        .await;
+    }
+    let mut data_fetcher = RpcDataFetcher::new_with_optional_url(source.avail_url.as_deref()).await;
+    fetch_rotate_struct_with::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F, _>(
+        &mut data_fetcher,
+        authority_set_id,
+    )
+    .await
+}
+
+/// Fetches the rotate data of authority_set_id with data_fetcher, as in `fetch_rotate_struct`.
+pub async fn fetch_rotate_struct_with<
+    const HEADER_LENGTH: usize,
+    const MAX_AUTHORITY_SET_SIZE: usize,
+    F: RichField,
+    T: DataFetcher,
+>(
+    data_fetcher: &mut T,
+    authority_set_id: u64,
+) -> RotateStruct<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F> {
+    let start = Instant::now();
+    // last_justified_block returns 0 if there is no epoch end block for the authority set.
+    let epoch_end_block_nb = data_fetcher.last_justified_block(authority_set_id).await;
+    if !data_fetcher.is_epoch_end_block(epoch_end_block_nb).await {
+        let mut rotate = not_epoch_end_rotate::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F>();
+        // Without a justified block, the placeholder of block 0 is returned, which has no
+        // justification, so the proof fails.
+        let Some(block_number) = data_fetcher.latest_justified_block(authority_set_id).await else {
+            warn!(
+                "RotateHint: authority set has not ended, and has no justified block for authority_set_id={}",
+                authority_set_id
+            );
+            return rotate;
+        };
+        let header = data_fetcher
+            .try_get_header(block_number)
+            .await
+            .unwrap_or_else(|e| {
+                panic!(
+                    "RotateHint: failed to get header for block_number={} authority_set_id={}: {}",
+                    block_number, authority_set_id, e
+                )
+            });
+        rotate.epoch_end_block_number = block_number;
+        rotate.target_header = encode_header::<HEADER_LENGTH, F>(&header).expect("RotateHint");
+        info!(
+            "RotateHint: authority set has not ended, no rotation needed for authority_set_id={} block_number={} elapsed_ms={}",
+            authority_set_id,
+            block_number,
+            start.elapsed().as_millis()
+        );
+        return rotate;
+    }
+
+    let rotate_data = data_fetcher
+        .get_header_rotate::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE>(epoch_end_block_nb)
+        .await
+        .unwrap_or_else(|e| {
+            panic!(
+                "RotateHint: failed to get rotate header for block_number={} authority_set_id={}: {}",
+                epoch_end_block_nb, authority_set_id, e
+            )
+        });
+    info!(
+        "RotateHint: fetched rotate header for block_number={} authority_set_id={} num_authorities={} elapsed_ms={}",
+        epoch_end_block_nb,
+        authority_set_id,
+        rotate_data.num_authorities,
+        start.elapsed().as_millis()
+    );
+
+    // The new authority set would be truncated to MAX_AUTHORITY_SET_SIZE, so the circuit would
+    // commit to the wrong authority set. This is a configuration error of the circuit.
+    assert!(
+        rotate_data.num_authorities <= MAX_AUTHORITY_SET_SIZE,
+        "RotateHint: epoch end block {} has {} new authorities, which exceeds the max of {} (MAX_AUTHORITY_SET_SIZE)",
+        epoch_end_block_nb,
+        rotate_data.num_authorities,
+        MAX_AUTHORITY_SET_SIZE
+    );
+
+    RotateStruct::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F> {
+        epoch_end_block_number: epoch_end_block_nb,
+        target_header: EncodedHeader {
+            header_bytes: rotate_data.header_bytes,
+            header_size: rotate_data.header_size as u32,
+        },
+        target_header_num_authorities: F::from_canonical_usize(rotate_data.num_authorities),
+        next_authority_set_start_position: F::from_canonical_usize(rotate_data.start_position),
+        new_pubkeys: rotate_data.padded_pubkeys,
+        new_weights: rotate_data.padded_weights,
+        is_forced_change: rotate_data.is_forced_change,
+        forced_change_delay: rotate_data.forced_change_delay,
+        is_epoch_end: true,
+    }
+}
+
+// Get the data for the rotate circuit, see `fetch_rotate_struct`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RotateHint<const HEADER_LENGTH: usize, const MAX_AUTHORITY_SET_SIZE: usize> {}
+pub struct RotateHint<const HEADER_LENGTH: usize, const MAX_AUTHORITY_SET_SIZE: usize> {
+    /// Where the rotate data is fetched from.
+    pub source: DataSource,
+}
 
 #[async_trait]
 impl<
@@ -31,28 +200,11 @@ impl<
     ) {
         let authority_set_id = input_stream.read_value::<U64Variable>();
 
-        let mut data_fetcher = RpcDataFetcher::new().await;
-
-        let epoch_end_block_nb = data_fetcher.last_justified_block(authority_set_id).await;
-
-        let rotate_data = data_fetcher
-            .get_header_rotate::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE>(epoch_end_block_nb)
-            .await;
-
-        let rotate = RotateStruct::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, L::Field> {
-            epoch_end_block_number: epoch_end_block_nb,
-            target_header: EncodedHeader {
-                header_bytes: rotate_data.header_bytes,
-                header_size: rotate_data.header_size as u32,
-            },
-            target_header_num_authorities: L::Field::from_canonical_usize(
-                rotate_data.num_authorities,
-            ),
-            next_authority_set_start_position: L::Field::from_canonical_usize(
-                rotate_data.start_position,
-            ),
-            new_pubkeys: rotate_data.padded_pubkeys,
-        };
+        let rotate = fetch_rotate_struct::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, L::Field>(
+            &self.source,
+            authority_set_id,
+        )
+        .await;
 
         // Rotate data.
         output_stream.write_value::<RotateVariable<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE>>(rotate);
@@ -70,10 +222,68 @@ impl<
         const MAX_AUTHORITY_SET_SIZE: usize,
         const MAX_HEADER_SIZE: usize,
         const MAX_SUBARRAY_SIZE: usize,
-    > Circuit for RotateCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>
+    > RotateCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>
 {
-    fn define<L: PlonkParameters<D>, const D: usize>(builder: &mut CircuitBuilder<L, D>)
-    where
+    /// Public inputs, in the order they are read in `define`.
+    pub const INPUTS: [IoField; 2] = [
+        IoField::new("authority_set_id", "uint64", 8),
+        IoField::new("authority_set_hash", "bytes32", 32),
+    ];
+
+    /// Public outputs, in the order they are written in `define`.
+    pub const OUTPUTS: [IoField; 6] = [
+        IoField::new("new_authority_set_hash", "bytes32", 32),
+        IoField::new("next_authority_set_id", "uint64", 8),
+        IoField::new("num_new_authorities", "uint32", 4),
+        IoField::new("activation_block_number", "uint32", 4),
+        IoField::new("num_signed", "uint32", 4),
+        IoField::new("is_epoch_end", "bool", 1),
+    ];
+
+    /// Schema of the public inputs and outputs. Must be kept in sync with `define`.
+    pub fn io_schema() -> Value {
+        io_schema("rotate", &Self::INPUTS, &Self::OUTPUTS)
+    }
+
+    /// ABI encode packed public inputs, as passed by the contract.
+    pub fn encode_inputs(authority_set_id: u64, authority_set_hash: H256) -> Vec<u8> {
+        encode_packed(
+            &Self::INPUTS,
+            &[
+                IoValue::Uint64(authority_set_id),
+                IoValue::Bytes32(authority_set_hash),
+            ],
+        )
+    }
+
+    /// ABI encode packed public outputs, as decoded by the contract.
+    pub fn encode_outputs(
+        new_authority_set_hash: H256,
+        next_authority_set_id: u64,
+        num_new_authorities: u32,
+        activation_block_number: u32,
+        num_signed: u32,
+        is_epoch_end: bool,
+    ) -> Vec<u8> {
+        encode_packed(
+            &Self::OUTPUTS,
+            &[
+                IoValue::Bytes32(new_authority_set_hash),
+                IoValue::Uint64(next_authority_set_id),
+                IoValue::Uint32(num_new_authorities),
+                IoValue::Uint32(activation_block_number),
+                IoValue::Uint32(num_signed),
+                IoValue::Bool(is_epoch_end),
+            ],
+        )
+    }
+
+    /// Defines the circuit as in `define`, with the hints fetching the rotate data and the
+    /// justification from source instead of the Avail node at AVAIL_URL.
+    pub fn define_with_source<L: PlonkParameters<D>, const D: usize>(
+        builder: &mut CircuitBuilder<L, D>,
+        source: DataSource,
+    ) where
         <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
         plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
     {
@@ -83,7 +293,9 @@ impl<
         let authority_set_hash = builder.evm_read::<Bytes32Variable>();
 
         // Fetch the data for the rotate of authority_set_id.
-        let rotate_fetcher = RotateHint::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE> {};
+        let rotate_fetcher = RotateHint::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE> {
+            source: source.clone(),
+        };
         let mut input_stream = VariableStream::new();
         input_stream.write(&authority_set_id);
         let output_stream = builder.async_hint(input_stream, rotate_fetcher);
@@ -91,16 +303,74 @@ impl<
         // rotate_var is untrusted and needs to be linked to the public inputs.
         let rotate_var =
             output_stream.read::<RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>>(builder);
+        let num_new_authorities = rotate_var.target_header_num_authorities;
+        // The new authority set is active from delay blocks after the epoch end block. rotate
+        // verifies the delay against the epoch end header.
+        let activation_block_number = builder.activation_block_number(&rotate_var);
 
-        let new_authority_set_hash = builder
-            .rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, MAX_SUBARRAY_SIZE>(
+        let (new_authority_set_hash, num_signed, is_epoch_end) = builder
+            .rotate_from::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, MAX_SUBARRAY_SIZE>(
                 authority_set_id,
                 authority_set_hash,
                 rotate_var,
+                source,
             );
 
-        // Write the hash of the new authority set to the output.
+        // The new authority set is the authority set at authority_set_id + 1. rotate asserts that the
+        // authority set id in the precommit signed on the epoch end block is authority_set_id, so the
+        // new authority set is enacted by the end of authority_set_id.
+        let one = builder.constant::<U64Variable>(1u64);
+        let next_authority_set_id = builder.add(authority_set_id, one);
+
+        // The size of the new authority set, which rotate bounds by MAX_AUTHORITY_SET_SIZE, so it fits
+        // in a U32Variable.
+        let num_new_authorities = U32Variable::from_variables_unsafe(&[num_new_authorities]);
+
+        // If the authority set has not ended, there is no new authority set, and
+        // new_authority_set_hash is zero.
+        let zero = builder.zero::<U32Variable>();
+        let num_new_authorities = builder.select(is_epoch_end, num_new_authorities, zero);
+        let activation_block_number = builder.select(is_epoch_end, activation_block_number, zero);
+
+        // The number of active authorities of authority_set_id that signed the target block, which
+        // is at most MAX_AUTHORITY_SET_SIZE, so it fits in a U32Variable.
+        let num_signed = U32Variable::from_variables_unsafe(&[num_signed]);
+
+        // Write the outputs. The order of the outputs is (new_authority_set_hash, next_authority_set_id,
+        // num_new_authorities, activation_block_number, num_signed, is_epoch_end), which must match
+        // the decoding of the output in the contract and OUTPUTS.
         builder.evm_write::<Bytes32Variable>(new_authority_set_hash);
+        builder.evm_write::<U64Variable>(next_authority_set_id);
+        builder.evm_write::<U32Variable>(num_new_authorities);
+        builder.evm_write::<U32Variable>(activation_block_number);
+        builder.evm_write::<U32Variable>(num_signed);
+        builder.evm_write::<BoolVariable>(is_epoch_end);
+    }
+
+    /// Runs the rotate hint for authority_set_id without building or proving the circuit, and
+    /// returns the rotate data the circuit would be proven over. This is the authority set id read
+    /// in `define`, rather than the epoch end block, which the hint finds from it.
+    pub async fn debug_inputs<F: RichField>(
+        authority_set_id: u64,
+        source: &DataSource,
+    ) -> RotateStruct<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, F> {
+        fetch_rotate_struct::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, F>(source, authority_set_id)
+            .await
+    }
+}
+
+impl<
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_HEADER_SIZE: usize,
+        const MAX_SUBARRAY_SIZE: usize,
+    > Circuit for RotateCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>
+{
+    fn define<L: PlonkParameters<D>, const D: usize>(builder: &mut CircuitBuilder<L, D>)
+    where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+    {
+        Self::define_with_source(builder, DataSource::default());
     }
 
     fn register_generators<L: PlonkParameters<D>, const D: usize>(
@@ -117,13 +387,500 @@ impl<
 
 #[cfg(test)]
 mod tests {
-    use std::env;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::{env, fs};
 
+    use avail_subxt::config::substrate::DigestItem;
+    use codec::{Compact, Encode};
     use ethers::types::H256;
-    use plonky2x::prelude::{DefaultBuilder, GateRegistry, HintRegistry};
+    use plonky2x::backend::circuit::PublicInput;
+    use plonky2x::prelude::plonky2::field::types::PrimeField64;
+    use plonky2x::prelude::{
+        DefaultBuilder, EvmVariable, GateRegistry, GoldilocksField, HintRegistry,
+    };
+    use sp_core::Bytes;
 
     use super::*;
-    use crate::consts::{DELAY_LENGTH, MAX_HEADER_SIZE, VALIDATOR_LENGTH};
+    use crate::builder::header::HeaderMethods;
+    use crate::consts::{
+        header_chunk_size, BASE_PREFIX_LENGTH, FORCED_CHANGE_FLAG, MAX_HEADER_CHUNK_SIZE,
+        MAX_HEADER_SIZE, PUBKEY_LENGTH, SCHEDULED_CHANGE_FLAG, VALIDATOR_LENGTH,
+    };
+    use crate::input::mock::{Fixtures, JustificationFixture};
+    use crate::input::types::{Precommit, SignerMessage};
+    use crate::input::{compute_authority_set_hash, order_by_authority_set};
+
+    #[test]
+    fn test_header_chunk_size() {
+        // The rotate circuit is built with MAX_HEADER_SIZE, which is MAX_HEADER_CHUNK_SIZE chunks.
+        assert_eq!(header_chunk_size(MAX_HEADER_SIZE), MAX_HEADER_CHUNK_SIZE);
+
+        assert_eq!(header_chunk_size(1), 1);
+        assert_eq!(header_chunk_size(128), 1);
+        assert_eq!(header_chunk_size(129), 2);
+        assert_eq!(header_chunk_size(1024), 8);
+    }
+
+    #[test]
+    fn test_rotate_io_schema() {
+        const NUM_AUTHORITIES: usize = 4;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+        type Rotate = RotateCircuit<NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>;
+
+        let schema = Rotate::io_schema();
+
+        // The widths in the schema must match the encoding of the variables read and written in
+        // define.
+        let inputs_byte_length = U64Variable::encode_value::<GoldilocksField>(0).len()
+            + Bytes32Variable::encode_value::<GoldilocksField>(H256::zero()).len();
+        let outputs_byte_length = Bytes32Variable::encode_value::<GoldilocksField>(H256::zero())
+            .len()
+            + U64Variable::encode_value::<GoldilocksField>(0).len()
+            + U32Variable::encode_value::<GoldilocksField>(0).len()
+            + U32Variable::encode_value::<GoldilocksField>(0).len()
+            + U32Variable::encode_value::<GoldilocksField>(0).len()
+            + BoolVariable::encode_value::<GoldilocksField>(false).len();
+        assert_eq!(schema["inputs_byte_length"], inputs_byte_length);
+        assert_eq!(schema["outputs_byte_length"], outputs_byte_length);
+
+        assert_eq!(schema["inputs"][0]["name"], "authority_set_id");
+        assert_eq!(schema["inputs"][1]["offset"], 8);
+        assert_eq!(schema["outputs"][0]["name"], "new_authority_set_hash");
+        assert_eq!(schema["outputs"][1]["name"], "next_authority_set_id");
+        assert_eq!(schema["outputs"][1]["offset"], 32);
+        assert_eq!(schema["outputs"][2]["name"], "num_new_authorities");
+        assert_eq!(schema["outputs"][2]["offset"], 40);
+        assert_eq!(schema["outputs"][3]["name"], "activation_block_number");
+        assert_eq!(schema["outputs"][3]["offset"], 44);
+        assert_eq!(schema["outputs"][4]["name"], "num_signed");
+        assert_eq!(schema["outputs"][4]["offset"], 48);
+        assert_eq!(schema["outputs"][5]["name"], "is_epoch_end");
+        assert_eq!(schema["outputs"][5]["offset"], 52);
+    }
+
+    #[test]
+    fn test_rotate_io_schema_matches_contract() {
+        const NUM_AUTHORITIES: usize = 4;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+        type Rotate = RotateCircuit<NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>;
+
+        // VectorX.rotate decodes the outputs at the offsets of its ROTATE_*_OFFSET constants.
+        let contract = std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/contracts/src/VectorX.sol"
+        ))
+        .unwrap();
+        let contract_constant = |name: &str| {
+            let declaration = format!("uint256 internal constant {} = ", name);
+            let start = contract
+                .find(&declaration)
+                .unwrap_or_else(|| panic!("{} is not declared in VectorX.sol", name))
+                + declaration.len();
+            let end = start + contract[start..].find(';').unwrap();
+            contract[start..end].parse::<u64>().unwrap()
+        };
+
+        let schema = Rotate::io_schema();
+        for output in schema["outputs"].as_array().unwrap() {
+            let name = output["name"].as_str().unwrap().to_uppercase();
+            assert_eq!(
+                contract_constant(&format!("ROTATE_{}_OFFSET", name)),
+                output["offset"].as_u64().unwrap(),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            contract_constant("ROTATE_OUTPUT_LENGTH"),
+            schema["outputs_byte_length"].as_u64().unwrap()
+        );
+    }
+
+    /// Fixtures of the header of block_number with the digest logs, signed by all 4 authorities of
+    /// authority set 1. Returns the fixtures and the commitment of authority set 1.
+    fn signed_header_fixtures(block_number: u32, logs: Vec<DigestItem>) -> (Fixtures, H256) {
+        use sp_core::Pair;
+
+        // Parent hash, number, state root and extrinsics root, followed by the digest.
+        let mut header = vec![0u8; 32];
+        header.extend(Compact(block_number).encode());
+        header.extend_from_slice(&[0u8; 64]);
+        header.extend(logs.encode());
+        // A V2 header extension with an empty app lookup, whose Kate commitment ends with the data
+        // root.
+        header.extend_from_slice(&[1, 0, 0, 0, 0, 0]);
+        header.extend_from_slice(&[0xdd; 32]);
+        let block_hash = H256(sp_core::blake2_256(&header));
+
+        let pairs = (0..4u8)
+            .map(|i| sp_core::ed25519::Pair::from_seed(&[i + 1; 32]))
+            .collect::<Vec<_>>();
+        let authorities = pairs
+            .iter()
+            .map(|pair| CompressedEdwardsY(pair.public().0))
+            .collect::<Vec<_>>();
+        let precommit = Precommit {
+            target_hash: block_hash,
+            target_number: block_number,
+        };
+        let signed_message =
+            Encode::encode(&(&SignerMessage::PrecommitMessage(precommit), &7u64, &1u64));
+        let pubkey_to_signature = pairs
+            .iter()
+            .map(|pair| {
+                (
+                    pair.public().0.to_vec(),
+                    pair.sign(&signed_message).0.to_vec(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut fixtures = Fixtures::default();
+        fixtures.justifications.insert(
+            block_number,
+            JustificationFixture {
+                authority_set_id: 1,
+                justification: order_by_authority_set(
+                    block_number,
+                    signed_message,
+                    &authorities,
+                    &[1; 4],
+                    &pubkey_to_signature,
+                ),
+            },
+        );
+        fixtures.headers.insert(block_number, Bytes(header));
+
+        let authority_set_hash = H256::from_slice(&compute_authority_set_hash(&authorities));
+        (fixtures, authority_set_hash)
+    }
+
+    /// Fixtures of a rotate of authority set 1 at epoch_end_block, whose header enacts new_pubkeys
+    /// with a ScheduledChange log and is signed by all 4 authorities of authority set 1. Returns the
+    /// fixtures and the commitment of authority set 1.
+    fn rotate_fixtures(epoch_end_block: u32, new_pubkeys: &[[u8; 32]]) -> (Fixtures, H256) {
+        let mut log = vec![SCHEDULED_CHANGE_FLAG];
+        log.extend(Compact(new_pubkeys.len() as u32).encode());
+        for pubkey in new_pubkeys {
+            log.extend_from_slice(pubkey);
+            log.extend_from_slice(&1u64.to_le_bytes());
+        }
+        log.extend_from_slice(&[0u8; DELAY_LENGTH]);
+
+        let (mut fixtures, authority_set_hash) = signed_header_fixtures(
+            epoch_end_block,
+            vec![DigestItem::Consensus([70, 82, 78, 75], log)],
+        );
+        fixtures.authorities.insert(
+            epoch_end_block,
+            new_pubkeys
+                .iter()
+                .map(|pubkey| Bytes(pubkey.to_vec()))
+                .collect(),
+        );
+        fixtures.epoch_end_blocks.insert(1, epoch_end_block);
+        (fixtures, authority_set_hash)
+    }
+
+    /// Proves the rotate of authority set 1 with the fixtures, and returns the outputs.
+    fn prove_rotate_fixtures(
+        fixtures: &Fixtures,
+        authority_set_hash: H256,
+        name: &str,
+    ) -> (H256, u64, u32, u32, u32, bool) {
+        const NUM_AUTHORITIES: usize = 4;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+        type Rotate = RotateCircuit<NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>;
+
+        let fixtures_path = env::temp_dir().join(format!("vectorx_{}_fixtures.json", name));
+        fs::write(&fixtures_path, serde_json::to_string(fixtures).unwrap()).unwrap();
+
+        let mut builder = DefaultBuilder::new();
+        Rotate::define_with_source(
+            &mut builder,
+            DataSource::fixtures(fixtures_path.to_str().unwrap()),
+        );
+        let circuit = builder.build();
+
+        let input = PublicInput::Bytes(Rotate::encode_inputs(1, authority_set_hash));
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        (
+            output.evm_read::<Bytes32Variable>(),
+            output.evm_read::<U64Variable>(),
+            output.evm_read::<U32Variable>(),
+            output.evm_read::<U32Variable>(),
+            output.evm_read::<U32Variable>(),
+            output.evm_read::<BoolVariable>(),
+        )
+    }
+
+    #[test]
+    fn test_rotate_outputs_golden() {
+        const NUM_AUTHORITIES: usize = 4;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+        type Rotate = RotateCircuit<NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>;
+
+        let fixture: Value = serde_json::from_str(include_str!("fixtures/rotate_outputs.json"))
+            .expect("Failed to parse the rotate outputs fixture");
+        let new_pubkeys = fixture["new_pubkeys"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|pubkey| H256::from_str(pubkey.as_str().unwrap()).unwrap().0)
+            .collect::<Vec<_>>();
+        let activation_block_number = fixture["activation_block_number"].as_u64().unwrap() as u32;
+
+        // Prove the rotate of authority set 1 at the activation block, which is the epoch end block
+        // of a ScheduledChange.
+        let (fixtures, authority_set_hash) = rotate_fixtures(activation_block_number, &new_pubkeys);
+        let (
+            new_authority_set_hash,
+            next_authority_set_id,
+            num_new_authorities,
+            activation_block_number,
+            num_signed,
+            is_epoch_end,
+        ) = prove_rotate_fixtures(&fixtures, authority_set_hash, "rotate_outputs");
+
+        assert_eq!(
+            new_authority_set_hash,
+            H256::from_str(fixture["new_authority_set_hash"].as_str().unwrap()).unwrap()
+        );
+        assert_eq!(
+            next_authority_set_id,
+            fixture["next_authority_set_id"].as_u64().unwrap()
+        );
+        assert_eq!(
+            num_new_authorities as u64,
+            fixture["num_new_authorities"].as_u64().unwrap()
+        );
+        assert_eq!(num_signed as u64, fixture["num_signed"].as_u64().unwrap());
+        assert_eq!(is_epoch_end, fixture["is_epoch_end"].as_bool().unwrap());
+
+        // The proven outputs must match the golden bytes the contract decodes.
+        let output_bytes = Rotate::encode_outputs(
+            new_authority_set_hash,
+            next_authority_set_id,
+            num_new_authorities,
+            activation_block_number,
+            num_signed,
+            is_epoch_end,
+        );
+        assert_eq!(
+            format!("0x{}", hex::encode(&output_bytes)),
+            fixture["output_bytes"].as_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rotate_not_epoch_end_fixtures() {
+        // Authority set 1 has not ended, and its latest justified block is a mid-epoch block with a
+        // BABE PreRuntime and a BABE Seal log.
+        let logs = vec![
+            DigestItem::PreRuntime(*b"BABE", vec![2; 16]),
+            DigestItem::Seal(*b"BABE", vec![3; 64]),
+        ];
+        let (fixtures, authority_set_hash) = signed_header_fixtures(645000, logs);
+        let (
+            new_authority_set_hash,
+            next_authority_set_id,
+            num_new_authorities,
+            activation_block_number,
+            num_signed,
+            is_epoch_end,
+        ) = prove_rotate_fixtures(&fixtures, authority_set_hash, "rotate_not_epoch_end");
+
+        assert!(!is_epoch_end);
+        assert_eq!(new_authority_set_hash, H256::zero());
+        assert_eq!(next_authority_set_id, 2);
+        assert_eq!(num_new_authorities, 0);
+        assert_eq!(activation_block_number, 0);
+        assert_eq!(num_signed, 4);
+    }
+
+    /// Proves the epoch end header checks of `rotate` on the rotate data, and returns is_epoch_end.
+    fn prove_epoch_end_header_checks(
+        rotate: RotateStruct<MAX_HEADER_SIZE, 4, GoldilocksField>,
+    ) -> bool {
+        const NUM_AUTHORITIES: usize = 4;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+
+        let mut builder = DefaultBuilder::new();
+        let rotate_var = builder.read::<RotateVariable<MAX_HEADER_SIZE, NUM_AUTHORITIES>>();
+        let header_hash = builder.hash_encoded_header(&rotate_var.target_header);
+        builder.verify_epoch_end_header::<MAX_HEADER_SIZE, NUM_AUTHORITIES, MAX_SUBARRAY_SIZE>(
+            &rotate_var.target_header,
+            header_hash,
+            &rotate_var.target_header_num_authorities,
+            &rotate_var.next_authority_set_start_position,
+            &rotate_var.new_pubkeys,
+            &rotate_var.new_weights,
+            rotate_var.is_forced_change,
+            rotate_var.forced_change_delay,
+        );
+        builder.write(rotate_var.is_epoch_end);
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.write::<RotateVariable<MAX_HEADER_SIZE, NUM_AUTHORITIES>>(rotate);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        output.read::<BoolVariable>()
+    }
+
+    /// Rotate data for a header whose only digest log is a ForcedChange of a single authority. No
+    /// forced change has been enacted on Avail, so the header is constructed from the GRANDPA
+    /// ConsensusLog encoding.
+    fn forced_change_rotate(
+        is_forced_change: bool,
+        forced_change_delay: u32,
+    ) -> RotateStruct<MAX_HEADER_SIZE, 4, GoldilocksField> {
+        let mut rotate = not_epoch_end_rotate::<MAX_HEADER_SIZE, 4, GoldilocksField>();
+
+        // Skip 1 byte, consensus flag, consensus engine id, skip 2 bytes, forced change flag,
+        // median last finalized block number, compact encoding of 1 authority.
+        let mut header_bytes = vec![0u8, 4, 70, 82, 78, 75, 0, 0, FORCED_CHANGE_FLAG];
+        header_bytes.extend_from_slice(&4321u32.to_le_bytes());
+        header_bytes.push(4);
+        // The authority's pubkey and weight, followed by the forced delay.
+        header_bytes.extend_from_slice(&DUMMY_PUBLIC_KEY);
+        header_bytes.extend_from_slice(&1u64.to_le_bytes());
+        header_bytes.extend_from_slice(&10u32.to_le_bytes());
+        rotate.target_header.header_size = header_bytes.len() as u32;
+        header_bytes.resize(MAX_HEADER_SIZE, 0);
+        rotate.target_header.header_bytes = header_bytes;
+
+        rotate.is_forced_change = is_forced_change;
+        rotate.forced_change_delay = forced_change_delay;
+        rotate.is_epoch_end = true;
+        rotate
+    }
+
+    #[test]
+    fn test_forced_change_activation_block_number() {
+        const NUM_AUTHORITIES: usize = 4;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+
+        let mut builder = DefaultBuilder::new();
+        let rotate_var = builder.read::<RotateVariable<MAX_HEADER_SIZE, NUM_AUTHORITIES>>();
+        let header_hash = builder.hash_encoded_header(&rotate_var.target_header);
+        builder.verify_epoch_end_header::<MAX_HEADER_SIZE, NUM_AUTHORITIES, MAX_SUBARRAY_SIZE>(
+            &rotate_var.target_header,
+            header_hash,
+            &rotate_var.target_header_num_authorities,
+            &rotate_var.next_authority_set_start_position,
+            &rotate_var.new_pubkeys,
+            &rotate_var.new_weights,
+            rotate_var.is_forced_change,
+            rotate_var.forced_change_delay,
+        );
+        let activation_block_number = builder.activation_block_number(&rotate_var);
+        builder.write(activation_block_number);
+        let circuit = builder.build();
+
+        // The ForcedChange header encodes a delay of 10 blocks.
+        let mut rotate = forced_change_rotate(true, 10);
+        rotate.epoch_end_block_number = 4400;
+        let mut input = circuit.input();
+        input.write::<RotateVariable<MAX_HEADER_SIZE, NUM_AUTHORITIES>>(rotate);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+        assert_eq!(output.read::<U32Variable>(), 4410);
+    }
+
+    #[test]
+    fn test_not_epoch_end_rotate_satisfies_header_checks() {
+        let rotate = not_epoch_end_rotate::<MAX_HEADER_SIZE, 4, GoldilocksField>();
+        assert!(!prove_epoch_end_header_checks(rotate));
+    }
+
+    #[test]
+    fn test_forced_change_header_checks() {
+        assert!(prove_epoch_end_header_checks(forced_change_rotate(
+            true, 10
+        )));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_forced_change_header_checks_wrong_delay() {
+        prove_epoch_end_header_checks(forced_change_rotate(true, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_forced_change_header_checks_as_scheduled_change() {
+        prove_epoch_end_header_checks(forced_change_rotate(false, 10));
+    }
+
+    /// Rotate data for the header of `forced_change_rotate`, with the authority's weight encoded as
+    /// header_weight and witnessed as weight.
+    fn weighted_rotate(
+        header_weight: u64,
+        weight: u64,
+    ) -> RotateStruct<MAX_HEADER_SIZE, 4, GoldilocksField> {
+        let mut rotate = forced_change_rotate(true, 10);
+        // The weight follows the 14 byte prefix and the authority's pubkey.
+        rotate.target_header.header_bytes[46..54].copy_from_slice(&header_weight.to_le_bytes());
+        rotate.new_weights[0] = weight;
+        rotate
+    }
+
+    #[test]
+    fn test_epoch_end_header_checks_weighted() {
+        assert!(prove_epoch_end_header_checks(weighted_rotate(5, 5)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_epoch_end_header_checks_wrong_weight() {
+        prove_epoch_end_header_checks(weighted_rotate(5, 1));
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_rotate_not_epoch_end() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
+
+        const NUM_AUTHORITIES: usize = 8;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+
+        let mut builder = DefaultBuilder::new();
+        RotateCircuit::<NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>::define(&mut builder);
+        let circuit = builder.build();
+
+        // The current authority set has not ended yet, so its latest justified block is mid-epoch
+        // and there is no rotation.
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (authority_set_id, authority_set_hash) = rt.block_on(async {
+            let mut data_fetcher = RpcDataFetcher::new().await;
+            let head = data_fetcher.get_head().await;
+            (
+                data_fetcher.get_authority_set_id(head.number).await,
+                data_fetcher.compute_authority_set_hash(head.number).await,
+            )
+        });
+
+        let mut input = circuit.input();
+        input.evm_write::<U64Variable>(authority_set_id);
+        input.evm_write::<Bytes32Variable>(authority_set_hash);
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        let new_authority_set_hash = output.evm_read::<Bytes32Variable>();
+        let next_authority_set_id = output.evm_read::<U64Variable>();
+        let num_new_authorities = output.evm_read::<U32Variable>();
+        let _ = output.evm_read::<U32Variable>();
+        let _ = output.evm_read::<U32Variable>();
+        let is_epoch_end = output.evm_read::<BoolVariable>();
+        assert!(!is_epoch_end);
+        assert_eq!(new_authority_set_hash, H256::zero());
+        assert_eq!(next_authority_set_id, authority_set_id + 1);
+        assert_eq!(num_new_authorities, 0);
+    }
 
     #[test]
     #[cfg_attr(feature = "ci", ignore)]
@@ -156,6 +913,52 @@ mod tests {
         circuit.test_serializers(&gate_registry, &hint_registry);
     }
 
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn test_rotate_debug_inputs() {
+        dotenv::dotenv().ok();
+
+        const NUM_AUTHORITIES: usize = 8;
+        const MAX_SUBARRAY_SIZE: usize = NUM_AUTHORITIES * VALIDATOR_LENGTH + DELAY_LENGTH;
+
+        let rotate =
+            RotateCircuit::<NUM_AUTHORITIES, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE>::debug_inputs::<
+                GoldilocksField,
+            >(0, &DataSource::default())
+            .await;
+        assert!(rotate.is_epoch_end);
+        assert!(rotate.epoch_end_block_number > 0);
+
+        let header_bytes = &rotate.target_header.header_bytes;
+        let header_size = rotate.target_header.header_size as usize;
+        let num_authorities = rotate.target_header_num_authorities.to_canonical_u64() as usize;
+        let start_position = rotate.next_authority_set_start_position.to_canonical_u64() as usize;
+        assert!(num_authorities > 0 && num_authorities <= NUM_AUTHORITIES);
+        assert!(start_position < header_size);
+
+        // The start position is the start of the GRANDPA consensus log with the new authority set.
+        // Skip 1 byte, consensus flag, consensus engine id, skip 2 bytes, scheduled change flag.
+        assert_eq!(header_bytes[start_position + 1], 4);
+        assert_eq!(
+            &header_bytes[start_position + 2..start_position + 6],
+            b"FRNK"
+        );
+        assert_eq!(header_bytes[start_position + 8], SCHEDULED_CHANGE_FLAG);
+
+        // The encoded new authority set follows the prefix, and ends before the end of the header.
+        let prefix_length = BASE_PREFIX_LENGTH + Compact(num_authorities as u32).encode().len();
+        let end_position =
+            start_position + prefix_length + num_authorities * VALIDATOR_LENGTH + DELAY_LENGTH;
+        assert!(end_position <= header_size);
+        for i in 0..num_authorities {
+            let pubkey_start = start_position + prefix_length + i * VALIDATOR_LENGTH;
+            assert_eq!(
+                &header_bytes[pubkey_start..pubkey_start + PUBKEY_LENGTH],
+                rotate.new_pubkeys[i].as_bytes()
+            );
+        }
+    }
+
     #[test]
     #[cfg_attr(feature = "ci", ignore)]
     fn test_rotate_small_authority_set() {
@@ -190,7 +993,15 @@ mod tests {
 
         circuit.verify(&proof, &input, &output);
         let new_authority_set_hash = output.evm_read::<Bytes32Variable>();
+        let next_authority_set_id = output.evm_read::<U64Variable>();
+        let num_new_authorities = output.evm_read::<U32Variable>();
         println!("new_authority_set_hash {:?}", new_authority_set_hash);
+        println!("next_authority_set_id {:?}", next_authority_set_id);
+        println!("num_new_authorities {:?}", num_new_authorities);
+
+        // The rotate always advances by one authority set.
+        assert_eq!(next_authority_set_id, authority_set_id + 1);
+        assert!(num_new_authorities > 0 && num_new_authorities as usize <= NUM_AUTHORITIES);
     }
 
     #[test]
@@ -230,7 +1041,15 @@ mod tests {
 
         circuit.verify(&proof, &input, &output);
         let new_authority_set_hash = output.evm_read::<Bytes32Variable>();
+        let next_authority_set_id = output.evm_read::<U64Variable>();
+        let num_new_authorities = output.evm_read::<U32Variable>();
         println!("new_authority_set_hash {:?}", new_authority_set_hash);
+        println!("next_authority_set_id {:?}", next_authority_set_id);
+        println!("num_new_authorities {:?}", num_new_authorities);
+
+        // The rotate always advances by one authority set.
+        assert_eq!(next_authority_set_id, authority_set_id + 1);
+        assert!(num_new_authorities > 0 && num_new_authorities as usize <= NUM_AUTHORITIES);
     }
 
     #[test]
@@ -267,6 +1086,14 @@ mod tests {
 
         circuit.verify(&proof, &input, &output);
         let new_authority_set_hash = output.evm_read::<Bytes32Variable>();
+        let next_authority_set_id = output.evm_read::<U64Variable>();
+        let num_new_authorities = output.evm_read::<U32Variable>();
         println!("new_authority_set_hash {:?}", new_authority_set_hash);
+        println!("next_authority_set_id {:?}", next_authority_set_id);
+        println!("num_new_authorities {:?}", num_new_authorities);
+
+        // The rotate always advances by one authority set.
+        assert_eq!(next_authority_set_id, authority_set_id + 1);
+        assert!(num_new_authorities > 0 && num_new_authorities as usize <= NUM_AUTHORITIES);
     }
 }
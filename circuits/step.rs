@@ -0,0 +1,417 @@
+use async_trait::async_trait;
+use avail_subxt::primitives::Header;
+use ethers::types::H256;
+use log::debug;
+use plonky2x::backend::circuit::Circuit;
+use plonky2x::frontend::hint::asynchronous::hint::AsyncHint;
+use plonky2x::frontend::uint::uint64::U64Variable;
+use plonky2x::prelude::{
+    Bytes32Variable, CircuitBuilder, PlonkParameters, RichField, U32Variable, ValueStream,
+    Variable, VariableStream,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::builder::header::HeaderMethods;
+use crate::builder::justification::{
+    GrandpaJustificationVerifier, HintSimpleJustification, VerificationOptions,
+};
+use crate::builder::rotate::RotateMethods;
+#[cfg(any(test, feature = "mock"))]
+use crate::input::mock::MockDataFetcher;
+use crate::input::types::DataSource;
+use crate::input::{encode_header, RpcDataFetcher};
+use crate::io_schema::{io_schema, IoField};
+use crate::rotate::{fetch_rotate_struct, not_epoch_end_rotate};
+use crate::vars::{EncodedHeaderVariable, RotateStruct, RotateVariable};
+
+/// Fetches the header of block_number from source.
+async fn get_header_from_source(source: &DataSource, block_number: u32) -> Header {
+    #[cfg(any(test, feature = "mock"))]
+    if let Some(fixtures_path) = &source.fixtures_path {
+        return MockDataFetcher::from_file(fixtures_path)
+            .get_header(block_number)
+            .expect("StepHint");
+    }
+    RpcDataFetcher::new_with_optional_url(source.avail_url.as_deref())
+        .await
+        .get_header(block_number)
+        .await
+}
+
+/// Fetches the data for a step to block_number, whose justification is signed by
+/// authority_set_id. The target header is always the header of block_number. If block_number is
+/// the epoch end block of authority_set_id, the rest of the data is its rotate data, otherwise it is
+/// the placeholder of `not_epoch_end_rotate`.
+pub async fn fetch_step_struct<
+    const HEADER_LENGTH: usize,
+    const MAX_AUTHORITY_SET_SIZE: usize,
+    F: RichField,
+>(
+    source: &DataSource,
+    block_number: u32,
+    authority_set_id: u64,
+) -> RotateStruct<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F> {
+    let rotate =
+        fetch_rotate_struct::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F>(source, authority_set_id)
+            .await;
+    if rotate.is_epoch_end && rotate.epoch_end_block_number == block_number {
+        return rotate;
+    }
+    debug!(
+        "StepHint: block {} is not the epoch end block of authority set {}",
+        block_number, authority_set_id
+    );
+
+    let header = get_header_from_source(source, block_number).await;
+    let mut step = not_epoch_end_rotate::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, F>();
+    step.epoch_end_block_number = block_number;
+    step.target_header = encode_header::<HEADER_LENGTH, F>(&header).expect("StepHint");
+    step
+}
+
+// Get the data for a step to a block. The output is a RotateVariable whose target header is the
+// header of the block, see `fetch_step_struct`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepHint<const HEADER_LENGTH: usize, const MAX_AUTHORITY_SET_SIZE: usize> {
+    /// Where the step data is fetched from.
+    pub source: DataSource,
+}
+
+#[async_trait]
+impl<
+        const HEADER_LENGTH: usize,
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        L: PlonkParameters<D>,
+        const D: usize,
+    > AsyncHint<L, D> for StepHint<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE>
+{
+    async fn hint(
+        &self,
+        input_stream: &mut ValueStream<L, D>,
+        output_stream: &mut ValueStream<L, D>,
+    ) {
+        let block_number = input_stream.read_value::<U32Variable>();
+        let authority_set_id = input_stream.read_value::<U64Variable>();
+
+        let step = fetch_step_struct::<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE, L::Field>(
+            &self.source,
+            block_number,
+            authority_set_id,
+        )
+        .await;
+
+        output_stream.write_value::<RotateVariable<HEADER_LENGTH, MAX_AUTHORITY_SET_SIZE>>(step);
+    }
+}
+
+/// Verifies the justifications of NUM_STEPS blocks in a single proof. Each step is a
+/// (block_number, authority_set_id) pair, and the blocks must be strictly ascending. A step to the
+/// epoch end block of its authority set rotates to the next authority set, whose commitment is
+/// extracted from the epoch end header and used to verify the justification of the next step.
+#[derive(Clone, Debug)]
+pub struct StepCircuit<
+    const MAX_AUTHORITY_SET_SIZE: usize,
+    const MAX_HEADER_SIZE: usize,
+    const MAX_SUBARRAY_SIZE: usize,
+    const NUM_STEPS: usize,
+> {}
+
+impl<
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_HEADER_SIZE: usize,
+        const MAX_SUBARRAY_SIZE: usize,
+        const NUM_STEPS: usize,
+    > StepCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE, NUM_STEPS>
+{
+    /// Public inputs, in the order they are read in `define`. The steps are NUM_STEPS packed
+    /// (uint32 block_number, uint64 authority_set_id) pairs.
+    pub const INPUTS: [IoField; 3] = [
+        IoField::new("authority_set_id", "uint64", 8),
+        IoField::new("authority_set_hash", "bytes32", 32),
+        IoField::new("steps", "bytes", NUM_STEPS * 12),
+    ];
+
+    /// Public outputs, in the order they are written in `define`.
+    pub const OUTPUTS: [IoField; 4] = [
+        IoField::new("target_header_hash", "bytes32", 32),
+        IoField::new("next_authority_set_id", "uint64", 8),
+        IoField::new("next_authority_set_hash", "bytes32", 32),
+        IoField::new("num_signed", "uint32", 4),
+    ];
+
+    /// Schema of the public inputs and outputs. Must be kept in sync with `define`.
+    pub fn io_schema() -> Value {
+        io_schema("step", &Self::INPUTS, &Self::OUTPUTS)
+    }
+
+    /// Defines the circuit as in `define`, with the hints fetching the steps and their
+    /// justifications from source instead of the Avail node at AVAIL_URL.
+    pub fn define_with_source<L: PlonkParameters<D>, const D: usize>(
+        builder: &mut CircuitBuilder<L, D>,
+        source: DataSource,
+    ) where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+    {
+        assert!(NUM_STEPS > 0, "NUM_STEPS must be at least 1.");
+
+        // Read the on-chain inputs. The validators that signed the first step are defined by
+        // authority_set_id and authority_set_hash.
+        let authority_set_id = builder.evm_read::<U64Variable>();
+        let authority_set_hash = builder.evm_read::<Bytes32Variable>();
+        let steps = (0..NUM_STEPS)
+            .map(|_| {
+                let block_number = builder.evm_read::<U32Variable>();
+                let step_authority_set_id = builder.evm_read::<U64Variable>();
+                (block_number, step_authority_set_id)
+            })
+            .collect::<Vec<_>>();
+
+        let true_v = builder._true();
+        let one = builder.constant::<U64Variable>(1u64);
+        // The header the epoch end header checks run on for a step that is not an epoch end block.
+        let placeholder_header = builder.constant::<EncodedHeaderVariable<MAX_HEADER_SIZE>>(
+            not_epoch_end_rotate::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, L::Field>()
+                .target_header,
+        );
+
+        let mut current_authority_set_id = authority_set_id;
+        let mut current_authority_set_hash = authority_set_hash;
+        // Whether the previous step was an epoch end block, and the commitment of the authority set
+        // it enacts. There is no rotation before the first step.
+        let mut is_rotation = builder._false();
+        let mut new_authority_set_hash = builder.constant::<Bytes32Variable>(H256::zero());
+        let mut prev_block_number: Option<U32Variable> = None;
+        let mut num_signed = builder.zero::<Variable>();
+        let mut target_header_hash = builder.constant::<Bytes32Variable>(H256::zero());
+
+        for (block_number, step_authority_set_id) in steps {
+            // Thread the authority set: the step after an epoch end block is signed by the next
+            // authority set, and every other step by the same authority set as the previous step.
+            let next_authority_set_id = builder.add(current_authority_set_id, one);
+            current_authority_set_id =
+                builder.select(is_rotation, next_authority_set_id, current_authority_set_id);
+            current_authority_set_hash = builder.select(
+                is_rotation,
+                new_authority_set_hash,
+                current_authority_set_hash,
+            );
+            builder.assert_is_equal(step_authority_set_id, current_authority_set_id);
+
+            if let Some(prev_block_number) = prev_block_number {
+                let is_ascending = builder.lt(prev_block_number, block_number);
+                builder.assert_is_equal(is_ascending, true_v);
+            }
+
+            // Fetch the header of the step, and the rotate data if it is an epoch end block.
+            let step_fetcher = StepHint::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE> {
+                source: source.clone(),
+            };
+            let mut input_stream = VariableStream::new();
+            input_stream.write(&block_number);
+            input_stream.write(&current_authority_set_id);
+            let output_stream = builder.async_hint(input_stream, step_fetcher);
+            let step = output_stream
+                .read::<RotateVariable<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>>(builder);
+
+            // Verify the justification on the step's header by the current authority set.
+            // Note: current_authority_set_id and current_authority_set_hash are trusted at this
+            // point. The justification binds block_number to header_hash.
+            let header_hash = builder.hash_encoded_header::<MAX_HEADER_SIZE>(&step.target_header);
+            // The new authority set of an epoch end block is read at the hinted start position, so
+            // the padding past the end of the hashed header must be zero.
+            builder.verify_header_padding(&step.target_header);
+            let verified_justification = builder
+                .verify_simple_justification_from::<MAX_AUTHORITY_SET_SIZE>(
+                    block_number,
+                    header_hash,
+                    current_authority_set_id,
+                    current_authority_set_hash,
+                    VerificationOptions::default(),
+                    source.clone(),
+                );
+            builder.assert_is_equal(verified_justification.success, true_v);
+            num_signed = builder.add(num_signed, verified_justification.num_signed);
+
+            // If the step is an epoch end block, verify the new authority set against its header.
+            // Otherwise the checks run on the placeholder header, which satisfies them.
+            let epoch_end_header = builder.select(
+                step.is_epoch_end,
+                step.target_header,
+                placeholder_header.clone(),
+            );
+            builder
+                .verify_epoch_end_header::<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE, MAX_SUBARRAY_SIZE>(
+                    &epoch_end_header,
+                    header_hash,
+                    &step.target_header_num_authorities,
+                    &step.next_authority_set_start_position,
+                    &step.new_pubkeys,
+                    &step.new_weights,
+                    step.is_forced_change,
+                    step.forced_change_delay,
+                );
+            new_authority_set_hash = builder.compute_new_authority_set_commitment(
+                step.target_header_num_authorities,
+                &step.new_pubkeys,
+                &step.new_weights,
+            );
+            is_rotation = step.is_epoch_end;
+
+            prev_block_number = Some(block_number);
+            target_header_hash = header_hash;
+        }
+
+        // The authority set that signs the blocks after the last step.
+        let next_authority_set_id = builder.add(current_authority_set_id, one);
+        let next_authority_set_id =
+            builder.select(is_rotation, next_authority_set_id, current_authority_set_id);
+        let next_authority_set_hash = builder.select(
+            is_rotation,
+            new_authority_set_hash,
+            current_authority_set_hash,
+        );
+
+        // The total number of signatures over all steps, which is at most
+        // NUM_STEPS * MAX_AUTHORITY_SET_SIZE, so it fits in a U32Variable.
+        let num_signed = U32Variable::from_variables_unsafe(&[num_signed]);
+
+        // Write the outputs. The order of the outputs must match OUTPUTS.
+        builder.evm_write::<Bytes32Variable>(target_header_hash);
+        builder.evm_write::<U64Variable>(next_authority_set_id);
+        builder.evm_write::<Bytes32Variable>(next_authority_set_hash);
+        builder.evm_write::<U32Variable>(num_signed);
+    }
+}
+
+impl<
+        const MAX_AUTHORITY_SET_SIZE: usize,
+        const MAX_HEADER_SIZE: usize,
+        const MAX_SUBARRAY_SIZE: usize,
+        const NUM_STEPS: usize,
+    > Circuit
+    for StepCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE, NUM_STEPS>
+{
+    fn define<L: PlonkParameters<D>, const D: usize>(builder: &mut CircuitBuilder<L, D>)
+    where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+    {
+        Self::define_with_source(builder, DataSource::default());
+    }
+
+    fn register_generators<L: PlonkParameters<D>, const D: usize>(
+        generator_registry: &mut plonky2x::prelude::HintRegistry<L, D>,
+    ) where
+        <<L as PlonkParameters<D>>::Config as plonky2x::prelude::plonky2::plonk::config::GenericConfig<D>>::Hasher:
+        plonky2x::prelude::plonky2::plonk::config::AlgebraicHasher<L::Field>,
+    {
+        generator_registry
+            .register_async_hint::<StepHint<MAX_HEADER_SIZE, MAX_AUTHORITY_SET_SIZE>>();
+        generator_registry.register_async_hint::<HintSimpleJustification<MAX_AUTHORITY_SET_SIZE>>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use plonky2x::prelude::{DefaultBuilder, EvmVariable, GoldilocksField};
+
+    use super::*;
+    use crate::consts::{MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE};
+
+    #[test]
+    fn test_step_io_schema() {
+        type Step = StepCircuit<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE, 4>;
+
+        let schema = Step::io_schema();
+
+        // The widths in the schema must match the encoding of the variables read and written in
+        // define.
+        let u32_length = U32Variable::encode_value::<GoldilocksField>(0).len();
+        let u64_length = U64Variable::encode_value::<GoldilocksField>(0).len();
+        let bytes32_length = Bytes32Variable::encode_value::<GoldilocksField>(H256::zero()).len();
+        assert_eq!(
+            schema["inputs_byte_length"],
+            u64_length + bytes32_length + 4 * (u32_length + u64_length)
+        );
+        assert_eq!(
+            schema["outputs_byte_length"],
+            2 * bytes32_length + u64_length + u32_length
+        );
+
+        assert_eq!(schema["inputs"][2]["name"], "steps");
+        assert_eq!(schema["inputs"][2]["offset"], 40);
+        assert_eq!(schema["outputs"][3]["name"], "num_signed");
+        assert_eq!(schema["outputs"][3]["offset"], 72);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "ci", ignore)]
+    fn test_step_with_rotation() {
+        env::set_var("RUST_LOG", "debug");
+        env_logger::try_init().unwrap_or_default();
+
+        const NUM_STEPS: usize = 4;
+        let authority_set_id = 215u64;
+
+        // A batch of a stored justification in authority set 215, the epoch end block of 215, and
+        // two stored justifications in authority set 216.
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (steps, authority_set_hash, target_header_hash, next_authority_set_hash) =
+            rt.block_on(async {
+                let mut fetcher = RpcDataFetcher::new().await;
+                let epoch_end_block = fetcher.last_justified_block(authority_set_id).await;
+                let blocks = fetcher
+                    .find_justifications_in_range(epoch_end_block - 1000, epoch_end_block + 1000)
+                    .await;
+                let before = *blocks.iter().rev().find(|b| **b < epoch_end_block).unwrap();
+                let after = blocks
+                    .iter()
+                    .filter(|b| **b > epoch_end_block)
+                    .take(2)
+                    .copied()
+                    .collect::<Vec<_>>();
+                let steps = vec![
+                    (before, authority_set_id),
+                    (epoch_end_block, authority_set_id),
+                    (after[0], authority_set_id + 1),
+                    (after[1], authority_set_id + 1),
+                ];
+                (
+                    steps,
+                    fetcher.get_authority_set_hash(authority_set_id).await,
+                    fetcher.get_block_hash(after[1]).await,
+                    fetcher.get_authority_set_hash(authority_set_id + 1).await,
+                )
+            });
+
+        let mut builder = DefaultBuilder::new();
+        StepCircuit::<MAX_AUTHORITY_SET_SIZE, MAX_HEADER_SIZE, MAX_SUBARRAY_SIZE, NUM_STEPS>::define(
+            &mut builder,
+        );
+        let circuit = builder.build();
+
+        let mut input = circuit.input();
+        input.evm_write::<U64Variable>(authority_set_id);
+        input.evm_write::<Bytes32Variable>(authority_set_hash);
+        for (block_number, step_authority_set_id) in steps {
+            input.evm_write::<U32Variable>(block_number);
+            input.evm_write::<U64Variable>(step_authority_set_id);
+        }
+
+        let (proof, mut output) = circuit.prove(&input);
+        circuit.verify(&proof, &input, &output);
+
+        assert_eq!(output.evm_read::<Bytes32Variable>(), target_header_hash);
+        assert_eq!(output.evm_read::<U64Variable>(), authority_set_id + 1);
+        assert_eq!(
+            output.evm_read::<Bytes32Variable>(),
+            next_authority_set_hash
+        );
+        let num_signed = output.evm_read::<U32Variable>();
+        assert!(num_signed > 0);
+    }
+}
@@ -42,7 +42,10 @@ pub struct JustificationVariable<const MAX_AUTHORITY_SET_SIZE: usize> {
     pub validator_signed: ArrayVariable<BoolVariable, MAX_AUTHORITY_SET_SIZE>,
     pub signatures: ArrayVariable<EDDSASignatureVariable, MAX_AUTHORITY_SET_SIZE>,
     pub pubkeys: ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>,
+    pub weights: ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>,
     pub num_authorities: U32Variable,
+    /// GRANDPA round of the justification.
+    pub round: U64Variable,
 }
 
 #[derive(Clone, Debug, CircuitVariable)]
@@ -53,6 +56,19 @@ pub struct RotateVariable<const MAX_HEADER_SIZE: usize, const MAX_AUTHORITY_SET_
     pub target_header_num_authorities: Variable,
     pub next_authority_set_start_position: Variable,
     pub new_pubkeys: ArrayVariable<CompressedEdwardsYVariable, MAX_AUTHORITY_SET_SIZE>,
+    /// Voting weights of the new authorities, as encoded in the epoch end header. The weights of
+    /// the padding authorities are 1.
+    pub new_weights: ArrayVariable<U64Variable, MAX_AUTHORITY_SET_SIZE>,
+    /// True if the new authority set is enacted by a ForcedChange log instead of a ScheduledChange
+    /// log.
+    pub is_forced_change: BoolVariable,
+    /// The delay (in blocks) of the ForcedChange encoded in the epoch end header. Zero for a
+    /// ScheduledChange.
+    pub forced_change_delay: U32Variable,
+    /// False if the authority set has not ended yet, in which case the target header is a later
+    /// header signed by the authority set, and the rest of the rotate data is a placeholder. `rotate`
+    /// checks it against the authority set change log in the target header.
+    pub is_epoch_end: BoolVariable,
 }
 
 #[derive(Clone, Debug, CircuitVariable)]
@@ -38,6 +38,22 @@ contract VectorX is IVectorX, TimelockedUpgradeable {
     ///     keccak256(abi.encode(startBlock, endBlock)).
     mapping(bytes32 => bytes32) public stateRootCommitments;
 
+    /// @notice Maps authority set id to the block number from which the authority set signs. For a
+    ///     scheduled change this is the epoch end block of the previous authority set, for a forced
+    ///     change the epoch end block plus the delay of the change.
+    mapping(uint64 => uint32) public authoritySetIdToActivationBlock;
+
+    /// @notice Byte offsets of the packed rotate outputs, as in `RotateCircuit::io_schema()`.
+    uint256 internal constant ROTATE_NEW_AUTHORITY_SET_HASH_OFFSET = 0;
+    uint256 internal constant ROTATE_NEXT_AUTHORITY_SET_ID_OFFSET = 32;
+    uint256 internal constant ROTATE_NUM_NEW_AUTHORITIES_OFFSET = 40;
+    uint256 internal constant ROTATE_ACTIVATION_BLOCK_NUMBER_OFFSET = 44;
+    uint256 internal constant ROTATE_NUM_SIGNED_OFFSET = 48;
+    uint256 internal constant ROTATE_IS_EPOCH_END_OFFSET = 52;
+
+    /// @notice Byte length of the packed rotate outputs, as in `RotateCircuit::io_schema()`.
+    uint256 internal constant ROTATE_OUTPUT_LENGTH = 53;
+
     struct InitParameters {
         address guardian;
         address gateway;
@@ -319,14 +335,79 @@ contract VectorX is IVectorX, TimelockedUpgradeable {
             input
         );
 
-        bytes32 newAuthoritySetHash = abi.decode(output, (bytes32));
+        // The output is (newAuthoritySetHash, nextAuthoritySetId, numNewAuthorities,
+        // activationBlockNumber, numSigned, isEpochEnd), packed.
+        (
+            bytes32 newAuthoritySetHash,
+            ,
+            ,
+            uint32 activationBlockNumber,
+            ,
+            bool isEpochEnd
+        ) = decodeRotateOutput(output);
+
+        // If the current authority set has not ended yet, there is no new authority set to store.
+        if (!isEpochEnd) {
+            emit RotateNotNeeded(_currentAuthoritySetId);
+            return;
+        }
 
         // Store the authority set hash for the next authority set id.
         authoritySetIdToHash[_currentAuthoritySetId + 1] = newAuthoritySetHash;
+        authoritySetIdToActivationBlock[
+            _currentAuthoritySetId + 1
+        ] = activationBlockNumber;
 
         emit AuthoritySetStored(
             _currentAuthoritySetId + 1,
             newAuthoritySetHash
         );
     }
+
+    /// @notice Decodes the packed rotate outputs, at the offsets of `RotateCircuit::io_schema()`.
+    /// @param _output The packed outputs of the rotate circuit.
+    function decodeRotateOutput(
+        bytes memory _output
+    )
+        public
+        pure
+        returns (
+            bytes32 newAuthoritySetHash,
+            uint64 nextAuthoritySetId,
+            uint32 numNewAuthorities,
+            uint32 activationBlockNumber,
+            uint32 numSigned,
+            bool isEpochEnd
+        )
+    {
+        require(_output.length == ROTATE_OUTPUT_LENGTH);
+        newAuthoritySetHash = bytes32(
+            readPacked(_output, ROTATE_NEW_AUTHORITY_SET_HASH_OFFSET, 32)
+        );
+        nextAuthoritySetId = uint64(
+            readPacked(_output, ROTATE_NEXT_AUTHORITY_SET_ID_OFFSET, 8)
+        );
+        numNewAuthorities = uint32(
+            readPacked(_output, ROTATE_NUM_NEW_AUTHORITIES_OFFSET, 4)
+        );
+        activationBlockNumber = uint32(
+            readPacked(_output, ROTATE_ACTIVATION_BLOCK_NUMBER_OFFSET, 4)
+        );
+        numSigned = uint32(readPacked(_output, ROTATE_NUM_SIGNED_OFFSET, 4));
+        isEpochEnd = readPacked(_output, ROTATE_IS_EPOCH_END_OFFSET, 1) != 0;
+    }
+
+    /// @dev Reads the big-endian value of the _length bytes at _offset of packed outputs.
+    function readPacked(
+        bytes memory _output,
+        uint256 _offset,
+        uint256 _length
+    ) internal pure returns (uint256 value) {
+        assembly {
+            value := shr(
+                mul(sub(32, _length), 8),
+                mload(add(add(_output, 32), _offset))
+            )
+        }
+    }
 }
@@ -35,6 +35,9 @@ interface IVectorX {
         bytes32 stateCommitment
     );
 
+    /// @notice Emitted when a rotate is verified for an authority set that has not ended yet.
+    event RotateNotNeeded(uint64 currentAuthoritySetId);
+
     /// @notice Emitted when a new authority set is stored.
     event AuthoritySetStored(uint64 authoritySetId, bytes32 authoritySetHash);
 
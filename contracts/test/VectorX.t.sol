@@ -25,4 +25,64 @@ contract VectorXTest is Test {
 
         console.logBytes(encodedBytes);
     }
+
+    function testDecodeRotateOutput() public view {
+        // The golden outputs of the rotate circuit, from test_rotate_outputs_golden.
+        string memory fixture = vm.readFile(
+            string.concat(
+                vm.projectRoot(),
+                "/../circuits/fixtures/rotate_outputs.json"
+            )
+        );
+        (
+            bytes32 newAuthoritySetHash,
+            uint64 nextAuthoritySetId,
+            uint32 numNewAuthorities,
+            uint32 activationBlockNumber,
+            uint32 numSigned,
+            bool isEpochEnd
+        ) = lightClient.decodeRotateOutput(
+                vm.parseJsonBytes(fixture, ".output_bytes")
+            );
+
+        assertEq(
+            newAuthoritySetHash,
+            vm.parseJsonBytes32(fixture, ".new_authority_set_hash")
+        );
+        assertEq(
+            nextAuthoritySetId,
+            vm.parseJsonUint(fixture, ".next_authority_set_id")
+        );
+        assertEq(
+            numNewAuthorities,
+            vm.parseJsonUint(fixture, ".num_new_authorities")
+        );
+        assertEq(
+            activationBlockNumber,
+            vm.parseJsonUint(fixture, ".activation_block_number")
+        );
+        assertEq(numSigned, vm.parseJsonUint(fixture, ".num_signed"));
+        assertEq(isEpochEnd, vm.parseJsonBool(fixture, ".is_epoch_end"));
+    }
+
+    function testDecodeRotateOutputNotEpochEnd() public view {
+        bytes memory output = abi.encodePacked(
+            bytes32(0),
+            uint64(2),
+            uint32(0),
+            uint32(0),
+            uint32(4),
+            false
+        );
+        (, uint64 nextAuthoritySetId, , , uint32 numSigned, bool isEpochEnd) = lightClient
+            .decodeRotateOutput(output);
+        assertEq(nextAuthoritySetId, 2);
+        assertEq(numSigned, 4);
+        assertFalse(isEpochEnd);
+    }
+
+    function testDecodeRotateOutputWrongLength() public {
+        vm.expectRevert();
+        lightClient.decodeRotateOutput(new bytes(52));
+    }
 }